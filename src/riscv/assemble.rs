@@ -1,136 +1,1158 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use super::instructions::*;
+use super::compressed::{self, CompressedInstruction, Fields, lookup_compressed};
+use super::slices::{AQ, INSTRUCTION_NOP_VALUE, RL};
+use super::float::{DYN, ROUNDING_MODE_NAMES};
+use super::csr::parse_csr;
+use super::parse::*;
+use super::pseudo::{expand_pseudo, pseudo_syntax};
+use super::expr::{constant, evaluate, split_relocation, split_symbol};
+use super::preprocess::{Line, preprocess};
+use super::error::{AssembleError, ErrorKind};
+use super::image::{Image, Section};
+use super::listing::{Listing, ListingLine};
+use super::object::{Object, ObjectSymbol, Relocation, RelocationKind, SymbolSection};
+
+/// the largest size of `.space` and `.zero`, so that a mistyped size does not exhaust memory
+const MAX_SPACE: i64 = 0x100_0000;
+
+/// label name to address
+pub type Symbols<'a> = HashMap<&'a str, u32>;
+
+/// operand layout of an instruction in assembly source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+  /// `rd rs1 rs2`
+  R,
+  /// `rd rs1 imm`
+  I,
+  /// `rd imm(rs1)` for loads and `jalr`, `rs2 imm(rs1)` for stores,
+  /// also accepted in normalized form as `rd rs1 imm` or `rs2 rs1 imm`
+  MEM,
+  /// `rs1 rs2 offset`, where offset may be a label
+  B,
+  /// `rd imm`
+  U,
+  /// `rd offset`, where offset may be a label
+  J,
+  /// `fence`, `fence pred succ` or normalized `fence rd rs1 fm pred succ`
+  FENCE,
+  /// `rd rs2 (rs1)` of `sc.w` and `amo*.w`, whose mnemonics may end in `.aq`, `.rl` or `.aqrl`
+  AMO,
+  /// `rd (rs1)` of `lr.w`
+  LR,
+  /// `fd offset(rs1)` for floating-point loads, `fs2 offset(rs1)` for floating-point stores,
+  /// also accepted in normalized form like [`Format::MEM`]
+  FMEM,
+  /// `fd fs1 fs2`
+  FR,
+  /// `fd fs1 fs2 [rm]`, where the rounding mode defaults to `dyn`
+  FR_RM,
+  /// `fd fs1 fs2 fs3 [rm]` of fused multiply-add
+  FR4,
+  /// `fd fs1`
+  FR1,
+  /// `fd fs1 [rm]`
+  FR1_RM,
+  /// `rd fs1 fs2` of comparisons
+  FCMP,
+  /// `rd fs1`
+  XF,
+  /// `rd fs1 [rm]`
+  XF_RM,
+  /// `fd rs1`
+  FX,
+  /// `fd rs1 [rm]`
+  FX_RM,
+  /// `rd csr rs1`, where the CSR is given by name or number
+  CSR,
+  /// `rd csr uimm`
+  CSRI,
+  /// `[rs1 [rs2]]` of `sfence.vma`, where missing registers are x0
+  SFENCE,
+  /// no operands
+  NONE,
+}
+
+pub fn lookup_instruction(mnemonic: &str) -> Option<(Instruction, Format)> {
+  let instruction = match mnemonic {
+    "lui" => (INSTRUCTION_LUI, Format::U),
+    "auipc" => (INSTRUCTION_AUIPC, Format::U),
+    "jal" => (INSTRUCTION_JAL, Format::J),
+    "jalr" => (INSTRUCTION_JALR, Format::MEM),
+    "beq" => (INSTRUCTION_BEQ, Format::B),
+    "bne" => (INSTRUCTION_BNE, Format::B),
+    "blt" => (INSTRUCTION_BLT, Format::B),
+    "bge" => (INSTRUCTION_BGE, Format::B),
+    "bltu" => (INSTRUCTION_BLTU, Format::B),
+    "bgeu" => (INSTRUCTION_BGEU, Format::B),
+    "lb" => (INSTRUCTION_LB, Format::MEM),
+    "lh" => (INSTRUCTION_LH, Format::MEM),
+    "lw" => (INSTRUCTION_LW, Format::MEM),
+    "lbu" => (INSTRUCTION_LBU, Format::MEM),
+    "lhu" => (INSTRUCTION_LHU, Format::MEM),
+    "sb" => (INSTRUCTION_SB, Format::MEM),
+    "sh" => (INSTRUCTION_SH, Format::MEM),
+    "sw" => (INSTRUCTION_SW, Format::MEM),
+    "addi" => (INSTRUCTION_ADDI, Format::I),
+    "slti" => (INSTRUCTION_SLTI, Format::I),
+    "sltiu" => (INSTRUCTION_SLTIU, Format::I),
+    "xori" => (INSTRUCTION_XORI, Format::I),
+    "ori" => (INSTRUCTION_ORI, Format::I),
+    "andi" => (INSTRUCTION_ANDI, Format::I),
+    "slli" => (INSTRUCTION_SLLI, Format::I),
+    "srli" => (INSTRUCTION_SRLI, Format::I),
+    "srai" => (INSTRUCTION_SRAI, Format::I),
+    "add" => (INSTRUCTION_ADD, Format::R),
+    "sub" => (INSTRUCTION_SUB, Format::R),
+    "sll" => (INSTRUCTION_SLL, Format::R),
+    "slt" => (INSTRUCTION_SLT, Format::R),
+    "sltu" => (INSTRUCTION_SLTU, Format::R),
+    "xor" => (INSTRUCTION_XOR, Format::R),
+    "srl" => (INSTRUCTION_SRL, Format::R),
+    "sra" => (INSTRUCTION_SRA, Format::R),
+    "or" => (INSTRUCTION_OR, Format::R),
+    "and" => (INSTRUCTION_AND, Format::R),
+    "fence" => (INSTRUCTION_FENCE, Format::FENCE),
+    "fence.tso" => (INSTRUCTION_FENCE_TSO, Format::NONE),
+    "pause" => (INSTRUCTION_PAUSE, Format::NONE),
+    "ecall" => (INSTRUCTION_ECALL, Format::NONE),
+    "ebreak" => (INSTRUCTION_EBREAK, Format::NONE),
+    "sret" => (INSTRUCTION_SRET, Format::NONE),
+    "mret" => (INSTRUCTION_MRET, Format::NONE),
+    "sfence.vma" => (INSTRUCTION_SFENCE_VMA, Format::SFENCE),
+    "mul" => (INSTRUCTION_MUL, Format::R),
+    "mulh" => (INSTRUCTION_MULH, Format::R),
+    "mulhsu" => (INSTRUCTION_MULHSU, Format::R),
+    "mulhu" => (INSTRUCTION_MULHU, Format::R),
+    "div" => (INSTRUCTION_DIV, Format::R),
+    "divu" => (INSTRUCTION_DIVU, Format::R),
+    "rem" => (INSTRUCTION_REM, Format::R),
+    "remu" => (INSTRUCTION_REMU, Format::R),
+    "lr.w" => (INSTRUCTION_LR_W, Format::LR),
+    "sc.w" => (INSTRUCTION_SC_W, Format::AMO),
+    "amoswap.w" => (INSTRUCTION_AMOSWAP_W, Format::AMO),
+    "amoadd.w" => (INSTRUCTION_AMOADD_W, Format::AMO),
+    "amoxor.w" => (INSTRUCTION_AMOXOR_W, Format::AMO),
+    "amoand.w" => (INSTRUCTION_AMOAND_W, Format::AMO),
+    "amoor.w" => (INSTRUCTION_AMOOR_W, Format::AMO),
+    "amomin.w" => (INSTRUCTION_AMOMIN_W, Format::AMO),
+    "amomax.w" => (INSTRUCTION_AMOMAX_W, Format::AMO),
+    "amominu.w" => (INSTRUCTION_AMOMINU_W, Format::AMO),
+    "amomaxu.w" => (INSTRUCTION_AMOMAXU_W, Format::AMO),
+    "flw" => (INSTRUCTION_FLW, Format::FMEM),
+    "fsw" => (INSTRUCTION_FSW, Format::FMEM),
+    "fmadd.s" => (INSTRUCTION_FMADD_S, Format::FR4),
+    "fmsub.s" => (INSTRUCTION_FMSUB_S, Format::FR4),
+    "fnmsub.s" => (INSTRUCTION_FNMSUB_S, Format::FR4),
+    "fnmadd.s" => (INSTRUCTION_FNMADD_S, Format::FR4),
+    "fadd.s" => (INSTRUCTION_FADD_S, Format::FR_RM),
+    "fsub.s" => (INSTRUCTION_FSUB_S, Format::FR_RM),
+    "fmul.s" => (INSTRUCTION_FMUL_S, Format::FR_RM),
+    "fdiv.s" => (INSTRUCTION_FDIV_S, Format::FR_RM),
+    "fsqrt.s" => (INSTRUCTION_FSQRT_S, Format::FR1_RM),
+    "fsgnj.s" => (INSTRUCTION_FSGNJ_S, Format::FR),
+    "fsgnjn.s" => (INSTRUCTION_FSGNJN_S, Format::FR),
+    "fsgnjx.s" => (INSTRUCTION_FSGNJX_S, Format::FR),
+    "fmin.s" => (INSTRUCTION_FMIN_S, Format::FR),
+    "fmax.s" => (INSTRUCTION_FMAX_S, Format::FR),
+    "fcvt.w.s" => (INSTRUCTION_FCVT_W_S, Format::XF_RM),
+    "fcvt.wu.s" => (INSTRUCTION_FCVT_WU_S, Format::XF_RM),
+    "fmv.x.w" => (INSTRUCTION_FMV_X_W, Format::XF),
+    "feq.s" => (INSTRUCTION_FEQ_S, Format::FCMP),
+    "flt.s" => (INSTRUCTION_FLT_S, Format::FCMP),
+    "fle.s" => (INSTRUCTION_FLE_S, Format::FCMP),
+    "fclass.s" => (INSTRUCTION_FCLASS_S, Format::XF),
+    "fcvt.s.w" => (INSTRUCTION_FCVT_S_W, Format::FX_RM),
+    "fcvt.s.wu" => (INSTRUCTION_FCVT_S_WU, Format::FX_RM),
+    "fmv.w.x" => (INSTRUCTION_FMV_W_X, Format::FX),
+    "fld" => (INSTRUCTION_FLD, Format::FMEM),
+    "fsd" => (INSTRUCTION_FSD, Format::FMEM),
+    "fmadd.d" => (INSTRUCTION_FMADD_D, Format::FR4),
+    "fmsub.d" => (INSTRUCTION_FMSUB_D, Format::FR4),
+    "fnmsub.d" => (INSTRUCTION_FNMSUB_D, Format::FR4),
+    "fnmadd.d" => (INSTRUCTION_FNMADD_D, Format::FR4),
+    "fadd.d" => (INSTRUCTION_FADD_D, Format::FR_RM),
+    "fsub.d" => (INSTRUCTION_FSUB_D, Format::FR_RM),
+    "fmul.d" => (INSTRUCTION_FMUL_D, Format::FR_RM),
+    "fdiv.d" => (INSTRUCTION_FDIV_D, Format::FR_RM),
+    "fsqrt.d" => (INSTRUCTION_FSQRT_D, Format::FR1_RM),
+    "fsgnj.d" => (INSTRUCTION_FSGNJ_D, Format::FR),
+    "fsgnjn.d" => (INSTRUCTION_FSGNJN_D, Format::FR),
+    "fsgnjx.d" => (INSTRUCTION_FSGNJX_D, Format::FR),
+    "fmin.d" => (INSTRUCTION_FMIN_D, Format::FR),
+    "fmax.d" => (INSTRUCTION_FMAX_D, Format::FR),
+    "fcvt.s.d" => (INSTRUCTION_FCVT_S_D, Format::FR1_RM),
+    "fcvt.d.s" => (INSTRUCTION_FCVT_D_S, Format::FR1),
+    "feq.d" => (INSTRUCTION_FEQ_D, Format::FCMP),
+    "flt.d" => (INSTRUCTION_FLT_D, Format::FCMP),
+    "fle.d" => (INSTRUCTION_FLE_D, Format::FCMP),
+    "fclass.d" => (INSTRUCTION_FCLASS_D, Format::XF),
+    "fcvt.w.d" => (INSTRUCTION_FCVT_W_D, Format::XF_RM),
+    "fcvt.wu.d" => (INSTRUCTION_FCVT_WU_D, Format::XF_RM),
+    "fcvt.d.w" => (INSTRUCTION_FCVT_D_W, Format::FX),
+    "fcvt.d.wu" => (INSTRUCTION_FCVT_D_WU, Format::FX),
+    "csrrw" => (INSTRUCTION_CSRRW, Format::CSR),
+    "csrrs" => (INSTRUCTION_CSRRS, Format::CSR),
+    "csrrc" => (INSTRUCTION_CSRRC, Format::CSR),
+    "csrrwi" => (INSTRUCTION_CSRRWI, Format::CSRI),
+    "csrrsi" => (INSTRUCTION_CSRRSI, Format::CSRI),
+    "csrrci" => (INSTRUCTION_CSRRCI, Format::CSRI),
+    _ => return None,
+  };
+  Some(instruction)
+}
+
+impl Format {
+  /// operand syntax for error messages
+  pub fn syntax(&self) -> &'static str {
+    match self {
+      Format::R => "rd, rs1, rs2",
+      Format::I => "rd, rs1, imm",
+      Format::MEM => "reg, offset(rs1)",
+      Format::B => "rs1, rs2, offset",
+      Format::U => "rd, imm",
+      Format::J => "rd, offset",
+      Format::FENCE => "pred, succ",
+      Format::AMO => "rd, rs2, (rs1)",
+      Format::LR => "rd, (rs1)",
+      Format::FMEM => "freg, offset(rs1)",
+      Format::FR => "fd, fs1, fs2",
+      Format::FR_RM => "fd, fs1, fs2[, rm]",
+      Format::FR4 => "fd, fs1, fs2, fs3[, rm]",
+      Format::FR1 => "fd, fs1",
+      Format::FR1_RM => "fd, fs1[, rm]",
+      Format::FCMP => "rd, fs1, fs2",
+      Format::XF => "rd, fs1",
+      Format::XF_RM => "rd, fs1[, rm]",
+      Format::FX => "fd, rs1",
+      Format::FX_RM => "fd, rs1[, rm]",
+      Format::CSR => "rd, csr, rs1",
+      Format::CSRI => "rd, csr, uimm",
+      Format::SFENCE => "[rs1[, rs2]]",
+      Format::NONE => "no operands",
+    }
+  }
+}
+
+/// where an instruction is placed and which labels it can refer to
+pub struct Context<'a> {
+  pub symbols: &'a Symbols<'a>,
+  pub pc: u32,
+  /// in the first pass not all labels are known yet and undefined ones evaluate to 0
+  pub strict: bool,
+  /// address of each `auipc` with a `%pcrel_hi` operand to the argument of the `%pcrel_hi`,
+  /// which `%pcrel_lo` refers to by the label of the `auipc`
+  pub pcrel_hi: Option<&'a HashMap<u32, &'a str>>,
+  /// index of the section the instruction is in
+  pub section: usize,
+  /// set when assembling a relocatable object
+  pub relocatable: Option<&'a Relocatable<'a>>,
+}
+
+/// what is needed to record relocations while assembling a relocatable object,
+/// where `pc` and labels are offsets in their section
+#[derive(Default)]
+pub struct Relocatable<'a> {
+  /// the section index of each label
+  pub sections: HashMap<&'a str, usize>,
+  /// recorded in the second pass
+  pub relocations: RefCell<Vec<Relocation>>,
+  /// labels generated at `auipc`s of pseudo-instructions for their `%pcrel_lo` relocations,
+  /// with section index and offset
+  pub labels: RefCell<Vec<(String, usize, u32)>>,
+}
+
+impl<'a> Context<'a> {
+  pub fn new(symbols: &'a Symbols<'a>, pc: u32) -> Self {
+    Self { symbols, pc, strict: true, pcrel_hi: None, section: 0, relocatable: None }
+  }
+
+  /// the address of a label
+  pub fn resolve(&self, symbol: &str) -> Result<u32, ErrorKind> {
+    match self.symbols.get(symbol) {
+      Some(address) => Ok(*address),
+      None if !self.strict => Ok(0),
+      None => Err(ErrorKind::UndefinedLabel(symbol.to_owned())),
+    }
+  }
+
+  /// the target of the `%pcrel_hi` of the `auipc` at `address`, relative to that `auipc`
+  pub fn pcrel_target(&self, address: u32, label: &str) -> Result<u32, ErrorKind> {
+    match self.pcrel_hi.and_then(|pcrel_hi| pcrel_hi.get(&address)) {
+      Some(target) => Ok(parse_immediate(target, self)?.wrapping_sub(address)),
+      None if !self.strict => Ok(0),
+      None => Err(ErrorKind::InvalidOperand { operand: label.to_owned(), expected: "the label of an `auipc` with `%pcrel_hi`" }),
+    }
+  }
+
+  /// the symbol and addend of `expression` in a relocatable object if it is an address only known when linking,
+  /// that is an undefined symbol, or a label in another section if `pc_relative` and in any section otherwise
+  pub fn relocatable<'e>(&self, expression: &'e str, pc_relative: bool) -> Option<(&'e str, i64)> {
+    let relocatable = self.relocatable?;
+    let (symbol, addend) = split_symbol(expression)?;
+    let linked = match relocatable.sections.get(symbol) {
+      Some(section) => !pc_relative || *section != self.section,
+      None => !self.symbols.contains_key(symbol),
+    };
+    linked.then_some((symbol, addend))
+  }
+
+  /// record a relocation at `pc` if `expression` is [`Self::relocatable`] and return whether it was,
+  /// the field is then encoded as 0 and filled in by the linker
+  pub fn relocate(&self, expression: &str, kind: RelocationKind) -> bool {
+    let Some((symbol, addend)) = self.relocatable(expression, kind.is_pc_relative()) else {
+      return false;
+    };
+    self.record(kind, symbol, addend as i32);
+    true
+  }
+
+  /// [`Self::relocate`] for an `auipc` at `pc` with its pair, where `lo` is the relocation of the second instruction,
+  /// `Call` covers both instructions, others refer to a label generated at the `auipc`
+  pub fn relocate_pcrel(&self, expression: &str, lo: RelocationKind) -> bool {
+    let Some((symbol, addend)) = self.relocatable(expression, true) else {
+      return false;
+    };
+    if lo == RelocationKind::Call {
+      self.record(lo, symbol, addend as i32);
+      return true;
+    }
+    self.record(RelocationKind::PcrelHi20, symbol, addend as i32);
+    if let (true, Some(relocatable)) = (self.strict, self.relocatable) {
+      let mut labels = relocatable.labels.borrow_mut();
+      let label = format!(".Lpcrel_hi{}", labels.len());
+      self.advance(1).record(lo, &label, 0);
+      labels.push((label, self.section, self.pc));
+    }
+    true
+  }
+
+  /// record a relocation of `%pcrel_lo(label)` at `pc` if the `%pcrel_hi` of the `auipc` at `label` is relocated
+  fn relocate_pcrel_lo(&self, label: &str, kind: RelocationKind) -> bool {
+    let target = self.symbols.get(label).and_then(|address| self.pcrel_hi?.get(address));
+    if target.is_none_or(|target| self.relocatable(target, true).is_none()) {
+      return false;
+    }
+    self.record(kind, label, 0);
+    true
+  }
+
+  fn record(&self, kind: RelocationKind, symbol: &str, addend: i32) {
+    if let (true, Some(relocatable)) = (self.strict, self.relocatable) {
+      let relocation = Relocation { section: self.section, offset: self.pc, kind, symbol: symbol.to_owned(), addend };
+      relocatable.relocations.borrow_mut().push(relocation);
+    }
+  }
+
+  /// the context of the instruction `count` instructions further
+  pub fn advance(&self, count: u32) -> Self {
+    Self { pc: self.pc + 4 * count, ..*self }
+  }
+}
 
 /// assemble a instruction in valid normalized assembly form
 /// that is, register names starting with x, no commas,
 /// only separating single whitespace space characters, no labels,
 /// lowercase instruction mnemonics
-/// immediate offsets are not encoded like 
+/// immediate offsets are not encoded like
 /// for example, `addi x1 x2 134` but not `xor a3, a5, x8` nor `add s0 t1   t2`
-pub fn assemble_normalized(instruction: &str) -> u32 {
-  let instruction = instruction.split(' ').collect::<Vec<_>>();
-  let (instruction, operands) = (instruction[0], &instruction[1..]);
-  match instruction {
-    "lui" => assemble_normalized_UJ_type(INSTRUCTION_LUI, operands),
-    "auipc" => assemble_normalized_UJ_type(INSTRUCTION_AUIPC, operands),
-    "jal" => assemble_normalized_UJ_type(INSTRUCTION_JAL, operands),
-    "jalr" => assemble_normalized_ISBJ_type(INSTRUCTION_JALR, operands),
-    "beq" => assemble_normalized_ISBJ_type(INSTRUCTION_BEQ, operands),
-    "bne" => assemble_normalized_ISBJ_type(INSTRUCTION_BNE, operands),
-    "blt" => assemble_normalized_ISBJ_type(INSTRUCTION_BLT, operands),
-    "bge" => assemble_normalized_ISBJ_type(INSTRUCTION_BGE, operands),
-    "bltu" => assemble_normalized_ISBJ_type(INSTRUCTION_BLTU, operands),
-    "bgeu" => assemble_normalized_ISBJ_type(INSTRUCTION_BGEU, operands),
-    "lb" => assemble_normalized_ISBJ_type(INSTRUCTION_LB, operands),
-    "lh" => assemble_normalized_ISBJ_type(INSTRUCTION_LH, operands),
-    "lw" => assemble_normalized_ISBJ_type(INSTRUCTION_LW, operands),
-    "lbu" => assemble_normalized_ISBJ_type(INSTRUCTION_LBU, operands),
-    "lhu" => assemble_normalized_ISBJ_type(INSTRUCTION_LHU, operands),
-    "sb" => assemble_normalized_ISBJ_type(INSTRUCTION_SB, operands),
-    "sh" => assemble_normalized_ISBJ_type(INSTRUCTION_SH, operands),
-    "sw" => assemble_normalized_ISBJ_type(INSTRUCTION_SW, operands),
-    "addi" => assemble_normalized_ISBJ_type(INSTRUCTION_ADDI, operands),
-    "slti" => assemble_normalized_ISBJ_type(INSTRUCTION_SLTI, operands),
-    "sltiu" => assemble_normalized_ISBJ_type(INSTRUCTION_SLTIU, operands),
-    "xori" => assemble_normalized_ISBJ_type(INSTRUCTION_XORI, operands),
-    "ori" => assemble_normalized_ISBJ_type(INSTRUCTION_ORI, operands),
-    "andi" => assemble_normalized_ISBJ_type(INSTRUCTION_ANDI, operands),
-    "slli" => assemble_normalized_ISBJ_type(INSTRUCTION_SLLI, operands),
-    "srli" => assemble_normalized_ISBJ_type(INSTRUCTION_SRLI, operands),
-    "srai" => assemble_normalized_ISBJ_type(INSTRUCTION_SRAI, operands),
-    "add" => assemble_normalized_R_type(INSTRUCTION_ADD, operands),
-    "sub" => assemble_normalized_R_type(INSTRUCTION_SUB, operands),
-    "sll" => assemble_normalized_R_type(INSTRUCTION_SLL, operands),
-    "slt" => assemble_normalized_R_type(INSTRUCTION_SLT, operands),
-    "sltu" => assemble_normalized_R_type(INSTRUCTION_SLTU, operands),
-    "xor" => assemble_normalized_R_type(INSTRUCTION_XOR, operands),
-    "srl" => assemble_normalized_R_type(INSTRUCTION_SRL, operands),
-    "sra" => assemble_normalized_R_type(INSTRUCTION_SRA, operands),
-    "or" => assemble_normalized_R_type(INSTRUCTION_OR, operands),
-    "and" => assemble_normalized_R_type(INSTRUCTION_AND, operands),
-    "fence" => assemble_normalized_FENCE(operands),
+pub fn assemble_normalized(instruction: &str) -> Result<u32, AssembleError> {
+  let operands = instruction.split(' ').collect::<Vec<_>>();
+  let (mnemonic, operands) = (operands[0], &operands[1..]);
+  assemble_instruction(mnemonic, operands, &Context::new(&Symbols::new(), 0))
+    .map_err(|kind| AssembleError::locate(instruction, 1, instruction, instruction, kind))
+}
+
+pub fn assemble_program(program: Vec<&str>) -> Result<Vec<u32>, AssembleError> {
+  program.iter().map(|instruction| assemble_normalized(instruction)).collect()
+}
+
+/// assemble a source file and return the contents of its `.text` section
+/// see [`assemble_image`] for the accepted syntax
+pub fn assemble(source: &str) -> Result<Vec<u32>, Vec<AssembleError>> {
+  let image = assemble_image(source)?;
+  Ok(image.section(".text").map(Section::words).unwrap_or_default())
+}
+
+/// assemble a source file with one statement per line into an image
+/// operands may be separated by commas and arbitrary whitespace,
+/// lines may carry `label:` definitions and `#` or `//` comments,
+/// branches and jumps may refer to labels defined anywhere in the file,
+/// macros, conditional assembly and includes are expanded first as described in [`preprocess`],
+/// pseudo-instructions are expanded as described in [`expand_pseudo`]
+/// and directives are handled as described in `Assembler::directive`
+/// sections are placed one after another in the order `.text`, `.rodata`, `.data`, `.bss`,
+/// followed by any other sections, starting at address 0
+/// execution starts at `_start` if defined, otherwise at the start of `.text`
+/// all errors in the file are returned, see `error::render_errors` to display them
+/// `.include` always fails, see [`assemble_image_with_includes`]
+pub fn assemble_image(source: &str) -> Result<Image, Vec<AssembleError>> {
+  assemble_image_with_includes(source, |_| None)
+}
+
+/// [`assemble_image`] with the contents of files to `.include` given by `include`
+pub fn assemble_image_with_includes(source: &str, include: impl FnMut(&str) -> Option<String>) -> Result<Image, Vec<AssembleError>> {
+  assemble_listing(source, include).map(|(image, _)| image)
+}
+
+/// [`assemble_image_with_includes`] that also returns a listing of the source
+pub fn assemble_listing(source: &str, mut include: impl FnMut(&str) -> Option<String>) -> Result<(Image, Listing), Vec<AssembleError>> {
+  let (lines, errors) = preprocess(source, &mut include);
+  let statements = lines.iter().map(Line::parse).collect::<Vec<_>>();
+  let mut assembler = Assembler::new(source, false);
+  assembler.errors = errors;
+  assembler.assemble(&statements);
+  assembler.image()
+}
+
+/// assemble a source file like [`assemble_image_with_includes`] into a relocatable object,
+/// where symbols may be defined in other objects and sections are placed by the linker,
+/// see [`elf::write_object`](super::elf::write_object) to write it as an ELF file.
+/// addresses only known when linking are described by relocations, they are allowed
+/// in branches and jumps, in pseudo-instructions taking a symbol, in `.word`
+/// and with the relocation operators `%hi`, `%lo`, `%pcrel_hi` and `%pcrel_lo`
+pub fn assemble_object(source: &str, mut include: impl FnMut(&str) -> Option<String>) -> Result<Object, Vec<AssembleError>> {
+  let (lines, errors) = preprocess(source, &mut include);
+  let statements = lines.iter().map(Line::parse).collect::<Vec<_>>();
+  let mut assembler = Assembler::new(source, true);
+  assembler.errors = errors;
+  assembler.assemble(&statements);
+  assembler.object()
+}
+
+/// state of a pass over the source
+struct Assembler<'a> {
+  source: &'a str,
+  symbols: Symbols<'a>,
+  /// labels with the index of their section, collected in the first pass while their value is still an offset
+  labels: Vec<(&'a str, usize)>,
+  sections: Vec<Section>,
+  current: usize,
+  globals: Vec<&'a str>,
+  /// `.equ` names and expressions, collected in the first pass
+  constants: Vec<(&'a str, &'a str)>,
+  /// `auipc`s with `%pcrel_hi` as section index, offset and argument, collected in the first pass
+  auipcs: Vec<(usize, u32, &'a str)>,
+  /// see [`Context::pcrel_hi`], filled in by [`Self::layout`]
+  pcrel_hi: HashMap<u32, &'a str>,
+  /// bytes emitted for each statement in the first pass,
+  /// so statements failing in the second pass can be replaced by as many zeros
+  lengths: Vec<usize>,
+  errors: Vec<AssembleError>,
+  /// collected in the second pass
+  listing: Vec<ListingLine>,
+  /// set when assembling a relocatable object
+  relocatable: Option<Relocatable<'a>>,
+  strict: bool,
+}
+
+impl<'a> Assembler<'a> {
+  fn new(source: &'a str, relocatable: bool) -> Self {
+    let mut assembler = Self {
+      source,
+      symbols: Symbols::new(),
+      labels: vec![],
+      sections: vec![],
+      current: 0,
+      globals: vec![],
+      constants: vec![],
+      auipcs: vec![],
+      pcrel_hi: HashMap::new(),
+      lengths: vec![],
+      errors: vec![],
+      listing: vec![],
+      relocatable: relocatable.then(Relocatable::default),
+      strict: false,
+    };
+    assembler.switch_section(".text");
+    assembler
+  }
+
+  fn assemble(&mut self, statements: &[Statement<'a>]) {
+    // first pass: size all sections and assign an address to every label
+    self.pass(statements);
+    self.layout();
+    // second pass: encode with all labels known
+    self.strict = true;
+    self.pass(statements);
+  }
+
+  fn pass(&mut self, statements: &[Statement<'a>]) {
+    self.switch_section(".text");
+    for (index, statement) in statements.iter().enumerate() {
+      for label in &statement.labels {
+        self.define_label(label, statement);
+      }
+      let (section, start) = (self.current, self.section().data.len());
+      if let (false, Some("auipc"), [_, imm]) = (self.strict, statement.mnemonic, statement.operands.as_slice()) {
+        if let Some(("pcrel_hi", target)) = split_relocation(imm) {
+          self.auipcs.push((section, start as u32, target));
+        }
+      }
+      let result = match statement.mnemonic {
+        Some(directive) if directive.starts_with('.') => self.directive(directive, &statement.operands),
+        Some(mnemonic) => self.instruction(mnemonic, &statement.operands),
+        None => Ok(()),
+      };
+      if !self.strict {
+        let length = if self.current == section { self.section().data.len() - start } else { 0 };
+        self.lengths.push(length);
+      } else {
+        if let Err(kind) = result {
+          // keep the layout of the first pass for the following statements
+          self.emit(&vec![0; self.lengths[index]]);
+          self.error(statement, kind);
+        }
+        let previous = index.checked_sub(1).map(|index| statements[index].origin);
+        self.list(statement, previous, section, start);
+      }
+    }
+  }
+
+  /// add a statement starting at `start` in `section` to the listing,
+  /// preceded by the line it was expanded from unless the `previous` statement was expanded from it as well
+  fn list(&mut self, statement: &Statement, previous: Option<&str>, section: usize, start: usize) {
+    let expanded = !std::ptr::eq(statement.text, statement.origin);
+    let section = &self.sections[section];
+    let address = section.address + start as u32;
+    if expanded && !previous.is_some_and(|previous| std::ptr::eq(previous, statement.origin)) {
+      let text = statement.origin.to_owned();
+      self.listing.push(ListingLine { line: statement.line, address, bytes: vec![], instruction: false, text, expanded: false });
+    }
+    self.listing.push(ListingLine {
+      line: statement.line,
+      address,
+      bytes: section.data.get(start..).unwrap_or_default().to_vec(),
+      instruction: statement.mnemonic.is_some_and(|mnemonic| !mnemonic.starts_with('.')),
+      text: statement.text.to_owned(),
+      expanded,
+    });
+  }
+
+  fn error(&mut self, statement: &Statement, kind: ErrorKind) {
+    self.errors.push(AssembleError::in_statement(self.source, statement, statement.code(), kind));
+  }
+
+  fn instruction(&mut self, mnemonic: &str, operands: &[&str]) -> Result<(), ErrorKind> {
+    if self.section().name == ".bss" {
+      return Err(ErrorKind::DataInBss);
+    }
+    let words = assemble_statement(mnemonic, operands, &self.context())?;
+    let size = if lookup_compressed(mnemonic).is_some() { 2 } else { 4 };
+    for word in words {
+      self.emit(&word.to_le_bytes()[..size]);
+    }
+    Ok(())
+  }
+
+  /// place the sections after the first pass and move the labels along,
+  /// in relocatable objects all sections stay at 0 for the linker to place them
+  fn layout(&mut self) {
+    let mut order = (0..self.sections.len()).collect::<Vec<_>>();
+    order.sort_by_key(|index| section_rank(&self.sections[*index].name));
+    let mut address = 0;
+    for index in order {
+      let section = &mut self.sections[index];
+      section.address = align(address, section.alignment);
+      address = section.end();
+    }
+    if let Some(relocatable) = &mut self.relocatable {
+      for section in &mut self.sections {
+        section.address = 0;
+      }
+      relocatable.sections = self.labels.iter().copied().collect();
+    }
+    for (label, index) in &self.labels {
+      let address = self.sections[*index].address;
+      *self.symbols.get_mut(label).unwrap() += address;
+    }
+    // constants referring to labels were evaluated with offsets, use the addresses in the second pass
+    for (name, expression) in self.constants.clone() {
+      if let Ok(value) = parse_immediate(expression, &self.context()) {
+        self.symbols.insert(name, value);
+      }
+    }
+    for (index, offset, target) in &self.auipcs {
+      self.pcrel_hi.insert(self.sections[*index].address + offset, target);
+    }
+    for section in &mut self.sections {
+      section.data.clear();
+    }
+  }
+
+  fn image(mut self) -> Result<(Image, Listing), Vec<AssembleError>> {
+    self.errors()?;
+    let constants = self.constant_names().map(str::to_owned).collect();
+    let mut sections = self.sections;
+    sections.sort_by_key(|section| section.address);
+    let entry = match self.symbols.get("_start") {
+      Some(entry) => *entry,
+      None => sections.iter().find(|section| section.name == ".text").map_or(0, |section| section.address),
+    };
+    let image = Image {
+      sections,
+      symbols: self.symbols.iter().map(|(name, value)| (name.to_string(), *value)).collect(),
+      globals: self.globals.iter().map(|name| name.to_string()).collect(),
+      constants,
+      entry,
+    };
+    let mut symbols = image.symbols.iter().map(|(name, value)| (name.clone(), *value, image.globals.contains(name))).collect::<Vec<_>>();
+    symbols.sort_by(|a, b| (a.1, &a.0).cmp(&(b.1, &b.0)));
+    Ok((image, Listing { lines: self.listing, symbols }))
+  }
+
+  /// the names defined by `.equ` and `.set` but not as labels, in order of their first definition
+  fn constant_names(&self) -> impl Iterator<Item = &'a str> + '_ {
+    self.constants.iter().enumerate()
+      .filter(|(index, (name, _))| self.constants[..*index].iter().all(|(other, _)| other != name))
+      .map(|(_, (name, _))| *name)
+      .filter(|name| self.labels.iter().all(|(label, _)| label != name))
+  }
+
+  fn errors(&mut self) -> Result<(), Vec<AssembleError>> {
+    if self.errors.is_empty() {
+      return Ok(());
+    }
+    let mut errors = std::mem::take(&mut self.errors);
+    errors.sort_by_key(|error| error.span.start);
+    Err(errors)
+  }
+
+  fn object(mut self) -> Result<Object, Vec<AssembleError>> {
+    self.errors()?;
+    let constants = self.constant_names().collect::<Vec<_>>();
+    let relocatable = self.relocatable.unwrap_or_default();
+    let relocations = relocatable.relocations.into_inner();
+    let global = |name: &str| self.globals.contains(&name);
+    let mut symbols = vec![];
+    for (name, section) in &self.labels {
+      let value = self.symbols[name];
+      symbols.push(ObjectSymbol { name: name.to_string(), section: SymbolSection::Section(*section), value, global: global(name) });
+    }
+    for (name, section, value) in relocatable.labels.into_inner() {
+      symbols.push(ObjectSymbol { name, section: SymbolSection::Section(section), value, global: false });
+    }
+    for name in constants {
+      if symbols.iter().all(|symbol| symbol.name != name) {
+        let value = self.symbols[name];
+        symbols.push(ObjectSymbol { name: name.to_string(), section: SymbolSection::Absolute, value, global: global(name) });
+      }
+    }
+    let undefined = relocations.iter().map(|relocation| relocation.symbol.as_str()).chain(self.globals.iter().copied());
+    for name in undefined {
+      if symbols.iter().all(|symbol| symbol.name != name) {
+        symbols.push(ObjectSymbol { name: name.to_owned(), section: SymbolSection::Undefined, value: 0, global: true });
+      }
+    }
+    Ok(Object { sections: self.sections, symbols, relocations })
+  }
+
+  fn context(&self) -> Context<'_> {
+    Context {
+      symbols: &self.symbols,
+      pc: self.section().end(),
+      strict: self.strict,
+      pcrel_hi: Some(&self.pcrel_hi),
+      section: self.current,
+      relocatable: self.relocatable.as_ref(),
+    }
+  }
+
+  fn section(&self) -> &Section {
+    &self.sections[self.current]
+  }
+
+  fn switch_section(&mut self, name: &str) {
+    self.current = match self.sections.iter().position(|section| section.name == name) {
+      Some(index) => index,
+      None => {
+        self.sections.push(Section { name: name.to_owned(), address: 0, alignment: 4, data: vec![] });
+        self.sections.len() - 1
+      }
+    };
+  }
+
+  fn emit(&mut self, bytes: &[u8]) {
+    self.sections[self.current].data.extend_from_slice(bytes);
+  }
+
+  /// labels are defined in the first pass with their offset, which is turned into an address by [`Self::layout`]
+  fn define_label(&mut self, label: &'a str, statement: &Statement) {
+    if self.strict {
+      return;
+    }
+    let offset = self.section().data.len() as u32;
+    if self.symbols.insert(label, offset).is_some() {
+      self.errors.push(AssembleError::in_statement(self.source, statement, label, ErrorKind::DuplicateLabel(label.to_owned())));
+      return;
+    }
+    self.labels.push((label, self.current));
+  }
+
+  /// handle an assembler directive
+  /// - `.text`, `.data`, `.rodata`, `.bss` and `.section name` switch to a section
+  /// - `.byte`, `.half`, `.word` and their aliases emit integers or addresses
+  /// - `.float` and `.double` emit floating-point numbers
+  /// - `.ascii` emits strings, `.asciz` and `.string` emit them zero terminated
+  /// - `.space` and `.zero` emit zeros or a given fill byte
+  /// - `.align` and `.p2align` align to a power of two, `.balign` to a number of bytes
+  /// - `.equ` and `.set` define a symbol
+  /// - `.globl` and `.global` mark symbols as global
+  /// - `.local` marks symbols as local again, visible only in their own file
+  fn directive(&mut self, directive: &str, operands: &[&'a str]) -> Result<(), ErrorKind> {
+    let wrong_operand_count = |expected| ErrorKind::WrongOperandCount {
+      mnemonic: directive.to_owned(),
+      expected,
+      found: operands.len(),
+    };
+    let data = match directive {
+      ".text" | ".data" | ".rodata" | ".bss" => {
+        self.switch_section(directive);
+        return Ok(());
+      }
+      ".section" => {
+        let Some(name) = operands.first() else {
+          return Err(wrong_operand_count("name[, flags]"));
+        };
+        self.switch_section(name);
+        return Ok(());
+      }
+      ".align" | ".p2align" => {
+        let (alignment, fill) = self.align_operands(operands).ok_or_else(|| wrong_operand_count("alignment[, fill]"))??;
+        if alignment >= 32 {
+          return Err(ErrorKind::ImmediateOutOfRange { value: alignment as i64, min: 0, max: 31 });
+        }
+        self.align(1 << alignment, fill);
+        return Ok(());
+      }
+      ".balign" => {
+        let (alignment, fill) = self.align_operands(operands).ok_or_else(|| wrong_operand_count("alignment[, fill]"))??;
+        self.align(alignment, fill);
+        return Ok(());
+      }
+      ".equ" | ".set" => {
+        let [name, value] = *operands else {
+          return Err(wrong_operand_count("name, value"));
+        };
+        if !is_symbol(name) {
+          return Err(ErrorKind::InvalidOperand { operand: name.to_owned(), expected: "a symbol name" });
+        }
+        if !self.strict {
+          self.constants.push((name, value));
+        }
+        let value = parse_immediate(value, &self.context())?;
+        self.symbols.insert(name, value);
+        return Ok(());
+      }
+      ".globl" | ".global" => {
+        if !self.strict {
+          self.globals.extend(operands);
+        }
+        return Ok(());
+      }
+      ".local" => {
+        if !self.strict {
+          self.globals.retain(|name| !operands.contains(name));
+        }
+        return Ok(());
+      }
+      ".space" | ".zero" => match *operands {
+        [size] => vec![0; self.space_size(size)?],
+        [size, fill] if directive == ".space" => {
+          let fill = parse_integer(fill, &self.context(), 1)?;
+          vec![fill as u8; self.space_size(size)?]
+        }
+        _ => return Err(wrong_operand_count("size")),
+      },
+      ".byte" => self.integers(operands, 1)?,
+      ".half" | ".2byte" | ".short" => self.integers(operands, 2)?,
+      ".word" | ".4byte" | ".long" => self.integers(operands, 4)?,
+      ".float" => floats(operands, |value| value.parse::<f32>().map(|value| value.to_le_bytes().to_vec()))?,
+      ".double" => floats(operands, |value| value.parse::<f64>().map(|value| value.to_le_bytes().to_vec()))?,
+      ".ascii" => self.strings(operands, false)?,
+      ".asciz" | ".string" => self.strings(operands, true)?,
+      _ => return Err(ErrorKind::UnknownDirective(directive.to_owned())),
+    };
+    if self.section().name == ".bss" && data.iter().any(|byte| *byte != 0) {
+      return Err(ErrorKind::DataInBss);
+    }
+    self.emit(&data);
+    Ok(())
+  }
+
+  /// the alignment and optional fill byte of an alignment directive, `None` for the wrong number of operands
+  fn align_operands(&self, operands: &[&str]) -> Option<Result<(u32, Option<u8>), ErrorKind>> {
+    let context = self.context();
+    let operands = match *operands {
+      [alignment] => parse_immediate(alignment, &context).map(|alignment| (alignment, None)),
+      [alignment, fill] => parse_immediate(alignment, &context)
+        .and_then(|alignment| Ok((alignment, Some(parse_integer(fill, &context, 1)? as u8)))),
+      _ => return None,
+    };
+    Some(operands)
+  }
+
+  /// pad the current section to a multiple of `alignment`,
+  /// with `nop`s in `.text` unless a fill byte is given
+  fn align(&mut self, alignment: u32, fill: Option<u8>) {
+    let section = &mut self.sections[self.current];
+    section.alignment = section.alignment.max(alignment);
+    let size = section.data.len() as u32;
+    let padding = align(size, alignment) - size;
+    match fill {
+      None if section.name == ".text" && size.is_multiple_of(4) => {
+        let nop = INSTRUCTION_NOP_VALUE.to_le_bytes();
+        self.emit(&nop.repeat(padding as usize / 4));
+      }
+      fill => self.emit(&vec![fill.unwrap_or(0); padding as usize]),
+    }
+  }
+
+  /// the size of `.space` and `.zero`, which must fit into the address space after the current offset of the section
+  fn space_size(&self, size: &str) -> Result<usize, ErrorKind> {
+    let value = evaluate(size, &self.context())?;
+    let max = MAX_SPACE.min((1 << 32) - self.section().data.len() as i64);
+    if !(0..=max).contains(&value) {
+      return Err(ErrorKind::ImmediateOutOfRange { value, min: 0, max });
+    }
+    Ok(value as usize)
+  }
+
+  /// `.` refers to the address of each integer,
+  /// in relocatable objects words may be addresses only known when linking
+  fn integers(&self, operands: &[&str], size: usize) -> Result<Vec<u8>, ErrorKind> {
+    let context = self.context();
+    let mut data = vec![];
+    for operand in operands {
+      let context = Context { pc: context.pc + data.len() as u32, ..context };
+      let value = match context.relocatable(operand, false) {
+        Some(_) if size != 4 && context.strict => return Err(ErrorKind::NotRelocatable(operand.to_string())),
+        Some(_) => {
+          context.relocate(operand, RelocationKind::Absolute32);
+          0
+        }
+        None => parse_integer(operand, &context, size)?,
+      };
+      data.extend_from_slice(&value.to_le_bytes()[..size]);
+    }
+    Ok(data)
+  }
+
+  fn strings(&self, operands: &[&str], terminate: bool) -> Result<Vec<u8>, ErrorKind> {
+    let mut data = vec![];
+    for operand in operands {
+      let Some(string) = parse_string(operand) else {
+        return Err(ErrorKind::InvalidOperand { operand: operand.to_string(), expected: "a string" });
+      };
+      data.extend(string);
+      if terminate {
+        data.push(0);
+      }
+    }
+    Ok(data)
+  }
+}
+
+/// position of a section in the layout
+pub fn section_rank(name: &str) -> usize {
+  match name {
+    ".text" => 0,
+    ".rodata" => 1,
+    ".data" => 2,
+    ".bss" => 3,
+    _ => 4,
+  }
+}
+
+/// round up to a multiple of `alignment`
+pub fn align(address: u32, alignment: u32) -> u32 {
+  address.next_multiple_of(alignment.max(1))
+}
+
+/// encode a base or pseudo-instruction located at `context.pc`
+fn assemble_statement(mnemonic: &str, operands: &[&str], context: &Context) -> Result<Vec<u32>, ErrorKind> {
+  match expand_pseudo(mnemonic, operands, context)? {
+    Some(expansion) => expansion.iter().zip(0..).map(|((mnemonic, operands), index)| {
+      let operands = operands.iter().map(String::as_str).collect::<Vec<_>>();
+      assemble_instruction(mnemonic, &operands, &context.advance(index))
+    }).collect(),
+    None => Ok(vec![assemble_instruction(mnemonic, operands, context)?]),
+  }
+}
+
+/// encode a single base or compressed instruction located at `context.pc`,
+/// compressed instructions are returned in the lower half
+fn assemble_instruction(mnemonic: &str, operands: &[&str], context: &Context) -> Result<u32, ErrorKind> {
+  if let Some(instruction) = lookup_compressed(mnemonic) {
+    return match assemble_compressed(instruction, operands, context) {
+      Err(_) if !context.strict => Ok(0),
+      result => result.map(u32::from),
+    };
+  }
+  let (base, aqrl) = split_ordering(mnemonic);
+  let Some((instruction, format)) = lookup_instruction(base) else {
+    return Err(match pseudo_syntax(mnemonic) {
+      Some(expected) => ErrorKind::WrongOperandCount { mnemonic: mnemonic.to_owned(), expected, found: operands.len() },
+      None => ErrorKind::UnknownMnemonic(mnemonic.to_owned()),
+    });
+  };
+  let wrong_operand_count = || ErrorKind::WrongOperandCount {
+    mnemonic: mnemonic.to_owned(),
+    expected: format.syntax(),
+    found: operands.len(),
+  };
+  let store = matches!(mnemonic, "sb" | "sh" | "sw" | "fsw" | "fsd");
+  let operands = match (format, operands) {
+    (Format::R, [rd, rs1, rs2]) => vec![register(rd)?, register(rs1)?, register(rs2)?],
+    (Format::I | Format::MEM, [rd, rs1, imm]) => vec![register(rd)?, register(rs1)?, immediate(imm, context, store)?],
+    (Format::MEM, [rd, address]) => {
+      let (imm, rs1) = split_address(address)?;
+      vec![register(rd)?, register(rs1)?, immediate(imm, context, store)?]
+    }
+    (Format::B, [rs1, rs2, offset]) => vec![register(rs1)?, register(rs2)?, offset_to(offset, context, RelocationKind::Branch)?],
+    (Format::U, [rd, imm]) => vec![register(rd)?, immediate(imm, context, false)?],
+    (Format::J, [rd, offset]) => vec![register(rd)?, offset_to(offset, context, RelocationKind::Jal)?],
+    (Format::FENCE, []) => vec![0, 0, 0, 0b1111, 0b1111],
+    (Format::FENCE, [pred, succ]) => vec![0, 0, 0, parse_fence_set(pred)?, parse_fence_set(succ)?],
+    (Format::FENCE, [rd, rs1, fm, pred, succ]) => vec![
+      register(rd)?,
+      register(rs1)?,
+      parse_integer(fm, context, 1)?,
+      parse_integer(pred, context, 1)?,
+      parse_integer(succ, context, 1)?,
+    ],
+    (Format::AMO, [rd, rs2, address]) => vec![register(rd)?, atomic_address(address, context)?, register(rs2)?, aqrl],
+    (Format::LR, [rd, address]) => vec![register(rd)?, atomic_address(address, context)?, 0, aqrl],
+    (Format::FMEM, [rd, rs1, imm]) => vec![float_register(rd)?, register(rs1)?, immediate(imm, context, store)?],
+    (Format::FMEM, [rd, address]) => {
+      let (imm, rs1) = split_address(address)?;
+      vec![float_register(rd)?, register(rs1)?, immediate(imm, context, store)?]
+    }
+    // floating-point instructions are passed [rd, rs1, rs2, rm] with fused multiply-add inserting rs3 before rm,
+    // missing registers and the rounding mode of instructions without one are passed as 0
+    (Format::FR, [rd, rs1, rs2]) => vec![float_register(rd)?, float_register(rs1)?, float_register(rs2)?, 0],
+    (Format::FR_RM, [rd, rs1, rs2, rm @ ..]) if rm.len() <= 1 => {
+      vec![float_register(rd)?, float_register(rs1)?, float_register(rs2)?, rounding_mode(rm)?]
+    }
+    (Format::FR4, [rd, rs1, rs2, rs3, rm @ ..]) if rm.len() <= 1 => {
+      vec![float_register(rd)?, float_register(rs1)?, float_register(rs2)?, float_register(rs3)?, rounding_mode(rm)?]
+    }
+    (Format::FR1, [rd, rs1]) => vec![float_register(rd)?, float_register(rs1)?, 0, 0],
+    (Format::FR1_RM, [rd, rs1, rm @ ..]) if rm.len() <= 1 => vec![float_register(rd)?, float_register(rs1)?, 0, rounding_mode(rm)?],
+    (Format::FCMP, [rd, rs1, rs2]) => vec![register(rd)?, float_register(rs1)?, float_register(rs2)?, 0],
+    (Format::XF, [rd, rs1]) => vec![register(rd)?, float_register(rs1)?, 0, 0],
+    (Format::XF_RM, [rd, rs1, rm @ ..]) if rm.len() <= 1 => vec![register(rd)?, float_register(rs1)?, 0, rounding_mode(rm)?],
+    (Format::FX, [rd, rs1]) => vec![float_register(rd)?, register(rs1)?, 0, 0],
+    (Format::FX_RM, [rd, rs1, rm @ ..]) if rm.len() <= 1 => vec![float_register(rd)?, register(rs1)?, 0, rounding_mode(rm)?],
+    (Format::CSR, [rd, csr_operand, rs1]) => vec![register(rd)?, csr(csr_operand, context)?, register(rs1)?],
+    (Format::CSRI, [rd, csr_operand, imm]) => vec![register(rd)?, csr(csr_operand, context)?, parse_immediate(imm, context)?],
+    (Format::SFENCE, []) => vec![0, 0],
+    (Format::SFENCE, [rs1]) => vec![register(rs1)?, 0],
+    (Format::SFENCE, [rs1, rs2]) => vec![register(rs1)?, register(rs2)?],
     // these take no arguments, pass in empty slice `&[]`
-    "fence.tso" => (INSTRUCTION_FENCE_TSO.assembler)(&[]),
-    "pause" => (INSTRUCTION_PAUSE.assembler)(&[]),
-    "ecall" => (INSTRUCTION_ECALL.assembler)(&[]),
-    "ebreak" => (INSTRUCTION_EBREAK.assembler)(&[]),
-    "mul" => assemble_normalized_R_type(INSTRUCTION_MUL, operands),
-    "mulh" => assemble_normalized_R_type(INSTRUCTION_MULH, operands),
-    "mulhsu" => assemble_normalized_R_type(INSTRUCTION_MULHSU, operands),
-    "mulhu" => assemble_normalized_R_type(INSTRUCTION_MULHU, operands),
-    "div" => assemble_normalized_R_type(INSTRUCTION_DIV, operands),
-    "divu" => assemble_normalized_R_type(INSTRUCTION_DIVU, operands),
-    "rem" => assemble_normalized_R_type(INSTRUCTION_REM, operands),
-    "remu" => assemble_normalized_R_type(INSTRUCTION_REMU, operands),
-    _ => panic!("Invalid instruction for assembling: {instruction:?}")
-  }
-}
-
-fn assemble_normalized_R_type(instruction: Instruction, operands: &[&str]) -> u32 {
-  if let [rd, rs1, rs2] = *operands {
-    let rd = parse_normalized_register(rd);
-    let rs1 = parse_normalized_register(rs1);
-    let rs2 = parse_normalized_register(rs2);
-    let operands = &[rd, rs1, rs2];
-    (instruction.assembler)(operands)
-  } else {
-    panic!("Invalid operands to instruction {0}: {operands:?}", instruction.name);
+    (Format::NONE, []) => vec![],
+    _ => return Err(wrong_operand_count()),
+  };
+  match (instruction.assembler)(&operands) {
+    // labels are not placed yet in the first pass, only the size of the instruction matters
+    Err(_) if !context.strict => Ok(0),
+    result => result,
   }
 }
 
-fn assemble_normalized_ISBJ_type(instruction: Instruction, operands: &[&str]) -> u32 {
-  if let [rd, rs1, imm] = *operands {
-    let rd = parse_normalized_register(rd);
-    let rs1 = parse_normalized_register(rs1);
-    let imm = imm.parse::<i32>().unwrap() as u32;
-    let operands = &[rd, rs1, imm];
-    (instruction.assembler)(operands)
-  } else {
-    panic!("Invalid operands to instruction {0}: {operands:?}", instruction.name);
+/// encode a compressed instruction like `c.addi a0, -3` or `c.lw a1, 4(a2)`,
+/// which takes registers `x8` to `x15` where [`compressed::Operand`] says so
+fn assemble_compressed(instruction: &CompressedInstruction, operands: &[&str], context: &Context) -> Result<u16, ErrorKind> {
+  use compressed::Operand;
+  if operands.len() != instruction.operands.len() {
+    return Err(ErrorKind::WrongOperandCount {
+      mnemonic: instruction.name.to_owned(),
+      expected: instruction.syntax,
+      found: operands.len(),
+    });
   }
+  let prime = |register: u32, operand: &str| match register {
+    8..=15 => Ok(register),
+    _ => Err(ErrorKind::InvalidOperand { operand: operand.to_owned(), expected: "a register from `x8` to `x15`" }),
+  };
+  let stack_pointer = |register: &str| match parse_register(register) {
+    Some(2) => Ok(()),
+    _ => Err(ErrorKind::InvalidOperand { operand: register.to_owned(), expected: "`sp`" }),
+  };
+  let mut fields = Fields::default();
+  for (kind, operand) in instruction.operands.iter().zip(operands) {
+    match kind {
+      Operand::Rd => fields.high = register(operand)?,
+      Operand::Rs2 => fields.low = register(operand)?,
+      Operand::Rs1Prime => fields.high = prime(register(operand)?, operand)?,
+      Operand::Rs2Prime => fields.low = prime(register(operand)?, operand)?,
+      Operand::FloatRd => fields.high = float_register(operand)?,
+      Operand::FloatRs2 => fields.low = float_register(operand)?,
+      Operand::FloatRs2Prime => fields.low = prime(float_register(operand)?, operand)?,
+      Operand::Sp => stack_pointer(operand)?,
+      Operand::Immediate => fields.imm = parse_immediate(operand, context)?,
+      Operand::UpperImmediate => {
+        // the upper immediate of `lui` is given unsigned, so its negative values are those sign extended from bit 19
+        let imm = parse_immediate(operand, context)?;
+        fields.imm = if (0xfffe0..=0xfffff).contains(&imm) { imm | 0xfff0_0000 } else { imm };
+      }
+      Operand::Offset => {
+        let kind = if matches!(instruction.name, "c.j" | "c.jal") { RelocationKind::RvcJump } else { RelocationKind::RvcBranch };
+        fields.imm = offset_to(operand, context, kind)?;
+      }
+      Operand::Memory => {
+        let (imm, rs1) = split_address(operand)?;
+        fields.imm = parse_immediate(imm, context)?;
+        fields.high = prime(register(rs1)?, rs1)?;
+      }
+      Operand::StackMemory => {
+        let (imm, rs1) = split_address(operand)?;
+        fields.imm = parse_immediate(imm, context)?;
+        stack_pointer(rs1)?;
+      }
+    }
+  }
+  if !(instruction.valid)(&fields) {
+    return Err(ErrorKind::InvalidOperand { operand: operands.join(", "), expected: instruction.requires });
+  }
+  instruction.encode(&fields)
 }
 
-fn assemble_normalized_UJ_type(instruction: Instruction, operands: &[&str]) -> u32 {
-  if let [rd, imm] = *operands {
-    let rd = parse_normalized_register(rd);
-    let imm = imm.parse::<i32>().unwrap() as u32;
-    let operands = &[rd, imm];
-    (instruction.assembler)(operands)
-  } else {
-    panic!("Invalid operands to instruction {0}: {operands:?}", instruction.name);
+/// an immediate operand of an instruction, see [`parse_immediate`],
+/// in relocatable objects relocation operators record a relocation if their argument is only known when linking,
+/// `store` selects the S-type relocations for `%lo` and `%pcrel_lo`
+fn immediate(imm: &str, context: &Context, store: bool) -> Result<u32, ErrorKind> {
+  if context.relocatable.is_none() {
+    return parse_immediate(imm, context);
   }
+  let (lo, pcrel_lo) = match store {
+    true => (RelocationKind::Lo12S, RelocationKind::PcrelLo12S),
+    false => (RelocationKind::Lo12I, RelocationKind::PcrelLo12I),
+  };
+  let relocated = match split_relocation(imm) {
+    Some(("hi", argument)) => context.relocate(argument, RelocationKind::Hi20),
+    Some(("lo", argument)) => context.relocate(argument, lo),
+    Some(("pcrel_hi", argument)) => context.relocate(argument, RelocationKind::PcrelHi20),
+    Some(("pcrel_lo", label)) => context.relocate_pcrel_lo(label, pcrel_lo),
+    // in the first pass symbols defined later are not known to be constants yet
+    _ if context.relocatable(imm, false).is_some() && context.strict => return Err(ErrorKind::NotRelocatable(imm.to_owned())),
+    _ => false,
+  };
+  if relocated { Ok(0) } else { parse_immediate(imm, context) }
 }
 
-fn assemble_normalized_FENCE(operands: &[&str]) -> u32 {
-  let instruction = INSTRUCTION_FENCE;
-  if let [rd, rs1, fm, pred, succ] = operands[..5] {
-    let rd = parse_normalized_register(rd);
-    let rs1 = parse_normalized_register(rs1);
-    let fm = fm.parse::<u32>().unwrap();
-    let pred = pred.parse::<u32>().unwrap();
-    let succ = succ.parse::<u32>().unwrap();
-    let operands = &[rd, rs1, fm, pred, succ];
-    (instruction.assembler)(operands)
-  } else {
-    panic!("Invalid operands to instruction {0}: {operands:?}", instruction.name);
+/// an offset operand of a branch or jump, see [`parse_offset`],
+/// in relocatable objects targets only known when linking record a relocation of `kind`
+fn offset_to(offset: &str, context: &Context, kind: RelocationKind) -> Result<u32, ErrorKind> {
+  if context.relocate(offset, kind) { Ok(0) } else { parse_offset(offset, context) }
+}
+
+/// predecessor or successor set of a fence, e.g. `rw` or `iorw`
+fn parse_fence_set(set: &str) -> Result<u32, ErrorKind> {
+  set.chars().try_fold(0, |bits, c| Ok(bits | match c {
+    'i' => 0b1000,
+    'o' => 0b0100,
+    'r' => 0b0010,
+    'w' => 0b0001,
+    _ => return Err(ErrorKind::InvalidOperand { operand: set.to_owned(), expected: "a subset of `iorw`" }),
+  }))
+}
+
+/// a register operand, see [`parse_register`]
+fn register(register: &str) -> Result<u32, ErrorKind> {
+  parse_register(register).ok_or_else(|| ErrorKind::UnknownRegister(register.to_owned()))
+}
+
+/// a CSR operand, by name like `mstatus` or by number
+fn csr(csr: &str, context: &Context) -> Result<u32, ErrorKind> {
+  match parse_csr(csr) {
+    Some(number) => Ok(number),
+    None => parse_immediate(csr, context),
   }
 }
 
-pub fn assemble_program(program: Vec<&str>) -> Vec<u32> {
-  program.iter().map(|instruction| assemble_normalized(instruction)).collect()
+/// the bytes of floating-point literals like `1.5`, `-2e-3`, `inf` or `nan`
+fn floats<E>(operands: &[&str], bytes: impl Fn(&str) -> Result<Vec<u8>, E>) -> Result<Vec<u8>, ErrorKind> {
+  let mut data = vec![];
+  for operand in operands {
+    match bytes(operand) {
+      Ok(bytes) => data.extend(bytes),
+      Err(_) => return Err(ErrorKind::InvalidOperand { operand: operand.to_string(), expected: "a floating-point number" }),
+    }
+  }
+  Ok(data)
 }
 
-fn parse_normalized_register(register: &str) -> u32 {
-  register[1..].parse().expect("could not parse register {register}")
+/// a floating-point register operand, see [`parse_float_register`]
+fn float_register(register: &str) -> Result<u32, ErrorKind> {
+  parse_float_register(register).ok_or_else(|| ErrorKind::UnknownRegister(register.to_owned()))
 }
 
-fn _parse_immediate(_imm: &str) -> u32 {
-  1
+/// an optional rounding mode operand such as `rtz`, `dyn` if omitted
+fn rounding_mode(rm: &[&str]) -> Result<u32, ErrorKind> {
+  let Some(rm) = rm.first() else {
+    return Ok(DYN);
+  };
+  match ROUNDING_MODE_NAMES.iter().position(|name| !name.is_empty() && name == rm) {
+    Some(mode) => Ok(mode as u32),
+    None => Err(ErrorKind::InvalidOperand { operand: rm.to_string(), expected: "a rounding mode" }),
+  }
+}
+
+/// split `imm(rs1)` into `imm` and `rs1`, an omitted offset as in `(rs1)` is zero
+fn split_address(address: &str) -> Result<(&str, &str), ErrorKind> {
+  let Some((imm, rs1)) = address.strip_suffix(')').and_then(|address| address.rsplit_once('(')) else {
+    return Err(ErrorKind::InvalidOperand { operand: address.to_owned(), expected: "`offset(register)`" });
+  };
+  let imm = imm.trim();
+  Ok((if imm.is_empty() { "0" } else { imm }, rs1.trim()))
+}
+
+/// the register of the `(rs1)` operand of atomics, which may be written with a zero offset as `0(rs1)`
+fn atomic_address(address: &str, context: &Context) -> Result<u32, ErrorKind> {
+  let (imm, rs1) = split_address(address)?;
+  if parse_immediate(imm, context)? != 0 {
+    return Err(ErrorKind::InvalidOperand { operand: address.to_owned(), expected: "`(register)`" });
+  }
+  register(rs1)
+}
+
+/// split the mnemonic of an atomic instruction into the instruction and the `aq` and `rl` bits of its suffix,
+/// other mnemonics are returned unchanged
+fn split_ordering(mnemonic: &str) -> (&str, u32) {
+  for (suffix, aqrl) in [(".aqrl", AQ | RL), (".aq", AQ), (".rl", RL)] {
+    if let Some(base) = mnemonic.strip_suffix(suffix) {
+      if let Some((_, Format::AMO | Format::LR)) = lookup_instruction(base) {
+        return (base, aqrl);
+      }
+    }
+  }
+  (mnemonic, 0)
+}
+
+/// the value of an expression as a 32-bit word, either signed or unsigned, see [`evaluate`]
+pub fn parse_immediate(imm: &str, context: &Context) -> Result<u32, ErrorKind> {
+  parse_integer(imm, context, 4)
 }
 
-// fn parse_program(program: &str) -> Vec<(Instruction, &[u32])> {
-//   program.lines().map(|x| x.trim()).filter(|x| !x.is_empty()).map(parse_instruction).collect()
-// }
+/// an expression that fits into `size` bytes, either signed or unsigned
+fn parse_integer(imm: &str, context: &Context, size: usize) -> Result<u32, ErrorKind> {
+  let value = evaluate(imm, context)?;
+  let bits = 8 * size as u32;
+  let (min, max) = (-(1_i64 << (bits - 1)), (1_i64 << bits) - 1);
+  if (min..=max).contains(&value) {
+    Ok(value as u32)
+  } else {
+    Err(ErrorKind::ImmediateOutOfRange { value, min, max })
+  }
+}
 
-// fn parse_instruction(instruction: &str) -> Vec<(Instruction, &[u32])> {
-//   vec![INSTRUCTION_ADD]
-// }
+/// a constant offset, or an address referring to a symbol, which is made relative to `pc`
+pub fn parse_offset(offset: &str, context: &Context) -> Result<u32, ErrorKind> {
+  match constant(offset) {
+    Some(_) => parse_immediate(offset, context),
+    None => Ok(parse_immediate(offset, context)?.wrapping_sub(context.pc)),
+  }
+}
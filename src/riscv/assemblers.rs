@@ -1,8 +1,32 @@
 use super::slices::*;
+use super::error::ErrorKind;
 
-pub type Assembler = &'static dyn Fn(&[u32]) -> u32;
+/// encodes the operands of an instruction, checking that immediates fit their field
+pub type Assembler = &'static dyn Fn(&[u32]) -> Result<u32, ErrorKind>;
 
-const fn R_TYPE_ASSEMBLER(funct3: u32, funct7: u32) -> impl Fn(&[u32]) -> u32 {
+/// check that `imm` is a `bits` wide two's complement value and a multiple of `alignment`
+pub fn check_signed(imm: u32, bits: u32, alignment: u32) -> Result<u32, ErrorKind> {
+  let value = imm as i32 as i64;
+  let (min, max) = (-(1 << (bits - 1)), (1 << (bits - 1)) - 1);
+  if !(min..=max).contains(&value) {
+    return Err(ErrorKind::ImmediateOutOfRange { value, min, max: max & !(alignment as i64 - 1) });
+  }
+  if value % alignment as i64 != 0 {
+    return Err(ErrorKind::MisalignedImmediate { value, alignment });
+  }
+  Ok(imm)
+}
+
+/// check that `imm` is a `bits` wide unsigned value
+pub fn check_unsigned(imm: u32, bits: u32) -> Result<u32, ErrorKind> {
+  let max = (1 << bits) - 1;
+  if imm > max {
+    return Err(ErrorKind::ImmediateOutOfRange { value: imm as i32 as i64, min: 0, max: max as i64 });
+  }
+  Ok(imm)
+}
+
+const fn R_TYPE_ASSEMBLER(funct3: u32, funct7: u32) -> impl Fn(&[u32]) -> Result<u32, ErrorKind> {
   move |operands| {
     if let [rd, rs1, rs2, ..] = *operands {
       let mut instruction = 0;
@@ -12,79 +36,85 @@ const fn R_TYPE_ASSEMBLER(funct3: u32, funct7: u32) -> impl Fn(&[u32]) -> u32 {
       instruction = set_rd_bits(instruction, rd);
       instruction = set_rs1_bits(instruction, rs1);
       instruction = set_rs2_bits(instruction, rs2);
-      instruction
+      Ok(instruction)
     } else {
       panic!("Invalid operands to R_type instruction {operands:?}");
     }
   }
 }
 
-const fn I_TYPE_ASSEMBLER(opcode: u32, funct3: u32) -> impl Fn(&[u32]) -> u32 {
+const fn I_TYPE_ASSEMBLER(opcode: u32, funct3: u32) -> impl Fn(&[u32]) -> Result<u32, ErrorKind> {
   move |operands| {
     if let [rd, rs1, imm, ..] = *operands {
+      let imm = check_signed(imm, 12, 1)?;
       let mut instruction = 0;
       instruction = set_opcode_bits(instruction, opcode);
       instruction = set_funct3_bits(instruction, funct3);
       instruction = set_rd_bits(instruction, rd);
       instruction = set_rs1_bits(instruction, rs1);
       instruction = set_I_imm_bits(instruction, imm);
-      instruction
+      Ok(instruction)
     } else {
       panic!("Invalid operands to I_type instruction {operands:?}");
     }
   }
 }
 
-const fn S_TYPE_ASSEMBLER(funct3: u32) -> impl Fn(&[u32]) -> u32 {
+const fn S_TYPE_ASSEMBLER(opcode: u32, funct3: u32) -> impl Fn(&[u32]) -> Result<u32, ErrorKind> {
   move |operands: &[u32]| {
     // the order of rs2, rs1 is swapped in store instructions
     if let [rs2, rs1, imm, ..] = *operands {
+      let imm = check_signed(imm, 12, 1)?;
       let mut instruction = 0;
-      instruction = set_opcode_bits(instruction, OPCODE_STORE);
+      instruction = set_opcode_bits(instruction, opcode);
       instruction = set_funct3_bits(instruction, funct3);
       instruction = set_rs1_bits(instruction, rs1);
       instruction = set_rs2_bits(instruction, rs2);
       instruction = set_S_imm_bits(instruction, imm);
-      instruction
+      Ok(instruction)
     } else {
       panic!("Invalid operands to S_type instruction: {operands:?}");
     }
   }
 }
 
-const fn B_TYPE_ASSEMBLER(funct3: u32) -> impl Fn(&[u32]) -> u32 {
+const fn B_TYPE_ASSEMBLER(funct3: u32) -> impl Fn(&[u32]) -> Result<u32, ErrorKind> {
   move |operands| {
     if let [rs1, rs2, imm, ..] = *operands {
+      let imm = check_signed(imm, 13, 2)?;
       let mut instruction = 0;
       instruction = set_opcode_bits(instruction, OPCODE_BRANCH);
       instruction = set_funct3_bits(instruction, funct3);
       instruction = set_rs1_bits(instruction, rs1);
       instruction = set_rs2_bits(instruction, rs2);
       instruction = set_B_imm_bits(instruction, imm);
-      instruction
+      Ok(instruction)
     } else {
       panic!("Invalid operands to B_type instruction: {operands:?}");
     }
   }
 }
 
-const fn U_TYPE_ASSEMBLER(opcode: u32) -> impl Fn(&[u32]) -> u32 {
+const fn U_TYPE_ASSEMBLER(opcode: u32) -> impl Fn(&[u32]) -> Result<u32, ErrorKind> {
   move |operands| {
     if let [rd, imm, ..] = *operands {
+      // the upper 20 bits are given as an unshifted number
+      let imm = check_unsigned(imm, 20)?;
       let mut instruction = 0;
       instruction = set_opcode_bits(instruction, opcode);
       instruction = set_rd_bits(instruction, rd);
       instruction = set_U_imm_bits(instruction, imm);
-      instruction
+      Ok(instruction)
     } else {
       panic!("Invalid operands to U_type instruction: {operands:?}");
     }
   }
 }
 
-const fn SHIFT_IMM_ASSEMBLER(opcode: u32, funct3: u32, funct7: u32) -> impl Fn(&[u32]) -> u32 {
+const fn SHIFT_IMM_ASSEMBLER(opcode: u32, funct3: u32, funct7: u32) -> impl Fn(&[u32]) -> Result<u32, ErrorKind> {
   move |operands| {
     if let [rd, rs1, imm, ..] = *operands {
+      let imm = check_unsigned(imm, 5)?;
       let mut instruction = 0;
       instruction = set_opcode_bits(instruction, opcode);
       instruction = set_funct3_bits(instruction, funct3);
@@ -93,24 +123,83 @@ const fn SHIFT_IMM_ASSEMBLER(opcode: u32, funct3: u32, funct7: u32) -> impl Fn(&
       instruction = set_I_imm_bits(instruction, imm);
       // set the funct7 after the immediate to overwrite the upper bits of immediate
       instruction = set_funct7_bits(instruction, funct7);
-      instruction
+      Ok(instruction)
     } else {
       panic!("Invalid operands to shift instruction {operands:?}");
     }
   }
 }
 
+const fn AMO_ASSEMBLER(funct5: u32) -> impl Fn(&[u32]) -> Result<u32, ErrorKind> {
+  move |operands| {
+    // `lr.w` has no rs2 and is passed 0 for it
+    if let [rd, rs1, rs2, aqrl, ..] = *operands {
+      let mut instruction = 0;
+      instruction = set_opcode_bits(instruction, OPCODE_AMO);
+      instruction = set_funct3_bits(instruction, FUNCT3_AMO_W);
+      instruction = set_funct5_bits(instruction, funct5);
+      instruction = set_aqrl_bits(instruction, aqrl);
+      instruction = set_rd_bits(instruction, rd);
+      instruction = set_rs1_bits(instruction, rs1);
+      instruction = set_rs2_bits(instruction, rs2);
+      Ok(instruction)
+    } else {
+      panic!("Invalid operands to atomic instruction {operands:?}");
+    }
+  }
+}
+
+/// floating-point instructions on the `OP-FP` opcode with operands [rd, rs1, rs2, rm],
+/// a fixed `rs2` or `funct3` replaces the operand for instructions which use the field to select the operation
+const fn FP_ASSEMBLER(funct5: u32, fmt: u32, fixed_rs2: Option<u32>, fixed_funct3: Option<u32>) -> impl Fn(&[u32]) -> Result<u32, ErrorKind> {
+  move |operands| {
+    if let [rd, rs1, rs2, rm, ..] = *operands {
+      let mut instruction = 0;
+      instruction = set_opcode_bits(instruction, OPCODE_OP_FP);
+      instruction = set_funct3_bits(instruction, fixed_funct3.unwrap_or(rm));
+      instruction = set_funct5_bits(instruction, funct5);
+      instruction = set_fmt_bits(instruction, fmt);
+      instruction = set_rd_bits(instruction, rd);
+      instruction = set_rs1_bits(instruction, rs1);
+      instruction = set_rs2_bits(instruction, fixed_rs2.unwrap_or(rs2));
+      Ok(instruction)
+    } else {
+      panic!("Invalid operands to floating-point instruction {operands:?}");
+    }
+  }
+}
+
+/// fused multiply-add instructions with operands [rd, rs1, rs2, rs3, rm]
+const fn R4_TYPE_ASSEMBLER(opcode: u32, fmt: u32) -> impl Fn(&[u32]) -> Result<u32, ErrorKind> {
+  move |operands| {
+    if let [rd, rs1, rs2, rs3, rm, ..] = *operands {
+      let mut instruction = 0;
+      instruction = set_opcode_bits(instruction, opcode);
+      instruction = set_funct3_bits(instruction, rm);
+      instruction = set_fmt_bits(instruction, fmt);
+      instruction = set_rd_bits(instruction, rd);
+      instruction = set_rs1_bits(instruction, rs1);
+      instruction = set_rs2_bits(instruction, rs2);
+      instruction = set_rs3_bits(instruction, rs3);
+      Ok(instruction)
+    } else {
+      panic!("Invalid operands to R4_type instruction {operands:?}");
+    }
+  }
+}
+
 pub const LUI_ASSEMBLER: Assembler = &U_TYPE_ASSEMBLER(OPCODE_LUI);
 
 pub const AUIPC_ASSEMBLER: Assembler = &U_TYPE_ASSEMBLER(OPCODE_AUIPC);
 
-pub fn JAL_ASSEMBLER(operands: &[u32]) -> u32 {
+pub fn JAL_ASSEMBLER(operands: &[u32]) -> Result<u32, ErrorKind> {
   if let [rd, imm, ..] = *operands {
+    let imm = check_signed(imm, 21, 2)?;
     let mut instruction = 0;
     instruction = set_opcode_bits(instruction, OPCODE_JAL);
     instruction = set_rd_bits(instruction, rd);
     instruction = set_J_imm_bits(instruction, imm);
-    instruction
+    Ok(instruction)
   } else {
     panic!("Invalid operands to JAL instruction: {operands:?}");
   }
@@ -128,9 +217,9 @@ pub const LH_ASSEMBLER: Assembler = &I_TYPE_ASSEMBLER(OPCODE_LOAD, FUNCT3_LH);
 pub const LW_ASSEMBLER: Assembler = &I_TYPE_ASSEMBLER(OPCODE_LOAD, FUNCT3_LW);
 pub const LBU_ASSEMBLER: Assembler = &I_TYPE_ASSEMBLER(OPCODE_LOAD, FUNCT3_LBU);
 pub const LHU_ASSEMBLER: Assembler = &I_TYPE_ASSEMBLER(OPCODE_LOAD, FUNCT3_LHU);
-pub const SB_ASSEMBLER: Assembler = &S_TYPE_ASSEMBLER(FUNCT3_SB);
-pub const SH_ASSEMBLER: Assembler = &S_TYPE_ASSEMBLER(FUNCT3_SH);
-pub const SW_ASSEMBLER: Assembler = &S_TYPE_ASSEMBLER(FUNCT3_SW);
+pub const SB_ASSEMBLER: Assembler = &S_TYPE_ASSEMBLER(OPCODE_STORE, FUNCT3_SB);
+pub const SH_ASSEMBLER: Assembler = &S_TYPE_ASSEMBLER(OPCODE_STORE, FUNCT3_SH);
+pub const SW_ASSEMBLER: Assembler = &S_TYPE_ASSEMBLER(OPCODE_STORE, FUNCT3_SW);
 pub const ADDI_ASSEMBLER: Assembler = &I_TYPE_ASSEMBLER(OPCODE_OP_IMM, FUNCT3_ADDI);
 pub const SLTI_ASSEMBLER: Assembler = &I_TYPE_ASSEMBLER(OPCODE_OP_IMM, FUNCT3_SLTI);
 pub const SLTIU_ASSEMBLER: Assembler = &I_TYPE_ASSEMBLER(OPCODE_OP_IMM, FUNCT3_SLTIU);
@@ -151,36 +240,84 @@ pub const SRA_ASSEMBLER: Assembler = &R_TYPE_ASSEMBLER(FUNCT3_SRA, FUNCT7_SRA);
 pub const OR_ASSEMBLER: Assembler = &R_TYPE_ASSEMBLER(FUNCT3_OR, FUNCT7_OR); 
 pub const AND_ASSEMBLER: Assembler = &R_TYPE_ASSEMBLER(FUNCT3_AND, FUNCT7_AND);
 
-pub fn FENCE_ASSEMBLER(operands: &[u32]) -> u32 {
+pub fn FENCE_ASSEMBLER(operands: &[u32]) -> Result<u32, ErrorKind> {
   if let [rd, rs1, fm, pred, succ, ..] = *operands {
-    let funct12 = (fm << 8) | (pred << 4) | succ;
+    let funct12 = (check_unsigned(fm, 4)? << 8) | (check_unsigned(pred, 4)? << 4) | check_unsigned(succ, 4)?;
     let mut instruction = 0;
     instruction = set_opcode_bits(instruction, OPCODE_MISC_MEM);
     instruction = set_rd_bits(instruction, rd);
     instruction = set_rs1_bits(instruction, rs1);
     instruction = set_funct12_bits(instruction, funct12);
-    instruction
+    Ok(instruction)
   } else {
     panic!("Invalid operands to FENCE instruction: {operands:?}");
   }
 }
 
-pub fn FENCE_TSO_ASSEMBLER(_operands: &[u32]) -> u32 {
-  INSTRUCTION_FENCE_TSO_VALUE
+pub fn FENCE_TSO_ASSEMBLER(_operands: &[u32]) -> Result<u32, ErrorKind> {
+  Ok(INSTRUCTION_FENCE_TSO_VALUE)
+}
+
+pub fn PAUSE_ASSEMBLER(_operands: &[u32]) -> Result<u32, ErrorKind> {
+  Ok(INSTRUCTION_PAUSE_VALUE)
+}
+
+pub fn ECALL_ASSEMBLER(_operands: &[u32]) -> Result<u32, ErrorKind> {
+  Ok(INSTRUCTION_ECALL_VALUE)
+}
+
+pub fn EBREAK_ASSEMBLER(_operands: &[u32]) -> Result<u32, ErrorKind> {
+  Ok(INSTRUCTION_EBREAK_VALUE)
+}
+
+pub fn SRET_ASSEMBLER(_operands: &[u32]) -> Result<u32, ErrorKind> {
+  Ok(INSTRUCTION_SRET_VALUE)
 }
 
-pub fn PAUSE_ASSEMBLER(_operands: &[u32]) -> u32 {
-  INSTRUCTION_PAUSE_VALUE
+pub fn MRET_ASSEMBLER(_operands: &[u32]) -> Result<u32, ErrorKind> {
+  Ok(INSTRUCTION_MRET_VALUE)
 }
 
-pub fn ECALL_ASSEMBLER(_operands: &[u32]) -> u32 {
-  INSTRUCTION_ECALL_VALUE
+/// `[rs1, rs2]`
+pub fn SFENCE_VMA_ASSEMBLER(operands: &[u32]) -> Result<u32, ErrorKind> {
+  if let [rs1, rs2] = *operands {
+    let mut instruction = 0;
+    instruction = set_opcode_bits(instruction, OPCODE_SYSTEM);
+    instruction = set_funct7_bits(instruction, FUNCT7_SFENCE_VMA);
+    instruction = set_rs1_bits(instruction, rs1);
+    instruction = set_rs2_bits(instruction, rs2);
+    Ok(instruction)
+  } else {
+    panic!("Invalid operands to sfence.vma {operands:?}");
+  }
 }
 
-pub fn EBREAK_ASSEMBLER(_operands: &[u32]) -> u32 {
-  INSTRUCTION_EBREAK_VALUE
+/// `[rd, csr, rs1]`, where the immediate forms encode a 5-bit unsigned immediate in place of rs1
+const fn CSR_ASSEMBLER(funct3: u32) -> impl Fn(&[u32]) -> Result<u32, ErrorKind> {
+  move |operands| {
+    if let [rd, csr, rs1, ..] = *operands {
+      let csr = check_unsigned(csr, 12)?;
+      let rs1 = check_unsigned(rs1, 5)?;
+      let mut instruction = 0;
+      instruction = set_opcode_bits(instruction, OPCODE_SYSTEM);
+      instruction = set_funct3_bits(instruction, funct3);
+      instruction = set_rd_bits(instruction, rd);
+      instruction = set_rs1_bits(instruction, rs1);
+      instruction = set_funct12_bits(instruction, csr);
+      Ok(instruction)
+    } else {
+      panic!("Invalid operands to CSR instruction: {operands:?}");
+    }
+  }
 }
 
+pub const CSRRW_ASSEMBLER: Assembler = &CSR_ASSEMBLER(FUNCT3_CSRRW);
+pub const CSRRS_ASSEMBLER: Assembler = &CSR_ASSEMBLER(FUNCT3_CSRRS);
+pub const CSRRC_ASSEMBLER: Assembler = &CSR_ASSEMBLER(FUNCT3_CSRRC);
+pub const CSRRWI_ASSEMBLER: Assembler = &CSR_ASSEMBLER(FUNCT3_CSRRWI);
+pub const CSRRSI_ASSEMBLER: Assembler = &CSR_ASSEMBLER(FUNCT3_CSRRSI);
+pub const CSRRCI_ASSEMBLER: Assembler = &CSR_ASSEMBLER(FUNCT3_CSRRCI);
+
 pub const MUL_ASSEMBLER: Assembler = &R_TYPE_ASSEMBLER(FUNCT3_MUL, FUNCT7_MUL);
 pub const MULH_ASSEMBLER: Assembler = &R_TYPE_ASSEMBLER(FUNCT3_MULH, FUNCT7_MULH);
 pub const MULHSU_ASSEMBLER: Assembler = &R_TYPE_ASSEMBLER(FUNCT3_MULHSU, FUNCT7_MULHSU);
@@ -190,7 +327,74 @@ pub const DIVU_ASSEMBLER: Assembler = &R_TYPE_ASSEMBLER(FUNCT3_DIVU, FUNCT7_DIVU
 pub const REM_ASSEMBLER: Assembler = &R_TYPE_ASSEMBLER(FUNCT3_REM, FUNCT7_REM);
 pub const REMU_ASSEMBLER: Assembler = &R_TYPE_ASSEMBLER(FUNCT3_REMU, FUNCT7_REMU);
 
+pub const LR_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_LR);
+pub const SC_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_SC);
+pub const AMOSWAP_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_AMOSWAP);
+pub const AMOADD_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_AMOADD);
+pub const AMOXOR_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_AMOXOR);
+pub const AMOAND_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_AMOAND);
+pub const AMOOR_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_AMOOR);
+pub const AMOMIN_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_AMOMIN);
+pub const AMOMAX_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_AMOMAX);
+pub const AMOMINU_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_AMOMINU);
+pub const AMOMAXU_W_ASSEMBLER: Assembler = &AMO_ASSEMBLER(FUNCT5_AMOMAXU);
+
+pub const FLW_ASSEMBLER: Assembler = &I_TYPE_ASSEMBLER(OPCODE_LOAD_FP, FUNCT3_FLW);
+pub const FSW_ASSEMBLER: Assembler = &S_TYPE_ASSEMBLER(OPCODE_STORE_FP, FUNCT3_FSW);
+pub const FMADD_S_ASSEMBLER: Assembler = &R4_TYPE_ASSEMBLER(OPCODE_MADD, FMT_S);
+pub const FMSUB_S_ASSEMBLER: Assembler = &R4_TYPE_ASSEMBLER(OPCODE_MSUB, FMT_S);
+pub const FNMSUB_S_ASSEMBLER: Assembler = &R4_TYPE_ASSEMBLER(OPCODE_NMSUB, FMT_S);
+pub const FNMADD_S_ASSEMBLER: Assembler = &R4_TYPE_ASSEMBLER(OPCODE_NMADD, FMT_S);
+pub const FADD_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FADD, FMT_S, None, None);
+pub const FSUB_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FSUB, FMT_S, None, None);
+pub const FMUL_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FMUL, FMT_S, None, None);
+pub const FDIV_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FDIV, FMT_S, None, None);
+pub const FSQRT_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FSQRT, FMT_S, Some(0), None);
+pub const FSGNJ_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FSGNJ, FMT_S, None, Some(FUNCT3_FSGNJ));
+pub const FSGNJN_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FSGNJ, FMT_S, None, Some(FUNCT3_FSGNJN));
+pub const FSGNJX_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FSGNJ, FMT_S, None, Some(FUNCT3_FSGNJX));
+pub const FMIN_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FMIN_FMAX, FMT_S, None, Some(FUNCT3_FMIN));
+pub const FMAX_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FMIN_FMAX, FMT_S, None, Some(FUNCT3_FMAX));
+pub const FCVT_W_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCVT_W_F, FMT_S, Some(RS2_FCVT_W), None);
+pub const FCVT_WU_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCVT_W_F, FMT_S, Some(RS2_FCVT_WU), None);
+pub const FMV_X_W_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FMV_X_W_FCLASS, FMT_S, Some(0), Some(FUNCT3_FMV_X_W));
+pub const FEQ_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCMP, FMT_S, None, Some(FUNCT3_FEQ));
+pub const FLT_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCMP, FMT_S, None, Some(FUNCT3_FLT));
+pub const FLE_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCMP, FMT_S, None, Some(FUNCT3_FLE));
+pub const FCLASS_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FMV_X_W_FCLASS, FMT_S, Some(0), Some(FUNCT3_FCLASS));
+pub const FCVT_S_W_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCVT_F_W, FMT_S, Some(RS2_FCVT_W), None);
+pub const FCVT_S_WU_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCVT_F_W, FMT_S, Some(RS2_FCVT_WU), None);
+pub const FMV_W_X_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FMV_W_X, FMT_S, Some(0), Some(0));
+
+pub const FLD_ASSEMBLER: Assembler = &I_TYPE_ASSEMBLER(OPCODE_LOAD_FP, FUNCT3_FLD);
+pub const FSD_ASSEMBLER: Assembler = &S_TYPE_ASSEMBLER(OPCODE_STORE_FP, FUNCT3_FSD);
+pub const FMADD_D_ASSEMBLER: Assembler = &R4_TYPE_ASSEMBLER(OPCODE_MADD, FMT_D);
+pub const FMSUB_D_ASSEMBLER: Assembler = &R4_TYPE_ASSEMBLER(OPCODE_MSUB, FMT_D);
+pub const FNMSUB_D_ASSEMBLER: Assembler = &R4_TYPE_ASSEMBLER(OPCODE_NMSUB, FMT_D);
+pub const FNMADD_D_ASSEMBLER: Assembler = &R4_TYPE_ASSEMBLER(OPCODE_NMADD, FMT_D);
+pub const FADD_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FADD, FMT_D, None, None);
+pub const FSUB_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FSUB, FMT_D, None, None);
+pub const FMUL_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FMUL, FMT_D, None, None);
+pub const FDIV_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FDIV, FMT_D, None, None);
+pub const FSQRT_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FSQRT, FMT_D, Some(0), None);
+pub const FSGNJ_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FSGNJ, FMT_D, None, Some(FUNCT3_FSGNJ));
+pub const FSGNJN_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FSGNJ, FMT_D, None, Some(FUNCT3_FSGNJN));
+pub const FSGNJX_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FSGNJ, FMT_D, None, Some(FUNCT3_FSGNJX));
+pub const FMIN_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FMIN_FMAX, FMT_D, None, Some(FUNCT3_FMIN));
+pub const FMAX_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FMIN_FMAX, FMT_D, None, Some(FUNCT3_FMAX));
+// the source format of conversions between precisions is in rs2,
+// exact conversions have no rounding mode and encode 0 for it
+pub const FCVT_S_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCVT_F_F, FMT_S, Some(FMT_D), None);
+pub const FCVT_D_S_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCVT_F_F, FMT_D, Some(FMT_S), Some(0));
+pub const FEQ_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCMP, FMT_D, None, Some(FUNCT3_FEQ));
+pub const FLT_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCMP, FMT_D, None, Some(FUNCT3_FLT));
+pub const FLE_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCMP, FMT_D, None, Some(FUNCT3_FLE));
+pub const FCLASS_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FMV_X_W_FCLASS, FMT_D, Some(0), Some(FUNCT3_FCLASS));
+pub const FCVT_W_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCVT_W_F, FMT_D, Some(RS2_FCVT_W), None);
+pub const FCVT_WU_D_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCVT_W_F, FMT_D, Some(RS2_FCVT_WU), None);
+pub const FCVT_D_W_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCVT_F_W, FMT_D, Some(RS2_FCVT_W), Some(0));
+pub const FCVT_D_WU_ASSEMBLER: Assembler = &FP_ASSEMBLER(FUNCT5_FCVT_F_W, FMT_D, Some(RS2_FCVT_WU), Some(0));
 
-pub fn UNDEF_ASSEMBLER(_operands: &[u32]) -> u32 {
+pub fn UNDEF_ASSEMBLER(_operands: &[u32]) -> Result<u32, ErrorKind> {
   panic!("Invalid undef")
 }
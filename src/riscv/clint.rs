@@ -0,0 +1,64 @@
+/// the base address of the CLINT, above the end of memory
+pub const CLINT_BASE: u32 = 0x0200_0000;
+pub const CLINT_SIZE: u32 = 0x1_0000;
+/// the offsets of the registers of the only hart, with the layout of the SiFive CLINT
+pub const CLINT_MSIP: u32 = 0x0;
+pub const CLINT_MTIMECMP: u32 = 0x4000;
+pub const CLINT_MTIME: u32 = 0xbff8;
+
+/// the core-local interruptor, raising the machine software interrupt through `msip`
+/// and the machine timer interrupt while `mtime` is at least `mtimecmp`,
+/// `mtime` advances by one for each retired instruction so that runs are reproducible
+#[derive(Debug)]
+pub struct Clint {
+  pub msip: bool,
+  pub mtimecmp: u64,
+  pub mtime: u64,
+}
+
+impl Default for Clint {
+  fn default() -> Self {
+    Self {
+      msip: false,
+      mtimecmp: u64::MAX,
+      mtime: 0,
+    }
+  }
+}
+
+impl Clint {
+  /// whether `address` is mapped to the CLINT
+  pub fn contains(address: u32) -> bool {
+    (CLINT_BASE..CLINT_BASE + CLINT_SIZE).contains(&address)
+  }
+
+  pub fn timer_pending(&self) -> bool {
+    self.mtime >= self.mtimecmp
+  }
+
+  /// reserved offsets read as zero
+  pub fn load_byte(&self, offset: u32) -> u8 {
+    let (value, start) = match offset {
+      CLINT_MSIP..=0x3 => (self.msip as u64, CLINT_MSIP),
+      CLINT_MTIMECMP..=0x4007 => (self.mtimecmp, CLINT_MTIMECMP),
+      CLINT_MTIME..=0xbfff => (self.mtime, CLINT_MTIME),
+      _ => return 0,
+    };
+    (value >> (8 * (offset - start))) as u8
+  }
+
+  /// writes to reserved offsets and bits are ignored
+  pub fn store_byte(&mut self, offset: u32, value: u8) {
+    let (register, start) = match offset {
+      CLINT_MSIP => {
+        self.msip = value & 1 != 0;
+        return;
+      }
+      CLINT_MTIMECMP..=0x4007 => (&mut self.mtimecmp, CLINT_MTIMECMP),
+      CLINT_MTIME..=0xbfff => (&mut self.mtime, CLINT_MTIME),
+      _ => return,
+    };
+    let shift = 8 * (offset - start);
+    *register = *register & !(0xff << shift) | (value as u64) << shift;
+  }
+}
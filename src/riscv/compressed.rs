@@ -0,0 +1,635 @@
+use super::instructions::*;
+use super::slices::sign_extend;
+use super::assemblers::{check_signed, check_unsigned};
+use super::disassemblers::{FLOAT_REGISTER_NAMES, REGISTER_NAMES};
+use super::error::ErrorKind;
+
+/// whether an instruction is 16 bits long, which is the case unless its lowest two bits are set
+pub fn is_compressed(instruction: u32) -> bool {
+  instruction & 0b11 != 0b11
+}
+
+/// an operand of a compressed instruction in assembly order, named after the field it is encoded in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+  /// an integer register `x0` to `x31` in bits 11 to 7, `rd` or `rs1`
+  Rd,
+  /// an integer register `x0` to `x31` in bits 6 to 2
+  Rs2,
+  /// an integer register `x8` to `x15` in bits 9 to 7, `rd'` or `rs1'`
+  Rs1Prime,
+  /// an integer register `x8` to `x15` in bits 4 to 2, `rd'` of loads or `rs2'`
+  Rs2Prime,
+  /// a floating-point register in bits 11 to 7
+  FloatRd,
+  /// a floating-point register in bits 6 to 2
+  FloatRs2,
+  /// a floating-point register `f8` to `f15` in bits 4 to 2
+  FloatRs2Prime,
+  /// `sp`, which is written out but implied by the encoding
+  Sp,
+  Immediate,
+  /// the 20-bit immediate of `lui`, of which `c.lui` encodes the sign extended lowest 6 bits
+  UpperImmediate,
+  /// the offset of a branch or jump, which may be given as a label
+  Offset,
+  /// `offset(rs1')` of loads and stores
+  Memory,
+  /// `offset(sp)` of loads and stores relative to the stack pointer
+  StackMemory,
+}
+
+/// the registers and immediate of a compressed instruction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Fields {
+  /// the register in bits 11 to 7, or the one in bits 9 to 7 for instructions restricted to `x8` to `x15`
+  pub high: u32,
+  /// the register in bits 6 to 2, or the one in bits 4 to 2 for instructions restricted to `x8` to `x15`
+  pub low: u32,
+  /// sign extended for instructions with a signed immediate
+  pub imm: u32,
+}
+
+pub struct CompressedInstruction {
+  pub name: &'static str,
+  /// the bits that identify the instruction, which are at least its quadrant in bits 1 to 0 and funct3 in bits 15 to 13
+  pub mask: u16,
+  pub value: u16,
+  /// operand syntax for error messages
+  pub syntax: &'static str,
+  pub operands: &'static [Operand],
+  /// runs of immediate bits, each given by its highest instruction bit and the immediate bits placed from there downwards
+  pub immediate: &'static [(u32, &'static [u32])],
+  pub signed: bool,
+  /// whether the fields encode this instruction instead of a reserved encoding or another instruction,
+  /// with what an operand is expected to be otherwise
+  pub valid: fn(&Fields) -> bool,
+  pub requires: &'static str,
+  /// the base instruction it expands to and the operands for its assembler
+  pub expand: fn(&Fields) -> (Instruction, Vec<u32>),
+}
+
+impl CompressedInstruction {
+  /// the bits the immediate is made of, lowest and highest
+  fn immediate_bits(&self) -> (u32, u32) {
+    let bits = self.immediate.iter().flat_map(|(_, bits)| bits.iter().copied());
+    (bits.clone().min().unwrap_or(0), bits.max().unwrap_or(0))
+  }
+
+  fn get_immediate(&self, instruction: u16) -> u32 {
+    let mut imm = 0;
+    for (high, bits) in self.immediate {
+      for (position, bit) in (0..=*high).rev().zip(bits.iter()) {
+        imm |= (instruction as u32 >> position & 1) << bit;
+      }
+    }
+    if self.signed {
+      sign_extend(&mut imm, self.immediate_bits().1);
+    }
+    imm
+  }
+
+  /// the fields of an instruction that matches [`Self::mask`]
+  fn get_fields(&self, instruction: u16) -> Fields {
+    let bits = |high: u32, width: u32| instruction as u32 >> (high + 1 - width) & ((1 << width) - 1);
+    let mut fields = Fields { imm: self.get_immediate(instruction), ..Fields::default() };
+    for operand in self.operands {
+      match operand {
+        Operand::Rd | Operand::FloatRd => fields.high = bits(11, 5),
+        Operand::Rs2 | Operand::FloatRs2 => fields.low = bits(6, 5),
+        Operand::Rs1Prime | Operand::Memory => fields.high = 8 + bits(9, 3),
+        Operand::Rs2Prime | Operand::FloatRs2Prime => fields.low = 8 + bits(4, 3),
+        _ => {}
+      }
+    }
+    fields
+  }
+
+  /// encode the fields, checking that the immediate fits, registers restricted to `x8` to `x15`
+  /// and [`Self::valid`] are not checked
+  pub fn encode(&self, fields: &Fields) -> Result<u16, ErrorKind> {
+    let (lowest, highest) = self.immediate_bits();
+    let imm = match self.signed {
+      _ if self.immediate.is_empty() => 0,
+      true => check_signed(fields.imm, highest + 1, 1 << lowest)?,
+      false => {
+        let imm = check_unsigned(fields.imm, highest + 1)?;
+        if imm % (1 << lowest) != 0 {
+          return Err(ErrorKind::MisalignedImmediate { value: imm as i64, alignment: 1 << lowest });
+        }
+        imm
+      }
+    };
+    let mut instruction = self.value as u32;
+    for (high, bits) in self.immediate {
+      for (position, bit) in (0..=*high).rev().zip(bits.iter()) {
+        instruction |= (imm >> bit & 1) << position;
+      }
+    }
+    for operand in self.operands {
+      instruction |= match operand {
+        Operand::Rd | Operand::FloatRd => fields.high << 7,
+        Operand::Rs2 | Operand::FloatRs2 => fields.low << 2,
+        Operand::Rs1Prime | Operand::Memory => (fields.high & 0b111) << 7,
+        Operand::Rs2Prime | Operand::FloatRs2Prime => (fields.low & 0b111) << 2,
+        _ => 0,
+      };
+    }
+    Ok(instruction as u16)
+  }
+
+  /// the equivalent base instruction
+  pub fn expand(&self, fields: &Fields) -> u32 {
+    let (instruction, operands) = (self.expand)(fields);
+    (instruction.assembler)(&operands).expect("compressed instructions expand to valid base instructions")
+  }
+}
+
+fn always(_fields: &Fields) -> bool {
+  true
+}
+
+const FIELDS_MEMORY: [(u32, &[u32]); 2] = [(12, &[5, 4, 3]), (6, &[2, 6])];
+const FIELDS_MEMORY_DOUBLE: [(u32, &[u32]); 2] = [(12, &[5, 4, 3]), (6, &[7, 6])];
+const FIELDS_LOAD_SP: [(u32, &[u32]); 2] = [(12, &[5]), (6, &[4, 3, 2, 7, 6])];
+const FIELDS_LOAD_SP_DOUBLE: [(u32, &[u32]); 2] = [(12, &[5]), (6, &[4, 3, 8, 7, 6])];
+const FIELDS_STORE_SP: [(u32, &[u32]); 1] = [(12, &[5, 4, 3, 2, 7, 6])];
+const FIELDS_STORE_SP_DOUBLE: [(u32, &[u32]); 1] = [(12, &[5, 4, 3, 8, 7, 6])];
+/// 6-bit immediates and shift amounts
+const FIELDS_CI: [(u32, &[u32]); 2] = [(12, &[5]), (6, &[4, 3, 2, 1, 0])];
+const FIELDS_CB: [(u32, &[u32]); 2] = [(12, &[8, 4, 3]), (6, &[7, 6, 2, 1, 5])];
+const FIELDS_CJ: [(u32, &[u32]); 1] = [(12, &[11, 4, 9, 8, 10, 6, 7, 3, 2, 1, 5])];
+
+/// the compressed instructions of RV32C with those of F and D, encodings are matched in this order
+pub const COMPRESSED_INSTRUCTIONS: [CompressedInstruction; 35] = [
+  // quadrant 0
+  CompressedInstruction {
+    name: "c.addi4spn",
+    mask: 0xe003,
+    value: 0x0000,
+    syntax: "rd', sp, uimm",
+    operands: &[Operand::Rs2Prime, Operand::Sp, Operand::Immediate],
+    immediate: &[(12, &[5, 4, 9, 8, 7, 6, 2, 3])],
+    signed: false,
+    valid: |fields| fields.imm != 0,
+    requires: "a nonzero immediate",
+    expand: |fields| (INSTRUCTION_ADDI, vec![fields.low, 2, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.fld",
+    mask: 0xe003,
+    value: 0x2000,
+    syntax: "fd', offset(rs1')",
+    operands: &[Operand::FloatRs2Prime, Operand::Memory],
+    immediate: &FIELDS_MEMORY_DOUBLE,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_FLD, vec![fields.low, fields.high, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.lw",
+    mask: 0xe003,
+    value: 0x4000,
+    syntax: "rd', offset(rs1')",
+    operands: &[Operand::Rs2Prime, Operand::Memory],
+    immediate: &FIELDS_MEMORY,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_LW, vec![fields.low, fields.high, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.flw",
+    mask: 0xe003,
+    value: 0x6000,
+    syntax: "fd', offset(rs1')",
+    operands: &[Operand::FloatRs2Prime, Operand::Memory],
+    immediate: &FIELDS_MEMORY,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_FLW, vec![fields.low, fields.high, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.fsd",
+    mask: 0xe003,
+    value: 0xa000,
+    syntax: "fs2', offset(rs1')",
+    operands: &[Operand::FloatRs2Prime, Operand::Memory],
+    immediate: &FIELDS_MEMORY_DOUBLE,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_FSD, vec![fields.low, fields.high, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.sw",
+    mask: 0xe003,
+    value: 0xc000,
+    syntax: "rs2', offset(rs1')",
+    operands: &[Operand::Rs2Prime, Operand::Memory],
+    immediate: &FIELDS_MEMORY,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_SW, vec![fields.low, fields.high, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.fsw",
+    mask: 0xe003,
+    value: 0xe000,
+    syntax: "fs2', offset(rs1')",
+    operands: &[Operand::FloatRs2Prime, Operand::Memory],
+    immediate: &FIELDS_MEMORY,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_FSW, vec![fields.low, fields.high, fields.imm]),
+  },
+  // quadrant 1
+  CompressedInstruction {
+    name: "c.nop",
+    mask: 0xffff,
+    value: 0x0001,
+    syntax: "no operands",
+    operands: &[],
+    immediate: &[],
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |_| (INSTRUCTION_ADDI, vec![0, 0, 0]),
+  },
+  CompressedInstruction {
+    name: "c.addi",
+    mask: 0xe003,
+    value: 0x0001,
+    syntax: "rd, imm",
+    operands: &[Operand::Rd, Operand::Immediate],
+    immediate: &FIELDS_CI,
+    signed: true,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_ADDI, vec![fields.high, fields.high, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.jal",
+    mask: 0xe003,
+    value: 0x2001,
+    syntax: "offset",
+    operands: &[Operand::Offset],
+    immediate: &FIELDS_CJ,
+    signed: true,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_JAL, vec![1, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.li",
+    mask: 0xe003,
+    value: 0x4001,
+    syntax: "rd, imm",
+    operands: &[Operand::Rd, Operand::Immediate],
+    immediate: &FIELDS_CI,
+    signed: true,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_ADDI, vec![fields.high, 0, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.addi16sp",
+    mask: 0xef83,
+    value: 0x6101,
+    syntax: "sp, imm",
+    operands: &[Operand::Sp, Operand::Immediate],
+    immediate: &[(12, &[9]), (6, &[4, 6, 8, 7, 5])],
+    signed: true,
+    valid: |fields| fields.imm != 0,
+    requires: "a nonzero immediate",
+    expand: |fields| (INSTRUCTION_ADDI, vec![2, 2, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.lui",
+    mask: 0xe003,
+    value: 0x6001,
+    syntax: "rd, imm",
+    operands: &[Operand::Rd, Operand::UpperImmediate],
+    immediate: &FIELDS_CI,
+    signed: true,
+    valid: |fields| fields.high != 0 && fields.high != 2 && fields.imm != 0,
+    requires: "a register other than `x0` and `sp` and a nonzero immediate",
+    expand: |fields| (INSTRUCTION_LUI, vec![fields.high, fields.imm & 0xfffff]),
+  },
+  CompressedInstruction {
+    name: "c.srli",
+    mask: 0xec03,
+    value: 0x8001,
+    syntax: "rd', shamt",
+    operands: &[Operand::Rs1Prime, Operand::Immediate],
+    immediate: &FIELDS_CI,
+    signed: false,
+    valid: |fields| fields.imm < 32,
+    requires: "a shift amount below 32",
+    expand: |fields| (INSTRUCTION_SRLI, vec![fields.high, fields.high, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.srai",
+    mask: 0xec03,
+    value: 0x8401,
+    syntax: "rd', shamt",
+    operands: &[Operand::Rs1Prime, Operand::Immediate],
+    immediate: &FIELDS_CI,
+    signed: false,
+    valid: |fields| fields.imm < 32,
+    requires: "a shift amount below 32",
+    expand: |fields| (INSTRUCTION_SRAI, vec![fields.high, fields.high, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.andi",
+    mask: 0xec03,
+    value: 0x8801,
+    syntax: "rd', imm",
+    operands: &[Operand::Rs1Prime, Operand::Immediate],
+    immediate: &FIELDS_CI,
+    signed: true,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_ANDI, vec![fields.high, fields.high, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.sub",
+    mask: 0xfc63,
+    value: 0x8c01,
+    syntax: "rd', rs2'",
+    operands: &[Operand::Rs1Prime, Operand::Rs2Prime],
+    immediate: &[],
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_SUB, vec![fields.high, fields.high, fields.low]),
+  },
+  CompressedInstruction {
+    name: "c.xor",
+    mask: 0xfc63,
+    value: 0x8c21,
+    syntax: "rd', rs2'",
+    operands: &[Operand::Rs1Prime, Operand::Rs2Prime],
+    immediate: &[],
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_XOR, vec![fields.high, fields.high, fields.low]),
+  },
+  CompressedInstruction {
+    name: "c.or",
+    mask: 0xfc63,
+    value: 0x8c41,
+    syntax: "rd', rs2'",
+    operands: &[Operand::Rs1Prime, Operand::Rs2Prime],
+    immediate: &[],
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_OR, vec![fields.high, fields.high, fields.low]),
+  },
+  CompressedInstruction {
+    name: "c.and",
+    mask: 0xfc63,
+    value: 0x8c61,
+    syntax: "rd', rs2'",
+    operands: &[Operand::Rs1Prime, Operand::Rs2Prime],
+    immediate: &[],
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_AND, vec![fields.high, fields.high, fields.low]),
+  },
+  CompressedInstruction {
+    name: "c.j",
+    mask: 0xe003,
+    value: 0xa001,
+    syntax: "offset",
+    operands: &[Operand::Offset],
+    immediate: &FIELDS_CJ,
+    signed: true,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_JAL, vec![0, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.beqz",
+    mask: 0xe003,
+    value: 0xc001,
+    syntax: "rs1', offset",
+    operands: &[Operand::Rs1Prime, Operand::Offset],
+    immediate: &FIELDS_CB,
+    signed: true,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_BEQ, vec![fields.high, 0, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.bnez",
+    mask: 0xe003,
+    value: 0xe001,
+    syntax: "rs1', offset",
+    operands: &[Operand::Rs1Prime, Operand::Offset],
+    immediate: &FIELDS_CB,
+    signed: true,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_BNE, vec![fields.high, 0, fields.imm]),
+  },
+  // quadrant 2
+  CompressedInstruction {
+    name: "c.slli",
+    mask: 0xe003,
+    value: 0x0002,
+    syntax: "rd, shamt",
+    operands: &[Operand::Rd, Operand::Immediate],
+    immediate: &FIELDS_CI,
+    signed: false,
+    valid: |fields| fields.imm < 32,
+    requires: "a shift amount below 32",
+    expand: |fields| (INSTRUCTION_SLLI, vec![fields.high, fields.high, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.fldsp",
+    mask: 0xe003,
+    value: 0x2002,
+    syntax: "fd, offset(sp)",
+    operands: &[Operand::FloatRd, Operand::StackMemory],
+    immediate: &FIELDS_LOAD_SP_DOUBLE,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_FLD, vec![fields.high, 2, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.lwsp",
+    mask: 0xe003,
+    value: 0x4002,
+    syntax: "rd, offset(sp)",
+    operands: &[Operand::Rd, Operand::StackMemory],
+    immediate: &FIELDS_LOAD_SP,
+    signed: false,
+    valid: |fields| fields.high != 0,
+    requires: "a register other than `x0`",
+    expand: |fields| (INSTRUCTION_LW, vec![fields.high, 2, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.flwsp",
+    mask: 0xe003,
+    value: 0x6002,
+    syntax: "fd, offset(sp)",
+    operands: &[Operand::FloatRd, Operand::StackMemory],
+    immediate: &FIELDS_LOAD_SP,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_FLW, vec![fields.high, 2, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.jr",
+    mask: 0xf07f,
+    value: 0x8002,
+    syntax: "rs1",
+    operands: &[Operand::Rd],
+    immediate: &[],
+    signed: false,
+    valid: |fields| fields.high != 0,
+    requires: "a register other than `x0`",
+    expand: |fields| (INSTRUCTION_JALR, vec![0, fields.high, 0]),
+  },
+  CompressedInstruction {
+    name: "c.mv",
+    mask: 0xf003,
+    value: 0x8002,
+    syntax: "rd, rs2",
+    operands: &[Operand::Rd, Operand::Rs2],
+    immediate: &[],
+    signed: false,
+    valid: |fields| fields.low != 0,
+    requires: "a source register other than `x0`",
+    expand: |fields| (INSTRUCTION_ADD, vec![fields.high, 0, fields.low]),
+  },
+  CompressedInstruction {
+    name: "c.ebreak",
+    mask: 0xffff,
+    value: 0x9002,
+    syntax: "no operands",
+    operands: &[],
+    immediate: &[],
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |_| (INSTRUCTION_EBREAK, vec![]),
+  },
+  CompressedInstruction {
+    name: "c.jalr",
+    mask: 0xf07f,
+    value: 0x9002,
+    syntax: "rs1",
+    operands: &[Operand::Rd],
+    immediate: &[],
+    signed: false,
+    valid: |fields| fields.high != 0,
+    requires: "a register other than `x0`",
+    expand: |fields| (INSTRUCTION_JALR, vec![1, fields.high, 0]),
+  },
+  CompressedInstruction {
+    name: "c.add",
+    mask: 0xf003,
+    value: 0x9002,
+    syntax: "rd, rs2",
+    operands: &[Operand::Rd, Operand::Rs2],
+    immediate: &[],
+    signed: false,
+    valid: |fields| fields.low != 0,
+    requires: "a source register other than `x0`",
+    expand: |fields| (INSTRUCTION_ADD, vec![fields.high, fields.high, fields.low]),
+  },
+  CompressedInstruction {
+    name: "c.fsdsp",
+    mask: 0xe003,
+    value: 0xa002,
+    syntax: "fs2, offset(sp)",
+    operands: &[Operand::FloatRs2, Operand::StackMemory],
+    immediate: &FIELDS_STORE_SP_DOUBLE,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_FSD, vec![fields.low, 2, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.swsp",
+    mask: 0xe003,
+    value: 0xc002,
+    syntax: "rs2, offset(sp)",
+    operands: &[Operand::Rs2, Operand::StackMemory],
+    immediate: &FIELDS_STORE_SP,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_SW, vec![fields.low, 2, fields.imm]),
+  },
+  CompressedInstruction {
+    name: "c.fswsp",
+    mask: 0xe003,
+    value: 0xe002,
+    syntax: "fs2, offset(sp)",
+    operands: &[Operand::FloatRs2, Operand::StackMemory],
+    immediate: &FIELDS_STORE_SP,
+    signed: false,
+    valid: always,
+    requires: "",
+    expand: |fields| (INSTRUCTION_FSW, vec![fields.low, 2, fields.imm]),
+  },
+];
+
+pub fn lookup_compressed(mnemonic: &str) -> Option<&'static CompressedInstruction> {
+  COMPRESSED_INSTRUCTIONS.iter().find(|instruction| instruction.name == mnemonic)
+}
+
+/// the instruction a 16-bit encoding belongs to and its fields, `None` for illegal and reserved encodings
+pub fn decode_compressed(instruction: u16) -> Option<(&'static CompressedInstruction, Fields)> {
+  COMPRESSED_INSTRUCTIONS.iter()
+    .filter(|compressed| instruction & compressed.mask == compressed.value)
+    .map(|compressed| (compressed, compressed.get_fields(instruction)))
+    .find(|(compressed, fields)| (compressed.valid)(fields))
+}
+
+/// the base instruction a compressed instruction expands to
+pub fn expand(instruction: u16) -> Option<u32> {
+  decode_compressed(instruction).map(|(compressed, fields)| compressed.expand(&fields))
+}
+
+/// a compressed instruction with numeric register names like `c.addi x10, -3` and offsets relative to it
+pub fn disassemble_compressed(instruction: u16) -> Option<String> {
+  let (compressed, fields) = decode_compressed(instruction)?;
+  let operands = compressed.operands.iter().map(|operand| match operand {
+    Operand::Rd | Operand::Rs1Prime => REGISTER_NAMES[fields.high as usize].to_owned(),
+    Operand::Rs2 | Operand::Rs2Prime => REGISTER_NAMES[fields.low as usize].to_owned(),
+    Operand::FloatRd => FLOAT_REGISTER_NAMES[fields.high as usize].to_owned(),
+    Operand::FloatRs2 | Operand::FloatRs2Prime => FLOAT_REGISTER_NAMES[fields.low as usize].to_owned(),
+    Operand::Sp => REGISTER_NAMES[2].to_owned(),
+    Operand::Immediate | Operand::Offset => (fields.imm as i32).to_string(),
+    Operand::UpperImmediate => (fields.imm & 0xfffff).to_string(),
+    Operand::Memory => format!("{}({})", fields.imm, REGISTER_NAMES[fields.high as usize]),
+    Operand::StackMemory => format!("{}({})", fields.imm, REGISTER_NAMES[2]),
+  }).collect::<Vec<_>>();
+  match operands.is_empty() {
+    true => Some(compressed.name.to_owned()),
+    false => Some(format!("{} {}", compressed.name, operands.join(", "))),
+  }
+}
+
+/// set the offset of a compressed branch or jump, as the linker does for `R_RISCV_RVC_BRANCH` and `R_RISCV_RVC_JUMP`
+pub fn set_compressed_offset(instruction: u16, offset: u32) -> Result<u16, ErrorKind> {
+  match decode_compressed(instruction) {
+    Some((compressed, fields)) if compressed.operands.contains(&Operand::Offset) => {
+      compressed.encode(&Fields { imm: offset, ..fields })
+    }
+    _ => Err(ErrorKind::InvalidOperand { operand: format!("{instruction:#06x}"), expected: "a compressed branch or jump" }),
+  }
+}
@@ -0,0 +1,389 @@
+use super::machine::Machine;
+use super::trap::{Interrupt, MSTATUS_MIE, MSTATUS_MPIE, MSTATUS_MPP, MSTATUS_MPP_SHIFT, MSTATUS_MXR, MSTATUS_SIE, MSTATUS_SPIE, MSTATUS_SPP, MSTATUS_SUM};
+use super::mmu::{SATP_MODE, SATP_PPN};
+
+/// privilege levels, as encoded in bits 8 and 9 of a CSR address for the lowest level that may access it
+pub const PRIVILEGE_U: u32 = 0;
+pub const PRIVILEGE_S: u32 = 1;
+pub const PRIVILEGE_M: u32 = 3;
+
+pub const CSR_FFLAGS: u32 = 0x001;
+pub const CSR_FRM: u32 = 0x002;
+pub const CSR_FCSR: u32 = 0x003;
+pub const CSR_CYCLE: u32 = 0xc00;
+pub const CSR_TIME: u32 = 0xc01;
+pub const CSR_INSTRET: u32 = 0xc02;
+pub const CSR_CYCLEH: u32 = 0xc80;
+pub const CSR_TIMEH: u32 = 0xc81;
+pub const CSR_INSTRETH: u32 = 0xc82;
+pub const CSR_SSTATUS: u32 = 0x100;
+pub const CSR_SIE: u32 = 0x104;
+pub const CSR_STVEC: u32 = 0x105;
+pub const CSR_SCOUNTEREN: u32 = 0x106;
+pub const CSR_SSCRATCH: u32 = 0x140;
+pub const CSR_SEPC: u32 = 0x141;
+pub const CSR_SCAUSE: u32 = 0x142;
+pub const CSR_STVAL: u32 = 0x143;
+pub const CSR_SIP: u32 = 0x144;
+pub const CSR_SATP: u32 = 0x180;
+pub const CSR_MSTATUS: u32 = 0x300;
+pub const CSR_MISA: u32 = 0x301;
+pub const CSR_MEDELEG: u32 = 0x302;
+pub const CSR_MIDELEG: u32 = 0x303;
+pub const CSR_MIE: u32 = 0x304;
+pub const CSR_MTVEC: u32 = 0x305;
+pub const CSR_MCOUNTEREN: u32 = 0x306;
+pub const CSR_MSTATUSH: u32 = 0x310;
+pub const CSR_MSCRATCH: u32 = 0x340;
+pub const CSR_MEPC: u32 = 0x341;
+pub const CSR_MCAUSE: u32 = 0x342;
+pub const CSR_MTVAL: u32 = 0x343;
+pub const CSR_MIP: u32 = 0x344;
+pub const CSR_PMPCFG0: u32 = 0x3a0;
+pub const CSR_PMPCFG3: u32 = 0x3a3;
+pub const CSR_PMPADDR0: u32 = 0x3b0;
+pub const CSR_PMPADDR15: u32 = 0x3bf;
+pub const CSR_MCYCLE: u32 = 0xb00;
+pub const CSR_MINSTRET: u32 = 0xb02;
+pub const CSR_MCYCLEH: u32 = 0xb80;
+pub const CSR_MINSTRETH: u32 = 0xb82;
+pub const CSR_MVENDORID: u32 = 0xf11;
+pub const CSR_MARCHID: u32 = 0xf12;
+pub const CSR_MIMPID: u32 = 0xf13;
+pub const CSR_MHARTID: u32 = 0xf14;
+
+/// RV32 with the I, M, A, F, D and C extensions and S-mode and U-mode
+pub const MISA: u32 = 1 << 30
+  | extension('I') | extension('M') | extension('A') | extension('F') | extension('D') | MISA_C
+  | extension('S') | extension('U');
+/// the C extension is the only one that can be turned off, which makes 16-bit aligned jumps raise exceptions
+pub const MISA_C: u32 = extension('C');
+
+/// the bit of an extension in `misa`
+const fn extension(letter: char) -> u32 {
+  1 << (letter as u32 - 'A' as u32)
+}
+
+/// names of the standard CSRs for assembly and disassembly, including those this machine does not implement
+pub const CSR_NAMES: [(u32, &str); 64] = [
+  (CSR_FFLAGS, "fflags"),
+  (CSR_FRM, "frm"),
+  (CSR_FCSR, "fcsr"),
+  (CSR_CYCLE, "cycle"),
+  (CSR_TIME, "time"),
+  (CSR_INSTRET, "instret"),
+  (CSR_CYCLEH, "cycleh"),
+  (CSR_TIMEH, "timeh"),
+  (CSR_INSTRETH, "instreth"),
+  (CSR_SSTATUS, "sstatus"),
+  (CSR_SIE, "sie"),
+  (CSR_STVEC, "stvec"),
+  (CSR_SCOUNTEREN, "scounteren"),
+  (CSR_SSCRATCH, "sscratch"),
+  (CSR_SEPC, "sepc"),
+  (CSR_SCAUSE, "scause"),
+  (CSR_STVAL, "stval"),
+  (CSR_SIP, "sip"),
+  (CSR_SATP, "satp"),
+  (CSR_MSTATUS, "mstatus"),
+  (CSR_MISA, "misa"),
+  (CSR_MEDELEG, "medeleg"),
+  (CSR_MIDELEG, "mideleg"),
+  (CSR_MIE, "mie"),
+  (CSR_MTVEC, "mtvec"),
+  (CSR_MCOUNTEREN, "mcounteren"),
+  (CSR_MSTATUSH, "mstatush"),
+  (0x320, "mcountinhibit"),
+  (CSR_MSCRATCH, "mscratch"),
+  (CSR_MEPC, "mepc"),
+  (CSR_MCAUSE, "mcause"),
+  (CSR_MTVAL, "mtval"),
+  (CSR_MIP, "mip"),
+  (CSR_PMPCFG0, "pmpcfg0"),
+  (0x3a1, "pmpcfg1"),
+  (0x3a2, "pmpcfg2"),
+  (CSR_PMPCFG3, "pmpcfg3"),
+  (CSR_PMPADDR0, "pmpaddr0"),
+  (0x3b1, "pmpaddr1"),
+  (0x3b2, "pmpaddr2"),
+  (0x3b3, "pmpaddr3"),
+  (0x3b4, "pmpaddr4"),
+  (0x3b5, "pmpaddr5"),
+  (0x3b6, "pmpaddr6"),
+  (0x3b7, "pmpaddr7"),
+  (0x3b8, "pmpaddr8"),
+  (0x3b9, "pmpaddr9"),
+  (0x3ba, "pmpaddr10"),
+  (0x3bb, "pmpaddr11"),
+  (0x3bc, "pmpaddr12"),
+  (0x3bd, "pmpaddr13"),
+  (0x3be, "pmpaddr14"),
+  (CSR_PMPADDR15, "pmpaddr15"),
+  (CSR_MCYCLE, "mcycle"),
+  (CSR_MINSTRET, "minstret"),
+  (CSR_MCYCLEH, "mcycleh"),
+  (CSR_MINSTRETH, "minstreth"),
+  (CSR_MVENDORID, "mvendorid"),
+  (CSR_MARCHID, "marchid"),
+  (CSR_MIMPID, "mimpid"),
+  (CSR_MHARTID, "mhartid"),
+  (0x7a0, "tselect"),
+  (0x7a1, "tdata1"),
+  (0x7a2, "tdata2"),
+];
+
+pub fn csr_name(csr: u32) -> Option<&'static str> {
+  CSR_NAMES.iter().find(|(number, _)| *number == csr).map(|(_, name)| *name)
+}
+
+/// parse a CSR by its name, numbers are left to the expression evaluator
+pub fn parse_csr(name: &str) -> Option<u32> {
+  CSR_NAMES.iter().find(|(_, csr_name)| *csr_name == name).map(|(number, _)| *number)
+}
+
+/// whether `csr` may be accessed at `privilege`,
+/// CSRs with both bits 10 and 11 of their address set are read-only
+pub fn csr_accessible(csr: u32, privilege: u32, write: bool) -> bool {
+  let read_only = csr >> 10 & 0b11 == 0b11;
+  csr >> 8 & 0b11 <= privilege && !(write && read_only)
+}
+
+/// the fields of `sstatus`, the view of `mstatus` in S-mode
+const SSTATUS_MASK: u32 = MSTATUS_SIE | MSTATUS_SPIE | MSTATUS_SPP | MSTATUS_SUM | MSTATUS_MXR;
+/// the fields of `mstatus` that are implemented
+const MSTATUS_MASK: u32 = SSTATUS_MASK | MSTATUS_MIE | MSTATUS_MPIE | MSTATUS_MPP;
+/// the supervisor interrupts, the only ones that can be delegated and raised by software in `mip`
+const SUPERVISOR_INTERRUPTS: u32 = Interrupt::SupervisorSoftware.bit() | Interrupt::SupervisorTimer.bit();
+/// the exceptions that can be delegated, all but environment calls from M-mode
+const MEDELEG_MASK: u32 = 0xb3ff;
+
+/// the state behind the CSRs that are not part of other state of the machine,
+/// the 64-bit counters of Zicntr and the M-mode and S-mode trap CSRs,
+/// `time` is `mtime` of the CLINT and `mip` reflects its interrupts
+#[derive(Debug)]
+pub struct Csrs {
+  pub cycle: u64,
+  pub instret: u64,
+  pub misa: u32,
+  /// also holds the fields of `sstatus`
+  pub mstatus: u32,
+  pub medeleg: u32,
+  pub mideleg: u32,
+  /// also holds the fields of `sie`
+  pub mie: u32,
+  /// the supervisor interrupts raised by software, see [`Machine::mip`]
+  pub mip: u32,
+  pub mtvec: u32,
+  /// which counters S-mode can read, `cycle`, `time` and `instret` in bits 0 to 2
+  pub mcounteren: u32,
+  pub mscratch: u32,
+  pub mepc: u32,
+  pub mcause: u32,
+  pub mtval: u32,
+  pub stvec: u32,
+  /// which counters U-mode can read, if S-mode can read them
+  pub scounteren: u32,
+  pub sscratch: u32,
+  pub sepc: u32,
+  pub scause: u32,
+  pub stval: u32,
+  /// the mode and the physical page number of the root page table
+  pub satp: u32,
+}
+
+impl Default for Csrs {
+  fn default() -> Self {
+    Self {
+      cycle: 0,
+      instret: 0,
+      misa: MISA,
+      mstatus: MSTATUS_MPP,
+      medeleg: 0,
+      mideleg: 0,
+      mie: 0,
+      mip: 0,
+      mtvec: 0,
+      mcounteren: 0,
+      mscratch: 0,
+      mepc: 0,
+      mcause: 0,
+      mtval: 0,
+      stvec: 0,
+      scounteren: 0,
+      sscratch: 0,
+      sepc: 0,
+      scause: 0,
+      stval: 0,
+      satp: 0,
+    }
+  }
+}
+
+fn set_lower(counter: &mut u64, value: u32) {
+  *counter = *counter & !0xffff_ffff | value as u64;
+}
+
+fn set_upper(counter: &mut u64, value: u32) {
+  *counter = *counter & 0xffff_ffff | (value as u64) << 32;
+}
+
+impl Machine {
+  /// the value of a CSR, `None` if it does not exist or is not accessible at the current privilege level
+  pub fn read_csr(&self, csr: u32) -> Option<u32> {
+    if !csr_accessible(csr, self.privilege, false) || !self.counter_enabled(csr) {
+      return None;
+    }
+    let fcsr = self.float_registers.fcsr;
+    let value = match csr {
+      CSR_FFLAGS => fcsr & 0x1f,
+      CSR_FRM => fcsr >> 5 & 0b111,
+      CSR_FCSR => fcsr & 0xff,
+      CSR_CYCLE | CSR_MCYCLE => self.csrs.cycle as u32,
+      CSR_TIME => self.clint.mtime as u32,
+      CSR_INSTRET | CSR_MINSTRET => self.csrs.instret as u32,
+      CSR_CYCLEH | CSR_MCYCLEH => (self.csrs.cycle >> 32) as u32,
+      CSR_TIMEH => (self.clint.mtime >> 32) as u32,
+      CSR_INSTRETH | CSR_MINSTRETH => (self.csrs.instret >> 32) as u32,
+      CSR_SSTATUS => self.csrs.mstatus & SSTATUS_MASK,
+      CSR_SIE => self.csrs.mie & self.csrs.mideleg,
+      CSR_STVEC => self.csrs.stvec,
+      CSR_SCOUNTEREN => self.csrs.scounteren,
+      CSR_SSCRATCH => self.csrs.sscratch,
+      CSR_SEPC => self.read_sepc(),
+      CSR_SCAUSE => self.csrs.scause,
+      CSR_STVAL => self.csrs.stval,
+      CSR_SIP => self.mip() & self.csrs.mideleg,
+      CSR_SATP => self.csrs.satp,
+      CSR_MSTATUS => self.csrs.mstatus,
+      CSR_MISA => self.csrs.misa,
+      CSR_MEDELEG => self.csrs.medeleg,
+      CSR_MIDELEG => self.csrs.mideleg,
+      CSR_MIE => self.csrs.mie,
+      CSR_MTVEC => self.csrs.mtvec,
+      CSR_MCOUNTEREN => self.csrs.mcounteren,
+      CSR_MSTATUSH => 0,
+      CSR_MSCRATCH => self.csrs.mscratch,
+      CSR_MEPC => self.read_mepc(),
+      CSR_MCAUSE => self.csrs.mcause,
+      CSR_MTVAL => self.csrs.mtval,
+      CSR_MIP => self.mip(),
+      CSR_PMPCFG0..=CSR_PMPCFG3 => self.pmp.read_cfg((csr - CSR_PMPCFG0) as usize),
+      CSR_PMPADDR0..=CSR_PMPADDR15 => self.pmp.addr[(csr - CSR_PMPADDR0) as usize],
+      CSR_MVENDORID | CSR_MARCHID | CSR_MIMPID | CSR_MHARTID => 0,
+      _ => return None,
+    };
+    Some(value)
+  }
+
+  /// write a CSR, `None` if it does not exist, is read-only or is not accessible at the current privilege level,
+  /// fields that are not writable keep their value
+  pub fn write_csr(&mut self, csr: u32, value: u32) -> Option<()> {
+    if !csr_accessible(csr, self.privilege, true) {
+      return None;
+    }
+    let fcsr = &mut self.float_registers.fcsr;
+    match csr {
+      CSR_FFLAGS => *fcsr = *fcsr & !0x1f | value & 0x1f,
+      CSR_FRM => *fcsr = *fcsr & 0x1f | (value & 0b111) << 5,
+      CSR_FCSR => *fcsr = value & 0xff,
+      CSR_MCYCLE => set_lower(&mut self.csrs.cycle, value),
+      CSR_MINSTRET => set_lower(&mut self.csrs.instret, value),
+      CSR_MCYCLEH => set_upper(&mut self.csrs.cycle, value),
+      CSR_MINSTRETH => set_upper(&mut self.csrs.instret, value),
+      // only C can be turned off, and not when the next instruction isn't 32-bit aligned
+      CSR_MISA if value & MISA_C == 0 && !self.next_pc().is_multiple_of(4) => (),
+      CSR_MISA => self.csrs.misa = MISA & !MISA_C | value & MISA_C,
+      CSR_SSTATUS => self.csrs.mstatus = self.csrs.mstatus & !SSTATUS_MASK | value & SSTATUS_MASK,
+      CSR_SIE => self.csrs.mie = self.csrs.mie & !self.csrs.mideleg | value & self.csrs.mideleg,
+      CSR_STVEC => self.csrs.stvec = value & !0b10,
+      CSR_SCOUNTEREN => self.csrs.scounteren = value & 0b111,
+      CSR_SSCRATCH => self.csrs.sscratch = value,
+      CSR_SEPC => self.csrs.sepc = value & !1,
+      CSR_SCAUSE => self.csrs.scause = value,
+      CSR_STVAL => self.csrs.stval = value,
+      // only the supervisor software interrupt can be cleared or raised in S-mode
+      CSR_SIP => {
+        let mask = Interrupt::SupervisorSoftware.bit() & self.csrs.mideleg;
+        self.csrs.mip = self.csrs.mip & !mask | value & mask;
+      }
+      // cached translations are flushed as there are no address space identifiers to tell them apart
+      CSR_SATP => {
+        self.csrs.satp = value & (SATP_MODE | SATP_PPN);
+        self.tlb.flush(None);
+      }
+      // `MPP` keeps its value when written with the reserved privilege level
+      CSR_MSTATUS => {
+        let mpp = match value & MSTATUS_MPP {
+          mpp if mpp >> MSTATUS_MPP_SHIFT == 0b10 => self.csrs.mstatus & MSTATUS_MPP,
+          mpp => mpp,
+        };
+        self.csrs.mstatus = value & (MSTATUS_MASK & !MSTATUS_MPP) | mpp;
+      }
+      CSR_MEDELEG => self.csrs.medeleg = value & MEDELEG_MASK,
+      CSR_MIDELEG => self.csrs.mideleg = value & SUPERVISOR_INTERRUPTS,
+      CSR_MIE => {
+        let mask = Interrupt::MachineSoftware.bit() | Interrupt::MachineTimer.bit() | SUPERVISOR_INTERRUPTS;
+        self.csrs.mie = value & mask;
+      }
+      // direct and vectored mode, the reserved modes are not writable
+      CSR_MTVEC => self.csrs.mtvec = value & !0b10,
+      CSR_MCOUNTEREN => self.csrs.mcounteren = value & 0b111,
+      CSR_MSTATUSH => (),
+      CSR_MSCRATCH => self.csrs.mscratch = value,
+      CSR_MEPC => self.csrs.mepc = value & !1,
+      CSR_MCAUSE => self.csrs.mcause = value,
+      CSR_MTVAL => self.csrs.mtval = value,
+      // the machine interrupts are only raised and cleared through the CLINT
+      CSR_MIP => self.csrs.mip = value & SUPERVISOR_INTERRUPTS,
+      CSR_PMPCFG0..=CSR_PMPCFG3 => self.pmp.write_cfg((csr - CSR_PMPCFG0) as usize, value),
+      CSR_PMPADDR0..=CSR_PMPADDR15 => self.pmp.write_addr((csr - CSR_PMPADDR0) as usize, value),
+      _ => return None,
+    }
+    Some(())
+  }
+
+  /// the alignment of instructions in bytes, 2 with the C extension and 4 without
+  pub fn instruction_alignment(&self) -> u32 {
+    if self.csrs.misa & MISA_C != 0 { 2 } else { 4 }
+  }
+
+  /// `mepc` masked to the instruction alignment
+  pub fn read_mepc(&self) -> u32 {
+    self.csrs.mepc & !(self.instruction_alignment() - 1)
+  }
+
+  /// `sepc` masked to the instruction alignment
+  pub fn read_sepc(&self) -> u32 {
+    self.csrs.sepc & !(self.instruction_alignment() - 1)
+  }
+
+  /// whether a counter is enabled for S-mode in `mcounteren` and for U-mode in `scounteren` as well,
+  /// always true for other CSRs
+  fn counter_enabled(&self, csr: u32) -> bool {
+    let bit = match csr {
+      CSR_CYCLE..=CSR_INSTRET | CSR_CYCLEH..=CSR_INSTRETH => 1 << (csr & 0x1f),
+      _ => return true,
+    };
+    let supervisor = self.privilege == PRIVILEGE_M || self.csrs.mcounteren & bit != 0;
+    supervisor && (self.privilege != PRIVILEGE_U || self.csrs.scounteren & bit != 0)
+  }
+
+  /// the privilege level of trace output, `M`, `S` or `U`
+  pub fn privilege_name(&self) -> &'static str {
+    match self.privilege {
+      PRIVILEGE_U => "U",
+      PRIVILEGE_S => "S",
+      _ => "M",
+    }
+  }
+}
+
+#[test]
+fn test_csr_accessible() {
+  assert!(csr_accessible(CSR_CYCLE, PRIVILEGE_U, false));
+  assert!(!csr_accessible(CSR_CYCLE, PRIVILEGE_M, true));
+  assert!(csr_accessible(CSR_MCYCLE, PRIVILEGE_M, true));
+  assert!(!csr_accessible(CSR_MCYCLE, PRIVILEGE_S, false));
+  assert!(csr_accessible(0x100, PRIVILEGE_S, true));
+  assert!(!csr_accessible(CSR_MHARTID, PRIVILEGE_M, true));
+}
@@ -18,7 +18,13 @@ pub fn decode_instruction(instruction: u32) -> InstructionIndex {
     OPCODE_STORE => decode_opcode_store(instruction),
     OPCODE_OP_IMM => decode_opcode_op_imm(instruction),
     OPCODE_OP => decode_opcode_op(instruction),
+    OPCODE_MISC_MEM => decode_opcode_misc_mem(instruction),
     OPCODE_SYSTEM => decode_opcode_system(instruction),
+    OPCODE_AMO => decode_opcode_amo(instruction),
+    OPCODE_LOAD_FP => decode_opcode_load_fp(instruction),
+    OPCODE_STORE_FP => decode_opcode_store_fp(instruction),
+    OPCODE_MADD | OPCODE_MSUB | OPCODE_NMSUB | OPCODE_NMADD => decode_fused(instruction),
+    OPCODE_OP_FP => decode_opcode_op_fp(instruction),
     _ => InstructionIndex::UNDEF,
   }
 }
@@ -91,15 +97,181 @@ fn decode_opcode_op(instruction: u32) -> InstructionIndex {
   }
 }
 
+fn decode_opcode_misc_mem(instruction: u32) -> InstructionIndex {
+  match instruction {
+    INSTRUCTION_FENCE_TSO_VALUE => InstructionIndex::FENCE_TSO,
+    INSTRUCTION_PAUSE_VALUE => InstructionIndex::PAUSE,
+    _ if get_funct3_bits(instruction) == 0 => InstructionIndex::FENCE,
+    _ => InstructionIndex::UNDEF,
+  }
+}
+
 fn decode_opcode_system(instruction: u32) -> InstructionIndex {
-  const EBREAK_INSTRUCTION: u32 = OPCODE_SYSTEM | (1 << 20);
+  match get_funct3_bits(instruction) {
+    FUNCT3_PRIV => decode_PRIV(instruction),
+    FUNCT3_CSRRW => InstructionIndex::CSRRW,
+    FUNCT3_CSRRS => InstructionIndex::CSRRS,
+    FUNCT3_CSRRC => InstructionIndex::CSRRC,
+    FUNCT3_CSRRWI => InstructionIndex::CSRRWI,
+    FUNCT3_CSRRSI => InstructionIndex::CSRRSI,
+    FUNCT3_CSRRCI => InstructionIndex::CSRRCI,
+    _ => InstructionIndex::UNDEF,
+  }
+}
+
+fn decode_PRIV(instruction: u32) -> InstructionIndex {
   match instruction {
-    OPCODE_SYSTEM => InstructionIndex::ECALL,
-    EBREAK_INSTRUCTION => InstructionIndex::EBREAK,
+    INSTRUCTION_ECALL_VALUE => InstructionIndex::ECALL,
+    INSTRUCTION_EBREAK_VALUE => InstructionIndex::EBREAK,
+    INSTRUCTION_SRET_VALUE => InstructionIndex::SRET,
+    INSTRUCTION_MRET_VALUE => InstructionIndex::MRET,
+    _ if get_funct7_bits(instruction) == FUNCT7_SFENCE_VMA && get_rd_bits(instruction) == 0 => InstructionIndex::SFENCE_VMA,
     _ => InstructionIndex::UNDEF,
   }
 }
 
+fn decode_opcode_amo(instruction: u32) -> InstructionIndex {
+  if get_funct3_bits(instruction) != FUNCT3_AMO_W {
+    return InstructionIndex::UNDEF;
+  }
+  match get_funct5_bits(instruction) {
+    FUNCT5_LR if get_rs2_bits(instruction) == 0 => InstructionIndex::LR_W,
+    FUNCT5_SC => InstructionIndex::SC_W,
+    FUNCT5_AMOSWAP => InstructionIndex::AMOSWAP_W,
+    FUNCT5_AMOADD => InstructionIndex::AMOADD_W,
+    FUNCT5_AMOXOR => InstructionIndex::AMOXOR_W,
+    FUNCT5_AMOAND => InstructionIndex::AMOAND_W,
+    FUNCT5_AMOOR => InstructionIndex::AMOOR_W,
+    FUNCT5_AMOMIN => InstructionIndex::AMOMIN_W,
+    FUNCT5_AMOMAX => InstructionIndex::AMOMAX_W,
+    FUNCT5_AMOMINU => InstructionIndex::AMOMINU_W,
+    FUNCT5_AMOMAXU => InstructionIndex::AMOMAXU_W,
+    _ => InstructionIndex::UNDEF,
+  }
+}
+
+fn decode_opcode_load_fp(instruction: u32) -> InstructionIndex {
+  match get_funct3_bits(instruction) {
+    FUNCT3_FLW => InstructionIndex::FLW,
+    FUNCT3_FLD => InstructionIndex::FLD,
+    _ => InstructionIndex::UNDEF,
+  }
+}
+
+fn decode_opcode_store_fp(instruction: u32) -> InstructionIndex {
+  match get_funct3_bits(instruction) {
+    FUNCT3_FSW => InstructionIndex::FSW,
+    FUNCT3_FSD => InstructionIndex::FSD,
+    _ => InstructionIndex::UNDEF,
+  }
+}
+
+/// the rounding modes 5 and 6 are reserved
+fn has_valid_rounding_mode(instruction: u32) -> bool {
+  !matches!(get_funct3_bits(instruction), 5 | 6)
+}
+
+fn decode_fused(instruction: u32) -> InstructionIndex {
+  use InstructionIndex::*;
+  if !has_valid_rounding_mode(instruction) {
+    return UNDEF;
+  }
+  match (get_opcode_bits(instruction), get_fmt_bits(instruction)) {
+    (OPCODE_MADD, FMT_S) => FMADD_S,
+    (OPCODE_MSUB, FMT_S) => FMSUB_S,
+    (OPCODE_NMSUB, FMT_S) => FNMSUB_S,
+    (OPCODE_NMADD, FMT_S) => FNMADD_S,
+    (OPCODE_MADD, FMT_D) => FMADD_D,
+    (OPCODE_MSUB, FMT_D) => FMSUB_D,
+    (OPCODE_NMSUB, FMT_D) => FNMSUB_D,
+    (OPCODE_NMADD, FMT_D) => FNMADD_D,
+    _ => UNDEF,
+  }
+}
+
+fn decode_opcode_op_fp(instruction: u32) -> InstructionIndex {
+  use InstructionIndex::*;
+  let funct3 = get_funct3_bits(instruction);
+  let rs2 = get_rs2_bits(instruction);
+  let index = match (get_funct5_bits(instruction), get_fmt_bits(instruction)) {
+    (FUNCT5_FADD, FMT_S) => FADD_S,
+    (FUNCT5_FSUB, FMT_S) => FSUB_S,
+    (FUNCT5_FMUL, FMT_S) => FMUL_S,
+    (FUNCT5_FDIV, FMT_S) => FDIV_S,
+    (FUNCT5_FSQRT, FMT_S) if rs2 == 0 => FSQRT_S,
+    (FUNCT5_FCVT_W_F, FMT_S) if rs2 == RS2_FCVT_W => FCVT_W_S,
+    (FUNCT5_FCVT_W_F, FMT_S) if rs2 == RS2_FCVT_WU => FCVT_WU_S,
+    (FUNCT5_FCVT_F_W, FMT_S) if rs2 == RS2_FCVT_W => FCVT_S_W,
+    (FUNCT5_FCVT_F_W, FMT_S) if rs2 == RS2_FCVT_WU => FCVT_S_WU,
+    (FUNCT5_FCVT_F_F, FMT_S) if rs2 == FMT_D => FCVT_S_D,
+    (FUNCT5_FADD, FMT_D) => FADD_D,
+    (FUNCT5_FSUB, FMT_D) => FSUB_D,
+    (FUNCT5_FMUL, FMT_D) => FMUL_D,
+    (FUNCT5_FDIV, FMT_D) => FDIV_D,
+    (FUNCT5_FSQRT, FMT_D) if rs2 == 0 => FSQRT_D,
+    (FUNCT5_FCVT_W_F, FMT_D) if rs2 == RS2_FCVT_W => FCVT_W_D,
+    (FUNCT5_FCVT_W_F, FMT_D) if rs2 == RS2_FCVT_WU => FCVT_WU_D,
+    (FUNCT5_FCVT_F_W, FMT_D) if rs2 == RS2_FCVT_W => FCVT_D_W,
+    (FUNCT5_FCVT_F_W, FMT_D) if rs2 == RS2_FCVT_WU => FCVT_D_WU,
+    (FUNCT5_FCVT_F_F, FMT_D) if rs2 == FMT_S => FCVT_D_S,
+    // the remaining instructions use funct3 to select the operation instead of a rounding mode
+    (FUNCT5_FSGNJ, fmt) => return decode_FSGNJ(fmt, funct3),
+    (FUNCT5_FMIN_FMAX, fmt) => return decode_FMIN_FMAX(fmt, funct3),
+    (FUNCT5_FCMP, fmt) => return decode_FCMP(fmt, funct3),
+    (FUNCT5_FMV_X_W_FCLASS, fmt) if rs2 == 0 => return decode_FMV_X_W_FCLASS(fmt, funct3),
+    (FUNCT5_FMV_W_X, FMT_S) if rs2 == 0 && funct3 == 0 => return FMV_W_X,
+    _ => UNDEF,
+  };
+  if has_valid_rounding_mode(instruction) { index } else { UNDEF }
+}
+
+fn decode_FSGNJ(fmt: u32, funct3: u32) -> InstructionIndex {
+  use InstructionIndex::*;
+  match (fmt, funct3) {
+    (FMT_S, FUNCT3_FSGNJ) => FSGNJ_S,
+    (FMT_S, FUNCT3_FSGNJN) => FSGNJN_S,
+    (FMT_S, FUNCT3_FSGNJX) => FSGNJX_S,
+    (FMT_D, FUNCT3_FSGNJ) => FSGNJ_D,
+    (FMT_D, FUNCT3_FSGNJN) => FSGNJN_D,
+    (FMT_D, FUNCT3_FSGNJX) => FSGNJX_D,
+    _ => UNDEF,
+  }
+}
+
+fn decode_FMIN_FMAX(fmt: u32, funct3: u32) -> InstructionIndex {
+  use InstructionIndex::*;
+  match (fmt, funct3) {
+    (FMT_S, FUNCT3_FMIN) => FMIN_S,
+    (FMT_S, FUNCT3_FMAX) => FMAX_S,
+    (FMT_D, FUNCT3_FMIN) => FMIN_D,
+    (FMT_D, FUNCT3_FMAX) => FMAX_D,
+    _ => UNDEF,
+  }
+}
+
+fn decode_FCMP(fmt: u32, funct3: u32) -> InstructionIndex {
+  use InstructionIndex::*;
+  match (fmt, funct3) {
+    (FMT_S, FUNCT3_FEQ) => FEQ_S,
+    (FMT_S, FUNCT3_FLT) => FLT_S,
+    (FMT_S, FUNCT3_FLE) => FLE_S,
+    (FMT_D, FUNCT3_FEQ) => FEQ_D,
+    (FMT_D, FUNCT3_FLT) => FLT_D,
+    (FMT_D, FUNCT3_FLE) => FLE_D,
+    _ => UNDEF,
+  }
+}
+
+fn decode_FMV_X_W_FCLASS(fmt: u32, funct3: u32) -> InstructionIndex {
+  use InstructionIndex::*;
+  match (fmt, funct3) {
+    (FMT_S, FUNCT3_FMV_X_W) => FMV_X_W,
+    (FMT_S, FUNCT3_FCLASS) => FCLASS_S,
+    (FMT_D, FUNCT3_FCLASS) => FCLASS_D,
+    _ => UNDEF,
+  }
+}
+
 fn decode_SRLI_SRAI(instruction: u32) -> InstructionIndex {
   match get_funct7_bits(instruction) {
     FUNCT7_SRLI => InstructionIndex::SRLI,
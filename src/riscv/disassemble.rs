@@ -1,8 +1,15 @@
 use super::instructions::INSTRUCTIONS;
 use super::decode::decode_instruction;
+use super::compressed::{disassemble_compressed, is_compressed};
 use wasm_bindgen::prelude::*;
 
+/// a compressed instruction is given in the lower half
 #[wasm_bindgen]
 pub fn disassemble(instruction: u32) -> String {
+  if is_compressed(instruction) {
+    if let Some(text) = disassemble_compressed(instruction as u16) {
+      return text;
+    }
+  }
   INSTRUCTIONS[decode_instruction(instruction) as usize].disassemble(instruction, None)
 }
@@ -1,5 +1,7 @@
 use super::machine::*;
 use super::slices::*;
+use super::float::{DYN, ROUNDING_MODE_NAMES};
+use super::csr::csr_name;
 
 pub type Disassembler = &'static dyn Fn(u32, Option<&Machine>) -> String;
 
@@ -27,7 +29,7 @@ const fn I_TYPE_DISASSEMBLER(name: &str) -> impl Fn(u32, Option<&Machine>) -> St
     let rd = get_register_name(get_rd_bits(instruction) as usize);
     let rs1_raw = get_rs1_bits(instruction) as usize;
     let mut rs1 = get_register_name(rs1_raw);
-    let imm = get_I_imm_bits(instruction);
+    let imm = get_I_imm(instruction) as i32;
     if let Some(machine) = machine {
       let rs1_value = machine.registers.get(rs1_raw);
       rs1.push_str(&format!("={rs1_value}"));
@@ -39,7 +41,7 @@ const fn I_TYPE_DISASSEMBLER(name: &str) -> impl Fn(u32, Option<&Machine>) -> St
 const fn S_TYPE_DISASSEMBLER(name: &str) -> impl Fn(u32, Option<&Machine>) -> String + '_ {
   move |instruction, machine| {
     let name = name.to_owned();
-    let imm = get_S_imm_bits(instruction);
+    let imm = get_S_imm(instruction) as i32;
     let rs1_raw = get_rs1_bits(instruction) as usize;
     let rs2_raw = get_rs2_bits(instruction) as usize;
     let mut rs1 = get_register_name(rs1_raw);
@@ -96,7 +98,7 @@ const fn LOAD_DISASSEMBLER(name: &str) -> impl Fn(u32, Option<&Machine>) -> Stri
     let rd = get_register_name(get_rd_bits(instruction) as usize);
     let rs1_raw = get_rs1_bits(instruction) as usize;
     let mut rs1 = get_register_name(rs1_raw);
-    let imm = get_I_imm_bits(instruction);
+    let imm = get_I_imm(instruction) as i32;
     if let Some(machine) = machine {
       let rs1_value = machine.registers.get(rs1_raw);
       rs1.push_str(&format!("={rs1_value}"));
@@ -105,12 +107,128 @@ const fn LOAD_DISASSEMBLER(name: &str) -> impl Fn(u32, Option<&Machine>) -> Stri
   }
 }
 
+/// `amoadd.w.aq rd, rs2, (rs1)`, or `lr.w rd, (rs1)` without rs2
+const fn AMO_DISASSEMBLER(name: &str, with_rs2: bool) -> impl Fn(u32, Option<&Machine>) -> String + '_ {
+  move |instruction, machine| {
+    let suffix = get_ordering_suffix(instruction);
+    let rd = get_register_name(get_rd_bits(instruction) as usize);
+    let rs1_raw = get_rs1_bits(instruction) as usize;
+    let rs2_raw = get_rs2_bits(instruction) as usize;
+    let mut rs1 = get_register_name(rs1_raw);
+    let mut rs2 = get_register_name(rs2_raw);
+    if let Some(machine) = machine {
+      let rs1_value = machine.registers.get(rs1_raw);
+      let rs2_value = machine.registers.get(rs2_raw);
+      rs1.push_str(&format!("={rs1_value}"));
+      rs2.push_str(&format!("={rs2_value}"));
+    };
+    match with_rs2 {
+      true => format!("{name}{suffix} {rd}, {rs2}, ({rs1})"),
+      false => format!("{name}{suffix} {rd}, ({rs1})"),
+    }
+  }
+}
+
+/// the register file and precision of an operand of a floating-point instruction
+#[derive(Clone, Copy)]
+enum Operand {
+  X,
+  S,
+  D,
+}
+
+fn get_operand(register: usize, operand: Operand, machine: Option<&Machine>) -> String {
+  let name = match operand {
+    Operand::X => get_register_name(register),
+    Operand::S | Operand::D => get_float_register_name(register),
+  };
+  match (machine, operand) {
+    (None, _) => name,
+    (Some(machine), Operand::X) => format!("{name}={}", machine.registers.get(register)),
+    (Some(machine), Operand::S) => format!("{name}={}", machine.float_registers.get::<f32>(register)),
+    (Some(machine), Operand::D) => format!("{name}={}", machine.float_registers.get::<f64>(register)),
+  }
+}
+
+/// `fadd.s rd, rs1, rs2, rm` with the sources rs1, rs2 and rs3 as far as given,
+/// the rounding mode is shown unless it is dynamic
+const fn FP_DISASSEMBLER<'a>(name: &'a str, rd: Operand, sources: &'a [Operand], with_rm: bool) -> impl Fn(u32, Option<&Machine>) -> String + 'a {
+  move |instruction, machine| {
+    let rd = get_operand(get_rd_bits(instruction) as usize, rd, None);
+    let registers = [get_rs1_bits(instruction), get_rs2_bits(instruction), get_rs3_bits(instruction)];
+    let mut operands = vec![rd];
+    for (register, operand) in registers.iter().zip(sources) {
+      operands.push(get_operand(*register as usize, *operand, machine));
+    }
+    let rm = get_funct3_bits(instruction);
+    if with_rm && rm != DYN {
+      operands.push(ROUNDING_MODE_NAMES[rm as usize].to_owned());
+    }
+    format!("{name} {}", operands.join(", "))
+  }
+}
+
+const fn FP_LOAD_DISASSEMBLER(name: &str) -> impl Fn(u32, Option<&Machine>) -> String + '_ {
+  move |instruction, machine| {
+    let rd = get_float_register_name(get_rd_bits(instruction) as usize);
+    let rs1 = get_operand(get_rs1_bits(instruction) as usize, Operand::X, machine);
+    let imm = get_I_imm(instruction) as i32;
+    format!("{name} {rd}, {imm}({rs1})")
+  }
+}
+
+const fn FP_STORE_DISASSEMBLER(name: &str, precision: Operand) -> impl Fn(u32, Option<&Machine>) -> String + '_ {
+  move |instruction, machine| {
+    let rs1 = get_operand(get_rs1_bits(instruction) as usize, Operand::X, machine);
+    let rs2 = get_operand(get_rs2_bits(instruction) as usize, precision, machine);
+    let imm = get_S_imm(instruction) as i32;
+    format!("{name} {rs2}, {imm}({rs1})")
+  }
+}
+
 const fn SYSTEM_DISASSEMBLER(name: &str) -> impl Fn(u32, Option<&Machine>) -> String + '_ {
   move |_instruction, _machine| {
     name.to_owned()
   }
 }
 
+/// `csrrw rd, csr, rs1` or `csrrwi rd, csr, uimm`, the CSR by name if it has one
+const fn CSR_DISASSEMBLER(name: &str, immediate: bool) -> impl Fn(u32, Option<&Machine>) -> String + '_ {
+  move |instruction, machine| {
+    let rd = get_register_name(get_rd_bits(instruction) as usize);
+    let rs1_raw = get_rs1_bits(instruction);
+    let csr_raw = get_csr_bits(instruction);
+    let mut csr = csr_name(csr_raw).map_or_else(|| format!("{csr_raw:#x}"), str::to_owned);
+    let mut rs1 = match immediate {
+      true => rs1_raw.to_string(),
+      false => get_register_name(rs1_raw as usize),
+    };
+    if let Some(machine) = machine {
+      if let Some(csr_value) = machine.read_csr(csr_raw) {
+        csr.push_str(&format!("={csr_value}"));
+      }
+      if !immediate {
+        let rs1_value = machine.registers.get(rs1_raw as usize);
+        rs1.push_str(&format!("={rs1_value}"));
+      }
+    };
+    format!("{name} {rd}, {csr}, {rs1}")
+  }
+}
+
+/// `sfence.vma rs1, rs2`
+pub fn SFENCE_VMA_DISASSEMBLER(instruction: u32, machine: Option<&Machine>) -> String {
+  let rs1_raw = get_rs1_bits(instruction) as usize;
+  let rs2_raw = get_rs2_bits(instruction) as usize;
+  let mut rs1 = get_register_name(rs1_raw);
+  let mut rs2 = get_register_name(rs2_raw);
+  if let Some(machine) = machine {
+    rs1.push_str(&format!("={}", machine.registers.get(rs1_raw)));
+    rs2.push_str(&format!("={}", machine.registers.get(rs2_raw)));
+  }
+  format!("sfence.vma {rs1}, {rs2}")
+}
+
 pub const LUI_DISASSEMBLER: Disassembler = &U_TYPE_DISASSEMBLER("lui");
 pub const AUIPC_DISASSEMBLER: Disassembler = &U_TYPE_DISASSEMBLER("auipc");
 pub const JAL_DISASSEMBLER: Disassembler = &J_TYPE_DISASSEMBLER("jal");
@@ -119,7 +237,7 @@ pub fn JALR_DISASSEMBLER(instruction: u32, machine: Option<&Machine>) -> String
   let rd = get_register_name(get_rd_bits(instruction) as usize);
   let rs1_raw = get_rs1_bits(instruction) as usize;
   let mut rs1 = get_register_name(rs1_raw);
-  let imm = get_I_imm_bits(instruction);
+  let imm = get_I_imm(instruction) as i32;
   if let Some(machine) = machine {
     let rs1_value = machine.registers.get(rs1_raw);
     rs1.push_str(&format!("={rs1_value}"));
@@ -131,13 +249,13 @@ pub const BEQ_DISASSEMBLER: Disassembler = &B_TYPE_DISASSEMBLER("beq");
 pub const BNE_DISASSEMBLER: Disassembler = &B_TYPE_DISASSEMBLER("bne");
 pub const BLT_DISASSEMBLER: Disassembler = &B_TYPE_DISASSEMBLER("blt");
 pub const BGE_DISASSEMBLER: Disassembler = &B_TYPE_DISASSEMBLER("bge");
-pub const BLTU_DISASSEMBLER: Disassembler = &B_TYPE_DISASSEMBLER("add");
-pub const BGEU_DISASSEMBLER: Disassembler = &B_TYPE_DISASSEMBLER("add");
+pub const BLTU_DISASSEMBLER: Disassembler = &B_TYPE_DISASSEMBLER("bltu");
+pub const BGEU_DISASSEMBLER: Disassembler = &B_TYPE_DISASSEMBLER("bgeu");
 pub const LB_DISASSEMBLER: Disassembler = &LOAD_DISASSEMBLER("lb");
 pub const LH_DISASSEMBLER: Disassembler = &LOAD_DISASSEMBLER("lh");
 pub const LW_DISASSEMBLER: Disassembler = &LOAD_DISASSEMBLER("lw");
 pub const LBU_DISASSEMBLER: Disassembler = &LOAD_DISASSEMBLER("lbu");
-pub const LHU_DISASSEMBLER: Disassembler = &LOAD_DISASSEMBLER("lbhu");
+pub const LHU_DISASSEMBLER: Disassembler = &LOAD_DISASSEMBLER("lhu");
 pub const SB_DISASSEMBLER: Disassembler = &S_TYPE_DISASSEMBLER("sb");
 pub const SH_DISASSEMBLER: Disassembler = &S_TYPE_DISASSEMBLER("sh");
 pub const SW_DISASSEMBLER: Disassembler = &S_TYPE_DISASSEMBLER("sw");
@@ -161,10 +279,18 @@ pub const SRA_DISASSEMBLER: Disassembler = &R_TYPE_DISASSEMBLER("sra");
 pub const OR_DISASSEMBLER: Disassembler = &R_TYPE_DISASSEMBLER("or");
 pub const AND_DISASSEMBLER: Disassembler = &R_TYPE_DISASSEMBLER("and");
 pub const FENCE_DISASSEMBLER: Disassembler = &SYSTEM_DISASSEMBLER("fence");
-pub const FENCE_TSO_DISASSEMBLER: Disassembler = &SYSTEM_DISASSEMBLER("fence_tso");
+pub const FENCE_TSO_DISASSEMBLER: Disassembler = &SYSTEM_DISASSEMBLER("fence.tso");
 pub const PAUSE_DISASSEMBLER: Disassembler = &SYSTEM_DISASSEMBLER("pause");
 pub const ECALL_DISASSEMBLER: Disassembler = &SYSTEM_DISASSEMBLER("ecall");
 pub const EBREAK_DISASSEMBLER: Disassembler = &SYSTEM_DISASSEMBLER("ebreak");
+pub const SRET_DISASSEMBLER: Disassembler = &SYSTEM_DISASSEMBLER("sret");
+pub const MRET_DISASSEMBLER: Disassembler = &SYSTEM_DISASSEMBLER("mret");
+pub const CSRRW_DISASSEMBLER: Disassembler = &CSR_DISASSEMBLER("csrrw", false);
+pub const CSRRS_DISASSEMBLER: Disassembler = &CSR_DISASSEMBLER("csrrs", false);
+pub const CSRRC_DISASSEMBLER: Disassembler = &CSR_DISASSEMBLER("csrrc", false);
+pub const CSRRWI_DISASSEMBLER: Disassembler = &CSR_DISASSEMBLER("csrrwi", true);
+pub const CSRRSI_DISASSEMBLER: Disassembler = &CSR_DISASSEMBLER("csrrsi", true);
+pub const CSRRCI_DISASSEMBLER: Disassembler = &CSR_DISASSEMBLER("csrrci", true);
 pub const MUL_DISASSEMBLER: Disassembler = &R_TYPE_DISASSEMBLER("mul");
 pub const MULH_DISASSEMBLER: Disassembler = &R_TYPE_DISASSEMBLER("mulh");
 pub const MULHSU_DISASSEMBLER: Disassembler = &R_TYPE_DISASSEMBLER("mulhsu");
@@ -173,12 +299,80 @@ pub const DIV_DISASSEMBLER: Disassembler = &R_TYPE_DISASSEMBLER("div");
 pub const DIVU_DISASSEMBLER: Disassembler = &R_TYPE_DISASSEMBLER("divu");
 pub const REMU_DISASSEMBLER: Disassembler = &R_TYPE_DISASSEMBLER("remu");
 pub const REM_DISASSEMBLER: Disassembler = &R_TYPE_DISASSEMBLER("rem");
+pub const LR_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("lr.w", false);
+pub const SC_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("sc.w", true);
+pub const AMOSWAP_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("amoswap.w", true);
+pub const AMOADD_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("amoadd.w", true);
+pub const AMOXOR_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("amoxor.w", true);
+pub const AMOAND_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("amoand.w", true);
+pub const AMOOR_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("amoor.w", true);
+pub const AMOMIN_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("amomin.w", true);
+pub const AMOMAX_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("amomax.w", true);
+pub const AMOMINU_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("amominu.w", true);
+pub const AMOMAXU_W_DISASSEMBLER: Disassembler = &AMO_DISASSEMBLER("amomaxu.w", true);
+
+use Operand::{X, S, D};
+pub const FLW_DISASSEMBLER: Disassembler = &FP_LOAD_DISASSEMBLER("flw");
+pub const FSW_DISASSEMBLER: Disassembler = &FP_STORE_DISASSEMBLER("fsw", S);
+pub const FMADD_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmadd.s", S, &[S, S, S], true);
+pub const FMSUB_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmsub.s", S, &[S, S, S], true);
+pub const FNMSUB_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fnmsub.s", S, &[S, S, S], true);
+pub const FNMADD_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fnmadd.s", S, &[S, S, S], true);
+pub const FADD_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fadd.s", S, &[S, S], true);
+pub const FSUB_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fsub.s", S, &[S, S], true);
+pub const FMUL_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmul.s", S, &[S, S], true);
+pub const FDIV_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fdiv.s", S, &[S, S], true);
+pub const FSQRT_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fsqrt.s", S, &[S], true);
+pub const FSGNJ_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fsgnj.s", S, &[S, S], false);
+pub const FSGNJN_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fsgnjn.s", S, &[S, S], false);
+pub const FSGNJX_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fsgnjx.s", S, &[S, S], false);
+pub const FMIN_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmin.s", S, &[S, S], false);
+pub const FMAX_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmax.s", S, &[S, S], false);
+pub const FCVT_W_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fcvt.w.s", X, &[S], true);
+pub const FCVT_WU_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fcvt.wu.s", X, &[S], true);
+pub const FMV_X_W_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmv.x.w", X, &[S], false);
+pub const FEQ_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("feq.s", X, &[S, S], false);
+pub const FLT_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("flt.s", X, &[S, S], false);
+pub const FLE_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fle.s", X, &[S, S], false);
+pub const FCLASS_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fclass.s", X, &[S], false);
+pub const FCVT_S_W_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fcvt.s.w", S, &[X], true);
+pub const FCVT_S_WU_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fcvt.s.wu", S, &[X], true);
+pub const FMV_W_X_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmv.w.x", S, &[X], false);
+pub const FLD_DISASSEMBLER: Disassembler = &FP_LOAD_DISASSEMBLER("fld");
+pub const FSD_DISASSEMBLER: Disassembler = &FP_STORE_DISASSEMBLER("fsd", D);
+pub const FMADD_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmadd.d", D, &[D, D, D], true);
+pub const FMSUB_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmsub.d", D, &[D, D, D], true);
+pub const FNMSUB_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fnmsub.d", D, &[D, D, D], true);
+pub const FNMADD_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fnmadd.d", D, &[D, D, D], true);
+pub const FADD_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fadd.d", D, &[D, D], true);
+pub const FSUB_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fsub.d", D, &[D, D], true);
+pub const FMUL_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmul.d", D, &[D, D], true);
+pub const FDIV_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fdiv.d", D, &[D, D], true);
+pub const FSQRT_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fsqrt.d", D, &[D], true);
+pub const FSGNJ_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fsgnj.d", D, &[D, D], false);
+pub const FSGNJN_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fsgnjn.d", D, &[D, D], false);
+pub const FSGNJX_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fsgnjx.d", D, &[D, D], false);
+pub const FMIN_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmin.d", D, &[D, D], false);
+pub const FMAX_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fmax.d", D, &[D, D], false);
+pub const FCVT_S_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fcvt.s.d", S, &[D], true);
+// widening is exact and has no rounding mode
+pub const FCVT_D_S_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fcvt.d.s", D, &[S], false);
+pub const FEQ_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("feq.d", X, &[D, D], false);
+pub const FLT_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("flt.d", X, &[D, D], false);
+pub const FLE_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fle.d", X, &[D, D], false);
+pub const FCLASS_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fclass.d", X, &[D], false);
+pub const FCVT_W_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fcvt.w.d", X, &[D], true);
+pub const FCVT_WU_D_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fcvt.wu.d", X, &[D], true);
+pub const FCVT_D_W_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fcvt.d.w", D, &[X], false);
+pub const FCVT_D_WU_DISASSEMBLER: Disassembler = &FP_DISASSEMBLER("fcvt.d.wu", D, &[X], false);
 
+/// not an instruction, shown as data like objdump does
 pub fn UNDEF_DISASSEMBLER(instruction: u32, _machine: Option<&Machine>) -> String {
-  panic!("UNDEF ({instruction})")
+  format!(".word {instruction:#010x}")
 }
 
-const REGISTER_NAMES: [&str; 32] = [
+/// numeric register names, indexed by register number
+pub const REGISTER_NAMES: [&str; 32] = [
   "x0",
   "x1",
   "x2",
@@ -216,3 +410,53 @@ const REGISTER_NAMES: [&str; 32] = [
 fn get_register_name(register: usize) -> String {
   REGISTER_NAMES[register].to_owned()
 }
+
+/// numeric floating-point register names, indexed by register number
+pub const FLOAT_REGISTER_NAMES: [&str; 32] = [
+  "f0",
+  "f1",
+  "f2",
+  "f3",
+  "f4",
+  "f5",
+  "f6",
+  "f7",
+  "f8",
+  "f9",
+  "f10",
+  "f11",
+  "f12",
+  "f13",
+  "f14",
+  "f15",
+  "f16",
+  "f17",
+  "f18",
+  "f19",
+  "f20",
+  "f21",
+  "f22",
+  "f23",
+  "f24",
+  "f25",
+  "f26",
+  "f27",
+  "f28",
+  "f29",
+  "f30",
+  "f31",
+];
+
+fn get_float_register_name(register: usize) -> String {
+  FLOAT_REGISTER_NAMES[register].to_owned()
+}
+
+/// the mnemonic suffix for the `aq` and `rl` bits of an atomic instruction
+pub fn get_ordering_suffix(instruction: u32) -> &'static str {
+  match get_aqrl_bits(instruction) {
+    0 => "",
+    AQ => ".aq",
+    RL => ".rl",
+    _ => ".aqrl",
+  }
+}
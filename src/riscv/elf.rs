@@ -0,0 +1,402 @@
+use std::fmt;
+use super::assemble::align;
+use super::machine::Machine;
+use super::object::{Object, SymbolSection};
+
+pub const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+pub const ELFCLASS32: u8 = 1;
+pub const ELFDATA2LSB: u8 = 1;
+pub const EV_CURRENT: u8 = 1;
+pub const ET_REL: u16 = 1;
+pub const ET_EXEC: u16 = 2;
+pub const EM_RISCV: u16 = 243;
+pub const PT_LOAD: u32 = 1;
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_RELA: u32 = 4;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHF_WRITE: u32 = 0x1;
+pub const SHF_ALLOC: u32 = 0x2;
+pub const SHF_EXECINSTR: u32 = 0x4;
+pub const SHF_INFO_LINK: u32 = 0x40;
+pub const SHN_UNDEF: u16 = 0;
+pub const SHN_ABS: u16 = 0xfff1;
+pub const STB_LOCAL: u8 = 0;
+pub const STB_GLOBAL: u8 = 1;
+pub const STT_SECTION: u8 = 3;
+pub const STT_FILE: u8 = 4;
+
+const EHDR_SIZE: usize = 52;
+const PHDR_SIZE: usize = 32;
+const SHDR_SIZE: usize = 40;
+const SYM_SIZE: usize = 16;
+const RELA_SIZE: usize = 12;
+
+/// register the stack pointer is kept in by the calling convention
+const SP: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElfError {
+  /// the file ends before the end of the named structure
+  Truncated(&'static str),
+  NotElf,
+  /// only 32-bit little endian files are supported
+  UnsupportedFormat { class: u8, data: u8 },
+  WrongMachine(u16),
+  /// a segment does not fit into the memory of the machine
+  SegmentOutOfMemory { address: u32, size: u32 },
+}
+
+impl fmt::Display for ElfError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Truncated(what) => write!(f, "file too short for {what}"),
+      Self::NotElf => write!(f, "not an ELF file"),
+      Self::UnsupportedFormat { class, data } => {
+        write!(f, "unsupported ELF class {class} with data encoding {data}, expected 32-bit little endian")
+      }
+      Self::WrongMachine(machine) => write!(f, "ELF file for machine {machine}, expected RISC-V ({EM_RISCV})"),
+      Self::SegmentOutOfMemory { address, size } => {
+        write!(f, "segment at {address:#010x} of {size:#x} bytes does not fit into memory")
+      }
+    }
+  }
+}
+
+impl std::error::Error for ElfError {}
+
+/// a `PT_LOAD` program header with its contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+  pub address: u32,
+  /// contents from the file, shorter than `memory_size` if the rest is zero filled like `.bss`
+  pub data: Vec<u8>,
+  pub memory_size: u32,
+  pub flags: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfSection {
+  pub name: String,
+  pub kind: u32,
+  pub flags: u32,
+  pub address: u32,
+  /// empty for `SHT_NOBITS`
+  pub data: Vec<u8>,
+  pub size: u32,
+  pub link: u32,
+  pub info: u32,
+  pub alignment: u32,
+  pub entry_size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfSymbol {
+  pub name: String,
+  pub value: u32,
+  pub size: u32,
+  /// binding in the upper and type in the lower 4 bits
+  pub info: u8,
+  pub other: u8,
+  /// index of the section the symbol is defined in
+  pub section: u16,
+}
+
+impl ElfSymbol {
+  pub fn binding(&self) -> u8 {
+    self.info >> 4
+  }
+
+  pub fn kind(&self) -> u8 {
+    self.info & 0xf
+  }
+}
+
+/// a 32-bit little endian RISC-V ELF file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Elf {
+  /// `ET_EXEC` for executables, `ET_REL` for object files
+  pub kind: u16,
+  pub entry: u32,
+  pub segments: Vec<Segment>,
+  pub sections: Vec<ElfSection>,
+  /// the contents of `.symtab`, without the null symbol
+  pub symbols: Vec<ElfSymbol>,
+}
+
+impl Elf {
+  pub fn parse(bytes: &[u8]) -> Result<Self, ElfError> {
+    let header = slice(bytes, 0, EHDR_SIZE, "the ELF header")?;
+    if header[..4] != ELF_MAGIC {
+      return Err(ElfError::NotElf);
+    }
+    let (class, data) = (header[4], header[5]);
+    if class != ELFCLASS32 || data != ELFDATA2LSB {
+      return Err(ElfError::UnsupportedFormat { class, data });
+    }
+    let machine = half(header, 18);
+    if machine != EM_RISCV {
+      return Err(ElfError::WrongMachine(machine));
+    }
+    let (program_headers, program_header_count) = (word(header, 28) as usize, half(header, 44) as usize);
+    let (section_headers, section_header_count) = (word(header, 32) as usize, half(header, 48) as usize);
+    let section_names = half(header, 50) as usize;
+
+    let mut segments = vec![];
+    for index in 0..program_header_count {
+      let header = slice(bytes, program_headers + index * PHDR_SIZE, PHDR_SIZE, "a program header")?;
+      if word(header, 0) != PT_LOAD {
+        continue;
+      }
+      let (offset, file_size) = (word(header, 4) as usize, word(header, 16) as usize);
+      segments.push(Segment {
+        address: word(header, 8),
+        data: slice(bytes, offset, file_size, "a segment")?.to_vec(),
+        memory_size: word(header, 20),
+        flags: word(header, 24),
+      });
+    }
+
+    let mut sections = vec![];
+    let mut names = vec![];
+    for index in 0..section_header_count {
+      let header = slice(bytes, section_headers + index * SHDR_SIZE, SHDR_SIZE, "a section header")?;
+      let (kind, offset, size) = (word(header, 4), word(header, 16) as usize, word(header, 20));
+      let data = if kind == SHT_NOBITS { vec![] } else { slice(bytes, offset, size as usize, "a section")?.to_vec() };
+      names.push(word(header, 0) as usize);
+      sections.push(ElfSection {
+        name: String::new(),
+        kind,
+        flags: word(header, 8),
+        address: word(header, 12),
+        data,
+        size,
+        link: word(header, 24),
+        info: word(header, 28),
+        alignment: word(header, 32),
+        entry_size: word(header, 36),
+      });
+    }
+    if let Some(strings) = sections.get(section_names).map(|section| section.data.clone()) {
+      for (section, name) in sections.iter_mut().zip(names) {
+        section.name = string(&strings, name);
+      }
+    }
+
+    let mut symbols = vec![];
+    if let Some(symtab) = sections.iter().find(|section| section.kind == SHT_SYMTAB) {
+      let strings = sections.get(symtab.link as usize).map_or(&[][..], |section| &section.data);
+      for symbol in symtab.data.chunks_exact(SYM_SIZE).skip(1) {
+        symbols.push(ElfSymbol {
+          name: string(strings, word(symbol, 0) as usize),
+          value: word(symbol, 4),
+          size: word(symbol, 8),
+          info: symbol[12],
+          other: symbol[13],
+          section: half(symbol, 14),
+        });
+      }
+    }
+
+    Ok(Self { kind: half(header, 16), entry: word(header, 24), segments, sections, symbols })
+  }
+
+  /// the value of a symbol
+  pub fn symbol(&self, name: &str) -> Option<u32> {
+    self.symbols.iter().find(|symbol| symbol.name == name).map(|symbol| symbol.value)
+  }
+
+  /// copy all loadable segments into memory, zero filling the part not in the file,
+  /// then start execution at the entry point with the stack at the top of memory
+  pub fn load(&self, machine: &mut Machine) -> Result<(), ElfError> {
+    let size = machine.memory.size();
+    for segment in &self.segments {
+      let end = segment.address as usize + segment.memory_size.max(segment.data.len() as u32) as usize;
+      if end > size {
+        return Err(ElfError::SegmentOutOfMemory { address: segment.address, size: segment.memory_size });
+      }
+      let zeros = std::iter::repeat_n(&0, (segment.memory_size as usize).saturating_sub(segment.data.len()));
+      for (address, byte) in (segment.address as usize..).zip(segment.data.iter().chain(zeros)) {
+        machine.memory.store_byte(address, *byte);
+      }
+    }
+    machine.pc = self.entry;
+    // the stack grows down and is kept 16 byte aligned
+    machine.registers.set(SP, size as u32 & !0xf);
+    Ok(())
+  }
+}
+
+/// write a relocatable object as an ELF file with a `.rela` section for each section with relocations,
+/// see [`assemble_object`](super::assemble::assemble_object)
+pub fn write_object(object: &Object) -> Vec<u8> {
+  // local symbols come first, `sh_info` of the symbol table is the index of the first global one
+  let symbols = object.symbols.iter().filter(|symbol| !symbol.global)
+    .chain(object.symbols.iter().filter(|symbol| symbol.global))
+    .collect::<Vec<_>>();
+  let first_global = 1 + object.symbols.iter().filter(|symbol| !symbol.global).count();
+  let mut strtab = vec![0];
+  let mut symtab = vec![0; SYM_SIZE];
+  for symbol in &symbols {
+    let section = match symbol.section {
+      SymbolSection::Undefined => SHN_UNDEF,
+      SymbolSection::Absolute => SHN_ABS,
+      SymbolSection::Section(index) => index as u16 + 1,
+    };
+    let binding = if symbol.global { STB_GLOBAL } else { STB_LOCAL };
+    symtab.extend((strtab.len() as u32).to_le_bytes());
+    symtab.extend(symbol.value.to_le_bytes());
+    symtab.extend(0_u32.to_le_bytes());
+    symtab.extend([binding << 4, 0]);
+    symtab.extend(section.to_le_bytes());
+    strtab.extend(symbol.name.bytes().chain([0]));
+  }
+
+  let mut sections = object.sections.iter().map(|section| {
+    let (kind, flags) = section_type(&section.name);
+    ElfSection {
+      name: section.name.clone(),
+      kind,
+      flags,
+      address: 0,
+      data: if kind == SHT_NOBITS { vec![] } else { section.data.clone() },
+      size: section.data.len() as u32,
+      link: 0,
+      info: 0,
+      alignment: section.alignment,
+      entry_size: 0,
+    }
+  }).collect::<Vec<_>>();
+  let relocated = (0..object.sections.len())
+    .filter(|index| object.relocations.iter().any(|relocation| relocation.section == *index))
+    .collect::<Vec<_>>();
+  // the section header table starts with the null section
+  let symtab_index = 1 + sections.len() + relocated.len();
+  for index in relocated {
+    let mut data = vec![];
+    for relocation in object.relocations.iter().filter(|relocation| relocation.section == index) {
+      let symbol = 1 + symbols.iter().position(|symbol| symbol.name == relocation.symbol).unwrap();
+      data.extend(relocation.offset.to_le_bytes());
+      data.extend(((symbol as u32) << 8 | relocation.kind.number()).to_le_bytes());
+      data.extend(relocation.addend.to_le_bytes());
+    }
+    sections.push(ElfSection {
+      name: format!(".rela{}", object.sections[index].name),
+      kind: SHT_RELA,
+      flags: SHF_INFO_LINK,
+      address: 0,
+      size: data.len() as u32,
+      data,
+      link: symtab_index as u32,
+      info: index as u32 + 1,
+      alignment: 4,
+      entry_size: RELA_SIZE as u32,
+    });
+  }
+  let table = |name: &str, kind, data: Vec<u8>, link, info, entry_size| ElfSection {
+    name: name.to_owned(),
+    kind,
+    flags: 0,
+    address: 0,
+    size: data.len() as u32,
+    data,
+    link,
+    info,
+    alignment: if kind == SHT_SYMTAB { 4 } else { 1 },
+    entry_size,
+  };
+  sections.push(table(".symtab", SHT_SYMTAB, symtab, symtab_index as u32 + 1, first_global as u32, SYM_SIZE as u32));
+  sections.push(table(".strtab", SHT_STRTAB, strtab, 0, 0, 0));
+  write_elf(ET_REL, 0, sections)
+}
+
+/// type and flags of a section by its name
+fn section_type(name: &str) -> (u32, u32) {
+  let prefix = |prefix: &str| name == prefix || name.starts_with(&format!("{prefix}."));
+  if prefix(".text") {
+    (SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR)
+  } else if prefix(".rodata") {
+    (SHT_PROGBITS, SHF_ALLOC)
+  } else if prefix(".bss") || prefix(".sbss") {
+    (SHT_NOBITS, SHF_ALLOC | SHF_WRITE)
+  } else {
+    (SHT_PROGBITS, SHF_ALLOC | SHF_WRITE)
+  }
+}
+
+/// write an ELF file without program headers, adding the null section and the section name table
+fn write_elf(kind: u16, entry: u32, mut sections: Vec<ElfSection>) -> Vec<u8> {
+  let mut shstrtab = vec![0];
+  let mut names = vec![];
+  for section in &sections {
+    names.push(shstrtab.len() as u32);
+    shstrtab.extend(section.name.bytes().chain([0]));
+  }
+  names.push(shstrtab.len() as u32);
+  shstrtab.extend(b".shstrtab\0");
+  sections.push(ElfSection {
+    name: ".shstrtab".to_owned(),
+    kind: SHT_STRTAB,
+    flags: 0,
+    address: 0,
+    size: shstrtab.len() as u32,
+    data: shstrtab,
+    link: 0,
+    info: 0,
+    alignment: 1,
+    entry_size: 0,
+  });
+
+  let mut elf = vec![0; EHDR_SIZE];
+  let mut offsets = vec![];
+  for section in &sections {
+    elf.resize(align(elf.len() as u32, section.alignment) as usize, 0);
+    offsets.push(elf.len() as u32);
+    elf.extend(&section.data);
+  }
+  elf.resize(align(elf.len() as u32, 4) as usize, 0);
+  let section_headers = elf.len() as u32;
+  elf.extend([0; SHDR_SIZE]);
+  for ((section, name), offset) in sections.iter().zip(names).zip(offsets) {
+    let fields = [
+      name, section.kind, section.flags, section.address, offset,
+      section.size, section.link, section.info, section.alignment, section.entry_size,
+    ];
+    elf.extend(fields.iter().flat_map(|field| field.to_le_bytes()));
+  }
+
+  let mut header = vec![];
+  header.extend(ELF_MAGIC);
+  header.extend([ELFCLASS32, ELFDATA2LSB, EV_CURRENT]);
+  header.resize(16, 0);
+  header.extend(kind.to_le_bytes());
+  header.extend(EM_RISCV.to_le_bytes());
+  header.extend((EV_CURRENT as u32).to_le_bytes());
+  header.extend(entry.to_le_bytes());
+  // no program headers, then the section headers and no flags
+  header.extend([0_u32, section_headers, 0].iter().flat_map(|field| field.to_le_bytes()));
+  let sizes = [EHDR_SIZE, 0, 0, SHDR_SIZE, sections.len() + 1, sections.len()];
+  header.extend(sizes.iter().flat_map(|size| (*size as u16).to_le_bytes()));
+  elf[..EHDR_SIZE].copy_from_slice(&header);
+  elf
+}
+
+fn slice<'a>(bytes: &'a [u8], offset: usize, size: usize, what: &'static str) -> Result<&'a [u8], ElfError> {
+  bytes.get(offset..offset.checked_add(size).ok_or(ElfError::Truncated(what))?).ok_or(ElfError::Truncated(what))
+}
+
+fn half(bytes: &[u8], offset: usize) -> u16 {
+  u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn word(bytes: &[u8], offset: usize) -> u32 {
+  u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// a null terminated string starting at `offset` in a string table
+fn string(table: &[u8], offset: usize) -> String {
+  let bytes = table.get(offset..).unwrap_or_default();
+  let end = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+  String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+use std::ops::Range;
+use super::parse::Statement;
+
+/// what went wrong while assembling a statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+  UnknownMnemonic(String),
+  UnknownDirective(String),
+  WrongOperandCount { mnemonic: String, expected: &'static str, found: usize },
+  ImmediateOutOfRange { value: i64, min: i64, max: i64 },
+  /// branch and jump offsets must be even
+  MisalignedImmediate { value: i64, alignment: u32 },
+  UnknownRegister(String),
+  UndefinedLabel(String),
+  DuplicateLabel(String),
+  InvalidImmediate(String),
+  /// an operand that does not have the expected syntax, e.g. a string or an address
+  InvalidOperand { operand: String, expected: &'static str },
+  DataInBss,
+  /// a `.macro`, `.rept` or `.if` without its end
+  UnterminatedBlock { directive: String, end: &'static str },
+  /// an end of a block like `.endm` or `.endif`, or an `.else` outside of a block
+  UnmatchedDirective(String),
+  /// the file to `.include` could not be read
+  IncludeFailed(String),
+  /// macros or includes nested too deeply, usually because they recurse
+  RecursionLimit(String),
+  /// an address only known when linking a relocatable object, used where no relocation can describe it
+  NotRelocatable(String),
+}
+
+impl ErrorKind {
+  /// the source text the error refers to, used to locate it in the statement
+  fn text(&self) -> Option<&str> {
+    match self {
+      Self::UnknownMnemonic(text) | Self::UnknownDirective(text) | Self::UnknownRegister(text)
+      | Self::UndefinedLabel(text) | Self::DuplicateLabel(text) | Self::InvalidImmediate(text)
+      | Self::InvalidOperand { operand: text, .. } | Self::UnterminatedBlock { directive: text, .. }
+      | Self::UnmatchedDirective(text) | Self::IncludeFailed(text) | Self::RecursionLimit(text)
+      | Self::NotRelocatable(text) => Some(text),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for ErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::UnknownMnemonic(mnemonic) => write!(f, "unknown instruction `{mnemonic}`"),
+      Self::UnknownDirective(directive) => write!(f, "unknown directive `{directive}`"),
+      Self::WrongOperandCount { mnemonic, expected, found } => {
+        write!(f, "wrong number of operands for `{mnemonic}`: expected `{expected}`, found {found}")
+      }
+      Self::ImmediateOutOfRange { value, min, max } => {
+        write!(f, "immediate {value} out of range, expected {min} to {max}")
+      }
+      Self::MisalignedImmediate { value, alignment } => {
+        write!(f, "immediate {value} is not a multiple of {alignment}")
+      }
+      Self::UnknownRegister(register) => write!(f, "unknown register `{register}`"),
+      Self::UndefinedLabel(label) => write!(f, "undefined label `{label}`"),
+      Self::DuplicateLabel(label) => write!(f, "label `{label}` defined more than once"),
+      Self::InvalidImmediate(imm) => write!(f, "could not parse immediate `{imm}`"),
+      Self::InvalidOperand { operand, expected } => write!(f, "invalid operand `{operand}`, expected {expected}"),
+      Self::DataInBss => write!(f, ".bss may only contain zeros"),
+      Self::UnterminatedBlock { directive, end } => write!(f, "`{directive}` without matching `{end}`"),
+      Self::UnmatchedDirective(directive) => write!(f, "`{directive}` without matching start"),
+      Self::IncludeFailed(file) => write!(f, "could not include `{file}`"),
+      Self::RecursionLimit(name) => write!(f, "`{name}` nested too deeply"),
+      Self::NotRelocatable(expression) => write!(f, "`{expression}` is only known when linking and cannot be encoded here"),
+    }
+  }
+}
+
+/// an error located in the assembly source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+  /// starting at 1
+  pub line: usize,
+  /// in characters, starting at 1
+  pub column: usize,
+  /// byte range in the source
+  pub span: Range<usize>,
+  pub kind: ErrorKind,
+}
+
+impl AssembleError {
+  /// locate `kind` in `statement`, a line of `source` starting at `line`
+  /// errors without source text or whose text was generated by the assembler
+  /// cover `fallback`, which is part of `statement`
+  pub fn locate(source: &str, line: usize, statement: &str, fallback: &str, kind: ErrorKind) -> Self {
+    let line_start = offset_in(source, statement).unwrap_or(0);
+    let mut span = offset_in(statement, fallback).map_or(0..statement.len(), |start| start..start + fallback.len());
+    if let Some(text) = kind.text().filter(|text| !text.is_empty()) {
+      // prefer occurrences in the operands over ones in the mnemonic
+      let start = statement[span.start..].find(text).map(|start| span.start + start).or_else(|| statement.find(text));
+      if let Some(start) = start {
+        span = start..start + text.len();
+      }
+    }
+    let column = statement[..span.start].chars().count() + 1;
+    let span = line_start + span.start..line_start + span.end;
+    Self { line, column, span, kind }
+  }
+
+  /// locate `kind` in `statement`, see [`Self::locate`],
+  /// statements expanded by the preprocessor are not part of `source`,
+  /// their errors are located in the line they were expanded from instead
+  pub fn in_statement(source: &str, statement: &Statement, fallback: &str, kind: ErrorKind) -> Self {
+    match offset_in(source, statement.text) {
+      Some(_) => Self::locate(source, statement.line, statement.text, fallback, kind),
+      None => Self::locate(source, statement.line, statement.origin, statement.origin.trim(), kind),
+    }
+  }
+
+  /// render in the style of rustc with the offending source marked by carets
+  pub fn render(&self, source: &str) -> String {
+    let line_start = source[..self.span.start].rfind('\n').map_or(0, |newline| newline + 1);
+    let text = source[line_start..].lines().next().unwrap_or_default();
+    let indent = text[..self.span.start - line_start].chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+    let width = source[self.span.clone()].lines().next().unwrap_or_default().chars().count().max(1);
+    let number = self.line.to_string();
+    let gutter = " ".repeat(number.len());
+    format!(
+      "error: {kind}\n{gutter}--> {line}:{column}\n{gutter} |\n{number} | {text}\n{gutter} | {indent}{carets}\n",
+      kind = self.kind,
+      line = self.line,
+      column = self.column,
+      carets = "^".repeat(width),
+    )
+  }
+}
+
+impl fmt::Display for AssembleError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+  }
+}
+
+impl std::error::Error for AssembleError {}
+
+/// render all errors, see [`AssembleError::render`]
+pub fn render_errors(source: &str, errors: &[AssembleError]) -> String {
+  errors.iter().map(|error| error.render(source)).collect::<Vec<_>>().join("\n")
+}
+
+/// byte offset of `part` in `whole` if it is a subslice of it
+fn offset_in(whole: &str, part: &str) -> Option<usize> {
+  let offset = (part.as_ptr() as usize).checked_sub(whole.as_ptr() as usize)?;
+  (offset + part.len() <= whole.len()).then_some(offset)
+}
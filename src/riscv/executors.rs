@@ -1,79 +1,124 @@
 use super::machine::*;
+use super::trap::Exception;
+use super::csr::{PRIVILEGE_M, PRIVILEGE_S};
 use super::slices::*;
+use super::float::{self, Float, DYN, RMM};
 
-pub type Executor = &'static dyn Fn(&mut Machine, u32);
+pub type Executor = &'static dyn Fn(&mut Machine, u32) -> Result<(), Exception>;
 
 fn op_eq(a: u32, b: u32) -> u32 { (a == b) as u32 }
-fn op_ne(a: u32, b: u32) -> u32 { !op_eq(a, b) }
+fn op_ne(a: u32, b: u32) -> u32 { op_eq(a, b) ^ 1 }
 fn op_lt(a: u32, b: u32) -> u32 { ((a as i32) < (b as i32)) as u32 }
 fn op_ltu(a: u32, b: u32) -> u32 { (a < b) as u32 }
-fn op_ge(a: u32, b: u32) -> u32 { !op_lt(b, a) }
-fn op_geu(a: u32, b: u32) -> u32 { !op_ltu(b, a) }
-fn op_add(a: u32, b: u32) -> u32 { a + b }
-fn op_sub(a: u32, b: u32) -> u32 { a - b }
+fn op_ge(a: u32, b: u32) -> u32 { op_lt(a, b) ^ 1 }
+fn op_geu(a: u32, b: u32) -> u32 { op_ltu(a, b) ^ 1 }
+fn op_add(a: u32, b: u32) -> u32 { a.wrapping_add(b) }
+fn op_sub(a: u32, b: u32) -> u32 { a.wrapping_sub(b) }
 fn op_xor(a: u32, b: u32) -> u32 { a ^ b }
 fn op_or(a: u32, b: u32) -> u32 { a | b }
 fn op_and(a: u32, b: u32) -> u32 { a & b }
 fn op_sll(a: u32, b: u32) -> u32 { a << (b & 0x1f) }
 fn op_srl(a: u32, b: u32) -> u32 { a >> (b & 0x1f) }
 fn op_sra(a: u32, b: u32) -> u32 { ((a as i32) >> (b & 0x1f)) as u32 }
+fn op_min(a: u32, b: u32) -> u32 { (a as i32).min(b as i32) as u32 }
+fn op_max(a: u32, b: u32) -> u32 { (a as i32).max(b as i32) as u32 }
+fn op_minu(a: u32, b: u32) -> u32 { a.min(b) }
+fn op_maxu(a: u32, b: u32) -> u32 { a.max(b) }
+fn op_swap(_a: u32, b: u32) -> u32 { b }
+fn op_andn(a: u32, b: u32) -> u32 { a & !b }
+fn op_mul(a: u32, b: u32) -> u32 { a.wrapping_mul(b) }
+fn op_mulh(a: u32, b: u32) -> u32 { ((a as i32 as i64 * b as i32 as i64) >> 32) as u32 }
+fn op_mulhsu(a: u32, b: u32) -> u32 { ((a as i32 as i64 * b as i64) >> 32) as u32 }
+fn op_mulhu(a: u32, b: u32) -> u32 { ((a as u64 * b as u64) >> 32) as u32 }
+// division by zero and signed overflow don't trap, the results are defined by the spec
+fn op_div(a: u32, b: u32) -> u32 { if b == 0 { u32::MAX } else { (a as i32).wrapping_div(b as i32) as u32 } }
+fn op_divu(a: u32, b: u32) -> u32 { a.checked_div(b).unwrap_or(u32::MAX) }
+fn op_rem(a: u32, b: u32) -> u32 { if b == 0 { a } else { (a as i32).wrapping_rem(b as i32) as u32 } }
+fn op_remu(a: u32, b: u32) -> u32 { a.checked_rem(b).unwrap_or(a) }
 
-const fn R_TYPE_ARITH_EXECUTOR(op: fn(u32, u32) -> u32) -> impl Fn(&mut Machine, u32) {
+const fn R_TYPE_ARITH_EXECUTOR(op: fn(u32, u32) -> u32) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
   move |machine, instruction| {
     let rd = get_rd_bits(instruction) as usize;
     let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
     let rs2 = machine.registers.get(get_rs2_bits(instruction) as usize);
     machine.registers.set(rd, op(rs1, rs2));
-    machine.pc += 4;
+    machine.pc = machine.next_pc();
+    Ok(())
   }
 }
 
-const fn I_TYPE_ARITH_EXECUTOR(op: fn(u32, u32) -> u32) -> impl Fn(&mut Machine, u32) {
+const fn I_TYPE_ARITH_EXECUTOR(op: fn(u32, u32) -> u32) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
   move |machine, instruction| {
     let rd = get_rd_bits(instruction) as usize;
     let rs1 = get_rs1_bits(instruction) as usize;
     let rs1 = machine.registers.get(rs1);
     let imm = get_I_imm(instruction);
     machine.registers.set(rd, op(rs1, imm));
-    machine.pc += 4;
+    machine.pc = machine.next_pc();
+    Ok(())
   }
 }
 
-const fn B_TYPE_EXECUTOR(comparison: fn(u32, u32) -> u32) -> impl Fn(&mut Machine, u32) {
+/// read the word at the address in rs1 into rd and write `op` of it and rs2 back
+const fn AMO_EXECUTOR(op: fn(u32, u32) -> u32) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let address = machine.registers.get(get_rs1_bits(instruction) as usize);
+    let rs2 = machine.registers.get(get_rs2_bits(instruction) as usize);
+    let value = machine.load(address, 4, Access::Store)? as u32;
+    machine.store(address, 4, op(value, rs2) as u64)?;
+    machine.registers.set(rd, value);
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+const fn B_TYPE_EXECUTOR(comparison: fn(u32, u32) -> u32) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
   move |machine, instruction| {
     let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
     let rs2 = machine.registers.get(get_rs2_bits(instruction) as usize);
-    machine.pc += if comparison(rs1, rs2) != 0 { get_imm(instruction) } else { 4 };
+    match comparison(rs1, rs2) {
+      0 => machine.pc = machine.next_pc(),
+      _ => machine.jump(machine.pc.wrapping_add(get_imm(instruction)))?,
+    }
+    Ok(())
   }
 }
 
-pub fn LUI_EXECUTOR(machine: &mut Machine, instruction: u32) {
+pub fn LUI_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
   let rd = get_rd_bits(instruction) as usize;
   let imm = get_U_imm_bits(instruction);
   machine.registers.set(rd, imm);
-  machine.pc += 4;
+  machine.pc = machine.next_pc();
+  Ok(())
 }
 
-pub fn AUIPC_EXECUTOR(machine: &mut Machine, instruction: u32) {
+pub fn AUIPC_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
   let rd = get_rd_bits(instruction) as usize;
   let imm = get_U_imm_bits(instruction);
-  machine.registers.set(rd, machine.pc + imm);
-  machine.pc += 4;
+  machine.registers.set(rd, machine.pc.wrapping_add(imm));
+  machine.pc = machine.next_pc();
+  Ok(())
 }
 
-pub fn JAL_EXECUTOR(machine: &mut Machine, instruction: u32) {
+pub fn JAL_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
   let rd = get_rd_bits(instruction) as usize;
-  let imm = get_J_imm_bits(instruction);
-  machine.registers.set(rd, machine.pc + 4);
-  machine.pc += imm;
+  let imm = get_J_imm(instruction);
+  let link = machine.next_pc();
+  machine.jump(machine.pc.wrapping_add(imm))?;
+  machine.registers.set(rd, link);
+  Ok(())
 }
 
-pub fn JALR_EXECUTOR(machine: &mut Machine, instruction: u32) {
+/// jumps to rs1 plus the offset with the lowest bit cleared, so targets only need to be 2-byte aligned
+pub fn JALR_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
   let rd = get_rd_bits(instruction) as usize;
   let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
-  let imm = get_I_imm_bits(instruction);
-  machine.registers.set(rd, machine.pc + 4);
-  machine.pc += rs1 + imm;
+  let imm = get_I_imm(instruction);
+  let link = machine.next_pc();
+  machine.jump(rs1.wrapping_add(imm) & !1)?;
+  machine.registers.set(rd, link);
+  Ok(())
 }
 
 pub const BEQ_EXECUTOR: Executor = &B_TYPE_EXECUTOR(op_eq);
@@ -83,65 +128,81 @@ pub const BGE_EXECUTOR: Executor = &B_TYPE_EXECUTOR(op_ge);
 pub const BLTU_EXECUTOR: Executor = &B_TYPE_EXECUTOR(op_ltu);
 pub const BGEU_EXECUTOR: Executor = &B_TYPE_EXECUTOR(op_geu);
 
-pub fn LB_EXECUTOR(machine: &mut Machine, instruction: u32) {
+pub fn LB_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
   let rd = get_rd_bits(instruction) as usize;
-  let address = (get_rs1_bits(instruction) + get_I_imm_bits(instruction)) as usize;
-  let value = machine.memory.load_byte(address) as i8 as u32;
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_I_imm(instruction));
+  let value = machine.load(address, 1, Access::Load)? as i8 as u32;
   machine.registers.set(rd, value);
-  machine.pc += 4;
+  machine.pc = machine.next_pc();
+  Ok(())
 }
 
-pub fn LH_EXECUTOR(machine: &mut Machine, instruction: u32) {
+pub fn LH_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
   let rd = get_rd_bits(instruction) as usize;
-  let address = (get_rs1_bits(instruction) + get_I_imm_bits(instruction)) as usize;
-  let value = machine.memory.load_halfword(address) as i16 as u32;
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_I_imm(instruction));
+  let value = machine.load(address, 2, Access::Load)? as i16 as u32;
   machine.registers.set(rd, value);
-  machine.pc += 4;
+  machine.pc = machine.next_pc();
+  Ok(())
 }
 
-pub fn LW_EXECUTOR(machine: &mut Machine, instruction: u32) {
+pub fn LW_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
   let rd = get_rd_bits(instruction) as usize;
-  let address = (get_rs1_bits(instruction) + get_I_imm_bits(instruction)) as usize;
-  let value = machine.memory.load_word(address);
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_I_imm(instruction));
+  let value = machine.load(address, 4, Access::Load)? as u32;
   machine.registers.set(rd, value);
-  machine.pc += 4;
+  machine.pc = machine.next_pc();
+  Ok(())
 }
 
-pub fn LBU_EXECUTOR(machine: &mut Machine, instruction: u32) {
+pub fn LBU_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
   let rd = get_rd_bits(instruction) as usize;
-  let address = (get_rs1_bits(instruction) + get_I_imm_bits(instruction)) as usize;
-  let value = machine.memory.load_byte(address) as u32;
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_I_imm(instruction));
+  let value = machine.load(address, 1, Access::Load)? as u32;
   machine.registers.set(rd, value);
-  machine.pc += 4;
+  machine.pc = machine.next_pc();
+  Ok(())
 }
 
-pub fn LHU_EXECUTOR(machine: &mut Machine, instruction: u32) {
+pub fn LHU_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
   let rd = get_rd_bits(instruction) as usize;
-  let address = (get_rs1_bits(instruction) + get_I_imm_bits(instruction)) as usize;
-  let value = machine.memory.load_halfword(address) as u32;
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_I_imm(instruction));
+  let value = machine.load(address, 2, Access::Load)? as u32;
   machine.registers.set(rd, value);
-  machine.pc += 4;
+  machine.pc = machine.next_pc();
+  Ok(())
 }
 
-pub fn SB_EXECUTOR(machine: &mut Machine, instruction: u32) {
-  let address = (get_rs1_bits(instruction) + get_S_imm_bits(instruction)) as usize;
+pub fn SB_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_S_imm(instruction));
   let rs2 = machine.registers.get(get_rs2_bits(instruction) as usize);
-  machine.memory.store_byte(address, (rs2 & 0xff) as u8);
-  machine.pc += 4;
+  machine.store(address, 1, rs2 as u64)?;
+  machine.pc = machine.next_pc();
+  Ok(())
 }
 
-pub fn SH_EXECUTOR(machine: &mut Machine, instruction: u32) {
-  let address = (get_rs1_bits(instruction) + get_S_imm_bits(instruction)) as usize;
+pub fn SH_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_S_imm(instruction));
   let rs2 = machine.registers.get(get_rs2_bits(instruction) as usize);
-  machine.memory.store_halfword(address, (rs2 & 0xffff) as u16);
-  machine.pc += 4;
+  machine.store(address, 2, rs2 as u64)?;
+  machine.pc = machine.next_pc();
+  Ok(())
 }
 
-pub fn SW_EXECUTOR(machine: &mut Machine, instruction: u32) {
-  let address = (get_rs1_bits(instruction) + get_S_imm_bits(instruction)) as usize;
+pub fn SW_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_S_imm(instruction));
   let rs2 = machine.registers.get(get_rs2_bits(instruction) as usize);
-  machine.memory.store_word(address, rs2);
-  machine.pc += 4;
+  machine.store(address, 4, rs2 as u64)?;
+  machine.pc = machine.next_pc();
+  Ok(())
 }
 
 pub const ADDI_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
@@ -163,22 +224,386 @@ pub const SRL_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_srl);
 pub const SRA_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_sra);
 pub const OR_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_or);
 pub const AND_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_and);
+pub const MUL_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_mul);
+pub const MULH_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_mulh);
+pub const MULHSU_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_mulhsu);
+pub const MULHU_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_mulhu);
+pub const DIV_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_div);
+pub const DIVU_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_divu);
+pub const REM_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_rem);
+pub const REMU_EXECUTOR: Executor = &R_TYPE_ARITH_EXECUTOR(op_remu);
 
 // TODO
 pub const FENCE_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
 pub const FENCE_TSO_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
 pub const PAUSE_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-pub const ECALL_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-pub const EBREAK_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-pub const MUL_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-pub const MULH_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-pub const MULHSU_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-pub const MULHU_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-pub const DIV_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-pub const DIVU_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-pub const REM_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-pub const REMU_EXECUTOR: Executor = &I_TYPE_ARITH_EXECUTOR(op_add);
-
-pub fn UNDEF_EXECUTOR(_machine: &mut Machine, instruction: u32) {
-  panic!("UNDEF ({instruction})");
+
+pub fn ECALL_EXECUTOR(machine: &mut Machine, _instruction: u32) -> Result<(), Exception> {
+  Err(Exception::EnvironmentCall(machine.privilege))
+}
+
+pub fn EBREAK_EXECUTOR(machine: &mut Machine, _instruction: u32) -> Result<(), Exception> {
+  Err(Exception::Breakpoint(machine.pc))
+}
+
+/// S-mode and M-mode can return from S-mode traps
+pub fn SRET_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  if machine.privilege < PRIVILEGE_S {
+    return Err(Exception::IllegalInstruction(instruction));
+  }
+  machine.sret();
+  Ok(())
+}
+
+/// flush the cached translations of the page at the address in rs1, or all of them if rs1 is x0,
+/// cached translations are not tagged with an address space so rs2 is ignored
+pub fn SFENCE_VMA_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  if machine.privilege < PRIVILEGE_S {
+    return Err(Exception::IllegalInstruction(instruction));
+  }
+  let rs1 = get_rs1_bits(instruction) as usize;
+  machine.tlb.flush((rs1 != 0).then(|| machine.registers.get(rs1)));
+  machine.pc = machine.next_pc();
+  Ok(())
+}
+
+/// only M-mode can return from M-mode traps
+pub fn MRET_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  if machine.privilege != PRIVILEGE_M {
+    return Err(Exception::IllegalInstruction(instruction));
+  }
+  machine.mret();
+  Ok(())
+}
+
+/// read the CSR into rd and write `op` of its old value and rs1, or the immediate in the rs1 field, back,
+/// `csrrw` doesn't read if rd is x0 and the set and clear forms don't write if the rs1 field is 0,
+/// accessing a CSR that doesn't exist or isn't accessible is illegal
+const fn CSR_EXECUTOR(op: fn(u32, u32) -> u32, immediate: bool) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = get_rs1_bits(instruction);
+    let csr = get_csr_bits(instruction);
+    let source = if immediate { rs1 } else { machine.registers.get(rs1 as usize) };
+    let swap = get_funct3_bits(instruction) & 0b11 == FUNCT3_CSRRW;
+    let value = match swap && rd == 0 {
+      true => Some(0),
+      false => machine.read_csr(csr),
+    };
+    let illegal = Exception::IllegalInstruction(instruction);
+    let value = value.ok_or(illegal)?;
+    if swap || rs1 != 0 {
+      machine.write_csr(csr, op(value, source)).ok_or(illegal)?;
+    }
+    machine.registers.set(rd, value);
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+pub const CSRRW_EXECUTOR: Executor = &CSR_EXECUTOR(op_swap, false);
+pub const CSRRS_EXECUTOR: Executor = &CSR_EXECUTOR(op_or, false);
+pub const CSRRC_EXECUTOR: Executor = &CSR_EXECUTOR(op_andn, false);
+pub const CSRRWI_EXECUTOR: Executor = &CSR_EXECUTOR(op_swap, true);
+pub const CSRRSI_EXECUTOR: Executor = &CSR_EXECUTOR(op_or, true);
+pub const CSRRCI_EXECUTOR: Executor = &CSR_EXECUTOR(op_andn, true);
+
+pub fn LR_W_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rd = get_rd_bits(instruction) as usize;
+  let address = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let value = machine.load(address, 4, Access::Load)? as u32;
+  machine.registers.set(rd, value);
+  machine.reservation = Some(address);
+  machine.pc = machine.next_pc();
+  Ok(())
+}
+
+/// stores and writes 0 to rd only if the address is still reserved, writes 1 otherwise,
+/// the reservation is released either way
+pub fn SC_W_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rd = get_rd_bits(instruction) as usize;
+  let address = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let rs2 = machine.registers.get(get_rs2_bits(instruction) as usize);
+  let reserved = machine.reservation.take() == Some(address);
+  if reserved {
+    machine.store(address, 4, rs2 as u64)?;
+  }
+  machine.registers.set(rd, !reserved as u32);
+  machine.pc = machine.next_pc();
+  Ok(())
+}
+
+pub const AMOSWAP_W_EXECUTOR: Executor = &AMO_EXECUTOR(op_swap);
+pub const AMOADD_W_EXECUTOR: Executor = &AMO_EXECUTOR(op_add);
+pub const AMOXOR_W_EXECUTOR: Executor = &AMO_EXECUTOR(op_xor);
+pub const AMOAND_W_EXECUTOR: Executor = &AMO_EXECUTOR(op_and);
+pub const AMOOR_W_EXECUTOR: Executor = &AMO_EXECUTOR(op_or);
+pub const AMOMIN_W_EXECUTOR: Executor = &AMO_EXECUTOR(op_min);
+pub const AMOMAX_W_EXECUTOR: Executor = &AMO_EXECUTOR(op_max);
+pub const AMOMINU_W_EXECUTOR: Executor = &AMO_EXECUTOR(op_minu);
+pub const AMOMAXU_W_EXECUTOR: Executor = &AMO_EXECUTOR(op_maxu);
+
+/// the rounding mode in the instruction, or in `frm` if it is dynamic
+/// instructions with a reserved rounding mode are illegal
+fn get_rounding_mode(machine: &Machine, instruction: u32) -> Result<u32, Exception> {
+  let mode = match get_funct3_bits(instruction) {
+    DYN => machine.float_registers.rounding_mode(),
+    mode => mode,
+  };
+  if mode > RMM {
+    return Err(Exception::IllegalInstruction(instruction));
+  }
+  Ok(mode)
+}
+
+/// `op` of the values in rs1 and rs2 rounded into rd, raising its exception flags
+const fn FP_ARITH_EXECUTOR<F: Float>(op: fn(F, F, u32, &mut u32) -> F) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = machine.float_registers.get::<F>(get_rs1_bits(instruction) as usize);
+    let rs2 = machine.float_registers.get::<F>(get_rs2_bits(instruction) as usize);
+    let mode = get_rounding_mode(machine, instruction)?;
+    let mut flags = 0;
+    machine.float_registers.set(rd, op(rs1, rs2, mode, &mut flags));
+    machine.float_registers.raise(flags);
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+/// `rs1 * rs2 + rs3` with the product and the addend optionally negated
+const fn FUSED_EXECUTOR<F: Float>(negate_product: bool, negate_addend: bool) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = machine.float_registers.get::<F>(get_rs1_bits(instruction) as usize);
+    let rs2 = machine.float_registers.get::<F>(get_rs2_bits(instruction) as usize);
+    let rs3 = machine.float_registers.get::<F>(get_rs3_bits(instruction) as usize);
+    let rs1 = if negate_product { rs1.neg() } else { rs1 };
+    let rs3 = if negate_addend { rs3.neg() } else { rs3 };
+    let mode = get_rounding_mode(machine, instruction)?;
+    let mut flags = 0;
+    machine.float_registers.set(rd, float::fused(rs1, rs2, rs3, mode, &mut flags));
+    machine.float_registers.raise(flags);
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+const fn FSQRT_EXECUTOR<F: Float>() -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = machine.float_registers.get::<F>(get_rs1_bits(instruction) as usize);
+    let mode = get_rounding_mode(machine, instruction)?;
+    let mut flags = 0;
+    machine.float_registers.set(rd, float::sqrt(rs1, mode, &mut flags));
+    machine.float_registers.raise(flags);
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+const fn FSGNJ_EXECUTOR<F: Float>(sign: fn(bool, bool) -> bool) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = machine.float_registers.get::<F>(get_rs1_bits(instruction) as usize);
+    let rs2 = machine.float_registers.get::<F>(get_rs2_bits(instruction) as usize);
+    machine.float_registers.set(rd, float::inject_sign(rs1, rs2, sign));
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+const fn FMIN_FMAX_EXECUTOR<F: Float>(max: bool) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = machine.float_registers.get::<F>(get_rs1_bits(instruction) as usize);
+    let rs2 = machine.float_registers.get::<F>(get_rs2_bits(instruction) as usize);
+    let mut flags = 0;
+    machine.float_registers.set(rd, float::min_max(rs1, rs2, max, &mut flags));
+    machine.float_registers.raise(flags);
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+/// write 1 to the integer register rd if the comparison holds and 0 otherwise
+const fn FCMP_EXECUTOR<F: Float>(ordering: fn(&F, &F) -> bool, signaling: bool) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = machine.float_registers.get::<F>(get_rs1_bits(instruction) as usize);
+    let rs2 = machine.float_registers.get::<F>(get_rs2_bits(instruction) as usize);
+    let mut flags = 0;
+    let result = float::compare(rs1, rs2, ordering, signaling, &mut flags);
+    machine.registers.set(rd, result as u32);
+    machine.float_registers.raise(flags);
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+const fn FCLASS_EXECUTOR<F: Float>() -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = machine.float_registers.get::<F>(get_rs1_bits(instruction) as usize);
+    machine.registers.set(rd, float::classify(rs1));
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+/// convert to a signed or unsigned integer in the integer register rd
+const fn FCVT_W_EXECUTOR<F: Float>(signed: bool) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = machine.float_registers.get::<F>(get_rs1_bits(instruction) as usize);
+    let mode = get_rounding_mode(machine, instruction)?;
+    let (min, max) = if signed { (i32::MIN as i64, i32::MAX as i64) } else { (0, u32::MAX as i64) };
+    let mut flags = 0;
+    let value = float::to_integer(rs1, mode, min, max, &mut flags);
+    machine.registers.set(rd, value as u32);
+    machine.float_registers.raise(flags);
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+/// convert the signed or unsigned integer in the integer register rs1
+const fn FCVT_F_W_EXECUTOR<F: Float>(signed: bool) -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+    let rs1 = if signed { rs1 as i32 as f64 } else { rs1 as f64 };
+    let mode = get_rounding_mode(machine, instruction)?;
+    let mut flags = 0;
+    machine.float_registers.set(rd, float::convert::<F, f64>(rs1, mode, &mut flags));
+    machine.float_registers.raise(flags);
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+/// convert between precisions, from `G` to `F`
+const fn FCVT_F_F_EXECUTOR<F: Float, G: Float>() -> impl Fn(&mut Machine, u32) -> Result<(), Exception> {
+  move |machine, instruction| {
+    let rd = get_rd_bits(instruction) as usize;
+    let rs1 = machine.float_registers.get::<G>(get_rs1_bits(instruction) as usize);
+    let mode = get_rounding_mode(machine, instruction)?;
+    let mut flags = 0;
+    machine.float_registers.set(rd, float::convert::<F, G>(rs1, mode, &mut flags));
+    machine.float_registers.raise(flags);
+    machine.pc = machine.next_pc();
+    Ok(())
+  }
+}
+
+fn sign_fsgnj(_a: bool, b: bool) -> bool { b }
+fn sign_fsgnjn(_a: bool, b: bool) -> bool { !b }
+fn sign_fsgnjx(a: bool, b: bool) -> bool { a ^ b }
+
+pub fn FLW_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rd = get_rd_bits(instruction) as usize;
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_I_imm(instruction));
+  let value = f32::from_bits(machine.load(address, 4, Access::Load)? as u32);
+  machine.float_registers.set(rd, value);
+  machine.pc = machine.next_pc();
+  Ok(())
+}
+
+pub fn FSW_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_S_imm(instruction));
+  // stores the lower half whether or not it is NaN-boxed
+  let rs2 = machine.float_registers.get_bits(get_rs2_bits(instruction) as usize);
+  machine.store(address, 4, rs2)?;
+  machine.pc = machine.next_pc();
+  Ok(())
+}
+
+pub fn FLD_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rd = get_rd_bits(instruction) as usize;
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_I_imm(instruction));
+  let value = machine.load(address, 8, Access::Load)?;
+  machine.float_registers.set_bits(rd, value);
+  machine.pc = machine.next_pc();
+  Ok(())
+}
+
+pub fn FSD_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  let address = rs1.wrapping_add(get_S_imm(instruction));
+  let rs2 = machine.float_registers.get_bits(get_rs2_bits(instruction) as usize);
+  machine.store(address, 8, rs2)?;
+  machine.pc = machine.next_pc();
+  Ok(())
+}
+
+/// the raw lower half of rs1, without checking the NaN-boxing
+pub fn FMV_X_W_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rd = get_rd_bits(instruction) as usize;
+  let rs1 = machine.float_registers.get_bits(get_rs1_bits(instruction) as usize);
+  machine.registers.set(rd, rs1 as u32);
+  machine.pc = machine.next_pc();
+  Ok(())
+}
+
+pub fn FMV_W_X_EXECUTOR(machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  let rd = get_rd_bits(instruction) as usize;
+  let rs1 = machine.registers.get(get_rs1_bits(instruction) as usize);
+  machine.float_registers.set(rd, f32::from_bits(rs1));
+  machine.pc = machine.next_pc();
+  Ok(())
+}
+
+pub const FMADD_S_EXECUTOR: Executor = &FUSED_EXECUTOR::<f32>(false, false);
+pub const FMSUB_S_EXECUTOR: Executor = &FUSED_EXECUTOR::<f32>(false, true);
+pub const FNMSUB_S_EXECUTOR: Executor = &FUSED_EXECUTOR::<f32>(true, false);
+pub const FNMADD_S_EXECUTOR: Executor = &FUSED_EXECUTOR::<f32>(true, true);
+pub const FADD_S_EXECUTOR: Executor = &FP_ARITH_EXECUTOR::<f32>(float::add);
+pub const FSUB_S_EXECUTOR: Executor = &FP_ARITH_EXECUTOR::<f32>(float::sub);
+pub const FMUL_S_EXECUTOR: Executor = &FP_ARITH_EXECUTOR::<f32>(float::mul);
+pub const FDIV_S_EXECUTOR: Executor = &FP_ARITH_EXECUTOR::<f32>(float::div);
+pub const FSQRT_S_EXECUTOR: Executor = &FSQRT_EXECUTOR::<f32>();
+pub const FSGNJ_S_EXECUTOR: Executor = &FSGNJ_EXECUTOR::<f32>(sign_fsgnj);
+pub const FSGNJN_S_EXECUTOR: Executor = &FSGNJ_EXECUTOR::<f32>(sign_fsgnjn);
+pub const FSGNJX_S_EXECUTOR: Executor = &FSGNJ_EXECUTOR::<f32>(sign_fsgnjx);
+pub const FMIN_S_EXECUTOR: Executor = &FMIN_FMAX_EXECUTOR::<f32>(false);
+pub const FMAX_S_EXECUTOR: Executor = &FMIN_FMAX_EXECUTOR::<f32>(true);
+pub const FCVT_W_S_EXECUTOR: Executor = &FCVT_W_EXECUTOR::<f32>(true);
+pub const FCVT_WU_S_EXECUTOR: Executor = &FCVT_W_EXECUTOR::<f32>(false);
+pub const FEQ_S_EXECUTOR: Executor = &FCMP_EXECUTOR::<f32>(PartialEq::eq, false);
+pub const FLT_S_EXECUTOR: Executor = &FCMP_EXECUTOR::<f32>(PartialOrd::lt, true);
+pub const FLE_S_EXECUTOR: Executor = &FCMP_EXECUTOR::<f32>(PartialOrd::le, true);
+pub const FCLASS_S_EXECUTOR: Executor = &FCLASS_EXECUTOR::<f32>();
+pub const FCVT_S_W_EXECUTOR: Executor = &FCVT_F_W_EXECUTOR::<f32>(true);
+pub const FCVT_S_WU_EXECUTOR: Executor = &FCVT_F_W_EXECUTOR::<f32>(false);
+
+pub const FMADD_D_EXECUTOR: Executor = &FUSED_EXECUTOR::<f64>(false, false);
+pub const FMSUB_D_EXECUTOR: Executor = &FUSED_EXECUTOR::<f64>(false, true);
+pub const FNMSUB_D_EXECUTOR: Executor = &FUSED_EXECUTOR::<f64>(true, false);
+pub const FNMADD_D_EXECUTOR: Executor = &FUSED_EXECUTOR::<f64>(true, true);
+pub const FADD_D_EXECUTOR: Executor = &FP_ARITH_EXECUTOR::<f64>(float::add);
+pub const FSUB_D_EXECUTOR: Executor = &FP_ARITH_EXECUTOR::<f64>(float::sub);
+pub const FMUL_D_EXECUTOR: Executor = &FP_ARITH_EXECUTOR::<f64>(float::mul);
+pub const FDIV_D_EXECUTOR: Executor = &FP_ARITH_EXECUTOR::<f64>(float::div);
+pub const FSQRT_D_EXECUTOR: Executor = &FSQRT_EXECUTOR::<f64>();
+pub const FSGNJ_D_EXECUTOR: Executor = &FSGNJ_EXECUTOR::<f64>(sign_fsgnj);
+pub const FSGNJN_D_EXECUTOR: Executor = &FSGNJ_EXECUTOR::<f64>(sign_fsgnjn);
+pub const FSGNJX_D_EXECUTOR: Executor = &FSGNJ_EXECUTOR::<f64>(sign_fsgnjx);
+pub const FMIN_D_EXECUTOR: Executor = &FMIN_FMAX_EXECUTOR::<f64>(false);
+pub const FMAX_D_EXECUTOR: Executor = &FMIN_FMAX_EXECUTOR::<f64>(true);
+pub const FCVT_S_D_EXECUTOR: Executor = &FCVT_F_F_EXECUTOR::<f32, f64>();
+pub const FCVT_D_S_EXECUTOR: Executor = &FCVT_F_F_EXECUTOR::<f64, f32>();
+pub const FEQ_D_EXECUTOR: Executor = &FCMP_EXECUTOR::<f64>(PartialEq::eq, false);
+pub const FLT_D_EXECUTOR: Executor = &FCMP_EXECUTOR::<f64>(PartialOrd::lt, true);
+pub const FLE_D_EXECUTOR: Executor = &FCMP_EXECUTOR::<f64>(PartialOrd::le, true);
+pub const FCLASS_D_EXECUTOR: Executor = &FCLASS_EXECUTOR::<f64>();
+pub const FCVT_W_D_EXECUTOR: Executor = &FCVT_W_EXECUTOR::<f64>(true);
+pub const FCVT_WU_D_EXECUTOR: Executor = &FCVT_W_EXECUTOR::<f64>(false);
+pub const FCVT_D_W_EXECUTOR: Executor = &FCVT_F_W_EXECUTOR::<f64>(true);
+pub const FCVT_D_WU_EXECUTOR: Executor = &FCVT_F_W_EXECUTOR::<f64>(false);
+
+pub fn UNDEF_EXECUTOR(_machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
+  Err(Exception::IllegalInstruction(instruction))
 }
@@ -0,0 +1,276 @@
+use super::assemble::{Context, Symbols};
+use super::error::ErrorKind;
+use super::parse::{is_symbol, parse_char};
+use super::pseudo::split_hi_lo;
+
+/// binary operators with their precedence, higher binds tighter, as in C
+/// operators that are a prefix of another one come after it
+const OPERATORS: [(&str, u8); 18] = [
+  ("||", 1), ("&&", 2),
+  ("==", 6), ("!=", 6), ("<=", 7), (">=", 7),
+  ("<<", 8), (">>", 8),
+  ("|", 3), ("^", 4), ("&", 5),
+  ("<", 7), (">", 7),
+  ("+", 9), ("-", 9),
+  ("*", 10), ("/", 10), ("%", 10),
+];
+
+/// evaluate a constant expression made of
+/// - integer literals in decimal, hex `0x1f`, binary `0b101`, octal `0o17` or `017`
+///   and character literals like `'A'` or `'\n'`
+/// - symbols, that is labels and `.equ` constants, and `.` for the current address
+/// - the unary operators `-`, `+`, `~`, `!` and the binary operators `*`, `/`, `%`, `+`, `-`, `<<`, `>>`,
+///   `<`, `<=`, `>`, `>=`, `==`, `!=`, `&`, `^`, `|`, `&&`, `||` with the precedence and results of C
+/// - parentheses and the relocation operators `%hi`, `%lo`, `%pcrel_hi` and `%pcrel_lo`
+///
+/// arithmetic is done on 64 bits and wraps around
+pub fn evaluate(expression: &str, context: &Context) -> Result<i64, ErrorKind> {
+  let mut parser = Parser { expression, rest: expression, context, symbolic: false };
+  let value = parser.binary(0)?;
+  if !parser.rest.trim().is_empty() {
+    return Err(parser.invalid());
+  }
+  Ok(value)
+}
+
+/// the value of `expression` if it refers to neither symbols nor the current address
+pub fn constant(expression: &str) -> Option<i64> {
+  let symbols = Symbols::new();
+  let context = Context::new(&symbols, 0);
+  let mut parser = Parser { expression, rest: expression, context: &context, symbolic: false };
+  let value = parser.binary(0).ok()?;
+  (parser.rest.trim().is_empty() && !parser.symbolic).then_some(value)
+}
+
+/// split `symbol`, `symbol + constant` or `symbol - constant` into the symbol and the signed constant
+pub fn split_symbol(expression: &str) -> Option<(&str, i64)> {
+  let expression = expression.trim();
+  let end = expression.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$'))).unwrap_or(expression.len());
+  let (symbol, rest) = expression.split_at(end);
+  if !is_symbol(symbol) || symbol == "." {
+    return None;
+  }
+  let rest = rest.trim();
+  // operators binding less than `+` would apply to the symbol as well
+  if rest.contains(['<', '>', '&', '|', '^', '=', '!']) {
+    return None;
+  }
+  match rest.chars().next() {
+    None => Some((symbol, 0)),
+    Some('+' | '-') => Some((symbol, constant(rest)?)),
+    _ => None,
+  }
+}
+
+/// split `%operator(argument)` into its parts
+pub fn split_relocation(expression: &str) -> Option<(&str, &str)> {
+  let (operator, argument) = expression.strip_prefix('%')?.strip_suffix(')')?.split_once('(')?;
+  Some((operator.trim(), argument.trim()))
+}
+
+/// recursive descent parser evaluating while parsing
+struct Parser<'a, 'b> {
+  expression: &'a str,
+  /// the part not parsed yet
+  rest: &'a str,
+  context: &'b Context<'b>,
+  /// whether a symbol or the current address was used
+  symbolic: bool,
+}
+
+impl<'a> Parser<'a, '_> {
+  /// binary operations with operators of at least `precedence`
+  fn binary(&mut self, precedence: u8) -> Result<i64, ErrorKind> {
+    let mut left = self.unary()?;
+    loop {
+      self.rest = self.rest.trim_start();
+      let Some(&(operator, next)) = OPERATORS.iter().find(|(operator, _)| self.rest.starts_with(operator)) else {
+        return Ok(left);
+      };
+      if next < precedence {
+        return Ok(left);
+      }
+      self.rest = &self.rest[operator.len()..];
+      let right = self.binary(next + 1)?;
+      left = self.apply(operator, left, right)?;
+    }
+  }
+
+  fn apply(&self, operator: &str, left: i64, right: i64) -> Result<i64, ErrorKind> {
+    let invalid = |expected| ErrorKind::InvalidOperand { operand: self.expression.trim().to_owned(), expected };
+    let shift = || u32::try_from(right).ok().filter(|shift| *shift < 64).ok_or_else(|| invalid("a shift by 0 to 63"));
+    let value = match operator {
+      "||" => (left != 0 || right != 0) as i64,
+      "&&" => (left != 0 && right != 0) as i64,
+      "==" => (left == right) as i64,
+      "!=" => (left != right) as i64,
+      "<" => (left < right) as i64,
+      "<=" => (left <= right) as i64,
+      ">" => (left > right) as i64,
+      ">=" => (left >= right) as i64,
+      "|" => left | right,
+      "^" => left ^ right,
+      "&" => left & right,
+      "<<" => left << shift()?,
+      ">>" => left >> shift()?,
+      "+" => left.wrapping_add(right),
+      "-" => left.wrapping_sub(right),
+      "*" => left.wrapping_mul(right),
+      "/" => left.checked_div(right).ok_or_else(|| invalid("a nonzero divisor"))?,
+      "%" => left.checked_rem(right).ok_or_else(|| invalid("a nonzero divisor"))?,
+      _ => unreachable!("no operator {operator}"),
+    };
+    Ok(value)
+  }
+
+  fn unary(&mut self) -> Result<i64, ErrorKind> {
+    self.rest = self.rest.trim_start();
+    let Some(c) = self.rest.chars().next() else {
+      return Err(self.invalid());
+    };
+    match c {
+      '-' | '+' | '~' | '!' => {
+        self.rest = &self.rest[1..];
+        let value = self.unary()?;
+        Ok(match c {
+          '-' => value.wrapping_neg(),
+          '+' => value,
+          '~' => !value,
+          _ => (value == 0) as i64,
+        })
+      }
+      '(' => {
+        self.rest = &self.rest[1..];
+        let value = self.binary(0)?;
+        self.expect(')')?;
+        Ok(value)
+      }
+      '%' => self.relocation(),
+      '\'' => self.character(),
+      '0'..='9' => self.number(),
+      _ => self.symbol(),
+    }
+  }
+
+  fn number(&mut self) -> Result<i64, ErrorKind> {
+    let literal = self.take(|c| c.is_ascii_alphanumeric());
+    let (digits, radix) = match literal.get(..2) {
+      Some("0x" | "0X") => (&literal[2..], 16),
+      Some("0b" | "0B") => (&literal[2..], 2),
+      Some("0o" | "0O") => (&literal[2..], 8),
+      _ if literal.len() > 1 && literal.starts_with('0') => (&literal[1..], 8),
+      _ => (literal, 10),
+    };
+    match u64::from_str_radix(digits, radix) {
+      Ok(value) => Ok(value as i64),
+      Err(_) => Err(ErrorKind::InvalidImmediate(literal.to_owned())),
+    }
+  }
+
+  fn character(&mut self) -> Result<i64, ErrorKind> {
+    // the closing quote is the first one not escaped by a backslash
+    let mut escaped = false;
+    let end = self.rest.char_indices().skip(1).find(|&(_, c)| {
+      let end = c == '\'' && !escaped;
+      escaped = c == '\\' && !escaped;
+      end
+    });
+    let Some((end, _)) = end else {
+      return Err(self.invalid());
+    };
+    let literal = &self.rest[..=end];
+    self.rest = &self.rest[end + 1..];
+    parse_char(literal).map(i64::from).ok_or_else(|| ErrorKind::InvalidImmediate(literal.to_owned()))
+  }
+
+  fn symbol(&mut self) -> Result<i64, ErrorKind> {
+    let symbol = self.take(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$'));
+    self.symbolic = true;
+    match symbol {
+      "" => Err(self.invalid()),
+      "." => Ok(self.context.pc as i64),
+      symbol => Ok(self.context.resolve(symbol)? as i64),
+    }
+  }
+
+  /// `%hi` and `%lo` split a value into the parts for `lui` and a following `addi`, load or store,
+  /// `%pcrel_hi` and `%pcrel_lo` do the same for the offset from an `auipc`,
+  /// as in GNU as `%pcrel_lo` takes the label of the `auipc` and not the target itself
+  fn relocation(&mut self) -> Result<i64, ErrorKind> {
+    let start = self.rest;
+    self.rest = &self.rest[1..];
+    let operator = self.take(|c| c.is_ascii_alphanumeric() || c == '_');
+    self.rest = self.rest.trim_start();
+    self.expect('(')?;
+    let argument = self.rest;
+    let value = self.binary(0)?;
+    let argument = argument[..argument.len() - self.rest.len()].trim();
+    self.expect(')')?;
+    let value = match operator {
+      "hi" | "lo" => value as u32,
+      "pcrel_hi" => {
+        self.symbolic = true;
+        (value as u32).wrapping_sub(self.context.pc)
+      }
+      "pcrel_lo" => self.context.pcrel_target(value as u32, argument)?,
+      _ => return Err(ErrorKind::InvalidOperand {
+        operand: start[..start.len() - self.rest.len()].to_owned(),
+        expected: "`%hi`, `%lo`, `%pcrel_hi` or `%pcrel_lo`",
+      }),
+    };
+    let (hi, lo) = split_hi_lo(value as i32);
+    Ok(if operator.ends_with("hi") { hi as i64 } else { lo as i64 })
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), ErrorKind> {
+    self.rest = self.rest.trim_start();
+    match self.rest.strip_prefix(c) {
+      Some(rest) => {
+        self.rest = rest;
+        Ok(())
+      }
+      None => Err(self.invalid()),
+    }
+  }
+
+  /// the longest prefix of characters matching `predicate`
+  fn take(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+    let end = self.rest.find(|c| !predicate(c)).unwrap_or(self.rest.len());
+    let (taken, rest) = self.rest.split_at(end);
+    self.rest = rest;
+    taken
+  }
+
+  fn invalid(&self) -> ErrorKind {
+    ErrorKind::InvalidImmediate(self.expression.trim().to_owned())
+  }
+}
+
+#[test]
+fn test_evaluate() {
+  let symbols = Symbols::from([("START", 0x100), ("END", 0x140)]);
+  let context = Context::new(&symbols, 0x20);
+  let evaluate = |expression| evaluate(expression, &context);
+  assert_eq!(evaluate("0x10"), Ok(16));
+  assert_eq!(evaluate("0b1010 | 0o7"), Ok(15));
+  assert_eq!(evaluate("010"), Ok(8));
+  assert_eq!(evaluate("'A' + '\\n'"), Ok(75));
+  assert_eq!(evaluate("-(4*8)"), Ok(-32));
+  assert_eq!(evaluate("1 + 2 * 3 << 1"), Ok(14));
+  assert_eq!(evaluate("END-START"), Ok(0x40));
+  assert_eq!(evaluate("(END - .) / 4"), Ok(0x48));
+  assert_eq!(evaluate("~0 & 0xff ^ 1"), Ok(0xfe));
+  assert_eq!(evaluate("END > START && 1 + 1 == 2 || 0"), Ok(1));
+  assert_eq!(evaluate("!(3 <= 2)"), Ok(1));
+  assert_eq!(evaluate("%hi(0x12345fff) + %lo(0x12345fff)"), Ok(0x12346 - 1));
+  assert_eq!(evaluate("1 / 0"), Err(ErrorKind::InvalidOperand { operand: "1 / 0".to_owned(), expected: "a nonzero divisor" }));
+  assert_eq!(evaluate("1 +"), Err(ErrorKind::InvalidImmediate("1 +".to_owned())));
+  assert_eq!(evaluate("0x1g"), Err(ErrorKind::InvalidImmediate("0x1g".to_owned())));
+  assert_eq!(evaluate("MIDDLE"), Err(ErrorKind::UndefinedLabel("MIDDLE".to_owned())));
+  assert_eq!(constant("1 << 12"), Some(4096));
+  assert_eq!(constant("START + 4"), None);
+  assert_eq!(split_symbol("table"), Some(("table", 0)));
+  assert_eq!(split_symbol("table - 4 * 2"), Some(("table", -8)));
+  assert_eq!(split_symbol("END - START"), None);
+  assert_eq!(split_symbol("table + 1 << 2"), None);
+}
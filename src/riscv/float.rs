@@ -0,0 +1,375 @@
+use std::cmp::Ordering;
+
+/// rounding modes of the `rm` field of instructions and the `frm` field of `fcsr`
+pub const RNE: u32 = 0;
+pub const RTZ: u32 = 1;
+pub const RDN: u32 = 2;
+pub const RUP: u32 = 3;
+pub const RMM: u32 = 4;
+/// use the rounding mode in `frm`, only valid in instructions
+pub const DYN: u32 = 7;
+
+/// names of the rounding modes in assembly, indexed by their encoding, reserved ones are empty
+pub const ROUNDING_MODE_NAMES: [&str; 8] = ["rne", "rtz", "rdn", "rup", "rmm", "", "", "dyn"];
+
+/// exception flags of the `fflags` field of `fcsr`
+pub const NV: u32 = 1 << 4;
+pub const DZ: u32 = 1 << 3;
+pub const OF: u32 = 1 << 2;
+pub const UF: u32 = 1 << 1;
+pub const NX: u32 = 1;
+
+/// the NaN produced by operations, whose result would be a NaN
+pub const CANONICAL_NAN_S: u32 = 0x7fc00000;
+pub const CANONICAL_NAN_D: u64 = 0x7ff8000000000000;
+
+/// single or double precision values, computations are done on `f64` for both,
+/// which holds every single precision value and every product of two of them exactly
+pub trait Float: Copy + PartialEq + PartialOrd {
+  const MAX: Self;
+  const MIN_POSITIVE: Self;
+  const INFINITY: Self;
+  const CANONICAL_NAN: Self;
+  /// the bit of the significand telling quiet from signaling NaNs
+  const QUIET_BIT: u64;
+  const SIGN_BIT: u64;
+  /// the upper bits set when a value is NaN-boxed in a 64-bit register
+  const BOX: u64;
+  fn to_bits64(self) -> u64;
+  fn from_bits64(bits: u64) -> Self;
+  fn to_f64(self) -> f64;
+  /// rounds to nearest, ties to even
+  fn from_f64(value: f64) -> Self;
+  fn next_up(self) -> Self;
+  fn next_down(self) -> Self;
+  /// `a * b + c` rounded to nearest and the difference of the exact result to it,
+  /// see [`round`] for how exact the difference is
+  fn fused(a: f64, b: f64, c: f64) -> (f64, f64);
+
+  fn is_nan(self) -> bool {
+    self.to_f64().is_nan()
+  }
+
+  fn is_signaling(self) -> bool {
+    self.is_nan() && self.to_bits64() & Self::QUIET_BIT == 0
+  }
+
+  fn is_negative(self) -> bool {
+    self.to_bits64() & Self::SIGN_BIT != 0
+  }
+
+  fn neg(self) -> Self {
+    Self::from_bits64(self.to_bits64() ^ Self::SIGN_BIT)
+  }
+}
+
+impl Float for f32 {
+  const MAX: Self = f32::MAX;
+  const MIN_POSITIVE: Self = f32::MIN_POSITIVE;
+  const INFINITY: Self = f32::INFINITY;
+  const CANONICAL_NAN: Self = f32::from_bits(CANONICAL_NAN_S);
+  const QUIET_BIT: u64 = 1 << 22;
+  const SIGN_BIT: u64 = 1 << 31;
+  const BOX: u64 = 0xffff_ffff_0000_0000;
+  fn to_bits64(self) -> u64 { self.to_bits() as u64 }
+  fn from_bits64(bits: u64) -> Self { f32::from_bits(bits as u32) }
+  fn to_f64(self) -> f64 { self as f64 }
+  fn from_f64(value: f64) -> Self { value as f32 }
+  fn next_up(self) -> Self { f32::next_up(self) }
+  fn next_down(self) -> Self { f32::next_down(self) }
+
+  /// the product of single precision values is exact, which leaves the sum
+  fn fused(a: f64, b: f64, c: f64) -> (f64, f64) {
+    two_sum(a * b, c)
+  }
+}
+
+impl Float for f64 {
+  const MAX: Self = f64::MAX;
+  const MIN_POSITIVE: Self = f64::MIN_POSITIVE;
+  const INFINITY: Self = f64::INFINITY;
+  const CANONICAL_NAN: Self = f64::from_bits(CANONICAL_NAN_D);
+  const QUIET_BIT: u64 = 1 << 51;
+  const SIGN_BIT: u64 = 1 << 63;
+  const BOX: u64 = 0;
+  fn to_bits64(self) -> u64 { self.to_bits() }
+  fn from_bits64(bits: u64) -> Self { f64::from_bits(bits) }
+  fn to_f64(self) -> f64 { self }
+  fn from_f64(value: f64) -> Self { value }
+  fn next_up(self) -> Self { f64::next_up(self) }
+  fn next_down(self) -> Self { f64::next_down(self) }
+
+  /// the difference is the sum of the rounding errors of the product and the sum,
+  /// which has the right sign except for massive cancellation
+  fn fused(a: f64, b: f64, c: f64) -> (f64, f64) {
+    let result = a.mul_add(b, c);
+    let product = a * b;
+    let product_error = a.mul_add(b, -product);
+    let (sum, sum_error) = two_sum(product, c);
+    (result, (sum - result) + (sum_error + product_error))
+  }
+}
+
+/// `a + b` rounded to nearest and its exact rounding error
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+  let sum = a + b;
+  let b_virtual = sum - a;
+  let error = (a - (sum - b_virtual)) + (b - b_virtual);
+  (sum, error)
+}
+
+/// round the exact result `result + error` to a value of `F` in a rounding mode and raise `NX`, `OF` and `UF`,
+/// `error` must be smaller than half an ulp of `result` in `f64`
+/// and only needs to have the right sign where the exact result cannot be halfway between two values of `F`
+pub fn round<F: Float>(result: f64, error: f64, mode: u32, flags: &mut u32) -> F {
+  if result.is_infinite() {
+    return overflow(result < 0.0, mode, flags);
+  }
+  let nearest = F::from_f64(result);
+  if nearest.to_f64().is_infinite() {
+    return overflow(result < 0.0, mode, flags);
+  }
+  // the exact result minus `nearest` is `difference + error`, where `error` is smaller than any nonzero `difference`
+  let difference = result - nearest.to_f64();
+  let direction = match difference.partial_cmp(&0.0).unwrap() {
+    Ordering::Equal => error.partial_cmp(&0.0).unwrap(),
+    direction => direction,
+  };
+  if direction == Ordering::Equal {
+    return nearest;
+  }
+  *flags |= NX;
+  let other = if direction == Ordering::Greater { nearest.next_up() } else { nearest.next_down() };
+  let (lower, upper) = if direction == Ordering::Greater { (nearest, other) } else { (other, nearest) };
+  // where the exact result is relative to the middle between `nearest` and `other`
+  let half = (other.to_f64() - nearest.to_f64()).abs() / 2.0;
+  let position = match difference.abs().partial_cmp(&half).unwrap() {
+    _ if half.is_infinite() => Ordering::Less,
+    _ if difference == 0.0 => error.abs().partial_cmp(&half).unwrap(),
+    Ordering::Equal => (error * difference.signum()).partial_cmp(&0.0).unwrap(),
+    position => position,
+  };
+  let rounded = match (mode, position) {
+    (RNE | RMM, Ordering::Less) => nearest,
+    (RNE | RMM, Ordering::Greater) => other,
+    (RNE, Ordering::Equal) => if nearest.to_bits64() & 1 == 0 { nearest } else { other },
+    (RMM, Ordering::Equal) => if lower.is_negative() { lower } else { upper },
+    (RTZ, _) => if lower.is_negative() { upper } else { lower },
+    (RDN, _) => lower,
+    (RUP, _) => upper,
+    _ => unreachable!("invalid rounding mode {mode}"),
+  };
+  if rounded.to_f64().is_infinite() {
+    *flags |= OF;
+  } else if rounded.to_f64().abs() < F::MIN_POSITIVE.to_f64() {
+    *flags |= UF;
+  }
+  rounded
+}
+
+/// the result of a finite value too large for `F`, the largest value or infinity depending on the rounding mode
+fn overflow<F: Float>(negative: bool, mode: u32, flags: &mut u32) -> F {
+  *flags |= OF | NX;
+  let infinity = match mode {
+    RNE | RMM => true,
+    RTZ => false,
+    RDN => negative,
+    RUP => !negative,
+    _ => unreachable!("invalid rounding mode {mode}"),
+  };
+  let magnitude = if infinity { F::INFINITY } else { F::MAX };
+  if negative { magnitude.neg() } else { magnitude }
+}
+
+/// the canonical NaN if an operand is a NaN, raising `NV` for signaling NaNs
+fn propagate_nan<F: Float>(operands: &[F], flags: &mut u32) -> Option<F> {
+  if operands.iter().any(|operand| operand.is_signaling()) {
+    *flags |= NV;
+  }
+  operands.iter().any(|operand| operand.is_nan()).then_some(F::CANONICAL_NAN)
+}
+
+/// the canonical NaN for an invalid operation
+fn invalid<F: Float>(flags: &mut u32) -> F {
+  *flags |= NV;
+  F::CANONICAL_NAN
+}
+
+pub fn add<F: Float>(a: F, b: F, mode: u32, flags: &mut u32) -> F {
+  if let Some(nan) = propagate_nan(&[a, b], flags) {
+    return nan;
+  }
+  let (a, b) = (a.to_f64(), b.to_f64());
+  let (sum, error) = two_sum(a, b);
+  match sum {
+    _ if sum.is_nan() => invalid(flags),
+    _ if a.is_infinite() || b.is_infinite() => F::from_f64(sum),
+    // an exact zero sum of values with different signs is -0 only when rounding down
+    0.0 if error == 0.0 && a.is_sign_negative() != b.is_sign_negative() => F::from_f64(if mode == RDN { -0.0 } else { 0.0 }),
+    _ => round(sum, error, mode, flags),
+  }
+}
+
+pub fn sub<F: Float>(a: F, b: F, mode: u32, flags: &mut u32) -> F {
+  // negate after checking for NaNs so that the sign of a NaN does not matter
+  if let Some(nan) = propagate_nan(&[a, b], flags) {
+    return nan;
+  }
+  add(a, b.neg(), mode, flags)
+}
+
+pub fn mul<F: Float>(a: F, b: F, mode: u32, flags: &mut u32) -> F {
+  if let Some(nan) = propagate_nan(&[a, b], flags) {
+    return nan;
+  }
+  let (a, b) = (a.to_f64(), b.to_f64());
+  let product = a * b;
+  match product {
+    _ if product.is_nan() => invalid(flags),
+    _ if a.is_infinite() || b.is_infinite() => F::from_f64(product),
+    _ => round(product, a.mul_add(b, -product), mode, flags),
+  }
+}
+
+pub fn div<F: Float>(a: F, b: F, mode: u32, flags: &mut u32) -> F {
+  if let Some(nan) = propagate_nan(&[a, b], flags) {
+    return nan;
+  }
+  let (a, b) = (a.to_f64(), b.to_f64());
+  let quotient = a / b;
+  match quotient {
+    _ if quotient.is_nan() => invalid(flags),
+    _ if a.is_infinite() || b.is_infinite() => F::from_f64(quotient),
+    _ if b == 0.0 => {
+      *flags |= DZ;
+      F::from_f64(quotient)
+    }
+    // the remainder `a - quotient * b` is exact and a quotient is never halfway between two values
+    _ => round(quotient, -quotient.mul_add(b, -a) / b, mode, flags),
+  }
+}
+
+pub fn sqrt<F: Float>(a: F, mode: u32, flags: &mut u32) -> F {
+  if let Some(nan) = propagate_nan(&[a], flags) {
+    return nan;
+  }
+  let a = a.to_f64();
+  let root = a.sqrt();
+  match root {
+    _ if root.is_nan() => invalid(flags),
+    _ if a.is_infinite() || a == 0.0 => F::from_f64(root),
+    // like quotients, square roots are never halfway between two values
+    _ => round(root, -root.mul_add(root, -a), mode, flags),
+  }
+}
+
+/// `a * b + c` with a single rounding, `fmsub`, `fnmsub` and `fnmadd` negate the operands
+pub fn fused<F: Float>(a: F, b: F, c: F, mode: u32, flags: &mut u32) -> F {
+  // infinity times zero is invalid even if the addend is a quiet NaN
+  let infinity_times_zero = |a: F, b: F| a.to_f64().is_infinite() && b.to_f64() == 0.0;
+  if infinity_times_zero(a, b) || infinity_times_zero(b, a) {
+    propagate_nan(&[c], flags);
+    return invalid(flags);
+  }
+  if let Some(nan) = propagate_nan(&[a, b, c], flags) {
+    return nan;
+  }
+  let (a, b, c) = (a.to_f64(), b.to_f64(), c.to_f64());
+  let (result, error) = F::fused(a, b, c);
+  let product_negative = a.is_sign_negative() != b.is_sign_negative();
+  match result {
+    _ if result.is_nan() => invalid(flags),
+    _ if a.is_infinite() || b.is_infinite() || c.is_infinite() => F::from_f64(result),
+    0.0 if error == 0.0 && (a == 0.0 || b == 0.0) && c == 0.0 => {
+      let negative = if product_negative == c.is_sign_negative() { product_negative } else { mode == RDN };
+      F::from_f64(if negative { -0.0 } else { 0.0 })
+    }
+    0.0 if error == 0.0 => F::from_f64(if mode == RDN { -0.0 } else { 0.0 }),
+    _ => round(result, error, mode, flags),
+  }
+}
+
+/// `fmin` and `fmax`, the other operand if one is a NaN, where -0 is less than +0
+pub fn min_max<F: Float>(a: F, b: F, max: bool, flags: &mut u32) -> F {
+  if a.is_signaling() || b.is_signaling() {
+    *flags |= NV;
+  }
+  match (a.is_nan(), b.is_nan()) {
+    (true, true) => F::CANONICAL_NAN,
+    (true, false) => b,
+    (false, true) => a,
+    _ => {
+      let a_less = a < b || (a == b && a.is_negative());
+      if a_less != max { a } else { b }
+    }
+  }
+}
+
+/// `feq`, `flt` and `fle`, false for NaNs, ordered comparisons raise `NV` for any NaN and `feq` only for signaling ones
+pub fn compare<F: Float>(a: F, b: F, ordering: fn(&F, &F) -> bool, signaling: bool, flags: &mut u32) -> bool {
+  if (signaling && (a.is_nan() || b.is_nan())) || a.is_signaling() || b.is_signaling() {
+    *flags |= NV;
+  }
+  ordering(&a, &b)
+}
+
+/// the 10-bit mask of `fclass` with a single bit set for
+/// -inf, negative normal, negative subnormal, -0, +0, positive subnormal, positive normal, +inf, signaling NaN and quiet NaN
+pub fn classify<F: Float>(a: F) -> u32 {
+  let value = a.to_f64();
+  let negative = a.is_negative();
+  let bit = match () {
+    _ if a.is_signaling() => 8,
+    _ if a.is_nan() => 9,
+    _ if value.is_infinite() => if negative { 0 } else { 7 },
+    _ if value == 0.0 => if negative { 3 } else { 4 },
+    _ if value.abs() < F::MIN_POSITIVE.to_f64() => if negative { 2 } else { 5 },
+    _ => if negative { 1 } else { 6 },
+  };
+  1 << bit
+}
+
+/// the bits of `fsgnj`, `fsgnjn` and `fsgnjx`, the value of `a` with a sign computed from the signs of both
+pub fn inject_sign<F: Float>(a: F, b: F, sign: fn(bool, bool) -> bool) -> F {
+  let magnitude = a.to_bits64() & !F::SIGN_BIT;
+  let negative = sign(a.is_negative(), b.is_negative());
+  F::from_bits64(if negative { magnitude | F::SIGN_BIT } else { magnitude })
+}
+
+/// round to an integer in `min..=max`, saturating and raising `NV` for values outside and NaNs,
+/// which convert to `max`
+pub fn to_integer<F: Float>(a: F, mode: u32, min: i64, max: i64, flags: &mut u32) -> i64 {
+  let value = a.to_f64();
+  if value.is_nan() {
+    *flags |= NV;
+    return max;
+  }
+  let rounded = match mode {
+    RNE => value.round_ties_even(),
+    RTZ => value.trunc(),
+    RDN => value.floor(),
+    RUP => value.ceil(),
+    RMM => value.round(),
+    _ => unreachable!("invalid rounding mode {mode}"),
+  };
+  if rounded < min as f64 || rounded > max as f64 {
+    *flags |= NV;
+    return if value < 0.0 { min } else { max };
+  }
+  if rounded != value {
+    *flags |= NX;
+  }
+  rounded as i64
+}
+
+/// convert a value of another precision, or an integer as `f64`, to `F` in a rounding mode
+pub fn convert<F: Float, G: Float>(value: G, mode: u32, flags: &mut u32) -> F {
+  if propagate_nan(&[value], flags).is_some() {
+    return F::CANONICAL_NAN;
+  }
+  let value = value.to_f64();
+  match value {
+    _ if value.is_infinite() || value == 0.0 => F::from_f64(value),
+    _ => round(value, 0.0, mode, flags),
+  }
+}
@@ -0,0 +1,254 @@
+use std::fmt;
+use std::fmt::Write;
+use super::image::{Image, Section};
+
+/// bytes per data record of Intel HEX and S-record files
+const RECORD_SIZE: usize = 16;
+
+const IHEX_DATA: u8 = 0;
+const IHEX_END: u8 = 1;
+const IHEX_SEGMENT_ADDRESS: u8 = 2;
+const IHEX_START_SEGMENT: u8 = 3;
+const IHEX_LINEAR_ADDRESS: u8 = 4;
+const IHEX_START_LINEAR: u8 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+  /// a line that is not a well formed record, counting from 1
+  InvalidRecord(usize),
+  /// a record whose checksum does not match its contents
+  Checksum(usize),
+  UnknownRecordType { line: usize, kind: u8 },
+  /// an Intel HEX file without an end of file record
+  MissingEnd,
+}
+
+impl fmt::Display for FormatError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::InvalidRecord(line) => write!(f, "line {line}: invalid record"),
+      Self::Checksum(line) => write!(f, "line {line}: wrong checksum"),
+      Self::UnknownRecordType { line, kind } => write!(f, "line {line}: unknown record type {kind}"),
+      Self::MissingEnd => write!(f, "missing end of file record"),
+    }
+  }
+}
+
+impl std::error::Error for FormatError {}
+
+/// the memory from the lowest to the highest address of the image, gaps filled with zeros
+pub fn write_bin(image: &Image) -> Vec<u8> {
+  let Some(start) = image.sections.iter().map(|section| section.address).min() else {
+    return vec![];
+  };
+  let mut bin = vec![];
+  for section in &image.sections {
+    let offset = (section.address - start) as usize;
+    if bin.len() < offset + section.data.len() {
+      bin.resize(offset + section.data.len(), 0);
+    }
+    bin[offset..offset + section.data.len()].copy_from_slice(&section.data);
+  }
+  bin
+}
+
+/// an image with the contents of a flat binary at `base`, which is also the entry point
+pub fn read_bin(bin: &[u8], base: u32) -> Image {
+  let section = Section { name: ".data".to_owned(), address: base, alignment: 1, data: bin.to_vec() };
+  Image { sections: vec![section], entry: base, ..Image::default() }
+}
+
+/// an Intel HEX file with 32-bit extended linear addresses and the entry point as start address
+pub fn write_ihex(image: &Image) -> String {
+  let mut hex = String::new();
+  let mut upper = 0;
+  for section in &image.sections {
+    for (address, chunk) in chunks(section) {
+      if address >> 16 != upper {
+        upper = address >> 16;
+        ihex_record(&mut hex, IHEX_LINEAR_ADDRESS, 0, &(upper as u16).to_be_bytes());
+      }
+      ihex_record(&mut hex, IHEX_DATA, address as u16, chunk);
+    }
+  }
+  ihex_record(&mut hex, IHEX_START_LINEAR, 0, &image.entry.to_be_bytes());
+  ihex_record(&mut hex, IHEX_END, 0, &[]);
+  hex
+}
+
+/// read an Intel HEX file, each contiguous range of bytes becomes a section named `.sec0`, `.sec1` and so on
+pub fn read_ihex(hex: &str) -> Result<Image, FormatError> {
+  let mut ranges = vec![];
+  let mut entry = None;
+  let mut base = 0_u32;
+  for (index, line) in hex.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+    let number = index + 1;
+    let bytes = line.trim().strip_prefix(':').and_then(hex_bytes).ok_or(FormatError::InvalidRecord(number))?;
+    if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+      return Err(FormatError::InvalidRecord(number));
+    }
+    if bytes.iter().fold(0_u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+      return Err(FormatError::Checksum(number));
+    }
+    let offset = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+    let data = &bytes[4..bytes.len() - 1];
+    let value = || data.iter().fold(0, |value, byte| value << 8 | *byte as u32);
+    match bytes[3] {
+      IHEX_DATA => ranges.push(data_record(base.wrapping_add(offset), data, number)?),
+      IHEX_END => return Ok(image(ranges, entry)),
+      IHEX_SEGMENT_ADDRESS => base = value() << 4,
+      IHEX_START_SEGMENT => entry = Some((value() >> 16 << 4) + (value() & 0xffff)),
+      IHEX_LINEAR_ADDRESS => base = value() << 16,
+      IHEX_START_LINEAR => entry = Some(value()),
+      kind => return Err(FormatError::UnknownRecordType { line: number, kind }),
+    }
+  }
+  Err(FormatError::MissingEnd)
+}
+
+/// a Motorola S-record file with 32-bit addresses in `S3` records and the entry point in the `S7` record
+pub fn write_srec(image: &Image) -> String {
+  let mut srec = String::new();
+  srec_record(&mut srec, 0, &[0, 0], b"xocolatl");
+  let mut count = 0;
+  for section in &image.sections {
+    for (address, chunk) in chunks(section) {
+      srec_record(&mut srec, 3, &address.to_be_bytes(), chunk);
+      count += 1;
+    }
+  }
+  // the count record is optional and only holds 16 or 24 bits
+  match count {
+    0..=0xffff => srec_record(&mut srec, 5, &(count as u16).to_be_bytes(), &[]),
+    0x10000..=0xffffff => srec_record(&mut srec, 6, &(count as u32).to_be_bytes()[1..], &[]),
+    _ => {}
+  }
+  srec_record(&mut srec, 7, &image.entry.to_be_bytes(), &[]);
+  srec
+}
+
+/// read a Motorola S-record file with 16, 24 or 32-bit addresses, sections are named as in [`read_ihex`]
+pub fn read_srec(srec: &str) -> Result<Image, FormatError> {
+  let mut ranges = vec![];
+  let mut entry = None;
+  for (index, line) in srec.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+    let number = index + 1;
+    let line = line.trim();
+    let kind = match line.as_bytes() {
+      [b'S', kind @ b'0'..=b'9', ..] => kind - b'0',
+      _ => return Err(FormatError::InvalidRecord(number)),
+    };
+    let bytes = hex_bytes(&line[2..]).ok_or(FormatError::InvalidRecord(number))?;
+    if bytes.len() < 2 || bytes.len() != bytes[0] as usize + 1 {
+      return Err(FormatError::InvalidRecord(number));
+    }
+    if bytes.iter().fold(0_u8, |sum, byte| sum.wrapping_add(*byte)) != 0xff {
+      return Err(FormatError::Checksum(number));
+    }
+    let address_size = match kind {
+      0 | 1 | 5 | 9 => 2,
+      2 | 6 | 8 => 3,
+      3 | 7 => 4,
+      _ => return Err(FormatError::UnknownRecordType { line: number, kind }),
+    };
+    if bytes.len() < address_size + 2 {
+      return Err(FormatError::InvalidRecord(number));
+    }
+    let address = bytes[1..=address_size].iter().fold(0, |value, byte| value << 8 | *byte as u32);
+    let data = &bytes[address_size + 1..bytes.len() - 1];
+    match kind {
+      1..=3 => ranges.push(data_record(address, data, number)?),
+      7..=9 => entry = Some(address),
+      _ => {}
+    }
+  }
+  Ok(image(ranges, entry))
+}
+
+/// a Verilog `$readmemh` file with an `@address` line for each section and a word per line,
+/// addresses count words of `word_size` bytes, which are written little endian
+pub fn write_readmemh(image: &Image, word_size: usize) -> String {
+  let mut memh = String::new();
+  for section in &image.sections {
+    // start at a word boundary
+    let padding = section.address as usize % word_size;
+    let mut data = vec![0; padding];
+    data.extend(&section.data);
+    writeln!(memh, "@{:08x}", section.address as usize / word_size).unwrap();
+    for word in data.chunks(word_size) {
+      let mut bytes = word.to_vec();
+      bytes.resize(word_size, 0);
+      memh.extend(bytes.iter().rev().map(|byte| format!("{byte:02x}")));
+      memh.push('\n');
+    }
+  }
+  memh
+}
+
+/// data records of a section with their address, aligned so a record never crosses a 64 KiB boundary
+fn chunks(section: &Section) -> Vec<(u32, &[u8])> {
+  let mut chunks = vec![];
+  let mut address = section.address;
+  let mut rest = section.data.as_slice();
+  while !rest.is_empty() {
+    let size = (RECORD_SIZE - address as usize % RECORD_SIZE).min(rest.len());
+    chunks.push((address, &rest[..size]));
+    // the last record may end at the top of the address space
+    address = address.wrapping_add(size as u32);
+    rest = &rest[size..];
+  }
+  chunks
+}
+
+fn ihex_record(hex: &mut String, kind: u8, address: u16, data: &[u8]) {
+  let mut bytes = vec![data.len() as u8];
+  bytes.extend(address.to_be_bytes());
+  bytes.push(kind);
+  bytes.extend(data);
+  let checksum = bytes.iter().fold(0_u8, |sum, byte| sum.wrapping_add(*byte)).wrapping_neg();
+  bytes.push(checksum);
+  hex.push(':');
+  hex.extend(bytes.iter().map(|byte| format!("{byte:02X}")));
+  hex.push('\n');
+}
+
+fn srec_record(srec: &mut String, kind: u8, address: &[u8], data: &[u8]) {
+  let mut bytes = vec![(address.len() + data.len() + 1) as u8];
+  bytes.extend(address);
+  bytes.extend(data);
+  let checksum = !bytes.iter().fold(0_u8, |sum, byte| sum.wrapping_add(*byte));
+  bytes.push(checksum);
+  write!(srec, "S{kind}").unwrap();
+  srec.extend(bytes.iter().map(|byte| format!("{byte:02X}")));
+  srec.push('\n');
+}
+
+fn hex_bytes(digits: &str) -> Option<Vec<u8>> {
+  if !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+    return None;
+  }
+  (0..digits.len()).step_by(2).map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok()).collect()
+}
+
+/// the address and contents of a data record on line `line`, which must not extend past the end of the address space
+fn data_record(address: u32, data: &[u8], line: usize) -> Result<(u32, Vec<u8>), FormatError> {
+  if address as u64 + data.len() as u64 > 1 << 32 {
+    return Err(FormatError::InvalidRecord(line));
+  }
+  Ok((address, data.to_vec()))
+}
+
+/// merge data records into sections, the entry point defaults to the lowest address
+fn image(mut ranges: Vec<(u32, Vec<u8>)>, entry: Option<u32>) -> Image {
+  ranges.sort_by_key(|(address, _)| *address);
+  let mut sections = Vec::<Section>::new();
+  for (address, data) in ranges {
+    match sections.last_mut() {
+      // compared as 64 bits as a section may end at the top of the address space
+      Some(section) if section.address as u64 + section.data.len() as u64 == address as u64 => section.data.extend(data),
+      _ => sections.push(Section { name: format!(".sec{}", sections.len()), address, alignment: 1, data }),
+    }
+  }
+  let entry = entry.or(sections.first().map(|section| section.address)).unwrap_or(0);
+  Image { sections, entry, ..Image::default() }
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use super::machine::Machine;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+  /// a section does not fit into the memory of the machine
+  SectionOutOfMemory { name: String, address: u32, size: u32 },
+}
+
+impl fmt::Display for ImageError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::SectionOutOfMemory { name, address, size } => {
+        write!(f, "section {name} at {address:#010x} of {size:#x} bytes does not fit into memory")
+      }
+    }
+  }
+}
+
+impl std::error::Error for ImageError {}
+
+/// a contiguous block of an assembled program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+  pub name: String,
+  pub address: u32,
+  /// the start address is a multiple of this
+  pub alignment: u32,
+  /// contents, zeros for `.bss`
+  pub data: Vec<u8>,
+}
+
+impl Section {
+  pub fn end(&self) -> u32 {
+    self.address + self.data.len() as u32
+  }
+
+  /// the contents as little endian words, the last word padded with zeros
+  pub fn words(&self) -> Vec<u32> {
+    self.data.chunks(4).map(|chunk| {
+      let mut word = [0; 4];
+      word[..chunk.len()].copy_from_slice(chunk);
+      u32::from_le_bytes(word)
+    }).collect()
+  }
+}
+
+/// an assembled program with its sections placed at their final addresses
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Image {
+  /// sorted by address
+  pub sections: Vec<Section>,
+  pub symbols: BTreeMap<String, u32>,
+  /// symbols declared with `.globl`
+  pub globals: Vec<String>,
+  /// absolute symbols defined with `.equ` or `.set`, which keep their value when rebasing
+  pub constants: Vec<String>,
+  pub entry: u32,
+}
+
+impl Image {
+  pub fn section(&self, name: &str) -> Option<&Section> {
+    self.sections.iter().find(|section| section.name == name)
+  }
+
+  /// move the sections, labels and entry point so the lowest section starts at `base`
+  pub fn rebase(&mut self, base: u32) {
+    let Some(start) = self.sections.iter().map(|section| section.address).min() else {
+      return;
+    };
+    let delta = base.wrapping_sub(start);
+    for section in &mut self.sections {
+      section.address = section.address.wrapping_add(delta);
+    }
+    for (name, value) in &mut self.symbols {
+      if !self.constants.contains(name) {
+        *value = value.wrapping_add(delta);
+      }
+    }
+    self.entry = self.entry.wrapping_add(delta);
+  }
+
+  /// copy all sections into memory and start execution at the entry point
+  pub fn load(&self, machine: &mut Machine) -> Result<(), ImageError> {
+    for section in &self.sections {
+      if section.address as usize + section.data.len() > machine.memory.size() {
+        return Err(ImageError::SectionOutOfMemory {
+          name: section.name.clone(),
+          address: section.address,
+          size: section.data.len() as u32,
+        });
+      }
+      for (address, byte) in (section.address as usize..).zip(&section.data) {
+        machine.memory.store_byte(address, *byte);
+      }
+    }
+    machine.pc = self.entry;
+    Ok(())
+  }
+}
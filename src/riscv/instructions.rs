@@ -1,4 +1,5 @@
 use super::machine::Machine; 
+use super::trap::Exception;
 use super::executors::*;
 use super::assemblers::*;
 use super::disassemblers::*;
@@ -54,6 +55,78 @@ pub enum InstructionIndex {
   DIVU,
   REM,
   REMU,
+  LR_W,
+  SC_W,
+  AMOSWAP_W,
+  AMOADD_W,
+  AMOXOR_W,
+  AMOAND_W,
+  AMOOR_W,
+  AMOMIN_W,
+  AMOMAX_W,
+  AMOMINU_W,
+  AMOMAXU_W,
+  FLW,
+  FSW,
+  FMADD_S,
+  FMSUB_S,
+  FNMSUB_S,
+  FNMADD_S,
+  FADD_S,
+  FSUB_S,
+  FMUL_S,
+  FDIV_S,
+  FSQRT_S,
+  FSGNJ_S,
+  FSGNJN_S,
+  FSGNJX_S,
+  FMIN_S,
+  FMAX_S,
+  FCVT_W_S,
+  FCVT_WU_S,
+  FMV_X_W,
+  FEQ_S,
+  FLT_S,
+  FLE_S,
+  FCLASS_S,
+  FCVT_S_W,
+  FCVT_S_WU,
+  FMV_W_X,
+  FLD,
+  FSD,
+  FMADD_D,
+  FMSUB_D,
+  FNMSUB_D,
+  FNMADD_D,
+  FADD_D,
+  FSUB_D,
+  FMUL_D,
+  FDIV_D,
+  FSQRT_D,
+  FSGNJ_D,
+  FSGNJN_D,
+  FSGNJX_D,
+  FMIN_D,
+  FMAX_D,
+  FCVT_S_D,
+  FCVT_D_S,
+  FEQ_D,
+  FLT_D,
+  FLE_D,
+  FCLASS_D,
+  FCVT_W_D,
+  FCVT_WU_D,
+  FCVT_D_W,
+  FCVT_D_WU,
+  CSRRW,
+  CSRRS,
+  CSRRC,
+  CSRRWI,
+  CSRRSI,
+  CSRRCI,
+  SRET,
+  MRET,
+  SFENCE_VMA,
   UNDEF,
 }
 
@@ -67,9 +140,9 @@ pub struct Instruction {
 }
 
 impl Instruction {
-  pub fn exec(&self, machine: &mut Machine, instruction: u32) {
+  pub fn exec(&self, machine: &mut Machine, instruction: u32) -> Result<(), Exception> {
     println!("Executing {}", self.name);
-    (self.executor)(machine, instruction);
+    (self.executor)(machine, instruction)
   }
 
   pub fn disassemble(&self, instruction: u32, machine: Option<&Machine>) -> String {
@@ -344,7 +417,7 @@ pub const INSTRUCTION_FENCE: Instruction = Instruction {
 };
 
 pub const INSTRUCTION_FENCE_TSO: Instruction = Instruction {
-  name: "fence_tso",
+  name: "fence.tso",
   executor: &FENCE_TSO_EXECUTOR,
   disassembler: &FENCE_TSO_DISASSEMBLER,
   assembler: &FENCE_TSO_ASSEMBLER,
@@ -427,7 +500,511 @@ pub const INSTRUCTION_REMU: Instruction = Instruction {
   assembler: &REMU_ASSEMBLER,
 };
 
-/// sentinel instruction; panics
+pub const INSTRUCTION_LR_W: Instruction = Instruction {
+  name: "lr.w",
+  executor: &LR_W_EXECUTOR,
+  disassembler: &LR_W_DISASSEMBLER,
+  assembler: &LR_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_SC_W: Instruction = Instruction {
+  name: "sc.w",
+  executor: &SC_W_EXECUTOR,
+  disassembler: &SC_W_DISASSEMBLER,
+  assembler: &SC_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_AMOSWAP_W: Instruction = Instruction {
+  name: "amoswap.w",
+  executor: &AMOSWAP_W_EXECUTOR,
+  disassembler: &AMOSWAP_W_DISASSEMBLER,
+  assembler: &AMOSWAP_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_AMOADD_W: Instruction = Instruction {
+  name: "amoadd.w",
+  executor: &AMOADD_W_EXECUTOR,
+  disassembler: &AMOADD_W_DISASSEMBLER,
+  assembler: &AMOADD_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_AMOXOR_W: Instruction = Instruction {
+  name: "amoxor.w",
+  executor: &AMOXOR_W_EXECUTOR,
+  disassembler: &AMOXOR_W_DISASSEMBLER,
+  assembler: &AMOXOR_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_AMOAND_W: Instruction = Instruction {
+  name: "amoand.w",
+  executor: &AMOAND_W_EXECUTOR,
+  disassembler: &AMOAND_W_DISASSEMBLER,
+  assembler: &AMOAND_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_AMOOR_W: Instruction = Instruction {
+  name: "amoor.w",
+  executor: &AMOOR_W_EXECUTOR,
+  disassembler: &AMOOR_W_DISASSEMBLER,
+  assembler: &AMOOR_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_AMOMIN_W: Instruction = Instruction {
+  name: "amomin.w",
+  executor: &AMOMIN_W_EXECUTOR,
+  disassembler: &AMOMIN_W_DISASSEMBLER,
+  assembler: &AMOMIN_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_AMOMAX_W: Instruction = Instruction {
+  name: "amomax.w",
+  executor: &AMOMAX_W_EXECUTOR,
+  disassembler: &AMOMAX_W_DISASSEMBLER,
+  assembler: &AMOMAX_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_AMOMINU_W: Instruction = Instruction {
+  name: "amominu.w",
+  executor: &AMOMINU_W_EXECUTOR,
+  disassembler: &AMOMINU_W_DISASSEMBLER,
+  assembler: &AMOMINU_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_AMOMAXU_W: Instruction = Instruction {
+  name: "amomaxu.w",
+  executor: &AMOMAXU_W_EXECUTOR,
+  disassembler: &AMOMAXU_W_DISASSEMBLER,
+  assembler: &AMOMAXU_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FLW: Instruction = Instruction {
+  name: "flw",
+  executor: &FLW_EXECUTOR,
+  disassembler: &FLW_DISASSEMBLER,
+  assembler: &FLW_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSW: Instruction = Instruction {
+  name: "fsw",
+  executor: &FSW_EXECUTOR,
+  disassembler: &FSW_DISASSEMBLER,
+  assembler: &FSW_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMADD_S: Instruction = Instruction {
+  name: "fmadd.s",
+  executor: &FMADD_S_EXECUTOR,
+  disassembler: &FMADD_S_DISASSEMBLER,
+  assembler: &FMADD_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMSUB_S: Instruction = Instruction {
+  name: "fmsub.s",
+  executor: &FMSUB_S_EXECUTOR,
+  disassembler: &FMSUB_S_DISASSEMBLER,
+  assembler: &FMSUB_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FNMSUB_S: Instruction = Instruction {
+  name: "fnmsub.s",
+  executor: &FNMSUB_S_EXECUTOR,
+  disassembler: &FNMSUB_S_DISASSEMBLER,
+  assembler: &FNMSUB_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FNMADD_S: Instruction = Instruction {
+  name: "fnmadd.s",
+  executor: &FNMADD_S_EXECUTOR,
+  disassembler: &FNMADD_S_DISASSEMBLER,
+  assembler: &FNMADD_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FADD_S: Instruction = Instruction {
+  name: "fadd.s",
+  executor: &FADD_S_EXECUTOR,
+  disassembler: &FADD_S_DISASSEMBLER,
+  assembler: &FADD_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSUB_S: Instruction = Instruction {
+  name: "fsub.s",
+  executor: &FSUB_S_EXECUTOR,
+  disassembler: &FSUB_S_DISASSEMBLER,
+  assembler: &FSUB_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMUL_S: Instruction = Instruction {
+  name: "fmul.s",
+  executor: &FMUL_S_EXECUTOR,
+  disassembler: &FMUL_S_DISASSEMBLER,
+  assembler: &FMUL_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FDIV_S: Instruction = Instruction {
+  name: "fdiv.s",
+  executor: &FDIV_S_EXECUTOR,
+  disassembler: &FDIV_S_DISASSEMBLER,
+  assembler: &FDIV_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSQRT_S: Instruction = Instruction {
+  name: "fsqrt.s",
+  executor: &FSQRT_S_EXECUTOR,
+  disassembler: &FSQRT_S_DISASSEMBLER,
+  assembler: &FSQRT_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSGNJ_S: Instruction = Instruction {
+  name: "fsgnj.s",
+  executor: &FSGNJ_S_EXECUTOR,
+  disassembler: &FSGNJ_S_DISASSEMBLER,
+  assembler: &FSGNJ_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSGNJN_S: Instruction = Instruction {
+  name: "fsgnjn.s",
+  executor: &FSGNJN_S_EXECUTOR,
+  disassembler: &FSGNJN_S_DISASSEMBLER,
+  assembler: &FSGNJN_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSGNJX_S: Instruction = Instruction {
+  name: "fsgnjx.s",
+  executor: &FSGNJX_S_EXECUTOR,
+  disassembler: &FSGNJX_S_DISASSEMBLER,
+  assembler: &FSGNJX_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMIN_S: Instruction = Instruction {
+  name: "fmin.s",
+  executor: &FMIN_S_EXECUTOR,
+  disassembler: &FMIN_S_DISASSEMBLER,
+  assembler: &FMIN_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMAX_S: Instruction = Instruction {
+  name: "fmax.s",
+  executor: &FMAX_S_EXECUTOR,
+  disassembler: &FMAX_S_DISASSEMBLER,
+  assembler: &FMAX_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCVT_W_S: Instruction = Instruction {
+  name: "fcvt.w.s",
+  executor: &FCVT_W_S_EXECUTOR,
+  disassembler: &FCVT_W_S_DISASSEMBLER,
+  assembler: &FCVT_W_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCVT_WU_S: Instruction = Instruction {
+  name: "fcvt.wu.s",
+  executor: &FCVT_WU_S_EXECUTOR,
+  disassembler: &FCVT_WU_S_DISASSEMBLER,
+  assembler: &FCVT_WU_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMV_X_W: Instruction = Instruction {
+  name: "fmv.x.w",
+  executor: &FMV_X_W_EXECUTOR,
+  disassembler: &FMV_X_W_DISASSEMBLER,
+  assembler: &FMV_X_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FEQ_S: Instruction = Instruction {
+  name: "feq.s",
+  executor: &FEQ_S_EXECUTOR,
+  disassembler: &FEQ_S_DISASSEMBLER,
+  assembler: &FEQ_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FLT_S: Instruction = Instruction {
+  name: "flt.s",
+  executor: &FLT_S_EXECUTOR,
+  disassembler: &FLT_S_DISASSEMBLER,
+  assembler: &FLT_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FLE_S: Instruction = Instruction {
+  name: "fle.s",
+  executor: &FLE_S_EXECUTOR,
+  disassembler: &FLE_S_DISASSEMBLER,
+  assembler: &FLE_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCLASS_S: Instruction = Instruction {
+  name: "fclass.s",
+  executor: &FCLASS_S_EXECUTOR,
+  disassembler: &FCLASS_S_DISASSEMBLER,
+  assembler: &FCLASS_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCVT_S_W: Instruction = Instruction {
+  name: "fcvt.s.w",
+  executor: &FCVT_S_W_EXECUTOR,
+  disassembler: &FCVT_S_W_DISASSEMBLER,
+  assembler: &FCVT_S_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCVT_S_WU: Instruction = Instruction {
+  name: "fcvt.s.wu",
+  executor: &FCVT_S_WU_EXECUTOR,
+  disassembler: &FCVT_S_WU_DISASSEMBLER,
+  assembler: &FCVT_S_WU_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMV_W_X: Instruction = Instruction {
+  name: "fmv.w.x",
+  executor: &FMV_W_X_EXECUTOR,
+  disassembler: &FMV_W_X_DISASSEMBLER,
+  assembler: &FMV_W_X_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FLD: Instruction = Instruction {
+  name: "fld",
+  executor: &FLD_EXECUTOR,
+  disassembler: &FLD_DISASSEMBLER,
+  assembler: &FLD_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSD: Instruction = Instruction {
+  name: "fsd",
+  executor: &FSD_EXECUTOR,
+  disassembler: &FSD_DISASSEMBLER,
+  assembler: &FSD_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMADD_D: Instruction = Instruction {
+  name: "fmadd.d",
+  executor: &FMADD_D_EXECUTOR,
+  disassembler: &FMADD_D_DISASSEMBLER,
+  assembler: &FMADD_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMSUB_D: Instruction = Instruction {
+  name: "fmsub.d",
+  executor: &FMSUB_D_EXECUTOR,
+  disassembler: &FMSUB_D_DISASSEMBLER,
+  assembler: &FMSUB_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FNMSUB_D: Instruction = Instruction {
+  name: "fnmsub.d",
+  executor: &FNMSUB_D_EXECUTOR,
+  disassembler: &FNMSUB_D_DISASSEMBLER,
+  assembler: &FNMSUB_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FNMADD_D: Instruction = Instruction {
+  name: "fnmadd.d",
+  executor: &FNMADD_D_EXECUTOR,
+  disassembler: &FNMADD_D_DISASSEMBLER,
+  assembler: &FNMADD_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FADD_D: Instruction = Instruction {
+  name: "fadd.d",
+  executor: &FADD_D_EXECUTOR,
+  disassembler: &FADD_D_DISASSEMBLER,
+  assembler: &FADD_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSUB_D: Instruction = Instruction {
+  name: "fsub.d",
+  executor: &FSUB_D_EXECUTOR,
+  disassembler: &FSUB_D_DISASSEMBLER,
+  assembler: &FSUB_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMUL_D: Instruction = Instruction {
+  name: "fmul.d",
+  executor: &FMUL_D_EXECUTOR,
+  disassembler: &FMUL_D_DISASSEMBLER,
+  assembler: &FMUL_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FDIV_D: Instruction = Instruction {
+  name: "fdiv.d",
+  executor: &FDIV_D_EXECUTOR,
+  disassembler: &FDIV_D_DISASSEMBLER,
+  assembler: &FDIV_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSQRT_D: Instruction = Instruction {
+  name: "fsqrt.d",
+  executor: &FSQRT_D_EXECUTOR,
+  disassembler: &FSQRT_D_DISASSEMBLER,
+  assembler: &FSQRT_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSGNJ_D: Instruction = Instruction {
+  name: "fsgnj.d",
+  executor: &FSGNJ_D_EXECUTOR,
+  disassembler: &FSGNJ_D_DISASSEMBLER,
+  assembler: &FSGNJ_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSGNJN_D: Instruction = Instruction {
+  name: "fsgnjn.d",
+  executor: &FSGNJN_D_EXECUTOR,
+  disassembler: &FSGNJN_D_DISASSEMBLER,
+  assembler: &FSGNJN_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FSGNJX_D: Instruction = Instruction {
+  name: "fsgnjx.d",
+  executor: &FSGNJX_D_EXECUTOR,
+  disassembler: &FSGNJX_D_DISASSEMBLER,
+  assembler: &FSGNJX_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMIN_D: Instruction = Instruction {
+  name: "fmin.d",
+  executor: &FMIN_D_EXECUTOR,
+  disassembler: &FMIN_D_DISASSEMBLER,
+  assembler: &FMIN_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FMAX_D: Instruction = Instruction {
+  name: "fmax.d",
+  executor: &FMAX_D_EXECUTOR,
+  disassembler: &FMAX_D_DISASSEMBLER,
+  assembler: &FMAX_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCVT_S_D: Instruction = Instruction {
+  name: "fcvt.s.d",
+  executor: &FCVT_S_D_EXECUTOR,
+  disassembler: &FCVT_S_D_DISASSEMBLER,
+  assembler: &FCVT_S_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCVT_D_S: Instruction = Instruction {
+  name: "fcvt.d.s",
+  executor: &FCVT_D_S_EXECUTOR,
+  disassembler: &FCVT_D_S_DISASSEMBLER,
+  assembler: &FCVT_D_S_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FEQ_D: Instruction = Instruction {
+  name: "feq.d",
+  executor: &FEQ_D_EXECUTOR,
+  disassembler: &FEQ_D_DISASSEMBLER,
+  assembler: &FEQ_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FLT_D: Instruction = Instruction {
+  name: "flt.d",
+  executor: &FLT_D_EXECUTOR,
+  disassembler: &FLT_D_DISASSEMBLER,
+  assembler: &FLT_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FLE_D: Instruction = Instruction {
+  name: "fle.d",
+  executor: &FLE_D_EXECUTOR,
+  disassembler: &FLE_D_DISASSEMBLER,
+  assembler: &FLE_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCLASS_D: Instruction = Instruction {
+  name: "fclass.d",
+  executor: &FCLASS_D_EXECUTOR,
+  disassembler: &FCLASS_D_DISASSEMBLER,
+  assembler: &FCLASS_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCVT_W_D: Instruction = Instruction {
+  name: "fcvt.w.d",
+  executor: &FCVT_W_D_EXECUTOR,
+  disassembler: &FCVT_W_D_DISASSEMBLER,
+  assembler: &FCVT_W_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCVT_WU_D: Instruction = Instruction {
+  name: "fcvt.wu.d",
+  executor: &FCVT_WU_D_EXECUTOR,
+  disassembler: &FCVT_WU_D_DISASSEMBLER,
+  assembler: &FCVT_WU_D_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCVT_D_W: Instruction = Instruction {
+  name: "fcvt.d.w",
+  executor: &FCVT_D_W_EXECUTOR,
+  disassembler: &FCVT_D_W_DISASSEMBLER,
+  assembler: &FCVT_D_W_ASSEMBLER,
+};
+
+pub const INSTRUCTION_FCVT_D_WU: Instruction = Instruction {
+  name: "fcvt.d.wu",
+  executor: &FCVT_D_WU_EXECUTOR,
+  disassembler: &FCVT_D_WU_DISASSEMBLER,
+  assembler: &FCVT_D_WU_ASSEMBLER,
+};
+
+pub const INSTRUCTION_CSRRW: Instruction = Instruction {
+  name: "csrrw",
+  executor: &CSRRW_EXECUTOR,
+  disassembler: &CSRRW_DISASSEMBLER,
+  assembler: &CSRRW_ASSEMBLER,
+};
+
+pub const INSTRUCTION_CSRRS: Instruction = Instruction {
+  name: "csrrs",
+  executor: &CSRRS_EXECUTOR,
+  disassembler: &CSRRS_DISASSEMBLER,
+  assembler: &CSRRS_ASSEMBLER,
+};
+
+pub const INSTRUCTION_CSRRC: Instruction = Instruction {
+  name: "csrrc",
+  executor: &CSRRC_EXECUTOR,
+  disassembler: &CSRRC_DISASSEMBLER,
+  assembler: &CSRRC_ASSEMBLER,
+};
+
+pub const INSTRUCTION_CSRRWI: Instruction = Instruction {
+  name: "csrrwi",
+  executor: &CSRRWI_EXECUTOR,
+  disassembler: &CSRRWI_DISASSEMBLER,
+  assembler: &CSRRWI_ASSEMBLER,
+};
+
+pub const INSTRUCTION_CSRRSI: Instruction = Instruction {
+  name: "csrrsi",
+  executor: &CSRRSI_EXECUTOR,
+  disassembler: &CSRRSI_DISASSEMBLER,
+  assembler: &CSRRSI_ASSEMBLER,
+};
+
+pub const INSTRUCTION_CSRRCI: Instruction = Instruction {
+  name: "csrrci",
+  executor: &CSRRCI_EXECUTOR,
+  disassembler: &CSRRCI_DISASSEMBLER,
+  assembler: &CSRRCI_ASSEMBLER,
+};
+
+pub const INSTRUCTION_SRET: Instruction = Instruction {
+  name: "sret",
+  executor: &SRET_EXECUTOR,
+  disassembler: &SRET_DISASSEMBLER,
+  assembler: &SRET_ASSEMBLER,
+};
+
+pub const INSTRUCTION_MRET: Instruction = Instruction {
+  name: "mret",
+  executor: &MRET_EXECUTOR,
+  disassembler: &MRET_DISASSEMBLER,
+  assembler: &MRET_ASSEMBLER,
+};
+
+pub const INSTRUCTION_SFENCE_VMA: Instruction = Instruction {
+  name: "sfence.vma",
+  executor: &SFENCE_VMA_EXECUTOR,
+  disassembler: &SFENCE_VMA_DISASSEMBLER,
+  assembler: &SFENCE_VMA_ASSEMBLER,
+};
+
+/// sentinel instruction; raises an illegal instruction exception
 pub const INSTRUCTION_UNDEF: Instruction = Instruction {
   name: "undef",
   executor: &UNDEF_EXECUTOR,
@@ -488,6 +1065,78 @@ pub const INSTRUCTIONS: [Instruction; INSTRUCTION_COUNT] = {
   instructions[DIVU as usize] = INSTRUCTION_DIVU;
   instructions[REM as usize] = INSTRUCTION_REM;
   instructions[REMU as usize] = INSTRUCTION_REMU;
+  instructions[LR_W as usize] = INSTRUCTION_LR_W;
+  instructions[SC_W as usize] = INSTRUCTION_SC_W;
+  instructions[AMOSWAP_W as usize] = INSTRUCTION_AMOSWAP_W;
+  instructions[AMOADD_W as usize] = INSTRUCTION_AMOADD_W;
+  instructions[AMOXOR_W as usize] = INSTRUCTION_AMOXOR_W;
+  instructions[AMOAND_W as usize] = INSTRUCTION_AMOAND_W;
+  instructions[AMOOR_W as usize] = INSTRUCTION_AMOOR_W;
+  instructions[AMOMIN_W as usize] = INSTRUCTION_AMOMIN_W;
+  instructions[AMOMAX_W as usize] = INSTRUCTION_AMOMAX_W;
+  instructions[AMOMINU_W as usize] = INSTRUCTION_AMOMINU_W;
+  instructions[AMOMAXU_W as usize] = INSTRUCTION_AMOMAXU_W;
+  instructions[FLW as usize] = INSTRUCTION_FLW;
+  instructions[FSW as usize] = INSTRUCTION_FSW;
+  instructions[FMADD_S as usize] = INSTRUCTION_FMADD_S;
+  instructions[FMSUB_S as usize] = INSTRUCTION_FMSUB_S;
+  instructions[FNMSUB_S as usize] = INSTRUCTION_FNMSUB_S;
+  instructions[FNMADD_S as usize] = INSTRUCTION_FNMADD_S;
+  instructions[FADD_S as usize] = INSTRUCTION_FADD_S;
+  instructions[FSUB_S as usize] = INSTRUCTION_FSUB_S;
+  instructions[FMUL_S as usize] = INSTRUCTION_FMUL_S;
+  instructions[FDIV_S as usize] = INSTRUCTION_FDIV_S;
+  instructions[FSQRT_S as usize] = INSTRUCTION_FSQRT_S;
+  instructions[FSGNJ_S as usize] = INSTRUCTION_FSGNJ_S;
+  instructions[FSGNJN_S as usize] = INSTRUCTION_FSGNJN_S;
+  instructions[FSGNJX_S as usize] = INSTRUCTION_FSGNJX_S;
+  instructions[FMIN_S as usize] = INSTRUCTION_FMIN_S;
+  instructions[FMAX_S as usize] = INSTRUCTION_FMAX_S;
+  instructions[FCVT_W_S as usize] = INSTRUCTION_FCVT_W_S;
+  instructions[FCVT_WU_S as usize] = INSTRUCTION_FCVT_WU_S;
+  instructions[FMV_X_W as usize] = INSTRUCTION_FMV_X_W;
+  instructions[FEQ_S as usize] = INSTRUCTION_FEQ_S;
+  instructions[FLT_S as usize] = INSTRUCTION_FLT_S;
+  instructions[FLE_S as usize] = INSTRUCTION_FLE_S;
+  instructions[FCLASS_S as usize] = INSTRUCTION_FCLASS_S;
+  instructions[FCVT_S_W as usize] = INSTRUCTION_FCVT_S_W;
+  instructions[FCVT_S_WU as usize] = INSTRUCTION_FCVT_S_WU;
+  instructions[FMV_W_X as usize] = INSTRUCTION_FMV_W_X;
+  instructions[FLD as usize] = INSTRUCTION_FLD;
+  instructions[FSD as usize] = INSTRUCTION_FSD;
+  instructions[FMADD_D as usize] = INSTRUCTION_FMADD_D;
+  instructions[FMSUB_D as usize] = INSTRUCTION_FMSUB_D;
+  instructions[FNMSUB_D as usize] = INSTRUCTION_FNMSUB_D;
+  instructions[FNMADD_D as usize] = INSTRUCTION_FNMADD_D;
+  instructions[FADD_D as usize] = INSTRUCTION_FADD_D;
+  instructions[FSUB_D as usize] = INSTRUCTION_FSUB_D;
+  instructions[FMUL_D as usize] = INSTRUCTION_FMUL_D;
+  instructions[FDIV_D as usize] = INSTRUCTION_FDIV_D;
+  instructions[FSQRT_D as usize] = INSTRUCTION_FSQRT_D;
+  instructions[FSGNJ_D as usize] = INSTRUCTION_FSGNJ_D;
+  instructions[FSGNJN_D as usize] = INSTRUCTION_FSGNJN_D;
+  instructions[FSGNJX_D as usize] = INSTRUCTION_FSGNJX_D;
+  instructions[FMIN_D as usize] = INSTRUCTION_FMIN_D;
+  instructions[FMAX_D as usize] = INSTRUCTION_FMAX_D;
+  instructions[FCVT_S_D as usize] = INSTRUCTION_FCVT_S_D;
+  instructions[FCVT_D_S as usize] = INSTRUCTION_FCVT_D_S;
+  instructions[FEQ_D as usize] = INSTRUCTION_FEQ_D;
+  instructions[FLT_D as usize] = INSTRUCTION_FLT_D;
+  instructions[FLE_D as usize] = INSTRUCTION_FLE_D;
+  instructions[FCLASS_D as usize] = INSTRUCTION_FCLASS_D;
+  instructions[FCVT_W_D as usize] = INSTRUCTION_FCVT_W_D;
+  instructions[FCVT_WU_D as usize] = INSTRUCTION_FCVT_WU_D;
+  instructions[FCVT_D_W as usize] = INSTRUCTION_FCVT_D_W;
+  instructions[FCVT_D_WU as usize] = INSTRUCTION_FCVT_D_WU;
+  instructions[CSRRW as usize] = INSTRUCTION_CSRRW;
+  instructions[CSRRS as usize] = INSTRUCTION_CSRRS;
+  instructions[CSRRC as usize] = INSTRUCTION_CSRRC;
+  instructions[CSRRWI as usize] = INSTRUCTION_CSRRWI;
+  instructions[CSRRSI as usize] = INSTRUCTION_CSRRSI;
+  instructions[CSRRCI as usize] = INSTRUCTION_CSRRCI;
+  instructions[SRET as usize] = INSTRUCTION_SRET;
+  instructions[MRET as usize] = INSTRUCTION_MRET;
+  instructions[SFENCE_VMA as usize] = INSTRUCTION_SFENCE_VMA;
 
   instructions
 };
@@ -0,0 +1,583 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use super::assemble::{align, section_rank};
+use super::assemblers::check_signed;
+use super::compressed::set_compressed_offset;
+use super::error::ErrorKind;
+use super::expr::constant;
+use super::image::{Image, Section};
+use super::object::{Object, RelocationKind, SymbolSection};
+use super::slices::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+  /// a linker script that could not be parsed, at a line counting from 1
+  Script { line: usize, message: String },
+  UnknownRegion(String),
+  /// an output section does not fit into the memory region it is placed in
+  RegionOverflow { region: String, section: String },
+  OverlappingSections(String, String),
+  /// an output section extends past the end of the 32-bit address space
+  AddressOverflow(String),
+  UndefinedSymbol(String),
+  /// a global symbol defined in more than one object
+  DuplicateSymbol(String),
+  /// the label of a `%pcrel_lo` is not at an `auipc` with a `%pcrel_hi`
+  MissingPcrelHi(String),
+  /// the value for a relocation does not fit into the instruction
+  Relocation { symbol: String, kind: RelocationKind, error: ErrorKind },
+}
+
+impl fmt::Display for LinkError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Script { line, message } => write!(f, "linker script line {line}: {message}"),
+      Self::UnknownRegion(region) => write!(f, "unknown memory region `{region}`"),
+      Self::RegionOverflow { region, section } => write!(f, "section `{section}` does not fit into memory region `{region}`"),
+      Self::OverlappingSections(first, second) => write!(f, "sections `{first}` and `{second}` overlap"),
+      Self::AddressOverflow(section) => write!(f, "section `{section}` extends past the end of the address space"),
+      Self::UndefinedSymbol(symbol) => write!(f, "undefined symbol `{symbol}`"),
+      Self::DuplicateSymbol(symbol) => write!(f, "symbol `{symbol}` is defined in more than one file"),
+      Self::MissingPcrelHi(label) => write!(f, "no `%pcrel_hi` at label `{label}`"),
+      Self::Relocation { symbol, kind, error } => write!(f, "relocation {kind:?} against `{symbol}`: {error}"),
+    }
+  }
+}
+
+impl std::error::Error for LinkError {}
+
+/// a part of the address space sections can be placed in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRegion {
+  pub name: String,
+  pub origin: u32,
+  pub length: u32,
+}
+
+/// the right hand side of an assignment in a linker script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+  Number(u32),
+  /// `.`, the location counter
+  Location,
+  /// `ALIGN(n)`, the location counter rounded up to a multiple of `n`
+  Align(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputSection {
+  pub name: String,
+  pub address: Option<u32>,
+  /// input section name patterns with `*` and `?` wildcards,
+  /// each statement like `*(.text .text.*)` places all its matches before the next one
+  pub inputs: Vec<Vec<String>>,
+  /// `> REGION`
+  pub region: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SectionCommand {
+  /// `symbol = value;`, assigning to `.` moves the location counter
+  Assign { symbol: String, value: Value },
+  Output(OutputSection),
+}
+
+/// the subset of GNU ld scripts with `ENTRY`, `MEMORY` and `SECTIONS`:
+/// ```text
+/// ENTRY(_start)
+/// MEMORY {
+///   ROM (rx) : ORIGIN = 0x0, LENGTH = 64K
+///   RAM (rw) : ORIGIN = 0x10000, LENGTH = 64K
+/// }
+/// SECTIONS {
+///   .text : { *(.text .text.*) } > ROM
+///   .data 0x10000 : { *(.rodata*) *(.data*) }
+///   .bss : { *(.bss*) } > RAM
+///   _end = .;
+/// }
+/// ```
+/// input sections not matched by any pattern are placed after the last output section
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkerScript {
+  pub entry: Option<String>,
+  pub memory: Vec<MemoryRegion>,
+  pub sections: Vec<SectionCommand>,
+}
+
+impl LinkerScript {
+  pub fn parse(script: &str) -> Result<Self, LinkError> {
+    let mut parser = ScriptParser { tokens: tokenize(script), position: 0 };
+    let mut result = Self::default();
+    while let Some(token) = parser.next() {
+      match token {
+        "ENTRY" => {
+          parser.expect("(")?;
+          result.entry = Some(parser.word()?.to_owned());
+          parser.expect(")")?;
+        }
+        "MEMORY" => {
+          parser.expect("{")?;
+          while !parser.accept("}") {
+            result.memory.push(parser.region()?);
+          }
+        }
+        "SECTIONS" => {
+          parser.expect("{")?;
+          while !parser.accept("}") {
+            let name = parser.word()?;
+            result.sections.push(parser.section_command(name)?);
+          }
+        }
+        symbol => result.sections.push(parser.assignment(symbol)?),
+      }
+    }
+    Ok(result)
+  }
+
+  /// the layout of [`assemble_image`](super::assemble::assemble_image), each section name
+  /// gets an output section, the standard sections first
+  fn default_for(objects: &[Object]) -> Self {
+    let mut names = Vec::<&str>::new();
+    for section in objects.iter().flat_map(|object| &object.sections) {
+      if !names.contains(&section.name.as_str()) {
+        names.push(&section.name);
+      }
+    }
+    names.sort_by_key(|name| section_rank(name));
+    let sections = names.into_iter()
+      .map(|name| SectionCommand::Output(OutputSection {
+        name: name.to_owned(),
+        address: None,
+        inputs: vec![vec![name.to_owned()]],
+        region: None,
+      }))
+      .collect();
+    Self { entry: None, memory: vec![], sections }
+  }
+}
+
+struct ScriptParser<'a> {
+  /// tokens with their line
+  tokens: Vec<(&'a str, usize)>,
+  position: usize,
+}
+
+impl<'a> ScriptParser<'a> {
+  fn next(&mut self) -> Option<&'a str> {
+    let token = self.tokens.get(self.position)?.0;
+    self.position += 1;
+    Some(token)
+  }
+
+  fn peek(&self) -> Option<&'a str> {
+    self.tokens.get(self.position).map(|(token, _)| *token)
+  }
+
+  fn accept(&mut self, token: &str) -> bool {
+    let accepted = self.peek() == Some(token);
+    self.position += accepted as usize;
+    accepted
+  }
+
+  fn error(&self, message: String) -> LinkError {
+    let line = self.tokens.get(self.position).or(self.tokens.last()).map_or(1, |(_, line)| *line);
+    LinkError::Script { line, message }
+  }
+
+  fn expect(&mut self, token: &str) -> Result<(), LinkError> {
+    match self.peek() {
+      Some(found) if found == token => {
+        self.position += 1;
+        Ok(())
+      }
+      Some(found) => Err(self.error(format!("expected `{token}`, found `{found}`"))),
+      None => Err(self.error(format!("expected `{token}` at the end"))),
+    }
+  }
+
+  fn word(&mut self) -> Result<&'a str, LinkError> {
+    match self.peek() {
+      Some(token) if is_word(token) => {
+        self.position += 1;
+        Ok(token)
+      }
+      Some(found) => Err(self.error(format!("expected a name, found `{found}`"))),
+      None => Err(self.error("expected a name at the end".to_owned())),
+    }
+  }
+
+  /// a number with an optional `K` or `M` suffix
+  fn number(&mut self) -> Result<u32, LinkError> {
+    let token = self.word()?;
+    let (digits, scale) = match token.as_bytes().last() {
+      Some(b'K' | b'k') => (&token[..token.len() - 1], 1 << 10),
+      Some(b'M' | b'm') => (&token[..token.len() - 1], 1 << 20),
+      _ => (token, 1),
+    };
+    match constant(digits).and_then(|value| u32::try_from(value * scale).ok()) {
+      Some(value) => Ok(value),
+      None => {
+        self.position -= 1;
+        Err(self.error(format!("expected a number, found `{token}`")))
+      }
+    }
+  }
+
+  /// `NAME (attributes) : ORIGIN = number, LENGTH = number`
+  fn region(&mut self) -> Result<MemoryRegion, LinkError> {
+    let name = self.word()?.to_owned();
+    if self.accept("(") {
+      while !self.accept(")") {
+        self.word()?;
+      }
+    }
+    self.expect(":")?;
+    let field = |parser: &mut Self, names: [&str; 3]| {
+      match parser.word()? {
+        word if names.contains(&word) => {
+          parser.expect("=")?;
+          parser.number()
+        }
+        found => {
+          parser.position -= 1;
+          Err(parser.error(format!("expected `{}`, found `{found}`", names[0])))
+        }
+      }
+    };
+    let origin = field(self, ["ORIGIN", "org", "o"])?;
+    self.expect(",")?;
+    let length = field(self, ["LENGTH", "len", "l"])?;
+    Ok(MemoryRegion { name, origin, length })
+  }
+
+  /// an assignment or `name [address] : { inputs } [> region]`
+  fn section_command(&mut self, name: &'a str) -> Result<SectionCommand, LinkError> {
+    if self.peek() == Some("=") {
+      return self.assignment(name);
+    }
+    let address = match self.peek() {
+      Some(":") => None,
+      _ => Some(self.number()?),
+    };
+    self.expect(":")?;
+    self.expect("{")?;
+    let mut inputs = vec![];
+    while !self.accept("}") {
+      let keep = self.accept("KEEP");
+      if keep {
+        self.expect("(")?;
+      }
+      // only the file pattern `*` is supported, all objects are anonymous
+      self.expect("*")?;
+      self.expect("(")?;
+      let mut patterns = vec![];
+      while !self.accept(")") {
+        patterns.push(self.word()?.to_owned());
+      }
+      if keep {
+        self.expect(")")?;
+      }
+      inputs.push(patterns);
+    }
+    let region = match self.accept(">") {
+      true => Some(self.word()?.to_owned()),
+      false => None,
+    };
+    Ok(SectionCommand::Output(OutputSection { name: name.to_owned(), address, inputs, region }))
+  }
+
+  /// `symbol = value;` after the symbol
+  fn assignment(&mut self, symbol: &str) -> Result<SectionCommand, LinkError> {
+    if !is_word(symbol) {
+      self.position -= 1;
+      return Err(self.error(format!("unexpected `{symbol}`")));
+    }
+    self.expect("=")?;
+    let value = match self.peek() {
+      Some(".") => {
+        self.position += 1;
+        Value::Location
+      }
+      Some("ALIGN") => {
+        self.position += 1;
+        self.expect("(")?;
+        let alignment = self.number()?;
+        self.expect(")")?;
+        Value::Align(alignment)
+      }
+      _ => Value::Number(self.number()?),
+    };
+    self.expect(";")?;
+    Ok(SectionCommand::Assign { symbol: symbol.to_owned(), value })
+  }
+}
+
+const PUNCTUATION: &str = "{}():;=,>";
+
+fn is_word(token: &str) -> bool {
+  !token.starts_with(|c| PUNCTUATION.contains(c))
+}
+
+/// split a script into names, numbers and punctuation, skipping `/* */` comments
+fn tokenize(script: &str) -> Vec<(&str, usize)> {
+  let mut tokens = vec![];
+  let mut line = 1;
+  let mut rest = script;
+  while let Some(c) = rest.chars().next() {
+    let length = if c.is_whitespace() {
+      c.len_utf8()
+    } else if rest.starts_with("/*") {
+      rest.find("*/").map_or(rest.len(), |end| end + 2)
+    } else if PUNCTUATION.contains(c) {
+      tokens.push((&rest[..1], line));
+      1
+    } else {
+      let end = rest.find(|c: char| c.is_whitespace() || PUNCTUATION.contains(c)).unwrap_or(rest.len());
+      tokens.push((&rest[..end], line));
+      end
+    };
+    line += rest[..length].matches('\n').count();
+    rest = &rest[length..];
+  }
+  tokens
+}
+
+/// match a name against a pattern where `*` matches any characters and `?` any one character
+fn matches(pattern: &str, name: &str) -> bool {
+  match pattern.chars().next() {
+    None => name.is_empty(),
+    Some('*') => (0..=name.len()).filter(|i| name.is_char_boundary(*i)).any(|i| matches(&pattern[1..], &name[i..])),
+    Some(c) => name.chars().next().is_some_and(|first| (c == '?' || c == first) && matches(&pattern[c.len_utf8()..], &name[first.len_utf8()..])),
+  }
+}
+
+/// link relocatable objects from [`assemble_object`](super::assemble::assemble_object) into an image,
+/// placing their sections as described by the script or one after another from address 0 without one.
+/// local symbols are only visible in their object, global ones in all objects
+pub fn link(objects: &[Object], script: Option<&LinkerScript>) -> Result<Image, LinkError> {
+  let default;
+  let script = match script {
+    Some(script) => script,
+    None => {
+      default = LinkerScript::default_for(objects);
+      &default
+    }
+  };
+
+  // assign input sections to output sections
+  let mut placed = objects.iter().map(|object| vec![None; object.sections.len()]).collect::<Vec<_>>();
+  let mut commands = vec![];
+  let mut output = |name: &str, address, region: Option<&String>, patterns: &[Vec<String>]| {
+    let mut inputs = vec![];
+    for patterns in patterns {
+      for (index, object) in objects.iter().enumerate() {
+        for (section_index, section) in object.sections.iter().enumerate() {
+          if placed[index][section_index].is_none() && patterns.iter().any(|pattern| matches(pattern, &section.name)) {
+            placed[index][section_index] = Some((0, 0));
+            inputs.push((index, section_index));
+          }
+        }
+      }
+    }
+    Command::Output { name: name.to_owned(), address, region: region.cloned(), inputs }
+  };
+  for command in &script.sections {
+    commands.push(match command {
+      SectionCommand::Assign { symbol, value } => Command::Assign(symbol, *value),
+      SectionCommand::Output(section) => output(&section.name, section.address, section.region.as_ref(), &section.inputs),
+    });
+  }
+  let orphans = objects.iter().flat_map(|object| &object.sections).map(|section| section.name.clone()).collect::<Vec<_>>();
+  for name in orphans {
+    commands.push(output(&name, None, None, &[vec![name.clone()]]));
+  }
+
+  // place output sections
+  let mut sections = vec![];
+  let mut symbols = HashMap::new();
+  let mut location = 0;
+  let mut cursors = script.memory.iter().map(|region| (region.name.as_str(), region.origin)).collect::<HashMap<_, _>>();
+  for command in commands {
+    let (name, address, region, inputs) = match command {
+      Command::Assign(symbol, value) => {
+        let value = match value {
+          Value::Number(value) => value,
+          Value::Location => location,
+          Value::Align(alignment) => align(location, alignment),
+        };
+        match symbol.as_str() {
+          "." => location = value,
+          _ => _ = symbols.insert(symbol.clone(), value),
+        }
+        continue;
+      }
+      Command::Output { name, address, region, inputs } => (name, address, region, inputs),
+    };
+    let alignment = inputs.iter().map(|(object, section)| objects[*object].sections[*section].alignment).max().unwrap_or(1);
+    let start = match (address, &region) {
+      (Some(address), _) => Some(address),
+      (None, Some(region)) => {
+        let cursor = cursors.get(region.as_str()).ok_or_else(|| LinkError::UnknownRegion(region.clone()))?;
+        cursor.checked_next_multiple_of(alignment.max(1))
+      }
+      (None, None) => location.checked_next_multiple_of(alignment.max(1)),
+    };
+    let Some(start) = start else {
+      return Err(LinkError::AddressOverflow(name));
+    };
+    let mut data = vec![];
+    for (object, section) in inputs {
+      let input = &objects[object].sections[section];
+      data.resize(align(data.len() as u32, input.alignment) as usize, 0);
+      placed[object][section] = Some((sections.len(), data.len() as u32));
+      data.extend(&input.data);
+    }
+    let Some(end) = u32::try_from(data.len()).ok().and_then(|size| start.checked_add(size)) else {
+      return Err(LinkError::AddressOverflow(name));
+    };
+    let section = Section { name, address: start, alignment, data };
+    if let Some(region) = &region {
+      let memory = script.memory.iter().find(|memory| memory.name == *region).ok_or_else(|| LinkError::UnknownRegion(region.clone()))?;
+      let fits = section.address >= memory.origin && end as u64 <= memory.origin as u64 + memory.length as u64;
+      if !fits {
+        return Err(LinkError::RegionOverflow { region: region.clone(), section: section.name });
+      }
+      cursors.insert(&memory.name, end);
+    }
+    location = end;
+    sections.push(section);
+  }
+  let mut order = sections.iter().filter(|section| !section.data.is_empty()).collect::<Vec<_>>();
+  order.sort_by_key(|section| section.address);
+  if let Some(pair) = order.windows(2).find(|pair| pair[0].end() > pair[1].address) {
+    return Err(LinkError::OverlappingSections(pair[0].name.clone(), pair[1].name.clone()));
+  }
+
+  // resolve symbols
+  let address = |object: usize, section: usize, offset: u32| {
+    let (output, start) = placed[object][section].unwrap();
+    sections[output].address + start + offset
+  };
+  let value = |object: usize, name: &str| {
+    objects[object].symbol(name).and_then(|symbol| match symbol.section {
+      SymbolSection::Undefined => None,
+      SymbolSection::Absolute => Some(symbol.value),
+      SymbolSection::Section(section) => Some(address(object, section, symbol.value)),
+    })
+  };
+  let mut globals = HashMap::new();
+  // absolute symbols keep their value when the image is rebased
+  let mut constants = vec![];
+  for (index, object) in objects.iter().enumerate() {
+    for symbol in object.symbols.iter().filter(|symbol| symbol.global) {
+      if let Some(value) = value(index, &symbol.name) {
+        if globals.insert(symbol.name.as_str(), value).is_some() {
+          return Err(LinkError::DuplicateSymbol(symbol.name.clone()));
+        }
+        if symbol.section == SymbolSection::Absolute {
+          constants.push(symbol.name.clone());
+        }
+      }
+    }
+  }
+  for (symbol, value) in &symbols {
+    globals.insert(symbol, *value);
+    constants.retain(|name| name != symbol);
+  }
+  let resolve = |object: usize, name: &str| {
+    value(object, name).or_else(|| globals.get(name).copied()).ok_or_else(|| LinkError::UndefinedSymbol(name.to_owned()))
+  };
+
+  // locals of different objects may share a name, the first one is kept
+  let mut image_symbols = globals.iter().map(|(name, value)| (name.to_string(), *value)).collect::<BTreeMap<_, _>>();
+  for (index, object) in objects.iter().enumerate() {
+    for symbol in object.symbols.iter().filter(|symbol| !symbol.name.starts_with(".L")) {
+      if let (false, Some(value)) = (image_symbols.contains_key(&symbol.name), value(index, &symbol.name)) {
+        image_symbols.insert(symbol.name.clone(), value);
+        if symbol.section == SymbolSection::Absolute {
+          constants.push(symbol.name.clone());
+        }
+      }
+    }
+  }
+
+  // patch relocations
+  let mut patches = vec![];
+  for (index, object) in objects.iter().enumerate() {
+    let mut pcrel_hi = HashMap::new();
+    for relocation in object.relocations.iter().filter(|relocation| relocation.kind == RelocationKind::PcrelHi20) {
+      let place = address(index, relocation.section, relocation.offset);
+      let target = resolve(index, &relocation.symbol)?.wrapping_add(relocation.addend as u32);
+      pcrel_hi.insert(place, target.wrapping_sub(place));
+    }
+    for relocation in &object.relocations {
+      let place = address(index, relocation.section, relocation.offset);
+      let target = resolve(index, &relocation.symbol)?.wrapping_add(relocation.addend as u32);
+      let value = match relocation.kind {
+        RelocationKind::PcrelLo12I | RelocationKind::PcrelLo12S => {
+          *pcrel_hi.get(&target).ok_or_else(|| LinkError::MissingPcrelHi(relocation.symbol.clone()))?
+        }
+        kind if kind.is_pc_relative() => target.wrapping_sub(place),
+        _ => target,
+      };
+      let (output, start) = placed[index][relocation.section].unwrap();
+      patches.push((output, start + relocation.offset, relocation, value));
+    }
+  }
+  for (output, offset, relocation, value) in patches {
+    let data = &mut sections[output].data;
+    let error = |error| LinkError::Relocation { symbol: relocation.symbol.clone(), kind: relocation.kind, error };
+    let word = |data: &[u8], offset: u32| u32::from_le_bytes(data[offset as usize..offset as usize + 4].try_into().unwrap());
+    if let RelocationKind::RvcBranch | RelocationKind::RvcJump = relocation.kind {
+      let halfword = u16::from_le_bytes(data[offset as usize..offset as usize + 2].try_into().unwrap());
+      let patched = set_compressed_offset(halfword, value).map_err(error)?;
+      data[offset as usize..offset as usize + 2].copy_from_slice(&patched.to_le_bytes());
+      continue;
+    }
+    let instruction = word(data, offset);
+    let patched = match relocation.kind {
+      RelocationKind::Absolute32 => value,
+      RelocationKind::Branch => set_B_imm_bits(instruction, check_signed(value, 13, 2).map_err(error)?),
+      RelocationKind::Jal => set_J_imm_bits(instruction, check_signed(value, 21, 2).map_err(error)?),
+      RelocationKind::Call => {
+        let jalr = set_I_imm_bits(word(data, offset + 4), lo(value));
+        data[offset as usize + 4..offset as usize + 8].copy_from_slice(&jalr.to_le_bytes());
+        set_U_imm_bits(instruction, hi(value))
+      }
+      RelocationKind::PcrelHi20 | RelocationKind::Hi20 => set_U_imm_bits(instruction, hi(value)),
+      RelocationKind::PcrelLo12I | RelocationKind::Lo12I => set_I_imm_bits(instruction, lo(value)),
+      RelocationKind::PcrelLo12S | RelocationKind::Lo12S => set_S_imm_bits(instruction, lo(value)),
+      RelocationKind::RvcBranch | RelocationKind::RvcJump => unreachable!("compressed instructions are patched above"),
+    };
+    data[offset as usize..offset as usize + 4].copy_from_slice(&patched.to_le_bytes());
+  }
+
+  let entry = match &script.entry {
+    Some(entry) => globals.get(entry.as_str()).copied().ok_or_else(|| LinkError::UndefinedSymbol(entry.clone()))?,
+    None => globals.get("_start").copied()
+      .or_else(|| sections.iter().find(|section| section.name == ".text").map(|section| section.address))
+      .unwrap_or(0),
+  };
+  let mut globals = globals.into_keys().map(str::to_owned).collect::<Vec<_>>();
+  globals.sort();
+  // output sections without contents only place their symbols
+  sections.retain(|section| !section.data.is_empty());
+  sections.sort_by_key(|section| section.address);
+  constants.sort();
+  Ok(Image { sections, symbols: image_symbols, globals, constants, entry })
+}
+
+/// a [`SectionCommand`] with the input sections it places
+enum Command<'a> {
+  Assign(&'a String, Value),
+  Output { name: String, address: Option<u32>, region: Option<String>, inputs: Vec<(usize, usize)> },
+}
+
+/// the upper 20 bits for `lui` or `auipc`, rounded so the sign extended lower 12 bits add up to `value`
+fn hi(value: u32) -> u32 {
+  value.wrapping_add(0x800) >> 12
+}
+
+fn lo(value: u32) -> u32 {
+  value.wrapping_sub(hi(value) << 12)
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use super::compressed::is_compressed;
+
+/// directives emitting more bytes than this only list the first ones
+const MAX_DATA_BYTES: usize = 16;
+
+/// a statement of the source with what it was assembled to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingLine {
+  /// line number in the source, starting at 1
+  pub line: usize,
+  /// where the first byte was placed
+  pub address: u32,
+  pub bytes: Vec<u8>,
+  /// instructions are listed as 32-bit words or 16-bit halfwords when compressed, data as bytes in memory order
+  pub instruction: bool,
+  pub text: String,
+  /// whether the line was expanded from a macro or included file
+  pub expanded: bool,
+}
+
+/// an assembler listing with the address, encoding and source of every line, followed by the symbol table
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Listing {
+  pub lines: Vec<ListingLine>,
+  /// sorted by address and name, with whether the symbol is global
+  pub symbols: Vec<(String, u32, bool)>,
+}
+
+impl fmt::Display for Listing {
+  /// ```text
+  ///    1  00000000 00a00513  li a0, 10
+  ///    2                     loop:
+  ///    3  00000004 fff50513    addi a0, a0, -1
+  /// ```
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for line in &self.lines {
+      let rows = if line.instruction {
+        instruction_rows(&line.bytes)
+      } else {
+        let mut rows = line.bytes.chunks(4).take(MAX_DATA_BYTES / 4).zip((0..).step_by(4))
+          .map(|(chunk, offset)| (offset, chunk.iter().map(|byte| format!("{byte:02x}")).collect::<String>()))
+          .collect::<Vec<_>>();
+        if line.bytes.len() > MAX_DATA_BYTES {
+          rows.push((MAX_DATA_BYTES as u32, "...".to_owned()));
+        }
+        rows
+      };
+      let marker = if line.expanded { '+' } else { ' ' };
+      let first = match rows.first() {
+        Some((_, row)) => format!("{:4}{marker} {:08x} {row:8}  {}", line.line, line.address, line.text),
+        None => format!("{:4}{marker} {:8} {:8}  {}", line.line, "", "", line.text),
+      };
+      writeln!(f, "{}", first.trim_end())?;
+      for (offset, row) in rows.iter().skip(1) {
+        writeln!(f, "{:5} {:08x} {row}", "", line.address + offset)?;
+      }
+    }
+    writeln!(f)?;
+    writeln!(f, "SYMBOL TABLE:")?;
+    for (name, value, global) in &self.symbols {
+      writeln!(f, "{value:08x} {} {name}", if *global { 'g' } else { 'l' })?;
+    }
+    Ok(())
+  }
+}
+
+/// the encodings of the instructions in `bytes` with their offsets
+fn instruction_rows(bytes: &[u8]) -> Vec<(u32, String)> {
+  let mut rows = vec![];
+  let mut offset = 0;
+  while offset + 2 <= bytes.len() {
+    let halfword = u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+    if is_compressed(halfword as u32) || offset + 4 > bytes.len() {
+      rows.push((offset as u32, format!("{halfword:04x}")));
+      offset += 2;
+    } else {
+      let word = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+      rows.push((offset as u32, format!("{word:08x}")));
+      offset += 4;
+    }
+  }
+  rows
+}
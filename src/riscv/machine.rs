@@ -1,4 +1,12 @@
 use super::decode::decode;
+use super::compressed::{disassemble_compressed, expand, is_compressed};
+use super::instructions::INSTRUCTION_UNDEF;
+use super::float::Float;
+use super::csr::{Csrs, MISA_C, PRIVILEGE_M};
+use super::trap::Exception;
+use super::clint::{Clint, CLINT_BASE};
+use super::mmu::Tlb;
+use super::pmp::Pmp;
 
 const MEM_SIZE: usize = 1 << 24;
 
@@ -14,6 +22,11 @@ impl Memory {
     }
   }
 
+  /// in bytes
+  pub fn size(&self) -> usize {
+    self.memory.len()
+  }
+
   pub fn load_byte(&self, address: usize) -> u8 {
     self.memory[address]
   }
@@ -49,6 +62,14 @@ impl Memory {
   }
 }
 
+/// register names as defined by the standard calling convention, indexed by register number
+pub const ABI_REGISTER_NAMES: [&str; 32] = [
+  "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2",
+  "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+  "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7",
+  "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6",
+];
+
 #[derive(Debug)]
 pub struct Registers {
   x: [u32; 32],
@@ -73,11 +94,110 @@ impl Registers {
   }
 }
 
+/// floating-point register names as defined by the standard calling convention, indexed by register number
+pub const ABI_FLOAT_REGISTER_NAMES: [&str; 32] = [
+  "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7",
+  "fs0", "fs1", "fa0", "fa1", "fa2", "fa3", "fa4", "fa5",
+  "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7",
+  "fs8", "fs9", "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+/// the 64-bit `f` registers of the D extension and `fcsr`,
+/// single precision values are NaN-boxed in the lower half with the upper half all ones
+#[derive(Debug)]
+pub struct FloatRegisters {
+  f: [u64; 32],
+  /// the rounding mode `frm` in bits 5 to 7 and the exception flags `fflags` in bits 0 to 4
+  pub fcsr: u32,
+}
+
+impl FloatRegisters {
+  fn new() -> Self {
+    Self { f: [0; 32], fcsr: 0 }
+  }
+
+  pub fn get_bits(&self, register: usize) -> u64 {
+    self.f[register]
+  }
+
+  pub fn set_bits(&mut self, register: usize, value: u64) {
+    self.f[register] = value;
+  }
+
+  /// the value in a register, single precision values that are not properly NaN-boxed read as the canonical NaN
+  pub fn get<F: Float>(&self, register: usize) -> F {
+    match self.f[register] {
+      bits if bits & F::BOX == F::BOX => F::from_bits64(bits),
+      _ => F::CANONICAL_NAN,
+    }
+  }
+
+  pub fn set<F: Float>(&mut self, register: usize, value: F) {
+    self.f[register] = F::BOX | value.to_bits64();
+  }
+
+  /// the dynamic rounding mode `frm`
+  pub fn rounding_mode(&self) -> u32 {
+    self.fcsr >> 5 & 0b111
+  }
+
+  /// accumulate exception flags into `fflags`
+  pub fn raise(&mut self, flags: u32) {
+    self.fcsr |= flags & 0x1f;
+  }
+}
+
+/// the kind of a memory access, which selects the exceptions it raises
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+  Fetch,
+  Load,
+  /// stores, and the loads of `sc.w` and AMOs
+  Store,
+}
+
+impl Access {
+  pub fn misaligned(self, address: u32) -> Exception {
+    match self {
+      Access::Fetch => Exception::InstructionAddressMisaligned(address),
+      Access::Load => Exception::LoadAddressMisaligned(address),
+      Access::Store => Exception::StoreAddressMisaligned(address),
+    }
+  }
+
+  pub fn access_fault(self, address: u32) -> Exception {
+    match self {
+      Access::Fetch => Exception::InstructionAccessFault(address),
+      Access::Load => Exception::LoadAccessFault(address),
+      Access::Store => Exception::StoreAccessFault(address),
+    }
+  }
+
+  pub fn page_fault(self, address: u32) -> Exception {
+    match self {
+      Access::Fetch => Exception::InstructionPageFault(address),
+      Access::Load => Exception::LoadPageFault(address),
+      Access::Store => Exception::StorePageFault(address),
+    }
+  }
+}
+
 #[derive(Debug)]
 pub struct Machine {
   pub pc: u32,
   pub registers: Registers,
+  pub float_registers: FloatRegisters,
   pub memory: Memory,
+  /// the address reserved by the last `lr.w`, until the next `sc.w`
+  pub reservation: Option<u32>,
+  /// the size in bytes of the instruction being executed, 2 for compressed instructions
+  pub instruction_size: u32,
+  pub csrs: Csrs,
+  /// the current privilege level, see [`PRIVILEGE_M`]
+  pub privilege: u32,
+  pub clint: Clint,
+  pub tlb: Tlb,
+  pub pmp: Pmp,
 }
 
 impl Default for Machine {
@@ -85,7 +205,15 @@ impl Default for Machine {
     Self {
       pc: 0,
       registers: Registers::new(),
+      float_registers: FloatRegisters::new(),
       memory: Memory::new(MEM_SIZE),
+      reservation: None,
+      instruction_size: 4,
+      csrs: Csrs::default(),
+      privilege: PRIVILEGE_M,
+      clint: Clint::default(),
+      tlb: Tlb::default(),
+      pmp: Pmp::default(),
     }
   }
 }
@@ -105,19 +233,136 @@ impl Machine {
     }
   }
 
+  /// take a pending interrupt, or execute one instruction and take a trap if it raises an exception,
+  /// counters written by the instruction keep the written value instead of being incremented,
+  /// `instret` and `mtime` only count instructions that retire
   pub fn cycle(&mut self) {
-    let instruction = self.fetch();
-    self.exec(instruction);
+    let Csrs { cycle, instret, .. } = self.csrs;
+    let mtime = self.clint.mtime;
+    if let Some(interrupt) = self.pending_interrupt() {
+      self.interrupt(interrupt);
+    } else {
+      match self.fetch().and_then(|instruction| self.exec(instruction)) {
+        Ok(()) => {
+          if self.csrs.instret == instret {
+            self.csrs.instret = instret.wrapping_add(1);
+          }
+          if self.clint.mtime == mtime {
+            self.clint.mtime = mtime.wrapping_add(1);
+          }
+        }
+        Err(exception) => self.trap(exception),
+      }
+    }
+    if self.csrs.cycle == cycle {
+      self.csrs.cycle = cycle.wrapping_add(1);
+    }
+  }
+
+  /// the address of the instruction after the one being executed
+  pub fn next_pc(&self) -> u32 {
+    self.pc.wrapping_add(self.instruction_size)
+  }
+
+  /// set `pc` to the target of a jump or taken branch, which must be aligned to the instruction alignment
+  pub fn jump(&mut self, target: u32) -> Result<(), Exception> {
+    if !target.is_multiple_of(self.instruction_alignment()) {
+      return Err(Exception::InstructionAddressMisaligned(target));
+    }
+    self.pc = target;
+    Ok(())
+  }
+
+  /// load `size` bytes in little endian order for an access of kind `access`
+  pub fn load(&mut self, address: u32, size: u32, access: Access) -> Result<u64, Exception> {
+    let physical = self.check_access(address, size, access)?;
+    Ok(self.load_physical(physical, size))
+  }
+
+  /// store the lower `size` bytes of `value` in little endian order
+  pub fn store(&mut self, address: u32, size: u32, value: u64) -> Result<(), Exception> {
+    let physical = self.check_access(address, size, Access::Store)?;
+    self.store_physical(physical, size, value);
+    Ok(())
+  }
+
+  /// load from a physical address checked by [`Machine::physical_address`]
+  pub fn load_physical(&self, address: u32, size: u32) -> u64 {
+    let bytes = (address..address + size).rev();
+    bytes.fold(0, |value, address| value << 8 | self.load_byte(address) as u64)
   }
 
-  fn fetch(&self) -> u32 {
-    self.memory.load_word(self.pc as usize)
+  /// store to a physical address checked by [`Machine::physical_address`]
+  pub fn store_physical(&mut self, address: u32, size: u32, value: u64) {
+    for (offset, address) in (address..address + size).enumerate() {
+      self.store_byte(address, (value >> (8 * offset)) as u8);
+    }
+  }
+
+  fn load_byte(&self, address: u32) -> u8 {
+    match Clint::contains(address) {
+      true => self.clint.load_byte(address - CLINT_BASE),
+      false => self.memory.load_byte(address as usize),
+    }
+  }
+
+  fn store_byte(&mut self, address: u32, value: u8) {
+    match Clint::contains(address) {
+      true => self.clint.store_byte(address - CLINT_BASE, value),
+      false => self.memory.store_byte(address as usize, value),
+    }
+  }
+
+  /// the physical address of an access, loads and stores must be naturally aligned
+  /// and the physical memory protection must permit the access at the current privilege level
+  fn check_access(&mut self, address: u32, size: u32, access: Access) -> Result<u32, Exception> {
+    if access != Access::Fetch && !address.is_multiple_of(size) {
+      return Err(access.misaligned(address));
+    }
+    let physical = self.translate(address, access)?;
+    let physical = self.physical_address(physical, size, access);
+    let permitted = physical.filter(|&physical| self.pmp.check(physical, size, access, self.privilege));
+    permitted.ok_or(access.access_fault(address))
+  }
+
+  /// a physical address as a 32-bit address if all `size` bytes at it are in memory,
+  /// or in the CLINT for loads and stores since instructions can only be fetched from memory
+  pub fn physical_address(&self, physical: u64, size: u32, access: Access) -> Option<u32> {
+    let address = u32::try_from(physical).ok()?;
+    let in_memory = physical + size as u64 <= self.memory.size() as u64;
+    let in_clint = access != Access::Fetch && Clint::contains(address) && Clint::contains(address + size - 1);
+    (in_memory || in_clint).then_some(address)
   }
 
-  fn exec(&mut self, instruction: u32) {
+  /// a compressed instruction is returned in the lower half,
+  /// the halves are fetched separately as the upper one may be on the next page
+  fn fetch(&mut self) -> Result<u32, Exception> {
+    let lower_halfword = self.load(self.pc, 2, Access::Fetch)? as u32;
+    if is_compressed(lower_halfword) {
+      Ok(lower_halfword)
+    } else {
+      let upper_halfword = self.load(self.pc.wrapping_add(2), 2, Access::Fetch)? as u32;
+      Ok(upper_halfword << 16 | lower_halfword)
+    }
+  }
+
+  /// compressed instructions are executed as the base instruction they expand to,
+  /// they are illegal while the C extension is turned off in `misa`,
+  /// each instruction is traced with the privilege level it is executed in
+  fn exec(&mut self, instruction: u32) -> Result<(), Exception> {
+    if is_compressed(instruction) {
+      self.instruction_size = 2;
+      let expanded = expand(instruction as u16).filter(|_| self.csrs.misa & MISA_C != 0);
+      let Some(expanded) = expanded else {
+        return INSTRUCTION_UNDEF.exec(self, instruction);
+      };
+      println!("{} {}", self.privilege_name(), disassemble_compressed(instruction as u16).unwrap());
+      return decode(expanded).exec(self, expanded);
+    }
+    self.instruction_size = 4;
     let instruction_bits = instruction;
     let instruction = decode(instruction);
-    println!("{}", instruction.disassemble(instruction_bits, Some(self)));
-    instruction.exec(self, instruction_bits);
+    println!("{} {}", self.privilege_name(), instruction.disassemble(instruction_bits, Some(self)));
+    instruction.exec(self, instruction_bits)
   }
 }
@@ -0,0 +1,151 @@
+use super::machine::{Access, Machine};
+use super::csr::{PRIVILEGE_M, PRIVILEGE_S, PRIVILEGE_U};
+use super::trap::{Exception, MSTATUS_MXR, MSTATUS_SUM};
+
+/// the mode of `satp`, Sv32 translation when set and no translation when clear
+pub const SATP_MODE: u32 = 1 << 31;
+/// the physical page number of the root page table in `satp`, there are no address space identifiers
+pub const SATP_PPN: u32 = 0x3f_ffff;
+
+/// the bits of a page-table entry
+pub const PTE_V: u32 = 1 << 0;
+pub const PTE_R: u32 = 1 << 1;
+pub const PTE_W: u32 = 1 << 2;
+pub const PTE_X: u32 = 1 << 3;
+pub const PTE_U: u32 = 1 << 4;
+pub const PTE_G: u32 = 1 << 5;
+pub const PTE_A: u32 = 1 << 6;
+pub const PTE_D: u32 = 1 << 7;
+
+/// pages are 4 KiB, megapages 4 MiB
+const PAGE_SHIFT: u32 = 12;
+const TLB_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Copy)]
+struct TlbEntry {
+  /// the virtual page number
+  vpn: u32,
+  /// the physical page number, of the page within the megapage for megapages
+  ppn: u32,
+  /// the bits of the leaf page-table entry
+  flags: u32,
+  megapage: bool,
+}
+
+/// a direct-mapped cache of translations indexed by the lower bits of the virtual page number,
+/// which holds a translation until it is replaced or flushed by `sfence.vma` or a write to `satp`
+#[derive(Debug)]
+pub struct Tlb {
+  entries: [Option<TlbEntry>; TLB_SIZE],
+}
+
+impl Default for Tlb {
+  fn default() -> Self {
+    Self { entries: [None; TLB_SIZE] }
+  }
+}
+
+impl Tlb {
+  fn lookup(&self, vpn: u32) -> Option<TlbEntry> {
+    self.entries[vpn as usize % TLB_SIZE].filter(|entry| entry.vpn == vpn)
+  }
+
+  fn insert(&mut self, entry: TlbEntry) {
+    self.entries[entry.vpn as usize % TLB_SIZE] = Some(entry);
+  }
+
+  /// invalidate the translations of the page or megapage containing `address`, or all translations for `None`
+  pub fn flush(&mut self, address: Option<u32>) {
+    let Some(address) = address else {
+      self.entries = [None; TLB_SIZE];
+      return;
+    };
+    let vpn = address >> PAGE_SHIFT;
+    for slot in &mut self.entries {
+      if slot.is_some_and(|entry| entry.vpn == vpn || entry.megapage && entry.vpn >> 10 == vpn >> 10) {
+        *slot = None;
+      }
+    }
+  }
+}
+
+impl Machine {
+  /// the physical address of an access of kind `access` to `address`, physical addresses of Sv32 are 34 bits,
+  /// addresses are translated below M-mode while `satp` selects Sv32
+  pub fn translate(&mut self, address: u32, access: Access) -> Result<u64, Exception> {
+    if self.privilege == PRIVILEGE_M || self.csrs.satp & SATP_MODE == 0 {
+      return Ok(address as u64);
+    }
+    let entry = match self.tlb.lookup(address >> PAGE_SHIFT) {
+      // the first store to a page walks the page table again to set D
+      Some(entry) if access != Access::Store || entry.flags & PTE_D != 0 => entry,
+      _ => self.walk(address, access)?,
+    };
+    if !self.permitted(entry.flags, access) {
+      return Err(access.page_fault(address));
+    }
+    Ok((entry.ppn as u64) << PAGE_SHIFT | (address & 0xfff) as u64)
+  }
+
+  /// walk the two-level page table for `address` and cache the translation,
+  /// setting A and for stores D in the leaf page-table entry if the access is permitted,
+  /// the page table is accessed as S-mode by the physical memory protection
+  fn walk(&mut self, address: u32, access: Access) -> Result<TlbEntry, Exception> {
+    let page_fault = access.page_fault(address);
+    let access_fault = access.access_fault(address);
+    let mut table = ((self.csrs.satp & SATP_PPN) as u64) << PAGE_SHIFT;
+    for level in [1, 0] {
+      let index = address >> (PAGE_SHIFT + 10 * level) & 0x3ff;
+      let pte_address = self.physical_address(table + 4 * index as u64, 4, Access::Load);
+      let pte_address = pte_address.filter(|&pte_address| self.pmp.check(pte_address, 4, Access::Load, PRIVILEGE_S));
+      let pte_address = pte_address.ok_or(access_fault)?;
+      let pte = self.load_physical(pte_address, 4) as u32;
+      // writable pages must be readable
+      if pte & PTE_V == 0 || pte & (PTE_R | PTE_W) == PTE_W {
+        return Err(page_fault);
+      }
+      let ppn = pte >> 10;
+      if pte & (PTE_R | PTE_X) == 0 {
+        table = (ppn as u64) << PAGE_SHIFT;
+        continue;
+      }
+      // megapages must be aligned to 4 MiB
+      if level == 1 && ppn & 0x3ff != 0 || !self.permitted(pte, access) {
+        return Err(page_fault);
+      }
+      let flags = pte | PTE_A | if access == Access::Store { PTE_D } else { 0 };
+      if flags != pte {
+        if !self.pmp.check(pte_address, 4, Access::Store, PRIVILEGE_S) {
+          return Err(access_fault);
+        }
+        self.store_physical(pte_address, 4, flags as u64);
+      }
+      let entry = TlbEntry {
+        vpn: address >> PAGE_SHIFT,
+        ppn: if level == 1 { ppn | address >> PAGE_SHIFT & 0x3ff } else { ppn },
+        flags,
+        megapage: level == 1,
+      };
+      self.tlb.insert(entry);
+      return Ok(entry);
+    }
+    Err(page_fault)
+  }
+
+  /// whether the current privilege level may access a page with the bits `flags`,
+  /// S-mode may only load and store U-mode pages while `SUM` is set and `MXR` makes executable pages readable
+  fn permitted(&self, flags: u32, access: Access) -> bool {
+    let mstatus = self.csrs.mstatus;
+    let user_page = flags & PTE_U != 0;
+    let allowed = match self.privilege {
+      PRIVILEGE_U => user_page,
+      PRIVILEGE_S => !user_page || access != Access::Fetch && mstatus & MSTATUS_SUM != 0,
+      _ => true,
+    };
+    allowed && match access {
+      Access::Fetch => flags & PTE_X != 0,
+      Access::Load => flags & PTE_R != 0 || mstatus & MSTATUS_MXR != 0 && flags & PTE_X != 0,
+      Access::Store => flags & PTE_W != 0,
+    }
+  }
+}
@@ -1,9 +1,28 @@
 pub mod instructions;
 pub mod slices;
 pub mod machine;
+pub mod csr;
+pub mod trap;
+pub mod clint;
+pub mod mmu;
+pub mod pmp;
 pub mod executors;
+pub mod float;
 pub mod decode;
+pub mod compressed;
 pub mod assemble;
+pub mod parse;
+pub mod expr;
+pub mod preprocess;
+pub mod pseudo;
+pub mod image;
+pub mod object;
+pub mod listing;
+pub mod elf;
+pub mod link;
+pub mod formats;
+pub mod error;
 pub mod assemblers;
 pub mod disassemble;
 pub mod disassemblers;
+pub mod objdump;
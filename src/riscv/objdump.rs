@@ -0,0 +1,434 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use super::assemble::{Format, lookup_instruction};
+use super::compressed::{self, decode_compressed, expand, is_compressed};
+use super::decode::decode;
+use super::elf::{Elf, SHF_EXECINSTR, SHN_UNDEF, STT_FILE, STT_SECTION};
+use super::image::Image;
+use super::machine::{ABI_FLOAT_REGISTER_NAMES, ABI_REGISTER_NAMES};
+use super::disassemblers::{FLOAT_REGISTER_NAMES, REGISTER_NAMES, get_ordering_suffix};
+use super::float::{DYN, ROUNDING_MODE_NAMES};
+use super::csr::*;
+use super::slices::*;
+
+/// an operand of a decoded instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+  Register(usize),
+  FloatRegister(usize),
+  Immediate(i32),
+  /// `offset(base)` of loads, stores and `jalr`
+  Memory { offset: i32, base: usize },
+  /// `(base)` of atomics
+  Address(usize),
+  /// the address a branch or jump goes to
+  Target(u32),
+  /// predecessor or successor set of a `fence` with the bits `iorw`
+  FenceSet(u32),
+  /// the rounding mode of a floating-point instruction
+  RoundingMode(u32),
+  /// the address of a CSR
+  Csr(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+  pub mnemonic: &'static str,
+  /// `.aq`, `.rl` or `.aqrl` of atomics, empty otherwise
+  pub suffix: &'static str,
+  pub operands: Vec<Operand>,
+}
+
+/// decode an instruction at `address` into its mnemonic and operands, `None` if it is not an instruction
+pub fn decode_operands(instruction: u32, address: u32) -> Option<Decoded> {
+  let mnemonic = decode(instruction).name;
+  let (_, format) = lookup_instruction(mnemonic)?;
+  let rd = Operand::Register(get_rd_bits(instruction) as usize);
+  let rs1 = get_rs1_bits(instruction) as usize;
+  let rs2 = Operand::Register(get_rs2_bits(instruction) as usize);
+  let frd = Operand::FloatRegister(get_rd_bits(instruction) as usize);
+  let frs1 = Operand::FloatRegister(rs1);
+  let frs2 = Operand::FloatRegister(get_rs2_bits(instruction) as usize);
+  let frs3 = Operand::FloatRegister(get_rs3_bits(instruction) as usize);
+  let rm = Operand::RoundingMode(get_funct3_bits(instruction));
+  let operands = match format {
+    Format::R => vec![rd, Operand::Register(rs1), rs2],
+    Format::I if matches!(mnemonic, "slli" | "srli" | "srai") => {
+      vec![rd, Operand::Register(rs1), Operand::Immediate(get_rs2_bits(instruction) as i32)]
+    }
+    Format::I => vec![rd, Operand::Register(rs1), Operand::Immediate(get_I_imm(instruction) as i32)],
+    Format::MEM if get_opcode_bits(instruction) == OPCODE_STORE => {
+      vec![rs2, Operand::Memory { offset: get_S_imm(instruction) as i32, base: rs1 }]
+    }
+    Format::MEM => vec![rd, Operand::Memory { offset: get_I_imm(instruction) as i32, base: rs1 }],
+    Format::B => vec![Operand::Register(rs1), rs2, Operand::Target(address.wrapping_add(get_B_imm(instruction)))],
+    Format::U => vec![rd, Operand::Immediate((get_U_imm_bits(instruction) >> 12) as i32)],
+    Format::J => vec![rd, Operand::Target(address.wrapping_add(get_J_imm(instruction)))],
+    Format::FENCE => vec![Operand::FenceSet(instruction >> 24 & 0xf), Operand::FenceSet(instruction >> 20 & 0xf)],
+    Format::AMO => vec![rd, rs2, Operand::Address(rs1)],
+    Format::LR => vec![rd, Operand::Address(rs1)],
+    Format::FMEM if get_opcode_bits(instruction) == OPCODE_STORE_FP => {
+      vec![frs2, Operand::Memory { offset: get_S_imm(instruction) as i32, base: rs1 }]
+    }
+    Format::FMEM => vec![frd, Operand::Memory { offset: get_I_imm(instruction) as i32, base: rs1 }],
+    Format::FR => vec![frd, frs1, frs2],
+    Format::FR_RM => vec![frd, frs1, frs2, rm],
+    Format::FR4 => vec![frd, frs1, frs2, frs3, rm],
+    Format::FR1 => vec![frd, frs1],
+    Format::FR1_RM => vec![frd, frs1, rm],
+    Format::FCMP => vec![rd, frs1, frs2],
+    Format::XF => vec![rd, frs1],
+    Format::XF_RM => vec![rd, frs1, rm],
+    Format::FX => vec![frd, Operand::Register(rs1)],
+    Format::FX_RM => vec![frd, Operand::Register(rs1), rm],
+    Format::CSR => vec![rd, Operand::Csr(get_csr_bits(instruction)), Operand::Register(rs1)],
+    Format::CSRI => vec![rd, Operand::Csr(get_csr_bits(instruction)), Operand::Immediate(rs1 as i32)],
+    Format::SFENCE => vec![Operand::Register(rs1), rs2],
+    Format::NONE => vec![],
+  };
+  let suffix = match format {
+    Format::AMO | Format::LR => get_ordering_suffix(instruction),
+    _ => "",
+  };
+  Some(Decoded { mnemonic, suffix, operands })
+}
+
+/// decode a compressed instruction at `address` into its `c.` mnemonic and operands, `None` if it is not an instruction
+pub fn decode_compressed_operands(instruction: u16, address: u32) -> Option<Decoded> {
+  use compressed::Operand as C;
+  let (compressed, fields) = decode_compressed(instruction)?;
+  let (high, low, imm) = (fields.high as usize, fields.low as usize, fields.imm as i32);
+  let operands = compressed.operands.iter().map(|operand| match operand {
+    C::Rd | C::Rs1Prime => Operand::Register(high),
+    C::Rs2 | C::Rs2Prime => Operand::Register(low),
+    C::FloatRd => Operand::FloatRegister(high),
+    C::FloatRs2 | C::FloatRs2Prime => Operand::FloatRegister(low),
+    C::Sp => Operand::Register(2),
+    C::Immediate => Operand::Immediate(imm),
+    C::UpperImmediate => Operand::Immediate(imm & 0xfffff),
+    C::Offset => Operand::Target(address.wrapping_add(fields.imm)),
+    C::Memory => Operand::Memory { offset: imm, base: high },
+    C::StackMemory => Operand::Memory { offset: imm, base: 2 },
+  }).collect();
+  Some(Decoded { mnemonic: compressed.name, suffix: "", operands })
+}
+
+/// the pseudo-instruction objdump shows for an instruction, or the instruction itself,
+/// the dynamic rounding mode is left out like the default operand it is in assembly
+pub fn pseudo(mut decoded: Decoded) -> Decoded {
+  use Operand::*;
+  const ZERO: Operand = Register(0);
+  const RA: Operand = Register(1);
+  if let Some(RoundingMode(DYN)) = decoded.operands.last() {
+    decoded.operands.pop();
+  }
+  let (mnemonic, operands) = match (decoded.mnemonic, decoded.operands.as_slice()) {
+    ("addi", [ZERO, ZERO, Immediate(0)]) => ("nop", vec![]),
+    ("addi", [rd, ZERO, imm]) => ("li", vec![*rd, *imm]),
+    ("addi", [rd, rs, Immediate(0)]) => ("mv", vec![*rd, *rs]),
+    ("xori", [rd, rs, Immediate(-1)]) => ("not", vec![*rd, *rs]),
+    ("sub", [rd, ZERO, rs]) => ("neg", vec![*rd, *rs]),
+    ("sltiu", [rd, rs, Immediate(1)]) => ("seqz", vec![*rd, *rs]),
+    ("sltu", [rd, ZERO, rs]) => ("snez", vec![*rd, *rs]),
+    ("slt", [rd, rs, ZERO]) => ("sltz", vec![*rd, *rs]),
+    ("slt", [rd, ZERO, rs]) => ("sgtz", vec![*rd, *rs]),
+    ("beq", [rs, ZERO, target]) => ("beqz", vec![*rs, *target]),
+    ("bne", [rs, ZERO, target]) => ("bnez", vec![*rs, *target]),
+    ("blt", [rs, ZERO, target]) => ("bltz", vec![*rs, *target]),
+    ("bge", [rs, ZERO, target]) => ("bgez", vec![*rs, *target]),
+    ("blt", [ZERO, rs, target]) => ("bgtz", vec![*rs, *target]),
+    ("bge", [ZERO, rs, target]) => ("blez", vec![*rs, *target]),
+    ("jal", [ZERO, target]) => ("j", vec![*target]),
+    ("jal", [RA, target]) => ("jal", vec![*target]),
+    ("jalr", [ZERO, Memory { offset: 0, base: 1 }]) => ("ret", vec![]),
+    ("jalr", [ZERO, Memory { offset: 0, base }]) => ("jr", vec![Register(*base)]),
+    ("jalr", [RA, Memory { offset: 0, base }]) => ("jalr", vec![Register(*base)]),
+    ("jalr", [RA, memory]) => ("jalr", vec![*memory]),
+    ("fence", [FenceSet(0xf), FenceSet(0xf)]) => ("fence", vec![]),
+    ("sfence.vma", [ZERO, ZERO]) => ("sfence.vma", vec![]),
+    ("sfence.vma", [rs1, ZERO]) => ("sfence.vma", vec![*rs1]),
+    ("fsgnj.s", [rd, rs1, rs2]) if rs1 == rs2 => ("fmv.s", vec![*rd, *rs1]),
+    ("fsgnjn.s", [rd, rs1, rs2]) if rs1 == rs2 => ("fneg.s", vec![*rd, *rs1]),
+    ("fsgnjx.s", [rd, rs1, rs2]) if rs1 == rs2 => ("fabs.s", vec![*rd, *rs1]),
+    ("fsgnj.d", [rd, rs1, rs2]) if rs1 == rs2 => ("fmv.d", vec![*rd, *rs1]),
+    ("fsgnjn.d", [rd, rs1, rs2]) if rs1 == rs2 => ("fneg.d", vec![*rd, *rs1]),
+    ("fsgnjx.d", [rd, rs1, rs2]) if rs1 == rs2 => ("fabs.d", vec![*rd, *rs1]),
+    ("csrrs", [rd, Csr(CSR_CYCLE), ZERO]) => ("rdcycle", vec![*rd]),
+    ("csrrs", [rd, Csr(CSR_CYCLEH), ZERO]) => ("rdcycleh", vec![*rd]),
+    ("csrrs", [rd, Csr(CSR_TIME), ZERO]) => ("rdtime", vec![*rd]),
+    ("csrrs", [rd, Csr(CSR_TIMEH), ZERO]) => ("rdtimeh", vec![*rd]),
+    ("csrrs", [rd, Csr(CSR_INSTRET), ZERO]) => ("rdinstret", vec![*rd]),
+    ("csrrs", [rd, Csr(CSR_INSTRETH), ZERO]) => ("rdinstreth", vec![*rd]),
+    ("csrrs", [rd, Csr(CSR_FCSR), ZERO]) => ("frcsr", vec![*rd]),
+    ("csrrs", [rd, Csr(CSR_FRM), ZERO]) => ("frrm", vec![*rd]),
+    ("csrrs", [rd, Csr(CSR_FFLAGS), ZERO]) => ("frflags", vec![*rd]),
+    ("csrrw", [ZERO, Csr(CSR_FCSR), rs]) => ("fscsr", vec![*rs]),
+    ("csrrw", [rd, Csr(CSR_FCSR), rs]) => ("fscsr", vec![*rd, *rs]),
+    ("csrrw", [ZERO, Csr(CSR_FRM), rs]) => ("fsrm", vec![*rs]),
+    ("csrrw", [rd, Csr(CSR_FRM), rs]) => ("fsrm", vec![*rd, *rs]),
+    ("csrrw", [ZERO, Csr(CSR_FFLAGS), rs]) => ("fsflags", vec![*rs]),
+    ("csrrw", [rd, Csr(CSR_FFLAGS), rs]) => ("fsflags", vec![*rd, *rs]),
+    ("csrrwi", [ZERO, Csr(CSR_FRM), imm]) => ("fsrmi", vec![*imm]),
+    ("csrrwi", [rd, Csr(CSR_FRM), imm]) => ("fsrmi", vec![*rd, *imm]),
+    ("csrrwi", [ZERO, Csr(CSR_FFLAGS), imm]) => ("fsflagsi", vec![*imm]),
+    ("csrrwi", [rd, Csr(CSR_FFLAGS), imm]) => ("fsflagsi", vec![*rd, *imm]),
+    ("csrrs", [rd, csr, ZERO]) => ("csrr", vec![*rd, *csr]),
+    ("csrrw", [ZERO, csr, rs]) => ("csrw", vec![*csr, *rs]),
+    ("csrrs", [ZERO, csr, rs]) => ("csrs", vec![*csr, *rs]),
+    ("csrrc", [ZERO, csr, rs]) => ("csrc", vec![*csr, *rs]),
+    ("csrrwi", [ZERO, csr, imm]) => ("csrwi", vec![*csr, *imm]),
+    ("csrrsi", [ZERO, csr, imm]) => ("csrsi", vec![*csr, *imm]),
+    ("csrrci", [ZERO, csr, imm]) => ("csrci", vec![*csr, *imm]),
+    _ => return decoded,
+  };
+  Decoded { mnemonic, suffix: "", operands }
+}
+
+/// layout of a disassembly listing and syntax of its instructions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Style {
+  /// `     1c:  00050463  beqz a0, 0x30 <done>`
+  #[default]
+  Xocolatl,
+  /// as `riscv64-unknown-elf-objdump -d`, `     1c:\t00050463          \tbeqz\ta0,30 <done>`,
+  /// with hexadecimal `lui`, `auipc` and shift immediates and the addresses `auipc` and `lui` pairs compute
+  /// as comments like `jalr\t16(ra) # 2c <main>`
+  Gnu,
+  /// as `llvm-objdump -d` with the instruction syntax of `llvm-mc`, `     1c: 63 04 05 00  \tbeqz\ta0, 0x30 <done>`
+  Llvm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisassembleOptions {
+  /// show pseudo-instructions like `li`, `mv`, `ret` and `j` where an instruction is one,
+  /// like `-M no-aliases` of objdump when disabled
+  pub pseudo: bool,
+  /// name registers like `a0` instead of `x10`, like `-M numeric` of objdump when disabled
+  pub abi_names: bool,
+  /// decode 16-bit compressed instructions, like objdump for code with the C extension,
+  /// otherwise everything is decoded as 32-bit instructions
+  pub compressed: bool,
+  pub style: Style,
+}
+
+impl Default for DisassembleOptions {
+  fn default() -> Self {
+    Self { pseudo: true, abi_names: true, compressed: false, style: Style::Xocolatl }
+  }
+}
+
+/// names for addresses, the labels of a listing
+struct Labels<'a> {
+  labels: BTreeMap<u32, &'a str>,
+}
+
+impl<'a> Labels<'a> {
+  /// one name per address, preferring names that are not local `.L` labels, then the first in order
+  fn new(symbols: impl IntoIterator<Item = (&'a str, u32)>) -> Self {
+    let mut labels = BTreeMap::<u32, &str>::new();
+    for (name, address) in symbols {
+      let key = |name: &str| (name.starts_with(".L"), name.to_owned());
+      match labels.get(&address) {
+        Some(other) if key(other) <= key(name) => {}
+        _ => _ = labels.insert(address, name),
+      }
+    }
+    Self { labels }
+  }
+
+  /// an address with the closest label before it, like `0x2c <main+0x8>` or `2c <main+0x8>` for GNU
+  fn address(&self, address: u32, style: Style) -> String {
+    match style {
+      Style::Gnu => format!("{address:x}{}", self.describe(address)),
+      Style::Xocolatl | Style::Llvm => format!("{address:#x}{}", self.describe(address)),
+    }
+  }
+
+  /// ` <name>` or ` <name+0x8>` for the closest label before an address
+  fn describe(&self, address: u32) -> String {
+    match self.labels.range(..=address).next_back() {
+      Some((start, name)) if *start == address => format!(" <{name}>"),
+      Some((start, name)) => format!(" <{name}+{:#x}>", address - start),
+      None => String::new(),
+    }
+  }
+}
+
+/// disassemble the bytes of a section starting at `address`, with a line for each instruction,
+/// `.word` and `.half` for words and halfwords that are not instructions and `.byte` for a rest shorter than those
+pub fn disassemble_bytes(bytes: &[u8], address: u32, symbols: &BTreeMap<String, u32>, options: &DisassembleOptions) -> String {
+  let labels = Labels::new(symbols.iter().map(|(name, value)| (name.as_str(), *value)));
+  let mut listing = String::new();
+  disassemble_section(&mut listing, bytes, address, &labels, options);
+  listing
+}
+
+/// disassemble all sections of an image, like `objdump -D`
+pub fn disassemble_image(image: &Image, options: &DisassembleOptions) -> String {
+  let labels = Labels::new(image.symbols.iter().map(|(name, value)| (name.as_str(), *value)));
+  let mut listing = String::new();
+  for section in &image.sections {
+    write!(listing, "\nDisassembly of section {}:\n", section.name).unwrap();
+    disassemble_section(&mut listing, &section.data, section.address, &labels, options);
+  }
+  listing
+}
+
+/// disassemble the executable sections of an ELF file, like `objdump -d`
+pub fn disassemble_elf(elf: &Elf, options: &DisassembleOptions) -> String {
+  let symbols = elf.symbols.iter()
+    .filter(|symbol| !symbol.name.is_empty() && symbol.section != SHN_UNDEF && ![STT_SECTION, STT_FILE].contains(&symbol.kind()))
+    .map(|symbol| (symbol.name.as_str(), symbol.value));
+  let labels = Labels::new(symbols);
+  let mut listing = String::new();
+  for section in elf.sections.iter().filter(|section| section.flags & SHF_EXECINSTR != 0) {
+    write!(listing, "\nDisassembly of section {}:\n", section.name).unwrap();
+    disassemble_section(&mut listing, &section.data, section.address, &labels, options);
+  }
+  listing
+}
+
+fn disassemble_section(listing: &mut String, bytes: &[u8], start: u32, labels: &Labels, options: &DisassembleOptions) {
+  // GNU objdump skips leading zeros common to all addresses in groups of 4 digits
+  let end = format!("{:08x}", start as u64 + bytes.len() as u64);
+  let zeros = end.len() - end.trim_start_matches('0').len();
+  let skip = if zeros == 0 || (zeros == end.len() && start != 0) { 0 } else { (zeros - 1) & !3 };
+  // values `lui` and `auipc` put into registers for the address comments of GNU objdump
+  let mut upper = [None; 32];
+  let mut offset = 0;
+  while offset < bytes.len() {
+    let address = start + offset as u32;
+    if let Some(name) = labels.labels.get(&address) {
+      write!(listing, "\n{address:08x} <{name}>:\n").unwrap();
+    }
+    // stop before the next label so it gets its own line
+    let compressed = options.compressed && is_compressed(bytes[offset] as u32);
+    let mut size = if compressed { 2 } else { 4 }.min(bytes.len() - offset);
+    if let Some((next, _)) = labels.labels.range(address + 1..address + size as u32).next() {
+      size = (next - address) as usize;
+    }
+    let chunk = &bytes[offset..offset + size];
+    let value = chunk.iter().rev().fold(0, |value, byte| value << 8 | *byte as u32);
+    // compressed instructions show as the instruction they expand to, or as themselves without aliases
+    let decoded = match (size, compressed) {
+      (4, false) => decode_operands(value, address).map(|decoded| (value, decoded)),
+      (2, true) => expand(value as u16).and_then(|expanded| {
+        let decoded = match (options.pseudo, decode_compressed_operands(value as u16, address)?) {
+          // unlike the `add` with `zero` it expands to, `c.mv` shows as `mv`
+          (true, Decoded { mnemonic: "c.mv", operands, .. }) => Decoded { mnemonic: "mv", suffix: "", operands },
+          (true, _) => decode_operands(expanded, address)?,
+          (false, decoded) => decoded,
+        };
+        Some((expanded, decoded))
+      }),
+      _ => None,
+    };
+    let text = match decoded {
+      Some((instruction, decoded)) => {
+        let shown = if options.pseudo { pseudo(decoded.clone()) } else { decoded.clone() };
+        let comment = address_comment(instruction, &shown, address, &mut upper);
+        let mut text = format_instruction(&shown, shown != decoded, labels, options);
+        if let (Style::Gnu, Some(comment)) = (options.style, comment) {
+          write!(text, " # {}", labels.address(comment, Style::Gnu)).unwrap();
+        }
+        text
+      }
+      None => match options.style {
+        Style::Xocolatl if size == 4 => format!(".word {value:#010x}"),
+        Style::Xocolatl if size == 2 && compressed => format!(".half {value:#06x}"),
+        Style::Xocolatl => {
+          let bytes = chunk.iter().map(|byte| format!("{byte:#04x}")).collect::<Vec<_>>();
+          format!(".byte {}", bytes.join(", "))
+        }
+        Style::Gnu => format!(".{size}byte\t{value:#x}"),
+        Style::Llvm => "<unknown>".to_owned(),
+      },
+    };
+    match options.style {
+      Style::Xocolatl => {
+        writeln!(listing, "{address:8x}:  {value:0width$x}{:pad$}  {text}", "", width = 2 * size, pad = 8 - 2 * size).unwrap();
+      }
+      Style::Gnu => {
+        let address = format!("{address:08x}");
+        let digits = &address[skip..];
+        let shown = digits.trim_start_matches('0');
+        let shown = if shown.is_empty() { "0" } else { shown };
+        let raw = format!("{value:0width$x}", width = 2 * size);
+        writeln!(listing, "{shown:>width$}:\t{raw:18}\t{text}", width = digits.len()).unwrap();
+      }
+      Style::Llvm => {
+        let raw = chunk.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<_>>().join(" ");
+        writeln!(listing, "{address:8x}: {raw:11}  \t{text}").unwrap();
+      }
+    }
+    offset += size;
+  }
+}
+
+/// the address an instruction computes from a register set by `lui` or `auipc`, as GNU objdump shows it,
+/// tracking the values of `lui` and `auipc` in `upper`
+fn address_comment(instruction: u32, shown: &Decoded, address: u32, upper: &mut [Option<u32>; 32]) -> Option<u32> {
+  let base = match (shown.mnemonic, shown.operands.as_slice()) {
+    ("addi", [_, Operand::Register(base @ 1..), Operand::Immediate(offset)]) => Some((*base, *offset)),
+    (_, [.., Operand::Memory { offset, base }]) => Some((*base, *offset)),
+    _ => None,
+  };
+  let comment = base.and_then(|(base, offset)| match upper[base].take() {
+    Some(value) => Some(value.wrapping_add(offset as u32)),
+    // `x0` and `tp` address absolutely
+    None if base == 0 || base == 4 => Some(offset as u32),
+    None => None,
+  });
+  let rd = get_rd_bits(instruction) as usize;
+  match get_opcode_bits(instruction) {
+    OPCODE_LUI => upper[rd] = Some(get_U_imm_bits(instruction)),
+    OPCODE_AUIPC => upper[rd] = Some(address.wrapping_add(get_U_imm_bits(instruction))),
+    // other instructions writing a register overwrite its value
+    OPCODE_JAL | OPCODE_JALR | OPCODE_LOAD | OPCODE_OP_IMM | OPCODE_OP | OPCODE_AMO | OPCODE_SYSTEM => upper[rd] = None,
+    // only comparisons, classification, moves and conversions to integers write an integer register
+    OPCODE_OP_FP if matches!(shown.operands.first(), Some(Operand::Register(_))) => upper[rd] = None,
+    _ => {}
+  }
+  comment
+}
+
+/// the text of an instruction in a style, `alias` if it is a pseudo-instruction
+fn format_instruction(decoded: &Decoded, alias: bool, labels: &Labels, options: &DisassembleOptions) -> String {
+  let names = if options.abi_names { &ABI_REGISTER_NAMES } else { &REGISTER_NAMES };
+  let float_names = if options.abi_names { &ABI_FLOAT_REGISTER_NAMES } else { &FLOAT_REGISTER_NAMES };
+  let hexadecimal = options.style == Style::Gnu && matches!(
+    decoded.mnemonic,
+    "lui" | "auipc" | "slli" | "srli" | "srai" | "c.lui" | "c.slli" | "c.srli" | "c.srai"
+  );
+  // GNU objdump leaves out the dynamic rounding mode even without aliases
+  let shown = decoded.operands.iter().filter(|operand| options.style != Style::Gnu || **operand != Operand::RoundingMode(DYN));
+  let operands = shown.map(|operand| match *operand {
+    Operand::Register(register) => names[register].to_owned(),
+    Operand::FloatRegister(register) => float_names[register].to_owned(),
+    Operand::RoundingMode(rm) => ROUNDING_MODE_NAMES[rm as usize].to_owned(),
+    Operand::Immediate(imm) if hexadecimal => format!("{imm:#x}"),
+    Operand::Immediate(imm) => imm.to_string(),
+    Operand::Memory { offset, base } => format!("{offset}({})", names[base]),
+    Operand::Address(base) => format!("({})", names[base]),
+    Operand::Target(target) => labels.address(target, options.style),
+    Operand::Csr(csr) => match csr_name(csr) {
+      Some(name) => name.to_owned(),
+      None if options.style == Style::Gnu => format!("{csr:#x}"),
+      None => csr.to_string(),
+    },
+    Operand::FenceSet(set) => {
+      let set = "iorw".chars().enumerate().filter(|(bit, _)| set & 8 >> bit != 0).map(|(_, c)| c).collect::<String>();
+      if set.is_empty() { "0".to_owned() } else { set }
+    }
+  }).collect::<Vec<_>>();
+  let mnemonic = format!("{}{}", decoded.mnemonic, decoded.suffix);
+  match options.style {
+    Style::Xocolatl if operands.is_empty() => mnemonic,
+    Style::Xocolatl => format!("{mnemonic} {}", operands.join(", ")),
+    Style::Gnu if operands.is_empty() => mnemonic,
+    Style::Gnu => format!("{mnemonic}\t{}", operands.join(",")),
+    // llvm-mc separates instructions without operands by a tab too, but not aliases
+    Style::Llvm if operands.is_empty() && alias => mnemonic,
+    Style::Llvm => format!("{mnemonic}\t{}", operands.join(", ")),
+  }
+}
@@ -0,0 +1,113 @@
+use super::image::Section;
+
+/// relocation types of the RISC-V ELF psABI emitted by the assembler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+  /// `R_RISCV_32`, an address in data
+  Absolute32,
+  /// `R_RISCV_BRANCH`, the offset of a conditional branch
+  Branch,
+  /// `R_RISCV_JAL`, the offset of a `jal`
+  Jal,
+  /// `R_RISCV_CALL_PLT`, the offset of an `auipc` and `jalr` pair
+  Call,
+  /// `R_RISCV_PCREL_HI20`, the upper offset of an `auipc`
+  PcrelHi20,
+  /// `R_RISCV_PCREL_LO12_I`, the lower offset for an `auipc` whose label is the symbol, in an I-type instruction
+  PcrelLo12I,
+  /// `R_RISCV_PCREL_LO12_S`, as `PcrelLo12I` in a store
+  PcrelLo12S,
+  /// `R_RISCV_HI20`, the upper bits of an address for `lui`
+  Hi20,
+  /// `R_RISCV_LO12_I`, the lower bits of an address in an I-type instruction
+  Lo12I,
+  /// `R_RISCV_LO12_S`, the lower bits of an address in a store
+  Lo12S,
+  /// `R_RISCV_RVC_BRANCH`, the offset of `c.beqz` or `c.bnez`
+  RvcBranch,
+  /// `R_RISCV_RVC_JUMP`, the offset of `c.j` or `c.jal`
+  RvcJump,
+}
+
+impl RelocationKind {
+  const NUMBERS: [(Self, u32); 12] = [
+    (Self::Absolute32, 1),
+    (Self::Branch, 16),
+    (Self::Jal, 17),
+    (Self::Call, 19),
+    (Self::PcrelHi20, 23),
+    (Self::PcrelLo12I, 24),
+    (Self::PcrelLo12S, 25),
+    (Self::Hi20, 26),
+    (Self::Lo12I, 27),
+    (Self::Lo12S, 28),
+    (Self::RvcBranch, 44),
+    (Self::RvcJump, 45),
+  ];
+
+  /// the `R_RISCV_*` number
+  pub fn number(self) -> u32 {
+    Self::NUMBERS.iter().find(|(kind, _)| *kind == self).unwrap().1
+  }
+
+  pub fn from_number(number: u32) -> Option<Self> {
+    Self::NUMBERS.iter().find(|(_, n)| *n == number).map(|(kind, _)| *kind)
+  }
+
+  /// whether the value is relative to the place being relocated
+  pub fn is_pc_relative(self) -> bool {
+    matches!(self, Self::Branch | Self::Jal | Self::Call | Self::PcrelHi20 | Self::PcrelLo12I | Self::PcrelLo12S | Self::RvcBranch | Self::RvcJump)
+  }
+}
+
+/// a place in a section to fill in with the address of a symbol once it is known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+  /// index of the section in [`Object::sections`]
+  pub section: usize,
+  /// offset in the section
+  pub offset: u32,
+  pub kind: RelocationKind,
+  pub symbol: String,
+  pub addend: i32,
+}
+
+/// where a symbol is defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolSection {
+  /// defined in another object
+  Undefined,
+  /// a constant
+  Absolute,
+  /// a label at an offset in the section with this index
+  Section(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectSymbol {
+  pub name: String,
+  pub section: SymbolSection,
+  /// the offset in the section for labels
+  pub value: u32,
+  /// declared with `.globl`, visible to other objects
+  pub global: bool,
+}
+
+/// an assembled source file with its sections not placed yet,
+/// so section addresses are 0 and label values are offsets into their section
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Object {
+  pub sections: Vec<Section>,
+  pub symbols: Vec<ObjectSymbol>,
+  pub relocations: Vec<Relocation>,
+}
+
+impl Object {
+  pub fn section(&self, name: &str) -> Option<&Section> {
+    self.sections.iter().find(|section| section.name == name)
+  }
+
+  pub fn symbol(&self, name: &str) -> Option<&ObjectSymbol> {
+    self.symbols.iter().find(|symbol| symbol.name == name)
+  }
+}
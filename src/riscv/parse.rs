@@ -0,0 +1,234 @@
+use super::machine::{ABI_FLOAT_REGISTER_NAMES, ABI_REGISTER_NAMES};
+
+/// a single line of assembly source, split into its parts
+/// all parts borrow from the source text
+#[derive(Debug)]
+pub struct Statement<'a> {
+  /// line number, starting at 1
+  pub line: usize,
+  /// the whole line
+  pub text: &'a str,
+  /// the line of the source the statement was expanded from by the preprocessor,
+  /// the same as `text` unless it comes from a macro or an included file
+  pub origin: &'a str,
+  pub labels: Vec<&'a str>,
+  pub mnemonic: Option<&'a str>,
+  pub operands: Vec<&'a str>,
+}
+
+pub fn parse_program(source: &str) -> Vec<Statement<'_>> {
+  source.lines().enumerate().map(|(index, text)| parse_line(index + 1, text)).collect()
+}
+
+/// parse a line of the form `[label:]... [mnemonic [operand[, operand]...]] [# comment]`
+pub fn parse_line(line: usize, source: &str) -> Statement<'_> {
+  let mut text = strip_comment(source).trim();
+  let mut labels = vec![];
+  while let Some((label, rest)) = split_label(text) {
+    labels.push(label);
+    text = rest.trim_start();
+  }
+  let (mnemonic, operands) = match text.split_once(char::is_whitespace) {
+    Some((mnemonic, operands)) => (Some(mnemonic), split_operands(operands)),
+    None if text.is_empty() => (None, vec![]),
+    None => (Some(text), vec![]),
+  };
+  Statement { line, text: source, origin: source, labels, mnemonic, operands }
+}
+
+impl<'a> Statement<'a> {
+  /// the mnemonic with its operands, or the whole statement if there is no mnemonic
+  pub fn code(&self) -> &'a str {
+    match (self.mnemonic, self.operands.last()) {
+      (Some(mnemonic), Some(last)) => {
+        let start = mnemonic.as_ptr() as usize - self.text.as_ptr() as usize;
+        let end = last.as_ptr() as usize + last.len() - self.text.as_ptr() as usize;
+        &self.text[start..end]
+      }
+      (Some(mnemonic), None) => mnemonic,
+      (None, _) => self.text.trim(),
+    }
+  }
+}
+
+/// everything after `#` or `//` outside of a string or character literal is a comment
+fn strip_comment(text: &str) -> &str {
+  match unquoted_chars(text).find(|&(index, c)| c == '#' || text[index..].starts_with("//")) {
+    Some((index, _)) => &text[..index],
+    None => text,
+  }
+}
+
+/// characters with their byte offset, skipping string and character literals including their quotes
+fn unquoted_chars(text: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+  let mut quote = None;
+  let mut escaped = false;
+  text.char_indices().filter(move |&(_, c)| {
+    match quote {
+      Some(_) if escaped => escaped = false,
+      Some(_) if c == '\\' => escaped = true,
+      Some(q) if c == q => quote = None,
+      Some(_) => (),
+      None if c == '"' || c == '\'' => quote = Some(c),
+      None => return true,
+    }
+    false
+  })
+}
+
+/// split at characters matching `separator` outside of literals and parentheses
+fn split_unquoted(text: &str, separator: impl Fn(char) -> bool) -> Vec<&str> {
+  let mut parts = vec![];
+  let mut start = 0;
+  let mut depth = 0_usize;
+  for (index, c) in unquoted_chars(text) {
+    match c {
+      '(' => depth += 1,
+      ')' => depth = depth.saturating_sub(1),
+      c if depth == 0 && separator(c) => {
+        parts.push(&text[start..index]);
+        start = index + c.len_utf8();
+      }
+      _ => (),
+    }
+  }
+  parts.push(&text[start..]);
+  parts
+}
+
+fn split_label(text: &str) -> Option<(&str, &str)> {
+  let (label, rest) = text.split_once(':')?;
+  is_symbol(label).then_some((label, rest))
+}
+
+/// operands are separated by commas, or by whitespace if there are no commas,
+/// so both `addi a0, a0, 1` and the normalized `addi x10 x10 1` are accepted
+fn split_operands(text: &str) -> Vec<&str> {
+  let text = text.trim();
+  if text.is_empty() {
+    vec![]
+  } else if unquoted_chars(text).any(|(_, c)| c == ',') {
+    split_unquoted(text, |c| c == ',').into_iter().map(str::trim).collect()
+  } else {
+    split_unquoted(text, char::is_whitespace).into_iter().filter(|operand| !operand.is_empty()).collect()
+  }
+}
+
+/// symbols consist of letters, digits, `_`, `.` and `$` and do not start with a digit
+pub fn is_symbol(text: &str) -> bool {
+  let mut chars = text.chars();
+  let is_symbol_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$');
+  match chars.next() {
+    Some(c) if is_symbol_char(c) && !c.is_ascii_digit() => chars.all(is_symbol_char),
+    _ => false,
+  }
+}
+
+/// parse a register by its number `x0`-`x31` or ABI name, `fp` is an alias for `s0`
+pub fn parse_register(register: &str) -> Option<u32> {
+  if let Some(Ok(number)) = register.strip_prefix('x').map(str::parse::<u32>) {
+    return (number < 32).then_some(number);
+  }
+  match register {
+    "fp" => Some(8),
+    _ => ABI_REGISTER_NAMES.iter().position(|name| *name == register).map(|number| number as u32),
+  }
+}
+
+/// parse a floating-point register by its number `f0`-`f31` or ABI name
+pub fn parse_float_register(register: &str) -> Option<u32> {
+  if let Some(Ok(number)) = register.strip_prefix('f').map(str::parse::<u32>) {
+    return (number < 32).then_some(number);
+  }
+  ABI_FLOAT_REGISTER_NAMES.iter().position(|name| *name == register).map(|number| number as u32)
+}
+
+/// decode a double quoted string literal with C style escapes
+pub fn parse_string(literal: &str) -> Option<Vec<u8>> {
+  unescape(literal.strip_prefix('"')?.strip_suffix('"')?)
+}
+
+/// decode a single quoted character literal with C style escapes, such as `'A'` or `'\n'`
+pub fn parse_char(literal: &str) -> Option<u8> {
+  match unescape(literal.strip_prefix('\'')?.strip_suffix('\'')?)?.as_slice() {
+    [byte] => Some(*byte),
+    _ => None,
+  }
+}
+
+fn unescape(content: &str) -> Option<Vec<u8>> {
+  let mut bytes = vec![];
+  let mut chars = content.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      let mut buffer = [0; 4];
+      bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+      continue;
+    }
+    let byte = match chars.next()? {
+      'n' => b'\n',
+      't' => b'\t',
+      'r' => b'\r',
+      '0' => 0,
+      'a' => 0x07,
+      'b' => 0x08,
+      'f' => 0x0c,
+      'v' => 0x0b,
+      'x' => {
+        let digits = chars.clone().take(2).take_while(char::is_ascii_hexdigit).collect::<String>();
+        chars = chars.as_str()[digits.len()..].chars();
+        u8::from_str_radix(&digits, 16).ok()?
+      }
+      c @ ('\\' | '"' | '\'') => c as u8,
+      _ => return None,
+    };
+    bytes.push(byte);
+  }
+  Some(bytes)
+}
+
+#[test]
+fn test_parse_line() {
+  let statement = parse_line(1, "loop: end:  addi a0,a0,  1 # increment");
+  assert_eq!(statement.labels, ["loop", "end"]);
+  assert_eq!(statement.mnemonic, Some("addi"));
+  assert_eq!(statement.operands, ["a0", "a0", "1"]);
+  let statement = parse_line(2, "  // nothing here");
+  assert!(statement.labels.is_empty() && statement.mnemonic.is_none());
+  let statement = parse_line(3, "jal x1 main");
+  assert_eq!(statement.operands, ["x1", "main"]);
+  let statement = parse_line(4, r#"msg: .string "a, #b\"", 'x'  # comment"#);
+  assert_eq!(statement.labels, ["msg"]);
+  assert_eq!(statement.operands, [r#""a, #b\"""#, "'x'"]);
+}
+
+#[test]
+fn test_parse_register() {
+  assert_eq!(parse_register("x31"), Some(31));
+  assert_eq!(parse_register("zero"), Some(0));
+  assert_eq!(parse_register("sp"), Some(2));
+  assert_eq!(parse_register("fp"), parse_register("s0"));
+  assert_eq!(parse_register("t6"), Some(31));
+  assert_eq!(parse_register("x32"), None);
+  assert_eq!(parse_register("a8"), None);
+}
+
+#[test]
+fn test_parse_float_register() {
+  assert_eq!(parse_float_register("f31"), Some(31));
+  assert_eq!(parse_float_register("ft0"), Some(0));
+  assert_eq!(parse_float_register("fa0"), Some(10));
+  assert_eq!(parse_float_register("fs11"), Some(27));
+  assert_eq!(parse_float_register("f32"), None);
+  assert_eq!(parse_float_register("a0"), None);
+}
+
+#[test]
+fn test_parse_string() {
+  assert_eq!(parse_string(r#""hi\n""#), Some(b"hi\n".to_vec()));
+  assert_eq!(parse_string(r#""\x41\"\\""#), Some(b"A\"\\".to_vec()));
+  assert_eq!(parse_string("hi"), None);
+  assert_eq!(parse_char("'A'"), Some(b'A'));
+  assert_eq!(parse_char(r"'\''"), Some(b'\''));
+  assert_eq!(parse_char("'ab'"), None);
+}
@@ -0,0 +1,131 @@
+use super::machine::Access;
+use super::csr::PRIVILEGE_M;
+
+pub const PMP_ENTRIES: usize = 16;
+
+/// the bits of the configuration of an entry
+pub const PMP_R: u8 = 1 << 0;
+pub const PMP_W: u8 = 1 << 1;
+pub const PMP_X: u8 = 1 << 2;
+/// the address-matching mode, two bits
+pub const PMP_A: u8 = 0b11 << 3;
+pub const PMP_TOR: u8 = 1 << 3;
+pub const PMP_NA4: u8 = 2 << 3;
+pub const PMP_NAPOT: u8 = 3 << 3;
+/// locks the entry until reset and applies it to M-mode as well
+pub const PMP_L: u8 = 1 << 7;
+
+/// the physical memory protection entries, matched in order with the lowest-numbered match deciding,
+/// the granularity is 4 bytes
+#[derive(Debug, Default)]
+pub struct Pmp {
+  /// the configuration of each entry, four to a `pmpcfg` CSR
+  pub cfg: [u8; PMP_ENTRIES],
+  /// bits 2 to 33 of the addresses of each entry
+  pub addr: [u32; PMP_ENTRIES],
+}
+
+impl Pmp {
+  /// the range of physical addresses of an entry from its start up to its end, `None` if it is off
+  fn range(&self, index: usize) -> Option<(u64, u64)> {
+    let address = (self.addr[index] as u64) << 2;
+    match self.cfg[index] & PMP_A {
+      PMP_TOR => {
+        let start = if index == 0 { 0 } else { (self.addr[index - 1] as u64) << 2 };
+        Some((start, address))
+      }
+      PMP_NA4 => Some((address, address + 4)),
+      // the number of trailing ones encodes a naturally aligned power of two of at least 8 bytes
+      PMP_NAPOT => {
+        let size = 8 << self.addr[index].trailing_ones();
+        let start = address & !(size - 1);
+        Some((start, start + size))
+      }
+      _ => None,
+    }
+  }
+
+  /// whether an access of kind `access` to `size` bytes at a physical address is permitted at `privilege`,
+  /// accesses that match no entry are only permitted in M-mode unless all entries are off,
+  /// so that S-mode and U-mode run unrestricted until the PMP is set up
+  pub fn check(&self, address: u32, size: u32, access: Access, privilege: u32) -> bool {
+    if self.cfg.iter().all(|cfg| cfg & PMP_A == 0) {
+      return true;
+    }
+    let (start, end) = (address as u64, address as u64 + size as u64);
+    for index in 0..PMP_ENTRIES {
+      let Some((low, high)) = self.range(index) else {
+        continue;
+      };
+      if end <= low || start >= high {
+        continue;
+      }
+      // accesses that only partially match fail
+      if start < low || end > high {
+        return false;
+      }
+      let cfg = self.cfg[index];
+      if privilege == PRIVILEGE_M && cfg & PMP_L == 0 {
+        return true;
+      }
+      return cfg & match access {
+        Access::Fetch => PMP_X,
+        Access::Load => PMP_R,
+        Access::Store => PMP_W,
+      } != 0;
+    }
+    privilege == PRIVILEGE_M
+  }
+
+  fn locked(&self, index: usize) -> bool {
+    self.cfg[index] & PMP_L != 0
+  }
+
+  /// the value of `pmpcfg0` to `pmpcfg3`
+  pub fn read_cfg(&self, csr: usize) -> u32 {
+    u32::from_le_bytes(self.cfg[4 * csr..4 * csr + 4].try_into().unwrap())
+  }
+
+  /// write `pmpcfg0` to `pmpcfg3`, the configuration of locked entries keeps its value,
+  /// the reserved bits and `W` without `R` are not writable
+  pub fn write_cfg(&mut self, csr: usize, value: u32) {
+    for (index, cfg) in (4 * csr..4 * csr + 4).zip(value.to_le_bytes()) {
+      if self.locked(index) {
+        continue;
+      }
+      let cfg = cfg & (PMP_R | PMP_W | PMP_X | PMP_A | PMP_L);
+      self.cfg[index] = if cfg & PMP_R == 0 { cfg & !PMP_W } else { cfg };
+    }
+  }
+
+  /// write `pmpaddr0` to `pmpaddr15`, which keep their value if the entry is locked
+  /// or is the bottom of a locked entry matching the top of a range
+  pub fn write_addr(&mut self, index: usize, value: u32) {
+    let next = index + 1;
+    let bottom_locked = next < PMP_ENTRIES && self.locked(next) && self.cfg[next] & PMP_A == PMP_TOR;
+    if !self.locked(index) && !bottom_locked {
+      self.addr[index] = value;
+    }
+  }
+}
+
+#[test]
+fn test_pmp_matching() {
+  let mut pmp = Pmp::default();
+  assert!(pmp.check(0x1000, 4, Access::Store, 0));
+  pmp.write_addr(0, 0x1000 >> 2);
+  pmp.write_addr(1, 0x2000 >> 2 | 0x1ff);
+  pmp.write_cfg(0, u32::from_le_bytes([PMP_TOR | PMP_R, PMP_NAPOT | PMP_W | PMP_L, 0, 0]));
+  // W without R is cleared
+  assert_eq!(pmp.cfg[1], PMP_NAPOT | PMP_L);
+  assert!(pmp.check(0xffc, 4, Access::Load, 0));
+  assert!(!pmp.check(0xffc, 4, Access::Store, 0));
+  assert!(!pmp.check(0x1000, 4, Access::Load, 0));
+  assert!(pmp.check(0x1000, 4, Access::Load, PRIVILEGE_M));
+  assert!(!pmp.check(0x2ffc, 4, Access::Load, PRIVILEGE_M));
+  assert!(pmp.check(0x3000, 4, Access::Load, PRIVILEGE_M));
+  pmp.write_addr(1, 0);
+  pmp.write_cfg(0, 0);
+  assert_eq!(pmp.read_cfg(0), (PMP_NAPOT as u32 | PMP_L as u32) << 8);
+  assert_eq!(pmp.addr[1], 0x2000 >> 2 | 0x1ff);
+}
@@ -0,0 +1,374 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use super::assemble::{Context, Symbols};
+use super::error::{AssembleError, ErrorKind};
+use super::expr::evaluate;
+use super::parse::{Statement, is_symbol, parse_line, parse_string};
+
+/// macros and includes nested deeper than this are assumed to recurse forever
+const MAX_DEPTH: usize = 64;
+/// the largest count of `.rept`, so that a mistyped count does not exhaust memory
+const MAX_REPEAT: i64 = 0x10000;
+
+/// a line of source after preprocessing
+#[derive(Debug, Clone)]
+pub struct Line<'a> {
+  /// line number in the source, starting at 1
+  pub line: usize,
+  /// the line of the source this line was expanded from, see [`Statement::origin`]
+  pub origin: &'a str,
+  pub text: Cow<'a, str>,
+}
+
+impl Line<'_> {
+  pub fn parse(&self) -> Statement<'_> {
+    Statement { origin: self.origin, ..parse_line(self.line, &self.text) }
+  }
+}
+
+/// expand macros, repetitions, conditional assembly and includes, compatible with GNU as
+/// - `.macro name [param[=default]]...` up to `.endm` defines a macro, in its body `\param` is replaced
+///   by the argument given by position or as `param=value`, `\@` by the number of macro invocations so far
+///   and `\()` by nothing, to separate a parameter from following text
+/// - `.rept count` up to `.endr` repeats the lines in between
+/// - `.irp param, value...` and `.irpc param, characters` up to `.endr` repeat the lines in between
+///   with `\param` replaced by each value or character
+/// - `.if expression`, `.ifdef symbol`, `.ifndef symbol`, `.ifb [text]` and `.ifnb [text]`
+///   with optional `.elseif expression` and `.else` up to `.endif` assemble lines conditionally,
+///   symbols are labels and `.equ` constants defined on earlier lines, expressions may only use constants
+/// - `.include "file"` includes the lines returned by `include` for the file name
+///
+/// lines expanded from a macro get the line number of the invocation,
+/// lines from included files that of the `.include`
+pub fn preprocess<'a>(source: &'a str, include: &mut dyn FnMut(&str) -> Option<String>) -> (Vec<Line<'a>>, Vec<AssembleError>) {
+  let lines = source.lines().enumerate().map(|(index, text)| {
+    Line { line: index + 1, origin: text, text: Cow::Borrowed(text) }
+  }).collect::<Vec<_>>();
+  let mut preprocessor = Preprocessor {
+    source,
+    include,
+    macros: HashMap::new(),
+    constants: HashMap::new(),
+    defined: HashSet::new(),
+    conditions: vec![],
+    invocations: 0,
+    depth: 0,
+    lines: vec![],
+    errors: vec![],
+  };
+  preprocessor.process(&lines);
+  for condition in std::mem::take(&mut preprocessor.conditions) {
+    let statement = condition.start.parse();
+    let directive = statement.mnemonic.unwrap_or_default().to_owned();
+    preprocessor.error(&statement, ErrorKind::UnterminatedBlock { directive, end: ".endif" });
+  }
+  (preprocessor.lines, preprocessor.errors)
+}
+
+#[derive(Clone)]
+struct Macro<'a> {
+  /// names with default values
+  parameters: Vec<(String, String)>,
+  body: Vec<Line<'a>>,
+}
+
+/// an `.if` block being preprocessed
+struct Condition<'a> {
+  start: Line<'a>,
+  /// whether the block containing the `.if` is assembled
+  enclosing: bool,
+  /// whether the current branch is assembled
+  active: bool,
+  /// whether a branch was assembled already, so that later ones are skipped
+  taken: bool,
+}
+
+struct Preprocessor<'a, 'i> {
+  source: &'a str,
+  include: &'i mut dyn FnMut(&str) -> Option<String>,
+  macros: HashMap<String, Macro<'a>>,
+  /// `.equ` constants for expressions
+  constants: HashMap<String, u32>,
+  /// labels and constants for `.ifdef`
+  defined: HashSet<String>,
+  conditions: Vec<Condition<'a>>,
+  invocations: usize,
+  /// nesting of macros and includes
+  depth: usize,
+  lines: Vec<Line<'a>>,
+  errors: Vec<AssembleError>,
+}
+
+impl<'a> Preprocessor<'a, '_> {
+  fn process(&mut self, lines: &[Line<'a>]) {
+    let mut index = 0;
+    while index < lines.len() {
+      let line = &lines[index];
+      index += 1;
+      let statement = line.parse();
+      if self.condition(line, &statement) || !self.active() {
+        continue;
+      }
+      let Some(mnemonic) = statement.mnemonic else {
+        self.pass(line, &statement);
+        continue;
+      };
+      match mnemonic {
+        ".macro" => {
+          self.labels(line, &statement);
+          let body;
+          (body, index) = self.block(lines, index, &statement, &[".macro"], ".endm");
+          self.define_macro(&statement, body);
+        }
+        ".rept" | ".irp" | ".irpc" => {
+          self.labels(line, &statement);
+          let body;
+          (body, index) = self.block(lines, index, &statement, &[".rept", ".irp", ".irpc"], ".endr");
+          self.repeat(&statement, body);
+        }
+        ".endm" | ".endr" => self.error(&statement, ErrorKind::UnmatchedDirective(mnemonic.to_owned())),
+        ".include" => {
+          self.labels(line, &statement);
+          self.include(line, &statement);
+        }
+        ".equ" | ".set" => {
+          if let [name, expression] = *statement.operands {
+            if let Ok(value) = self.evaluate(expression) {
+              self.constants.insert(name.to_owned(), value as u32);
+            }
+            self.defined.insert(name.to_owned());
+          }
+          self.pass(line, &statement);
+        }
+        name if self.macros.contains_key(name) => {
+          self.labels(line, &statement);
+          self.expand(line, &statement);
+        }
+        _ => self.pass(line, &statement),
+      }
+    }
+  }
+
+  /// keep a line for the assembler
+  fn pass(&mut self, line: &Line<'a>, statement: &Statement) {
+    self.defined.extend(statement.labels.iter().map(|label| label.to_string()));
+    self.lines.push(line.clone());
+  }
+
+  /// keep only the labels of a line that is replaced by its expansion
+  fn labels(&mut self, line: &Line<'a>, statement: &Statement) {
+    if !statement.labels.is_empty() {
+      let text = statement.labels.iter().map(|label| format!("{label}:")).collect::<Vec<_>>().join(" ");
+      self.pass(&Line { text: Cow::Owned(text), ..line.clone() }, statement);
+    }
+  }
+
+  fn active(&self) -> bool {
+    self.conditions.last().is_none_or(|condition| condition.active)
+  }
+
+  /// handle conditional assembly directives, returns whether the statement was one
+  fn condition(&mut self, line: &Line<'a>, statement: &Statement) -> bool {
+    match statement.mnemonic {
+      Some(".if" | ".ifdef" | ".ifndef" | ".ifb" | ".ifnb") => {
+        let enclosing = self.active();
+        let active = enclosing && self.test(statement);
+        self.conditions.push(Condition { start: line.clone(), enclosing, active, taken: active });
+      }
+      Some(directive @ (".elseif" | ".else")) => {
+        let Some(condition) = self.conditions.last() else {
+          self.error(statement, ErrorKind::UnmatchedDirective(directive.to_owned()));
+          return true;
+        };
+        let active = condition.enclosing && !condition.taken && (directive == ".else" || self.test(statement));
+        let condition = self.conditions.last_mut().unwrap();
+        condition.active = active;
+        condition.taken |= active;
+      }
+      Some(".endif") => {
+        if self.conditions.pop().is_none() {
+          self.error(statement, ErrorKind::UnmatchedDirective(".endif".to_owned()));
+        }
+      }
+      _ => return false,
+    }
+    true
+  }
+
+  /// evaluate the condition of an `.if` or `.elseif`
+  fn test(&mut self, statement: &Statement) -> bool {
+    let mnemonic = statement.mnemonic.unwrap_or_default();
+    let result = match (mnemonic, statement.operands.as_slice()) {
+      (".if" | ".elseif", [_, ..]) => self.evaluate(&statement.operands.join(" ")).map(|value| value != 0),
+      (".ifdef", [symbol]) => Ok(self.defined.contains(*symbol)),
+      (".ifndef", [symbol]) => Ok(!self.defined.contains(*symbol)),
+      (".ifb", operands) => Ok(operands.is_empty()),
+      (".ifnb", operands) => Ok(!operands.is_empty()),
+      (_, operands) => Err(ErrorKind::WrongOperandCount {
+        mnemonic: mnemonic.to_owned(),
+        expected: if mnemonic.starts_with(".ifd") || mnemonic.starts_with(".ifnd") { "symbol" } else { "expression" },
+        found: operands.len(),
+      }),
+    };
+    result.unwrap_or_else(|kind| {
+      self.error(statement, kind);
+      false
+    })
+  }
+
+  /// the lines from `start` up to the matching `end`, which may be nested in blocks started by any of `starts`,
+  /// and the index of the line after `end`
+  fn block<'l>(&mut self, lines: &'l [Line<'a>], start: usize, statement: &Statement, starts: &[&str], end: &'static str) -> (&'l [Line<'a>], usize) {
+    let mut depth = 0;
+    for (index, line) in lines.iter().enumerate().skip(start) {
+      match line.parse().mnemonic {
+        Some(mnemonic) if starts.contains(&mnemonic) => depth += 1,
+        Some(mnemonic) if mnemonic == end && depth == 0 => return (&lines[start..index], index + 1),
+        Some(mnemonic) if mnemonic == end => depth -= 1,
+        _ => (),
+      }
+    }
+    let directive = statement.mnemonic.unwrap_or_default().to_owned();
+    self.error(statement, ErrorKind::UnterminatedBlock { directive, end });
+    (&lines[start..], lines.len())
+  }
+
+  fn define_macro(&mut self, statement: &Statement, body: &[Line<'a>]) {
+    let mut words = statement.operands.iter().flat_map(|operand| operand.split_whitespace());
+    let Some(name) = words.next().filter(|name| is_symbol(name)) else {
+      let operand = statement.operands.first().copied().unwrap_or_default().to_owned();
+      self.error(statement, ErrorKind::InvalidOperand { operand, expected: "a macro name" });
+      return;
+    };
+    let parameters = words.map(|parameter| match parameter.split_once('=') {
+      Some((parameter, default)) => (parameter.to_owned(), default.to_owned()),
+      None => (parameter.to_owned(), String::new()),
+    }).collect();
+    self.macros.insert(name.to_owned(), Macro { parameters, body: body.to_vec() });
+  }
+
+  fn expand(&mut self, line: &Line<'a>, statement: &Statement) {
+    let name = statement.mnemonic.unwrap_or_default();
+    let Macro { mut parameters, body } = self.macros[name].clone();
+    for (position, operand) in statement.operands.iter().enumerate() {
+      let named = operand.split_once('=').and_then(|(parameter, value)| {
+        let index = parameters.iter().position(|(name, _)| name == parameter.trim())?;
+        Some((index, value.trim()))
+      });
+      match named {
+        Some((index, value)) => parameters[index].1 = value.to_owned(),
+        None if position < parameters.len() => parameters[position].1 = operand.to_string(),
+        None => {
+          let operand = operand.to_string();
+          return self.error(statement, ErrorKind::InvalidOperand { operand, expected: "one argument per macro parameter" });
+        }
+      }
+    }
+    let invocation = self.invocations;
+    self.invocations += 1;
+    let lines = body.iter().map(|body| Line {
+      text: Cow::Owned(substitute(&body.text, &parameters, Some(invocation))),
+      ..line.clone()
+    }).collect::<Vec<_>>();
+    self.nested(name, statement, &lines);
+  }
+
+  fn repeat(&mut self, statement: &Statement, body: &[Line<'a>]) {
+    let mnemonic = statement.mnemonic.unwrap_or_default();
+    let arguments = match (mnemonic, statement.operands.as_slice()) {
+      (".rept", [_, ..]) => match self.evaluate(&statement.operands.join(" ")) {
+        Ok(count) if (0..=MAX_REPEAT).contains(&count) => vec![vec![]; count as usize],
+        Ok(count) => return self.error(statement, ErrorKind::ImmediateOutOfRange { value: count, min: 0, max: MAX_REPEAT }),
+        Err(kind) => return self.error(statement, kind),
+      },
+      (".irp", [parameter, values @ ..]) => {
+        values.iter().map(|value| vec![(parameter.to_string(), value.to_string())]).collect()
+      }
+      (".irpc", [parameter, characters]) => {
+        characters.chars().map(|c| vec![(parameter.to_string(), c.to_string())]).collect()
+      }
+      _ => return self.error(statement, ErrorKind::WrongOperandCount {
+        mnemonic: mnemonic.to_owned(),
+        expected: if mnemonic == ".rept" { "count" } else { "parameter, values" },
+        found: statement.operands.len(),
+      }),
+    };
+    for arguments in arguments {
+      let lines = body.iter().map(|line| Line { text: Cow::Owned(substitute(&line.text, &arguments, None)), ..line.clone() });
+      self.process(&lines.collect::<Vec<_>>());
+    }
+  }
+
+  fn include(&mut self, line: &Line<'a>, statement: &Statement) {
+    let [file] = *statement.operands else {
+      let found = statement.operands.len();
+      return self.error(statement, ErrorKind::WrongOperandCount { mnemonic: ".include".to_owned(), expected: "\"file\"", found });
+    };
+    let Some(name) = parse_string(file).and_then(|name| String::from_utf8(name).ok()) else {
+      return self.error(statement, ErrorKind::InvalidOperand { operand: file.to_owned(), expected: "a string" });
+    };
+    let Some(contents) = (self.include)(&name) else {
+      return self.error(statement, ErrorKind::IncludeFailed(name));
+    };
+    let lines = contents.lines().map(|text| Line { text: Cow::Owned(text.to_owned()), ..line.clone() }).collect::<Vec<_>>();
+    self.nested(".include", statement, &lines);
+  }
+
+  /// process the expansion of a macro or include
+  fn nested(&mut self, name: &str, statement: &Statement, lines: &[Line<'a>]) {
+    if self.depth == MAX_DEPTH {
+      return self.error(statement, ErrorKind::RecursionLimit(name.to_owned()));
+    }
+    self.depth += 1;
+    self.process(lines);
+    self.depth -= 1;
+  }
+
+  fn evaluate(&self, expression: &str) -> Result<i64, ErrorKind> {
+    let symbols = self.constants.iter().map(|(name, value)| (name.as_str(), *value)).collect::<Symbols>();
+    evaluate(expression, &Context::new(&symbols, 0))
+  }
+
+  fn error(&mut self, statement: &Statement, kind: ErrorKind) {
+    self.errors.push(AssembleError::in_statement(self.source, statement, statement.code(), kind));
+  }
+}
+
+/// replace `\name` by the value of the parameter `name`, `\@` by `invocation` if given and `\()` by nothing
+fn substitute(text: &str, arguments: &[(String, String)], invocation: Option<usize>) -> String {
+  let mut result = String::new();
+  let mut rest = text;
+  while let Some(start) = rest.find('\\') {
+    result.push_str(&rest[..start]);
+    let after = &rest[start + 1..];
+    let length = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+    rest = match arguments.iter().find(|(parameter, _)| *parameter == after[..length]) {
+      Some((_, value)) => {
+        result.push_str(value);
+        &after[length..]
+      }
+      None if after.starts_with("()") => &after[2..],
+      None if after.starts_with('@') && invocation.is_some() => {
+        result.push_str(&invocation.unwrap().to_string());
+        &after[1..]
+      }
+      // keep escapes like `\\` in strings as they are
+      None => {
+        let length = after.chars().next().map_or(0, char::len_utf8);
+        result.push('\\');
+        result.push_str(&after[..length]);
+        &after[length..]
+      }
+    };
+  }
+  result.push_str(rest);
+  result
+}
+
+#[test]
+fn test_substitute() {
+  let arguments = [("reg".to_owned(), "a0".to_owned()), ("n".to_owned(), "4".to_owned())];
+  assert_eq!(substitute("addi \\reg, \\reg, \\n", &arguments, None), "addi a0, a0, 4");
+  assert_eq!(substitute("loop\\@: \\reg\\()_\\n", &arguments, Some(3)), "loop3: a0_4");
+  assert_eq!(substitute(".string \"\\\\n\\t\"", &arguments, None), ".string \"\\\\n\\t\"");
+}
@@ -0,0 +1,199 @@
+use super::assemble::{Context, parse_immediate};
+use super::object::RelocationKind;
+use super::error::ErrorKind;
+use super::expr::constant;
+use super::parse::parse_register;
+
+/// base instructions a pseudo-instruction expands to, as mnemonic and operands
+pub type Expansion<'a> = Vec<(&'a str, Vec<String>)>;
+
+/// expand a pseudo-instruction into base instructions,
+/// returns `None` if `mnemonic` with these operands is not a pseudo-instruction
+/// see the RISC-V Assembly Programmer's Manual for the list of pseudo-instructions
+pub fn expand_pseudo<'a>(mnemonic: &'a str, operands: &[&str], context: &Context) -> Result<Option<Expansion<'a>>, ErrorKind> {
+  let expansion = match (mnemonic, operands) {
+    ("nop", []) => vec![op("addi", ["zero", "zero", "0"])],
+    ("li", [rd, imm]) => expand_li(rd, imm, context)?,
+    ("la" | "lla", [rd, symbol]) => expand_pcrel(symbol, context, RelocationKind::PcrelLo12I, |hi, lo| vec![
+      op("auipc", [rd, &hi]),
+      op("addi", [rd, rd, &lo]),
+    ])?,
+    ("mv", [rd, rs]) => vec![op("addi", [rd, rs, "0"])],
+    ("not", [rd, rs]) => vec![op("xori", [rd, rs, "-1"])],
+    ("neg", [rd, rs]) => vec![op("sub", [rd, "zero", rs])],
+    ("seqz", [rd, rs]) => vec![op("sltiu", [rd, rs, "1"])],
+    ("snez", [rd, rs]) => vec![op("sltu", [rd, "zero", rs])],
+    ("sltz", [rd, rs]) => vec![op("slt", [rd, rs, "zero"])],
+    ("sgtz", [rd, rs]) => vec![op("slt", [rd, "zero", rs])],
+    ("beqz", [rs, offset]) => vec![op("beq", [rs, "zero", offset])],
+    ("bnez", [rs, offset]) => vec![op("bne", [rs, "zero", offset])],
+    ("blez", [rs, offset]) => vec![op("bge", ["zero", rs, offset])],
+    ("bgez", [rs, offset]) => vec![op("bge", [rs, "zero", offset])],
+    ("bltz", [rs, offset]) => vec![op("blt", [rs, "zero", offset])],
+    ("bgtz", [rs, offset]) => vec![op("blt", ["zero", rs, offset])],
+    ("bgt", [rs, rt, offset]) => vec![op("blt", [rt, rs, offset])],
+    ("ble", [rs, rt, offset]) => vec![op("bge", [rt, rs, offset])],
+    ("bgtu", [rs, rt, offset]) => vec![op("bltu", [rt, rs, offset])],
+    ("bleu", [rs, rt, offset]) => vec![op("bgeu", [rt, rs, offset])],
+    ("j", [offset]) => vec![op("jal", ["zero", offset])],
+    ("jal", [offset]) => vec![op("jal", ["ra", offset])],
+    ("jr", [rs]) => vec![op("jalr", ["zero", rs, "0"])],
+    ("jalr", [rs]) => vec![op("jalr", ["ra", rs, "0"])],
+    ("ret", []) => vec![op("jalr", ["zero", "ra", "0"])],
+    ("lb" | "lh" | "lw" | "lbu" | "lhu", [rd, symbol]) if is_address(symbol) => {
+      expand_pcrel(symbol, context, RelocationKind::PcrelLo12I, |hi, lo| vec![
+        op("auipc", [rd, &hi]),
+        op(mnemonic, [rd, &format!("{lo}({rd})")]),
+      ])?
+    }
+    ("sb" | "sh" | "sw", [rs, symbol, rt]) if is_address(symbol) => {
+      expand_pcrel(symbol, context, RelocationKind::PcrelLo12S, |hi, lo| vec![
+        op("auipc", [rt, &hi]),
+        op(mnemonic, [rs, &format!("{lo}({rt})")]),
+      ])?
+    }
+    // floating-point loads need an integer register for the address
+    ("flw" | "fld", [rd, symbol, rt]) if is_address(symbol) => {
+      expand_pcrel(symbol, context, RelocationKind::PcrelLo12I, |hi, lo| vec![
+        op("auipc", [rt, &hi]),
+        op(mnemonic, [rd, &format!("{lo}({rt})")]),
+      ])?
+    }
+    ("fsw" | "fsd", [rs, symbol, rt]) if is_address(symbol) => {
+      expand_pcrel(symbol, context, RelocationKind::PcrelLo12S, |hi, lo| vec![
+        op("auipc", [rt, &hi]),
+        op(mnemonic, [rs, &format!("{lo}({rt})")]),
+      ])?
+    }
+    ("fmv.s", [rd, rs]) => vec![op("fsgnj.s", [rd, rs, rs])],
+    ("fneg.s", [rd, rs]) => vec![op("fsgnjn.s", [rd, rs, rs])],
+    ("fabs.s", [rd, rs]) => vec![op("fsgnjx.s", [rd, rs, rs])],
+    ("fgt.s", [rd, rs, rt]) => vec![op("flt.s", [rd, rt, rs])],
+    ("fge.s", [rd, rs, rt]) => vec![op("fle.s", [rd, rt, rs])],
+    ("fmv.d", [rd, rs]) => vec![op("fsgnj.d", [rd, rs, rs])],
+    ("fneg.d", [rd, rs]) => vec![op("fsgnjn.d", [rd, rs, rs])],
+    ("fabs.d", [rd, rs]) => vec![op("fsgnjx.d", [rd, rs, rs])],
+    ("fgt.d", [rd, rs, rt]) => vec![op("flt.d", [rd, rt, rs])],
+    ("fge.d", [rd, rs, rt]) => vec![op("fle.d", [rd, rt, rs])],
+    ("csrr", [rd, csr]) => vec![op("csrrs", [rd, csr, "zero"])],
+    ("csrw", [csr, rs]) => vec![op("csrrw", ["zero", csr, rs])],
+    ("csrs", [csr, rs]) => vec![op("csrrs", ["zero", csr, rs])],
+    ("csrc", [csr, rs]) => vec![op("csrrc", ["zero", csr, rs])],
+    ("csrwi", [csr, imm]) => vec![op("csrrwi", ["zero", csr, imm])],
+    ("csrsi", [csr, imm]) => vec![op("csrrsi", ["zero", csr, imm])],
+    ("csrci", [csr, imm]) => vec![op("csrrci", ["zero", csr, imm])],
+    ("rdcycle", [rd]) => vec![op("csrrs", [rd, "cycle", "zero"])],
+    ("rdcycleh", [rd]) => vec![op("csrrs", [rd, "cycleh", "zero"])],
+    ("rdtime", [rd]) => vec![op("csrrs", [rd, "time", "zero"])],
+    ("rdtimeh", [rd]) => vec![op("csrrs", [rd, "timeh", "zero"])],
+    ("rdinstret", [rd]) => vec![op("csrrs", [rd, "instret", "zero"])],
+    ("rdinstreth", [rd]) => vec![op("csrrs", [rd, "instreth", "zero"])],
+    ("frcsr", [rd]) => vec![op("csrrs", [rd, "fcsr", "zero"])],
+    ("fscsr", [rs]) => vec![op("csrrw", ["zero", "fcsr", rs])],
+    ("fscsr", [rd, rs]) => vec![op("csrrw", [rd, "fcsr", rs])],
+    ("frrm", [rd]) => vec![op("csrrs", [rd, "frm", "zero"])],
+    ("fsrm", [rs]) => vec![op("csrrw", ["zero", "frm", rs])],
+    ("fsrm", [rd, rs]) => vec![op("csrrw", [rd, "frm", rs])],
+    ("fsrmi", [imm]) => vec![op("csrrwi", ["zero", "frm", imm])],
+    ("fsrmi", [rd, imm]) => vec![op("csrrwi", [rd, "frm", imm])],
+    ("frflags", [rd]) => vec![op("csrrs", [rd, "fflags", "zero"])],
+    ("fsflags", [rs]) => vec![op("csrrw", ["zero", "fflags", rs])],
+    ("fsflags", [rd, rs]) => vec![op("csrrw", [rd, "fflags", rs])],
+    ("fsflagsi", [imm]) => vec![op("csrrwi", ["zero", "fflags", imm])],
+    ("fsflagsi", [rd, imm]) => vec![op("csrrwi", [rd, "fflags", imm])],
+    ("call", [symbol]) => expand_call("ra", "ra", symbol, context)?,
+    ("call", [rd, symbol]) => expand_call(rd, rd, symbol, context)?,
+    ("tail", [symbol]) => expand_call("zero", "t1", symbol, context)?,
+    _ => return Ok(None),
+  };
+  Ok(Some(expansion))
+}
+
+/// operand syntax of pseudo-instructions that are not also base instructions, for error messages
+pub fn pseudo_syntax(mnemonic: &str) -> Option<&'static str> {
+  let syntax = match mnemonic {
+    "nop" | "ret" => "no operands",
+    "li" => "rd, imm",
+    "la" | "lla" => "rd, symbol",
+    "mv" | "not" | "neg" | "seqz" | "snez" | "sltz" | "sgtz" => "rd, rs",
+    "beqz" | "bnez" | "blez" | "bgez" | "bltz" | "bgtz" => "rs, offset",
+    "bgt" | "ble" | "bgtu" | "bleu" => "rs, rt, offset",
+    "j" => "offset",
+    "jr" => "rs",
+    "call" => "[rd, ]symbol",
+    "tail" => "symbol",
+    "fmv.s" | "fneg.s" | "fabs.s" | "fmv.d" | "fneg.d" | "fabs.d" => "fd, fs",
+    "fgt.s" | "fge.s" | "fgt.d" | "fge.d" => "rd, fs, ft",
+    "csrr" => "rd, csr",
+    "csrw" | "csrs" | "csrc" => "csr, rs",
+    "csrwi" | "csrsi" | "csrci" => "csr, uimm",
+    "rdcycle" | "rdcycleh" | "rdtime" | "rdtimeh" | "rdinstret" | "rdinstreth" | "frcsr" | "frrm" | "frflags" => "rd",
+    "fscsr" | "fsrm" | "fsflags" => "[rd, ]rs",
+    "fsrmi" | "fsflagsi" => "[rd, ]uimm",
+    _ => return None,
+  };
+  Some(syntax)
+}
+
+/// loads and stores take an address instead of an `offset(register)` or normalized register operand
+fn is_address(operand: &str) -> bool {
+  !operand.ends_with(')') && parse_register(operand).is_none()
+}
+
+fn op<'a, const N: usize>(mnemonic: &'a str, operands: [&str; N]) -> (&'a str, Vec<String>) {
+  (mnemonic, operands.map(str::to_owned).to_vec())
+}
+
+/// `lui` and `addi` as needed, constants get the shortest sequence,
+/// expressions with symbols always get both instructions as their value may not be known in the first pass
+/// and may need relocations in relocatable objects
+fn expand_li(rd: &str, imm: &str, context: &Context) -> Result<Expansion<'static>, ErrorKind> {
+  if context.relocate(imm, RelocationKind::Hi20) {
+    context.advance(1).relocate(imm, RelocationKind::Lo12I);
+    return Ok(vec![op("lui", [rd, "0"]), op("addi", [rd, rd, "0"])]);
+  }
+  let value = parse_immediate(imm, context)? as i32;
+  let (hi, lo) = split_hi_lo(value);
+  let (hi, lo) = (&hi.to_string(), &lo.to_string());
+  let expansion = match constant(imm) {
+    Some(value) if (-2048..2048).contains(&value) => vec![op("addi", [rd, "zero", lo])],
+    Some(value) if value & 0xfff == 0 => vec![op("lui", [rd, hi])],
+    _ => vec![op("lui", [rd, hi]), op("addi", [rd, rd, lo])],
+  };
+  Ok(expansion)
+}
+
+/// `auipc` followed by an instruction adding the lower part of the offset from the `auipc` to `symbol`,
+/// relocated as `%pcrel_lo` of kind `lo` in relocatable objects if needed
+fn expand_pcrel<'a>(symbol: &str, context: &Context, lo: RelocationKind, expand: impl Fn(String, String) -> Expansion<'a>) -> Result<Expansion<'a>, ErrorKind> {
+  if context.relocate_pcrel(symbol, lo) {
+    return Ok(expand("0".to_owned(), "0".to_owned()));
+  }
+  let offset = parse_immediate(symbol, context)?.wrapping_sub(context.pc) as i32;
+  let (hi, lo) = split_hi_lo(offset);
+  Ok(expand(hi.to_string(), lo.to_string()))
+}
+
+/// `auipc` into `temporary` and `jalr` linking into `rd`
+fn expand_call(rd: &str, temporary: &str, symbol: &str, context: &Context) -> Result<Expansion<'static>, ErrorKind> {
+  expand_pcrel(symbol, context, RelocationKind::Call, |hi, lo| vec![
+    op("auipc", [temporary, &hi]),
+    op("jalr", [rd, temporary, &lo]),
+  ])
+}
+
+/// split a value into the upper 20 bits and the sign extended lower 12 bits,
+/// such that `(hi << 12) + lo == value`
+pub fn split_hi_lo(value: i32) -> (u32, i32) {
+  let lo = (value << 20) >> 20;
+  let hi = (value.wrapping_sub(lo) as u32) >> 12;
+  (hi, lo)
+}
+
+#[test]
+fn test_split_hi_lo() {
+  assert_eq!(split_hi_lo(0x12345678), (0x12345, 0x678));
+  assert_eq!(split_hi_lo(0x12345fff), (0x12346, -1));
+  assert_eq!(split_hi_lo(-1), (0, -1));
+  assert_eq!(split_hi_lo(i32::MIN), (0x80000, 0));
+}
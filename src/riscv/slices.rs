@@ -23,6 +23,14 @@ pub const OPCODE_OP_IMM: u32 = 0x13;
 pub const OPCODE_OP: u32 = 0x33;
 pub const OPCODE_MISC_MEM: u32 = 0xf;
 pub const OPCODE_SYSTEM: u32 = 0x73;
+pub const OPCODE_AMO: u32 = 0x2f;
+pub const OPCODE_LOAD_FP: u32 = 0x07;
+pub const OPCODE_STORE_FP: u32 = 0x27;
+pub const OPCODE_MADD: u32 = 0x43;
+pub const OPCODE_MSUB: u32 = 0x47;
+pub const OPCODE_NMSUB: u32 = 0x4b;
+pub const OPCODE_NMADD: u32 = 0x4f;
+pub const OPCODE_OP_FP: u32 = 0x53;
 
 pub const FUNCT3_JALR: u32 = 0;
 pub const FUNCT3_BEQ: u32 = 0;
@@ -99,10 +107,77 @@ pub const FUNCT7_DIVU: u32 = 1;
 pub const FUNCT7_REM: u32 = 1;
 pub const FUNCT7_REMU: u32 = 1;
 
+pub const FUNCT3_AMO_W: u32 = 2;
+
+pub const FUNCT5_LR: u32 = 0b00010;
+pub const FUNCT5_SC: u32 = 0b00011;
+pub const FUNCT5_AMOSWAP: u32 = 0b00001;
+pub const FUNCT5_AMOADD: u32 = 0b00000;
+pub const FUNCT5_AMOXOR: u32 = 0b00100;
+pub const FUNCT5_AMOAND: u32 = 0b01100;
+pub const FUNCT5_AMOOR: u32 = 0b01000;
+pub const FUNCT5_AMOMIN: u32 = 0b10000;
+pub const FUNCT5_AMOMAX: u32 = 0b10100;
+pub const FUNCT5_AMOMINU: u32 = 0b11000;
+pub const FUNCT5_AMOMAXU: u32 = 0b11100;
+
+pub const FUNCT3_FLW: u32 = 2;
+pub const FUNCT3_FLD: u32 = 3;
+pub const FUNCT3_FSW: u32 = 2;
+pub const FUNCT3_FSD: u32 = 3;
+// the rounding mode field of arithmetic floating-point instructions selects the operation of some others
+pub const FUNCT3_FSGNJ: u32 = 0;
+pub const FUNCT3_FSGNJN: u32 = 1;
+pub const FUNCT3_FSGNJX: u32 = 2;
+pub const FUNCT3_FMIN: u32 = 0;
+pub const FUNCT3_FMAX: u32 = 1;
+pub const FUNCT3_FLE: u32 = 0;
+pub const FUNCT3_FLT: u32 = 1;
+pub const FUNCT3_FEQ: u32 = 2;
+pub const FUNCT3_FMV_X_W: u32 = 0;
+pub const FUNCT3_FCLASS: u32 = 1;
+
+/// the format field of floating-point instructions
+pub const FMT_S: u32 = 0;
+pub const FMT_D: u32 = 1;
+
+pub const FUNCT5_FADD: u32 = 0b00000;
+pub const FUNCT5_FSUB: u32 = 0b00001;
+pub const FUNCT5_FMUL: u32 = 0b00010;
+pub const FUNCT5_FDIV: u32 = 0b00011;
+pub const FUNCT5_FSQRT: u32 = 0b01011;
+pub const FUNCT5_FSGNJ: u32 = 0b00100;
+pub const FUNCT5_FMIN_FMAX: u32 = 0b00101;
+pub const FUNCT5_FCVT_F_F: u32 = 0b01000;
+pub const FUNCT5_FCMP: u32 = 0b10100;
+pub const FUNCT5_FCVT_W_F: u32 = 0b11000;
+pub const FUNCT5_FCVT_F_W: u32 = 0b11010;
+pub const FUNCT5_FMV_X_W_FCLASS: u32 = 0b11100;
+pub const FUNCT5_FMV_W_X: u32 = 0b11110;
+/// the `rs2` field of conversions to and from integers selects signedness
+pub const RS2_FCVT_W: u32 = 0;
+pub const RS2_FCVT_WU: u32 = 1;
+
+/// the `aq` bit of the ordering bits of atomics
+pub const AQ: u32 = 0b10;
+/// the `rl` bit of the ordering bits of atomics
+pub const RL: u32 = 0b01;
+
 pub const FUNCT12_FENCE_TSO: u32 = 0b1000_0011_0011;
 pub const FUNCT12_PAUSE: u32 = 0b0000_0001_0000;
 pub const FUNCT12_ECALL: u32 = 0;
 pub const FUNCT12_EBREAK: u32 = 1;
+pub const FUNCT12_SRET: u32 = 0b0001_0000_0010;
+pub const FUNCT12_MRET: u32 = 0b0011_0000_0010;
+pub const FUNCT7_SFENCE_VMA: u32 = 0b0001001;
+
+pub const FUNCT3_PRIV: u32 = 0;
+pub const FUNCT3_CSRRW: u32 = 1;
+pub const FUNCT3_CSRRS: u32 = 2;
+pub const FUNCT3_CSRRC: u32 = 3;
+pub const FUNCT3_CSRRWI: u32 = 5;
+pub const FUNCT3_CSRRSI: u32 = 6;
+pub const FUNCT3_CSRRCI: u32 = 7;
 
 const MASK_OPCODE: u32 = slice_mask(0, 6);
 const MASK_RD: u32 = slice_mask(7, 11);
@@ -111,6 +186,10 @@ const MASK_RS2: u32 = slice_mask(20, 24);
 const MASK_FUNCT3: u32 = slice_mask(12, 14);
 const MASK_FUNCT7: u32 = slice_mask(25, 31);
 const MASK_FUNCT12: u32 = slice_mask(20, 31);
+const MASK_FUNCT5: u32 = slice_mask(27, 31);
+const MASK_AQRL: u32 = slice_mask(25, 26);
+const MASK_FMT: u32 = slice_mask(25, 26);
+const MASK_RS3: u32 = slice_mask(27, 31);
 const MASK_I_IMM_PART0: u32 = slice_mask(20, 31);
 const MASK_S_IMM: u32 = MASK_S_IMM_PART1 | MASK_S_IMM_PART0;
 const MASK_S_IMM_PART0: u32 = slice_mask(7, 11);
@@ -135,6 +214,10 @@ const OFFSET_RS2: u32 = 20;
 const OFFSET_FUNCT3: u32 = 12;
 const OFFSET_FUNCT7: u32 = 25;
 const OFFSET_FUNCT12: u32 = 20;
+const OFFSET_FUNCT5: u32 = 27;
+const OFFSET_AQRL: u32 = 25;
+const OFFSET_FMT: u32 = 25;
+const OFFSET_RS3: u32 = 27;
 const OFFSET_I_IMM_PART0: u32 = 20;
 const OFFSET_S_IMM_PART0: u32 = 7;
 const OFFSET_S_IMM_PART1: u32 = 25;
@@ -155,6 +238,10 @@ const SLICE_RS2: (u32, u32) = (MASK_RS2, OFFSET_RS2);
 const SLICE_FUNCT3: (u32, u32) = (MASK_FUNCT3, OFFSET_FUNCT3);
 const SLICE_FUNCT7: (u32, u32) = (MASK_FUNCT7, OFFSET_FUNCT7);
 const SLICE_FUNCT12: (u32, u32) = (MASK_FUNCT12, OFFSET_FUNCT12);
+const SLICE_FUNCT5: (u32, u32) = (MASK_FUNCT5, OFFSET_FUNCT5);
+const SLICE_AQRL: (u32, u32) = (MASK_AQRL, OFFSET_AQRL);
+const SLICE_FMT: (u32, u32) = (MASK_FMT, OFFSET_FMT);
+const SLICE_RS3: (u32, u32) = (MASK_RS3, OFFSET_RS3);
 const SLICE_I_IMM: (u32, u32) = SLICE_I_IMM_PART0;
 const SLICE_I_IMM_PART0: (u32, u32) = (MASK_I_IMM_PART0, OFFSET_I_IMM_PART0);
 const SLICE_S_IMM_PART0: (u32, u32) = (MASK_S_IMM_PART0, OFFSET_S_IMM_PART0);
@@ -231,10 +318,14 @@ const SXT_BIT_B_IMM: u32 = 12;
 const SXT_BIT_U_IMM: u32 = 31;
 const SXT_BIT_J_IMM: u32 = 20;
 
+/// `addi x0 x0 0`
+pub const INSTRUCTION_NOP_VALUE: u32 = OPCODE_OP_IMM;
 pub const INSTRUCTION_FENCE_TSO_VALUE: u32 = (FUNCT12_FENCE_TSO << 20) | OPCODE_MISC_MEM;
 pub const INSTRUCTION_PAUSE_VALUE: u32 = (FUNCT12_PAUSE << 20) | OPCODE_MISC_MEM;
 pub const INSTRUCTION_ECALL_VALUE: u32 = (FUNCT12_ECALL << 20) | OPCODE_SYSTEM;
 pub const INSTRUCTION_EBREAK_VALUE: u32 = (FUNCT12_EBREAK << 20) | OPCODE_SYSTEM;
+pub const INSTRUCTION_SRET_VALUE: u32 = (FUNCT12_SRET << 20) | OPCODE_SYSTEM;
+pub const INSTRUCTION_MRET_VALUE: u32 = (FUNCT12_MRET << 20) | OPCODE_SYSTEM;
 
 /// copies the bit of `x` at position `pos` into higher bits
 pub fn sign_extend(x: &mut u32, pos: u32) {
@@ -282,6 +373,29 @@ pub fn _get_funct12_bits(instruction: u32) -> u32 {
   get_slice_bits(instruction, SLICE_FUNCT12)
 }
 
+/// the CSR address of Zicsr instructions, in place of funct12
+pub fn get_csr_bits(instruction: u32) -> u32 {
+  get_slice_bits(instruction, SLICE_FUNCT12)
+}
+
+pub fn get_funct5_bits(instruction: u32) -> u32 {
+  get_slice_bits(instruction, SLICE_FUNCT5)
+}
+
+/// the `aq` and `rl` bits of atomics, see [`AQ`] and [`RL`]
+pub fn get_aqrl_bits(instruction: u32) -> u32 {
+  get_slice_bits(instruction, SLICE_AQRL)
+}
+
+/// the format of floating-point instructions, see [`FMT_S`] and [`FMT_D`]
+pub fn get_fmt_bits(instruction: u32) -> u32 {
+  get_slice_bits(instruction, SLICE_FMT)
+}
+
+pub fn get_rs3_bits(instruction: u32) -> u32 {
+  get_slice_bits(instruction, SLICE_RS3)
+}
+
 pub fn get_rd_bits(instruction: u32) -> u32 {
   get_slice_bits(instruction, SLICE_RD)
 }
@@ -377,6 +491,22 @@ pub fn set_funct12_bits(instruction: u32, value: u32) -> u32 {
   set_slice_bits(instruction, value, SLICE_FUNCT12)
 }
 
+pub fn set_funct5_bits(instruction: u32, value: u32) -> u32 {
+  set_slice_bits(instruction, value, SLICE_FUNCT5)
+}
+
+pub fn set_aqrl_bits(instruction: u32, value: u32) -> u32 {
+  set_slice_bits(instruction, value, SLICE_AQRL)
+}
+
+pub fn set_fmt_bits(instruction: u32, value: u32) -> u32 {
+  set_slice_bits(instruction, value, SLICE_FMT)
+}
+
+pub fn set_rs3_bits(instruction: u32, value: u32) -> u32 {
+  set_slice_bits(instruction, value, SLICE_RS3)
+}
+
 pub fn set_rd_bits(instruction: u32, value: u32) -> u32 {
   set_slice_bits(instruction, value, SLICE_RD)
 }
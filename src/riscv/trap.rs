@@ -0,0 +1,209 @@
+use super::machine::Machine;
+use super::csr::{PRIVILEGE_M, PRIVILEGE_S, PRIVILEGE_U};
+
+/// the global interrupt enable of S-mode
+pub const MSTATUS_SIE: u32 = 1 << 1;
+/// the global interrupt enable of M-mode
+pub const MSTATUS_MIE: u32 = 1 << 3;
+/// the interrupt enable before the last trap into S-mode
+pub const MSTATUS_SPIE: u32 = 1 << 5;
+/// the interrupt enable before the last trap into M-mode
+pub const MSTATUS_MPIE: u32 = 1 << 7;
+/// the privilege level before the last trap into S-mode, one bit
+pub const MSTATUS_SPP: u32 = 1 << 8;
+/// the privilege level before the last trap into M-mode, two bits
+pub const MSTATUS_MPP: u32 = 0b11 << 11;
+/// permit S-mode to load and store U-mode pages
+pub const MSTATUS_SUM: u32 = 1 << 18;
+/// make executable pages readable
+pub const MSTATUS_MXR: u32 = 1 << 19;
+const MSTATUS_SPP_SHIFT: u32 = 8;
+pub const MSTATUS_MPP_SHIFT: u32 = 11;
+
+/// a synchronous exception, each with the value written to `mtval` or `stval`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exception {
+  /// the target of a jump or taken branch
+  InstructionAddressMisaligned(u32),
+  InstructionAccessFault(u32),
+  /// the instruction, 16 bits for compressed instructions
+  IllegalInstruction(u32),
+  /// the address of the `ebreak`
+  Breakpoint(u32),
+  LoadAddressMisaligned(u32),
+  LoadAccessFault(u32),
+  /// also raised by `sc.w` and AMOs, including their loads
+  StoreAddressMisaligned(u32),
+  StoreAccessFault(u32),
+  /// from the privilege level the `ecall` is executed in
+  EnvironmentCall(u32),
+  /// the virtual address, of the upper half of an instruction if only that half is on the faulting page
+  InstructionPageFault(u32),
+  LoadPageFault(u32),
+  /// also raised by `sc.w` and AMOs, including their loads
+  StorePageFault(u32),
+}
+
+impl Exception {
+  /// the exception code in `mcause`
+  pub fn code(&self) -> u32 {
+    match self {
+      Exception::InstructionAddressMisaligned(_) => 0,
+      Exception::InstructionAccessFault(_) => 1,
+      Exception::IllegalInstruction(_) => 2,
+      Exception::Breakpoint(_) => 3,
+      Exception::LoadAddressMisaligned(_) => 4,
+      Exception::LoadAccessFault(_) => 5,
+      Exception::StoreAddressMisaligned(_) => 6,
+      Exception::StoreAccessFault(_) => 7,
+      Exception::EnvironmentCall(privilege) => 8 + privilege,
+      Exception::InstructionPageFault(_) => 12,
+      Exception::LoadPageFault(_) => 13,
+      Exception::StorePageFault(_) => 15,
+    }
+  }
+
+  /// the faulting address or instruction in `mtval`, 0 for environment calls
+  pub fn value(&self) -> u32 {
+    match *self {
+      Exception::InstructionAddressMisaligned(value)
+      | Exception::InstructionAccessFault(value)
+      | Exception::IllegalInstruction(value)
+      | Exception::Breakpoint(value)
+      | Exception::LoadAddressMisaligned(value)
+      | Exception::LoadAccessFault(value)
+      | Exception::StoreAddressMisaligned(value)
+      | Exception::StoreAccessFault(value)
+      | Exception::InstructionPageFault(value)
+      | Exception::LoadPageFault(value)
+      | Exception::StorePageFault(value) => value,
+      Exception::EnvironmentCall(_) => 0,
+    }
+  }
+}
+
+/// an asynchronous interrupt, taken between instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+  SupervisorSoftware,
+  MachineSoftware,
+  SupervisorTimer,
+  MachineTimer,
+}
+
+impl Interrupt {
+  /// in decreasing priority
+  pub const ALL: [Interrupt; 4] = [
+    Interrupt::MachineSoftware,
+    Interrupt::MachineTimer,
+    Interrupt::SupervisorSoftware,
+    Interrupt::SupervisorTimer,
+  ];
+
+  /// the exception code in `mcause`, without the interrupt bit
+  pub const fn code(&self) -> u32 {
+    match self {
+      Interrupt::SupervisorSoftware => 1,
+      Interrupt::MachineSoftware => 3,
+      Interrupt::SupervisorTimer => 5,
+      Interrupt::MachineTimer => 7,
+    }
+  }
+
+  /// the bit of the interrupt in `mie` and `mip`
+  pub const fn bit(&self) -> u32 {
+    1 << self.code()
+  }
+}
+
+/// the bit of `mcause` set for interrupts
+pub const MCAUSE_INTERRUPT: u32 = 1 << 31;
+
+/// the address of a trap handler, the base of `tvec` in direct mode
+/// and the entry of the cause of an interrupt in vectored mode
+fn trap_vector(tvec: u32, interrupt: Option<Interrupt>) -> u32 {
+  let base = tvec & !0b11;
+  match (tvec & 0b11, interrupt) {
+    (1, Some(interrupt)) => base.wrapping_add(4 * interrupt.code()),
+    _ => base,
+  }
+}
+
+impl Machine {
+  /// take a trap at the instruction at `pc`, which does not retire,
+  /// into S-mode if the exception is delegated in `medeleg` and was raised below M-mode and into M-mode otherwise
+  pub fn trap(&mut self, exception: Exception) {
+    let code = exception.code();
+    let supervisor = self.privilege <= PRIVILEGE_S && self.csrs.medeleg & 1 << code != 0;
+    self.enter_trap(supervisor, code, exception.value(), None);
+  }
+
+  /// take an interrupt before the instruction at `pc`, into S-mode if it is delegated in `mideleg`
+  pub fn interrupt(&mut self, interrupt: Interrupt) {
+    let supervisor = self.csrs.mideleg & interrupt.bit() != 0;
+    self.enter_trap(supervisor, MCAUSE_INTERRUPT | interrupt.code(), 0, Some(interrupt));
+  }
+
+  /// the pending interrupt of the highest priority that is enabled in `mie`,
+  /// interrupts are globally enabled by `MIE` or `SIE` in `mstatus` at the privilege level they trap into
+  /// and always enabled below it, interrupts delegated to S-mode are never taken in M-mode
+  pub fn pending_interrupt(&self) -> Option<Interrupt> {
+    let mstatus = self.csrs.mstatus;
+    let machine = self.privilege < PRIVILEGE_M || mstatus & MSTATUS_MIE != 0;
+    let supervisor = self.privilege < PRIVILEGE_S || self.privilege == PRIVILEGE_S && mstatus & MSTATUS_SIE != 0;
+    let pending = self.mip() & self.csrs.mie;
+    Interrupt::ALL.into_iter().find(|interrupt| {
+      let enabled = if self.csrs.mideleg & interrupt.bit() != 0 { supervisor } else { machine };
+      enabled && pending & interrupt.bit() != 0
+    })
+  }
+
+  /// the interrupts pending from the CLINT and the supervisor interrupts raised by software in `mip`
+  pub fn mip(&self) -> u32 {
+    let software = if self.clint.msip { Interrupt::MachineSoftware.bit() } else { 0 };
+    let timer = if self.clint.timer_pending() { Interrupt::MachineTimer.bit() } else { 0 };
+    self.csrs.mip | software | timer
+  }
+
+  /// save the interrupt enable and privilege level in `mstatus` and jump to the trap handler
+  fn enter_trap(&mut self, supervisor: bool, cause: u32, value: u32, interrupt: Option<Interrupt>) {
+    let csrs = &mut self.csrs;
+    if supervisor {
+      csrs.sepc = self.pc;
+      csrs.scause = cause;
+      csrs.stval = value;
+      let spie = if csrs.mstatus & MSTATUS_SIE != 0 { MSTATUS_SPIE } else { 0 };
+      csrs.mstatus = csrs.mstatus & !(MSTATUS_SIE | MSTATUS_SPIE | MSTATUS_SPP) | spie | self.privilege << MSTATUS_SPP_SHIFT;
+      self.privilege = PRIVILEGE_S;
+      self.pc = trap_vector(csrs.stvec, interrupt);
+    } else {
+      csrs.mepc = self.pc;
+      csrs.mcause = cause;
+      csrs.mtval = value;
+      let mpie = if csrs.mstatus & MSTATUS_MIE != 0 { MSTATUS_MPIE } else { 0 };
+      csrs.mstatus = csrs.mstatus & !(MSTATUS_MIE | MSTATUS_MPIE | MSTATUS_MPP) | mpie | self.privilege << MSTATUS_MPP_SHIFT;
+      self.privilege = PRIVILEGE_M;
+      self.pc = trap_vector(csrs.mtvec, interrupt);
+    }
+  }
+
+  /// return from an M-mode trap, restoring the interrupt enable and privilege level saved in `mstatus`
+  /// and leaving `MPP` at U-mode
+  pub fn mret(&mut self) {
+    let csrs = &mut self.csrs;
+    let mie = if csrs.mstatus & MSTATUS_MPIE != 0 { MSTATUS_MIE } else { 0 };
+    self.privilege = (csrs.mstatus & MSTATUS_MPP) >> MSTATUS_MPP_SHIFT;
+    csrs.mstatus = csrs.mstatus & !(MSTATUS_MIE | MSTATUS_MPP) | mie | MSTATUS_MPIE | PRIVILEGE_U << MSTATUS_MPP_SHIFT;
+    self.pc = self.read_mepc();
+  }
+
+  /// return from an S-mode trap, restoring the interrupt enable and privilege level saved in `mstatus`
+  /// and leaving `SPP` at U-mode
+  pub fn sret(&mut self) {
+    let csrs = &mut self.csrs;
+    let sie = if csrs.mstatus & MSTATUS_SPIE != 0 { MSTATUS_SIE } else { 0 };
+    self.privilege = (csrs.mstatus & MSTATUS_SPP) >> MSTATUS_SPP_SHIFT;
+    csrs.mstatus = csrs.mstatus & !(MSTATUS_SIE | MSTATUS_SPP) | sie | MSTATUS_SPIE | PRIVILEGE_U << MSTATUS_SPP_SHIFT;
+    self.pc = self.read_sepc();
+  }
+}
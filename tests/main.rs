@@ -1,4 +1,21 @@
-use xocolatl::riscv::{disassemble::disassemble, assemble::assemble_normalized};
+use xocolatl::riscv::{
+  disassemble::disassemble,
+  assemble::{assemble, assemble_image, assemble_image_with_includes, assemble_listing, assemble_normalized, assemble_object},
+  machine::Machine,
+  decode::decode,
+};
+use xocolatl::riscv::error::{ErrorKind, render_errors};
+use xocolatl::riscv::elf::{Elf, ElfError, ET_REL, SHT_RELA, write_object};
+use xocolatl::riscv::object::{Object, Relocation, RelocationKind, SymbolSection};
+use xocolatl::riscv::link::{LinkError, LinkerScript, link};
+use xocolatl::riscv::objdump::{DisassembleOptions, Style, disassemble_bytes, disassemble_elf, disassemble_image};
+use xocolatl::riscv::float::{CANONICAL_NAN_S, DZ, NV, NX, OF, RUP, UF};
+use xocolatl::riscv::formats::{FormatError, read_bin, read_ihex, read_srec, write_bin, write_ihex, write_readmemh, write_srec};
+use xocolatl::riscv::image::{Image, ImageError};
+
+fn assemble_normalized_ok(instruction: &str) -> u32 {
+  assemble_normalized(instruction).unwrap()
+}
 
 #[test]
 fn test_disassemble() {
@@ -34,9 +51,2352 @@ fn test_assemble_normalized() {
   for test_case in TEST_CASES {
     let (assembly, disassembly) = test_case;
     let left = *assembly;
-    let right = assemble_normalized(disassembly);
+    let right = assemble_normalized(disassembly).unwrap();
     let left = format!("{left:#034b}");
     let right = format!("{right:#034b}");
     assert_eq!(left, right, "failed {test_case:?}");
   }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_assemble() {
+  const SOURCE: &str = "
+    # count x10 down to zero
+    start:  addi x10, x0, 3   // three iterations
+    loop:
+            addi x10,x10,-1
+            bne  x10, x0, loop
+            jal  x0, end
+            ecall
+    end:    jal x1 start
+  ";
+  let expected = [
+    assemble_normalized_ok("addi x10 x0 3"),
+    assemble_normalized_ok("addi x10 x10 -1"),
+    assemble_normalized_ok("bne x10 x0 -4"),
+    assemble_normalized_ok("jal x0 8"),
+    assemble_normalized_ok("ecall"),
+    assemble_normalized_ok("jal x1 -20"),
+  ];
+  assert_eq!(assemble(SOURCE).unwrap(), expected);
+}
+
+#[test]
+fn test_assemble_abi_names() {
+  const TEST_CASES: &[(&str, &str)] = &[
+    ("sb a1, 9(a2)", "sb x11 x12 9"),
+    ("lw a2, 10(a1)", "lw x12 x11 10"),
+    ("lw a2, (a1)", "lw x12 x11 0"),
+    ("jalr tp, 12(fp)", "jalr x4 x8 12"),
+    ("addi sp, sp, -16", "addi x2 x2 -16"),
+    ("add zero, ra, t6", "add x0 x1 x31"),
+  ];
+
+  for (source, normalized) in TEST_CASES {
+    assert_eq!(assemble(source).unwrap(), [assemble_normalized_ok(normalized)], "failed {source:?}");
+  }
+
+  // the disassembly of loads and stores assembles back to the same instruction
+  for instruction in [0x00b604a3, 0x00a5a603, 0x00c40267] {
+    assert_eq!(assemble(&disassemble(instruction)).unwrap(), [instruction]);
+  }
+}
+
+#[test]
+fn test_assemble_pseudo() {
+  const TEST_CASES: &[(&str, &[&str])] = &[
+    ("nop", &["addi x0 x0 0"]),
+    ("li a0, 123", &["addi x10 x0 123"]),
+    ("li a0, -2048", &["addi x10 x0 -2048"]),
+    ("li a0, 4096", &["lui x10 1"]),
+    ("li a0, 305422335", &["lui x10 74566", "addi x10 x10 -1"]),
+    ("mv t0, t1", &["addi x5 x6 0"]),
+    ("not t0, t1", &["xori x5 x6 -1"]),
+    ("neg t0, t1", &["sub x5 x0 x6"]),
+    ("seqz t0, t1", &["sltiu x5 x6 1"]),
+    ("snez t0, t1", &["sltu x5 x0 x6"]),
+    ("beqz a0, 8", &["beq x10 x0 8"]),
+    ("bgt a0, a1, 8", &["blt x11 x10 8"]),
+    ("bleu a0, a1, 8", &["bgeu x11 x10 8"]),
+    ("j 16", &["jal x0 16"]),
+    ("jal 16", &["jal x1 16"]),
+    ("jr t0", &["jalr x0 x5 0"]),
+    ("jalr t0", &["jalr x1 x5 0"]),
+    ("ret", &["jalr x0 x1 0"]),
+  ];
+
+  for (source, expansion) in TEST_CASES {
+    let expected = expansion.iter().map(|instruction| assemble_normalized_ok(instruction)).collect::<Vec<_>>();
+    assert_eq!(assemble(source).unwrap(), expected, "failed {source:?}");
+  }
+}
+
+#[test]
+fn test_assemble_pseudo_labels() {
+  const SOURCE: &str = "
+    main:
+      la a0, data
+      call function
+      tail main
+    function:
+      ret
+    data:
+  ";
+  let expected = [
+    "auipc x10 0", "addi x10 x10 28",
+    "auipc x1 0", "jalr x1 x1 16",
+    "auipc x6 0", "jalr x0 x6 -16",
+    "jalr x0 x1 0",
+  ].map(assemble_normalized_ok);
+  assert_eq!(assemble(SOURCE).unwrap(), expected);
+}
+
+const SECTIONS: &str = r#"
+    .equ LENGTH, 3
+    .globl _start
+    .data
+    array:  .word 1, 2, -1
+            .byte LENGTH
+    .rodata
+    message: .string "hi\n"
+    .bss
+    .align 4
+    buffer: .zero 32
+    .text
+    _start:
+      la a0, message
+      lw a1, array
+  "#;
+
+#[test]
+fn test_assemble_sections() {
+  let image = assemble_image(SECTIONS).unwrap();
+  let names = image.sections.iter().map(|section| section.name.as_str()).collect::<Vec<_>>();
+  assert_eq!(names, [".text", ".rodata", ".data", ".bss"]);
+  let rodata = image.section(".rodata").unwrap();
+  assert_eq!((rodata.address, rodata.data.as_slice()), (16, &b"hi\n\0"[..]));
+  let data = image.section(".data").unwrap();
+  assert_eq!(data.address, 20);
+  assert_eq!(data.data, [1, 0, 0, 0, 2, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 3]);
+  let bss = image.section(".bss").unwrap();
+  assert_eq!((bss.address, bss.data.len()), (48, 32));
+}
+
+#[test]
+fn test_assemble_sections_symbols() {
+  let image = assemble_image(SECTIONS).unwrap();
+  assert_eq!(image.symbols["buffer"], 48);
+  assert_eq!(image.symbols["LENGTH"], 3);
+  assert_eq!(image.globals, ["_start"]);
+}
+
+#[test]
+fn test_load_image() {
+  let image = assemble_image(SECTIONS).unwrap();
+  let mut machine = Machine::default();
+  image.load(&mut machine).unwrap();
+  assert_eq!(machine.pc, 0);
+  assert_eq!(machine.memory.load_word(20), 1);
+  assert_eq!(machine.memory.load_byte(18), b'\n');
+}
+
+const ERRORS: &str = "
+    start:
+      addx a0, a0, 1
+      addi a9, a0, 1
+      add a0, a1
+      beq a0, a1, nowhere
+    start:
+      .byte 300
+  ";
+
+#[test]
+fn test_assemble_errors() {
+  let errors = assemble(ERRORS).unwrap_err();
+  let kinds = errors.iter().map(|error| error.kind.clone()).collect::<Vec<_>>();
+  assert_eq!(kinds, [
+    ErrorKind::UnknownMnemonic("addx".to_owned()),
+    ErrorKind::UnknownRegister("a9".to_owned()),
+    ErrorKind::WrongOperandCount { mnemonic: "add".to_owned(), expected: "rd, rs1, rs2", found: 2 },
+    ErrorKind::UndefinedLabel("nowhere".to_owned()),
+    ErrorKind::DuplicateLabel("start".to_owned()),
+    ErrorKind::ImmediateOutOfRange { value: 300, min: -128, max: 255 },
+  ]);
+}
+
+#[test]
+fn test_assemble_error_locations() {
+  let errors = assemble(ERRORS).unwrap_err();
+  let positions = errors.iter().map(|error| (error.line, error.column)).collect::<Vec<_>>();
+  assert_eq!(positions, [(3, 7), (4, 12), (5, 7), (6, 19), (7, 5), (8, 7)]);
+  assert_eq!(&ERRORS[errors[3].span.clone()], "nowhere");
+}
+
+#[test]
+fn test_render_errors() {
+  let errors = assemble(ERRORS).unwrap_err();
+  let rendered = render_errors(ERRORS, &errors[1..2]);
+  assert_eq!(rendered, "\
+error: unknown register `a9`
+ --> 4:12
+  |
+4 |       addi a9, a0, 1
+  |            ^^
+");
+}
+
+#[test]
+fn test_assemble_immediate_ranges() {
+  const SOURCE: &str = "
+      addi a0, a0, 2047
+      addi a0, a0, 2048
+      sw a0, -2049(sp)
+      slli a0, a0, 32
+      lui a0, 1048576
+      beq a0, a1, 3
+      jal 1048576
+  ";
+  let errors = assemble(SOURCE).unwrap_err();
+  let kinds = errors.iter().map(|error| error.kind.clone()).collect::<Vec<_>>();
+  assert_eq!(kinds, [
+    ErrorKind::ImmediateOutOfRange { value: 2048, min: -2048, max: 2047 },
+    ErrorKind::ImmediateOutOfRange { value: -2049, min: -2048, max: 2047 },
+    ErrorKind::ImmediateOutOfRange { value: 32, min: 0, max: 31 },
+    ErrorKind::ImmediateOutOfRange { value: 0x100000, min: 0, max: 0xfffff },
+    ErrorKind::MisalignedImmediate { value: 3, alignment: 2 },
+    ErrorKind::ImmediateOutOfRange { value: 0x100000, min: -0x100000, max: 0xffffe },
+  ]);
+  assert_eq!(errors.iter().map(|error| error.line).collect::<Vec<_>>(), [3, 4, 5, 6, 7, 8]);
+}
+
+#[test]
+fn test_assemble_relocations() {
+  const SOURCE: &str = "
+    _start:
+      lui a0, %hi(value)
+      lw a1, %lo(value)(a0)
+    here:
+      auipc a2, %pcrel_hi(value)
+      addi a2, a2, %pcrel_lo(here)
+      .data
+      .space 2304
+    value: .word 7
+  ";
+  let image = assemble_image(SOURCE).unwrap();
+  assert_eq!(image.symbols["value"], 0x910);
+  let expected = ["lui x10 1", "lw x11 x10 -1776", "auipc x12 1", "addi x12 x12 -1784"];
+  assert_eq!(image.section(".text").unwrap().words(), expected.map(assemble_normalized_ok));
+}
+
+const EXPRESSIONS: &str = "
+      li a0, SIZE / 4
+      li a1, 1 << 12
+      addi a2, zero, -(4*8)
+      andi a3, a3, 0x0f | 0b10000
+      la a4, table + 4
+      lw a5, table + 8
+      .equ SIZE, END - START
+      .equ TABLE, table
+      .data
+    table:
+    START:
+      .word 'A', 0x10, TABLE, . - table
+    END:
+      .byte '\\n', ~0 & 0xff
+  ";
+
+#[test]
+fn test_assemble_expressions() {
+  let image = assemble_image(EXPRESSIONS).unwrap();
+  let expected = [
+    "lui x10 0",
+    "addi x10 x10 4",
+    "lui x11 1",
+    "addi x12 x0 -32",
+    "andi x13 x13 31",
+    "auipc x14 0",
+    "addi x14 x14 20",
+    "auipc x15 0",
+    "lw x15 x15 16",
+  ];
+  assert_eq!(image.section(".text").unwrap().words(), expected.map(assemble_normalized_ok));
+}
+
+#[test]
+fn test_assemble_constant_expressions() {
+  let image = assemble_image(EXPRESSIONS).unwrap();
+  assert_eq!(image.symbols["SIZE"], 16);
+  assert_eq!(image.symbols["TABLE"], 36);
+}
+
+#[test]
+fn test_assemble_data_expressions() {
+  let image = assemble_image(EXPRESSIONS).unwrap();
+  let data = image.section(".data").unwrap();
+  assert_eq!(data.words()[..4], [65, 16, 36, 12]);
+  assert_eq!(data.data[16..], [b'\n', 0xff]);
+}
+
+#[test]
+fn test_assemble_macros() {
+  const SOURCE: &str = r#"
+    .macro push reg, size=4
+      addi sp, sp, -\size
+      sw \reg, 0(sp)
+    .endm
+    .macro countdown reg
+      li \reg, 2
+    loop\@:
+      addi \reg, \reg, -1
+      bnez \reg, loop\@
+    .endm
+    _start:
+      push ra
+      push s0, size=8
+      countdown t0
+  "#;
+  let image = assemble_image(SOURCE).unwrap();
+  let expected = [
+    "addi x2 x2 -4",
+    "sw x1 x2 0",
+    "addi x2 x2 -8",
+    "sw x8 x2 0",
+    "addi x5 x0 2",
+    "addi x5 x5 -1",
+    "bne x5 x0 -4",
+  ];
+  assert_eq!(image.section(".text").unwrap().words(), expected.map(assemble_normalized_ok));
+  assert_eq!(image.symbols["loop2"], 20);
+}
+
+#[test]
+fn test_assemble_repetitions() {
+  const SOURCE: &str = r#"
+      .equ COUNT, 3
+      .rept COUNT - 1
+      nop
+      .endr
+      .irp reg, a0, a1
+      mv \reg, zero
+      .endr
+      .irpc n, 12
+      li a2, \n
+      .endr
+  "#;
+  let expected = ["addi x0 x0 0", "addi x0 x0 0", "addi x10 x0 0", "addi x11 x0 0", "addi x12 x0 1", "addi x12 x0 2"];
+  assert_eq!(assemble(SOURCE).unwrap(), expected.map(assemble_normalized_ok));
+}
+
+#[test]
+fn test_assemble_conditionals() {
+  const SOURCE: &str = "
+      .equ COUNT, 3
+      .equ DEBUG, 0
+    _start:
+      .ifdef _start
+      .if COUNT > 2 && DEBUG
+      ebreak
+      .elseif COUNT > 2
+      ecall
+      .else
+      unimplemented
+      .endif
+      .endif
+      .ifndef missing
+      ecall
+      .endif
+  ";
+  assert_eq!(assemble(SOURCE).unwrap(), ["ecall", "ecall"].map(assemble_normalized_ok));
+}
+
+#[test]
+fn test_assemble_includes() {
+  const SOURCE: &str = r#"
+    .include "constants.s"
+    li a0, COUNT
+  "#;
+  let include = |file: &str| (file == "constants.s").then(|| ".equ COUNT, 3\n.equ DEBUG, 0".to_owned());
+  let image = assemble_image_with_includes(SOURCE, include).unwrap();
+  assert_eq!(image.section(".text").unwrap().words(), ["lui x10 0", "addi x10 x10 3"].map(assemble_normalized_ok));
+  assert_eq!(image.symbols["DEBUG"], 0);
+}
+
+#[test]
+fn test_assemble_macro_errors() {
+  const SOURCE: &str = "
+    .macro load reg
+      lw \\reg, 0(sp)
+    .endm
+    load a9
+    .include \"missing.s\"
+    .endif
+    .rept 2
+  ";
+  let errors = assemble(SOURCE).unwrap_err();
+  let kinds = errors.iter().map(|error| error.kind.clone()).collect::<Vec<_>>();
+  assert_eq!(kinds, [
+    ErrorKind::UnknownRegister("a9".to_owned()),
+    ErrorKind::IncludeFailed("missing.s".to_owned()),
+    ErrorKind::UnmatchedDirective(".endif".to_owned()),
+    ErrorKind::UnterminatedBlock { directive: ".rept".to_owned(), end: ".endr" },
+  ]);
+  let positions = errors.iter().map(|error| (error.line, error.column)).collect::<Vec<_>>();
+  assert_eq!(positions, [(5, 10), (6, 15), (7, 5), (8, 5)]);
+}
+
+#[test]
+fn test_assemble_space_out_of_range() {
+  let errors = assemble(".space -1").unwrap_err();
+  assert_eq!(errors[0].kind, ErrorKind::ImmediateOutOfRange { value: -1, min: 0, max: 0x100_0000 });
+  let errors = assemble(".byte 1\n.zero 0xffff0000").unwrap_err();
+  assert_eq!(errors[0].kind, ErrorKind::ImmediateOutOfRange { value: 0xffff_0000, min: 0, max: 0x100_0000 });
+}
+
+#[test]
+fn test_assemble_rept_count_out_of_range() {
+  for (count, value) in [("0xffffffff", 0xffffffff), ("-1", -1)] {
+    let errors = assemble(&format!(".rept {count}\n nop\n.endr")).unwrap_err();
+    assert_eq!(errors[0].kind, ErrorKind::ImmediateOutOfRange { value, min: 0, max: 0x10000 });
+  }
+}
+
+#[test]
+fn test_assemble_listing() {
+  const SOURCE: &str = "\
+.macro inc reg
+  addi \\reg, \\reg, 1
+.endm
+.globl _start
+_start:
+  li a0, 0x12345
+loop: inc a0
+  j loop
+.data
+message: .string \"hello, world\"
+";
+  let (_, listing) = assemble_listing(SOURCE, |_| None).unwrap();
+  let expected = [
+    "   4                     .globl _start",
+    "   5                     _start:",
+    "   6  00000000 00012537    li a0, 0x12345",
+    "      00000004 34550513",
+    "   7                     loop: inc a0",
+    "   7+                    loop:",
+    "   7+ 00000008 00150513    addi a0, a0, 1",
+    "   8  0000000c ffdff06f    j loop",
+    "   9                     .data",
+    "  10  00000010 68656c6c  message: .string \"hello, world\"",
+    "      00000014 6f2c2077",
+    "      00000018 6f726c64",
+    "      0000001c 00",
+    "",
+    "SYMBOL TABLE:",
+    "00000000 g _start",
+    "00000008 l loop",
+    "00000010 l message",
+  ];
+  assert_eq!(listing.to_string().lines().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn test_assemble_listing_compressed() {
+  let (_, listing) = assemble_listing("c.li a0, 3\naddi a0, a0, 1\nc.addi a0, -1\n", |_| None).unwrap();
+  let expected = [
+    "   1  00000000 450d      c.li a0, 3",
+    "   2  00000002 00150513  addi a0, a0, 1",
+    "   3  00000006 157d      c.addi a0, -1",
+  ];
+  assert_eq!(listing.to_string().lines().take(3).collect::<Vec<_>>(), expected);
+}
+
+/// a minimal executable as `riscv32-unknown-elf-ld` would link it, with one segment
+/// holding `code` at 0x10000 followed by `bss` zeros, and symbols in `.symtab`
+fn elf_executable(code: &[u32], bss: u32, symbols: &[(&str, u32)]) -> Vec<u8> {
+  let text = code.iter().flat_map(|word| word.to_le_bytes()).collect::<Vec<_>>();
+  let mut strtab = vec![0];
+  let mut symtab = vec![0; 16];
+  for (name, value) in symbols {
+    symtab.extend((strtab.len() as u32).to_le_bytes());
+    symtab.extend(value.to_le_bytes());
+    symtab.extend([0, 0, 0, 0, 0x10, 0, 1, 0]);
+    strtab.extend(name.bytes().chain([0]));
+  }
+  let shstrtab = b"\0.text\0.symtab\0.strtab\0.shstrtab\0".to_vec();
+  let (text_offset, symtab_offset) = (52 + 32, 52 + 32 + text.len());
+  let strtab_offset = symtab_offset + symtab.len();
+  let shstrtab_offset = strtab_offset + strtab.len();
+  let section_headers = shstrtab_offset + shstrtab.len();
+  let half = |value: u16| value.to_le_bytes().to_vec();
+  let word = |value: u32| value.to_le_bytes().to_vec();
+  let mut elf = [0x7f, b'E', b'L', b'F', 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0].to_vec();
+  for field in [half(2), half(243), word(1), word(0x10000), word(52), word(section_headers as u32), word(0)] {
+    elf.extend(field);
+  }
+  for field in [half(52), half(32), half(1), half(40), half(5), half(4)] {
+    elf.extend(field);
+  }
+  for field in [1, text_offset as u32, 0x10000, 0x10000, text.len() as u32, text.len() as u32 + bss, 7, 4] {
+    elf.extend(word(field));
+  }
+  elf.extend(&text);
+  elf.extend(&symtab);
+  elf.extend(&strtab);
+  elf.extend(&shstrtab);
+  elf.extend([0; 40]);
+  let sections = [
+    (1, 1, 6, 0x10000, text_offset, text.len(), 0, 0, 4, 0),
+    (7, 2, 0, 0, symtab_offset, symtab.len(), 3, 1, 4, 16),
+    (15, 3, 0, 0, strtab_offset, strtab.len(), 0, 0, 1, 0),
+    (23, 3, 0, 0, shstrtab_offset, shstrtab.len(), 0, 0, 1, 0),
+  ];
+  for (name, kind, flags, address, offset, size, link, info, align, entsize) in sections {
+    for field in [name, kind, flags, address, offset as u32, size as u32, link, info, align, entsize] {
+      elf.extend(word(field));
+    }
+  }
+  elf
+}
+
+#[test]
+fn test_parse_elf() {
+  let code = ["addi x10 x0 5", "ecall"].map(assemble_normalized_ok);
+  let elf = Elf::parse(&elf_executable(&code, 8, &[("_start", 0x10000), ("counter", 0x10008)])).unwrap();
+  assert_eq!(elf.entry, 0x10000);
+  assert_eq!(elf.symbol("counter"), Some(0x10008));
+  assert_eq!(elf.sections.iter().map(|section| section.name.as_str()).collect::<Vec<_>>(), ["", ".text", ".symtab", ".strtab", ".shstrtab"]);
+}
+
+#[test]
+fn test_load_elf() {
+  let code = ["addi x10 x0 5", "ecall"].map(assemble_normalized_ok);
+  let elf = Elf::parse(&elf_executable(&code, 8, &[])).unwrap();
+  let mut machine = Machine::default();
+  machine.memory.store_word(0x1000c, 0xdeadbeef);
+  elf.load(&mut machine).unwrap();
+  assert_eq!(machine.pc, 0x10000);
+  assert_eq!(machine.registers.get(2), 1 << 24);
+  assert_eq!(machine.memory.load_word(0x10000), code[0]);
+  assert_eq!(machine.memory.load_word(0x1000c), 0);
+}
+
+#[test]
+fn test_parse_elf_errors() {
+  let bytes = elf_executable(&[assemble_normalized_ok("ecall")], 0, &[]);
+  let mut wrong_machine = bytes.clone();
+  wrong_machine[18] = 62;
+  assert_eq!(Elf::parse(&wrong_machine), Err(ElfError::WrongMachine(62)));
+  assert_eq!(Elf::parse(&bytes[..40]), Err(ElfError::Truncated("the ELF header")));
+  assert_eq!(Elf::parse(b"#!/bin/sh\n").unwrap_err(), ElfError::Truncated("the ELF header"));
+  let mut not_elf = bytes.clone();
+  not_elf[0] = 0;
+  assert_eq!(Elf::parse(&not_elf), Err(ElfError::NotElf));
+}
+
+const OBJECT: &str = "
+    .globl _start
+    _start:
+      la a0, message
+      call puts
+      lui a1, %hi(count)
+      sw zero, %lo(count)(a1)
+      beq a0, zero, _start
+    .data
+    message: .string \"hi\"
+    .align 2
+    table: .word _start + 4, 7
+    .bss
+    count: .space 4
+  ";
+
+#[test]
+fn test_assemble_object() {
+  let object = assemble_object(OBJECT, |_| None).unwrap();
+  let names = object.sections.iter().map(|section| section.name.as_str()).collect::<Vec<_>>();
+  assert_eq!(names, [".text", ".data", ".bss"]);
+  assert!(object.sections.iter().all(|section| section.address == 0));
+  let expected = ["auipc x10 0", "addi x10 x10 0", "auipc x1 0", "jalr x1 x1 0", "lui x11 0", "sw x0 x11 0", "beq x10 x0 -24"];
+  assert_eq!(object.section(".text").unwrap().words(), expected.map(assemble_normalized_ok));
+  assert_eq!(object.section(".data").unwrap().words()[1..], [0, 7]);
+}
+
+#[test]
+fn test_assemble_object_relocations() {
+  let object = assemble_object(OBJECT, |_| None).unwrap();
+  let relocation = |section, offset, kind, symbol: &str, addend| Relocation { section, offset, kind, symbol: symbol.to_owned(), addend };
+  assert_eq!(object.relocations, [
+    relocation(0, 0, RelocationKind::PcrelHi20, "message", 0),
+    relocation(0, 4, RelocationKind::PcrelLo12I, ".Lpcrel_hi0", 0),
+    relocation(0, 8, RelocationKind::Call, "puts", 0),
+    relocation(0, 16, RelocationKind::Hi20, "count", 0),
+    relocation(0, 20, RelocationKind::Lo12S, "count", 0),
+    relocation(1, 4, RelocationKind::Absolute32, "_start", 4),
+  ]);
+}
+
+#[test]
+fn test_assemble_object_symbols() {
+  let object = assemble_object(OBJECT, |_| None).unwrap();
+  assert_eq!(object.symbol("_start").map(|symbol| (symbol.section, symbol.value, symbol.global)), Some((SymbolSection::Section(0), 0, true)));
+  assert_eq!(object.symbol("table").map(|symbol| (symbol.section, symbol.value, symbol.global)), Some((SymbolSection::Section(1), 4, false)));
+  assert_eq!(object.symbol("puts").map(|symbol| (symbol.section, symbol.global)), Some((SymbolSection::Undefined, true)));
+}
+
+#[test]
+fn test_write_object() {
+  let object = assemble_object(OBJECT, |_| None).unwrap();
+  let elf = Elf::parse(&write_object(&object)).unwrap();
+  assert_eq!(elf.kind, ET_REL);
+  let names = elf.sections.iter().map(|section| section.name.as_str()).collect::<Vec<_>>();
+  assert_eq!(names, ["", ".text", ".data", ".bss", ".rela.text", ".rela.data", ".symtab", ".strtab", ".shstrtab"]);
+  let rela = &elf.sections[4];
+  assert_eq!((rela.kind, rela.info, rela.size), (SHT_RELA, 1, 5 * 12));
+  assert_eq!(elf.sections[3].size, 4);
+  let first = u32::from_le_bytes(rela.data[4..8].try_into().unwrap());
+  assert_eq!(first & 0xff, 23);
+  assert_eq!(elf.symbols[(first >> 8) as usize - 1].name, "message");
+}
+
+#[test]
+fn test_write_object_symbols() {
+  let object = assemble_object(OBJECT, |_| None).unwrap();
+  let elf = Elf::parse(&write_object(&object)).unwrap();
+  // locals come before globals
+  let binding = elf.symbols.iter().map(|symbol| symbol.binding()).collect::<Vec<_>>();
+  assert!(binding.is_sorted());
+  let start = elf.symbols.iter().find(|symbol| symbol.name == "_start").unwrap();
+  assert_eq!((start.binding(), start.section, start.value), (1, 1, 0));
+  let puts = elf.symbols.iter().find(|symbol| symbol.name == "puts").unwrap();
+  assert_eq!((puts.binding(), puts.section), (1, 0));
+}
+
+const LINK_MAIN: &str = "
+      .globl _start
+    _start:
+      la a0, message
+      call print
+      lw a1, counter
+    loop:
+      j loop
+      .data
+    message: .string \"hi\"
+  ";
+const LINK_LIB: &str = "
+      .globl print, counter, helper
+      .local helper
+    print:
+      lui t0, %hi(counter)
+      sw a0, %lo(counter)(t0)
+    loop:
+      bnez a0, loop
+    helper:
+      ret
+      .data
+    pointer: .word print
+      .bss
+    counter: .space 4
+  ";
+const LINK_SCRIPT: &str = "
+    /* programs run from ROM */
+    ENTRY(_start)
+    MEMORY {
+      ROM (rx) : ORIGIN = 0x1000, LENGTH = 4K
+      RAM (rw) : ORIGIN = 0x8000, LENGTH = 1K
+    }
+    SECTIONS {
+      .text : { *(.text) } > ROM
+      .data : { *(.rodata*) *(.data) } > RAM
+      .bss : { *(.bss) } > RAM
+      _end = .;
+    }
+  ";
+
+fn link_objects() -> [Object; 2] {
+  [LINK_MAIN, LINK_LIB].map(|source| assemble_object(source, |_| None).unwrap())
+}
+
+#[test]
+fn test_link() {
+  let objects = link_objects();
+  let script = LinkerScript::parse(LINK_SCRIPT).unwrap();
+  let image = link(&objects, Some(&script)).unwrap();
+  assert_eq!(image.entry, 0x1000);
+  let addresses = image.sections.iter().map(|section| (section.name.as_str(), section.address, section.data.len())).collect::<Vec<_>>();
+  assert_eq!(addresses, [(".text", 0x1000, 44), (".data", 0x8000, 8), (".bss", 0x8008, 4)]);
+  let expected = [
+    "auipc x10 7", "addi x10 x10 0",
+    "auipc x1 0", "jalr x1 x1 20",
+    "auipc x11 7", "lw x11 x11 -8",
+    "jal x0 0",
+    "lui x5 8", "sw x10 x5 8",
+    "bne x10 x0 0",
+    "jalr x0 x1 0",
+  ];
+  assert_eq!(image.section(".text").unwrap().words(), expected.map(assemble_normalized_ok));
+  assert_eq!(image.section(".data").unwrap().words()[1], 0x101c);
+}
+
+#[test]
+fn test_link_symbols() {
+  let objects = link_objects();
+  assert!(!objects[1].symbol("helper").unwrap().global);
+  let script = LinkerScript::parse(LINK_SCRIPT).unwrap();
+  let image = link(&objects, Some(&script)).unwrap();
+  assert_eq!((image.symbols["print"], image.symbols["counter"], image.symbols["_end"]), (0x101c, 0x8008, 0x800c));
+  // the first of the local labels with the same name
+  assert_eq!(image.symbols["loop"], 0x1018);
+  assert_eq!(image.globals, ["_end", "_start", "counter", "print"]);
+}
+
+#[test]
+fn test_link_without_script() {
+  // sections follow each other from address 0
+  let image = link(&link_objects(), None).unwrap();
+  assert_eq!(image.entry, 0);
+  assert_eq!((image.symbols["message"], image.symbols["pointer"], image.symbols["counter"]), (0x2c, 0x30, 0x34));
+}
+
+#[test]
+fn test_link_errors() {
+  let objects = link_objects();
+  assert_eq!(link(&objects[..1], None), Err(LinkError::UndefinedSymbol("counter".to_owned())));
+  let twice = [objects[1].clone(), objects[1].clone()];
+  assert_eq!(link(&twice, None), Err(LinkError::DuplicateSymbol("print".to_owned())));
+  let small = LinkerScript::parse(&LINK_SCRIPT.replace("LENGTH = 1K", "LENGTH = 8")).unwrap();
+  assert_eq!(link(&objects, Some(&small)), Err(LinkError::RegionOverflow { region: "RAM".to_owned(), section: ".bss".to_owned() }));
+  let overlapping = LinkerScript::parse("SECTIONS { .text 0x100 : { *(.text) } .data 0x120 : { *(.data) } }").unwrap();
+  assert_eq!(link(&objects, Some(&overlapping)), Err(LinkError::OverlappingSections(".text".to_owned(), ".data".to_owned())));
+  let top = LinkerScript::parse("SECTIONS { . = 0xfffffffc; .text : { *(.text) } }").unwrap();
+  let objects = [assemble_object("nop\nnop", |_| None).unwrap()];
+  assert_eq!(link(&objects, Some(&top)), Err(LinkError::AddressOverflow(".text".to_owned())));
+  let unaligned = LinkerScript::parse("SECTIONS { . = 0xfffffffe; .text : { *(.text) } }").unwrap();
+  assert_eq!(link(&objects, Some(&unaligned)), Err(LinkError::AddressOverflow(".text".to_owned())));
+}
+
+#[test]
+fn test_linker_script_errors() {
+  let error = LinkerScript::parse("MEMORY {\n  RAM : ORIGIN = 0x8000 LENGTH = 1K\n}").unwrap_err();
+  assert_eq!(error.to_string(), "linker script line 2: expected `,`, found `LENGTH`");
+}
+
+/// two instructions and three bytes of data at 0x10000
+fn formats_image() -> Image {
+  let mut image = assemble_image("_start: addi a0, zero, 1\n ecall\n.data\n.byte 1, 2, 3").unwrap();
+  image.rebase(0x10000);
+  image
+}
+
+#[test]
+fn test_image_rebase() {
+  let image = formats_image();
+  assert_eq!((image.entry, image.symbols["_start"]), (0x10000, 0x10000));
+  assert_eq!(image.sections.iter().map(|section| section.address).collect::<Vec<_>>(), [0x10000, 0x10008]);
+}
+
+#[test]
+fn test_write_ihex() {
+  let image = formats_image();
+  let ihex = write_ihex(&image);
+  let expected = [
+    ":020000040001F9",
+    ":0800000013051000730000005D",
+    ":03000800010203EF",
+    ":0400000500010000F6",
+    ":00000001FF",
+  ];
+  assert_eq!(ihex.lines().collect::<Vec<_>>(), expected);
+  let read = read_ihex(&ihex).unwrap();
+  assert_eq!((read.sections.len(), read.sections[0].address, read.entry), (1, 0x10000, 0x10000));
+  assert_eq!(read.sections[0].data, write_bin(&image));
+}
+
+#[test]
+fn test_read_ihex_errors() {
+  let ihex = write_ihex(&formats_image());
+  assert_eq!(read_ihex(&ihex.replace(":03000800010203EF", ":03000800010204EF")), Err(FormatError::Checksum(3)));
+  assert_eq!(read_ihex(":0800000013051000730000005D"), Err(FormatError::MissingEnd));
+  assert_eq!(read_ihex(":02000004FFFFFC\n:02FFFF000102FD\n:00000001FF"), Err(FormatError::InvalidRecord(2)));
+}
+
+#[test]
+fn test_formats_top_of_memory() {
+  let image = read_ihex(":02000004FFFFFC\n:02FFFE000102FE\n:00000001FF").unwrap();
+  assert_eq!((image.sections[0].address, image.sections[0].data.clone()), (0xffff_fffe, vec![1, 2]));
+  assert_eq!(read_srec(&write_srec(&image)).unwrap().sections[0].data, vec![1, 2]);
+  let image = read_srec("S306FFFFFFFF01FC\nS306FFFFFFFF02FB").unwrap();
+  assert_eq!(image.sections.iter().map(|section| section.address).collect::<Vec<_>>(), [0xffff_ffff, 0xffff_ffff]);
+}
+
+#[test]
+fn test_write_srec() {
+  let image = formats_image();
+  let srec = write_srec(&image);
+  let expected = [
+    "S00B0000786F636F6C61746C8E",
+    "S30D00010000130510007300000056",
+    "S30800010008010203E8",
+    "S5030002FA",
+    "S70500010000F9",
+  ];
+  assert_eq!(srec.lines().collect::<Vec<_>>(), expected);
+  let read = read_srec(&srec).unwrap();
+  assert_eq!((read.sections[0].address, read.sections[0].data.clone(), read.entry), (0x10000, write_bin(&image), 0x10000));
+}
+
+#[test]
+fn test_read_srec_errors() {
+  assert_eq!(read_srec("S4030000FC"), Err(FormatError::UnknownRecordType { line: 1, kind: 4 }));
+  assert_eq!(read_srec("S1130000"), Err(FormatError::InvalidRecord(1)));
+  assert_eq!(read_srec("S307FFFFFFFF0102F9"), Err(FormatError::InvalidRecord(1)));
+}
+
+#[test]
+fn test_write_readmemh() {
+  let memh = write_readmemh(&formats_image(), 4);
+  assert_eq!(memh.lines().collect::<Vec<_>>(), ["@00004000", "00100513", "00000073", "@00004002", "00030201"]);
+}
+
+#[test]
+fn test_read_bin() {
+  let bin = write_bin(&formats_image());
+  assert_eq!(bin.len(), 11);
+  let image = read_bin(&bin, 0x2000);
+  let mut machine = Machine::default();
+  image.load(&mut machine).unwrap();
+  assert_eq!(machine.pc, 0x2000);
+  assert_eq!(machine.memory.load_word(0x2004), 0x73);
+  assert_eq!(machine.memory.load_byte(0x200a), 3);
+}
+
+#[test]
+fn test_image_rebase_keeps_constants() {
+  const SOURCE: &str = "
+    .equ SIZE, 16
+    .globl LIMIT
+    .set LIMIT, SIZE * 2
+    _start:
+      li a0, SIZE
+  ";
+  let mut image = assemble_image(SOURCE).unwrap();
+  assert_eq!(image.constants, ["SIZE", "LIMIT"]);
+  image.rebase(0x10000);
+  assert_eq!((image.symbols["_start"], image.symbols["SIZE"], image.symbols["LIMIT"]), (0x10000, 16, 32));
+
+  let mut image = link(&[assemble_object(SOURCE, |_| None).unwrap()], None).unwrap();
+  image.rebase(0x20000);
+  assert_eq!((image.symbols["_start"], image.symbols["SIZE"], image.symbols["LIMIT"]), (0x20000, 16, 32));
+}
+
+const DISASSEMBLY: &str = "
+    _start:
+      li a0, 1
+      call main
+      fence
+      j _start
+    main:
+      beqz a0, done
+      sw a1, -4(sp)
+    done:
+      ret
+      .data
+    message: .string \"hi!\"
+    tail: .byte 1, 2
+  ";
+
+#[test]
+fn test_disassemble_image() {
+  let image = assemble_image(DISASSEMBLY).unwrap();
+  let expected = "
+Disassembly of section .text:
+
+00000000 <_start>:
+       0:  00100513  li a0, 1
+       4:  00000097  auipc ra, 0
+       8:  010080e7  jalr 16(ra)
+       c:  0ff0000f  fence
+      10:  ff1ff06f  j 0x0 <_start>
+
+00000014 <main>:
+      14:  00050463  beqz a0, 0x1c <done>
+      18:  feb12e23  sw a1, -4(sp)
+
+0000001c <done>:
+      1c:  00008067  ret
+
+Disassembly of section .data:
+
+00000020 <message>:
+      20:  00216968  .word 0x00216968
+
+00000024 <tail>:
+      24:  0201      .byte 0x01, 0x02
+";
+  assert_eq!(disassemble_image(&image, &DisassembleOptions::default()), expected);
+}
+
+#[test]
+fn test_disassemble_image_without_pseudo() {
+  let image = assemble_image(DISASSEMBLY).unwrap();
+  let raw = disassemble_image(&image, &DisassembleOptions { pseudo: false, ..DisassembleOptions::default() });
+  let lines = raw.lines().filter(|line| line.starts_with("  ")).take(5).map(|line| &line[21..]).collect::<Vec<_>>();
+  assert_eq!(lines, ["addi a0, zero, 1", "auipc ra, 0", "jalr ra, 16(ra)", "fence iorw, iorw", "jal zero, 0x0 <_start>"]);
+}
+
+#[test]
+fn test_disassemble_bytes() {
+  let symbols = [("loop".to_owned(), 0x100)].into();
+  let bytes = [0x6f, 0xf0, 0x1f, 0xff, 0xff, 0xff, 0xff, 0xff];
+  let expected = "\n00000100 <loop>:\n     100:  ff1ff06f  j 0xf0\n     104:  ffffffff  .word 0xffffffff\n";
+  assert_eq!(disassemble_bytes(&bytes, 0x100, &symbols, &DisassembleOptions::default()), expected);
+  let bytes = [0x6f, 0x00, 0x40, 0x00];
+  assert!(disassemble_bytes(&bytes, 0x100, &symbols, &DisassembleOptions::default()).ends_with("j 0x104 <loop+0x4>\n"));
+}
+
+#[test]
+fn test_disassemble_elf() {
+  let code = ["addi x10 x0 5", "jal x1 -4"].map(assemble_normalized_ok);
+  let elf = Elf::parse(&elf_executable(&code, 0, &[("_start", 0x10000)])).unwrap();
+  let expected = "\nDisassembly of section .text:\n\n00010000 <_start>:\n   10000:  00500513  li a0, 5\n   10004:  ffdff0ef  jal 0x10000 <_start>\n";
+  assert_eq!(disassemble_elf(&elf, &DisassembleOptions::default()), expected);
+}
+
+const STYLES: &str = "
+    _start:
+      call main
+      lui a1, 74565
+      srai a2, a1, 31
+      ecall
+    main:
+      la a0, main
+      beqz a0, _start
+      ret
+      .word -1
+  ";
+
+#[test]
+fn test_disassemble_gnu_style() {
+  let image = assemble_image(STYLES).unwrap();
+  let gnu = DisassembleOptions { style: Style::Gnu, ..DisassembleOptions::default() };
+  let expected = "
+Disassembly of section .text:
+
+00000000 <_start>:
+   0:\t00000097          \tauipc\tra,0x0
+   4:\t014080e7          \tjalr\t20(ra) # 14 <main>
+   8:\t123455b7          \tlui\ta1,0x12345
+   c:\t41f5d613          \tsrai\ta2,a1,0x1f
+  10:\t00000073          \tecall
+
+00000014 <main>:
+  14:\t00000517          \tauipc\ta0,0x0
+  18:\t00050513          \tmv\ta0,a0
+  1c:\tfe0502e3          \tbeqz\ta0,0 <_start>
+  20:\t00008067          \tret
+  24:\tffffffff          \t.4byte\t0xffffffff
+";
+  assert_eq!(disassemble_image(&image, &gnu), expected);
+}
+
+#[test]
+fn test_disassemble_gnu_style_numeric() {
+  let image = assemble_image(STYLES).unwrap();
+  let numeric = disassemble_image(&image, &DisassembleOptions { style: Style::Gnu, pseudo: false, abi_names: false, ..DisassembleOptions::default() });
+  assert!(numeric.contains("\tjalr\tx1,20(x1) # 14 <main>\n"));
+  assert!(numeric.contains("\taddi\tx10,x10,0 # 14 <main>\n"));
+}
+
+#[test]
+fn test_disassemble_llvm_style() {
+  let image = assemble_image(STYLES).unwrap();
+  let llvm = DisassembleOptions { style: Style::Llvm, ..DisassembleOptions::default() };
+  let expected = "
+Disassembly of section .text:
+
+00000000 <_start>:
+       0: 97 00 00 00  \tauipc\tra, 0
+       4: e7 80 40 01  \tjalr\t20(ra)
+       8: b7 55 34 12  \tlui\ta1, 74565
+       c: 13 d6 f5 41  \tsrai\ta2, a1, 31
+      10: 73 00 00 00  \tecall\t
+
+00000014 <main>:
+      14: 17 05 00 00  \tauipc\ta0, 0
+      18: 13 05 05 00  \tmv\ta0, a0
+      1c: e3 02 05 fe  \tbeqz\ta0, 0x0 <_start>
+      20: 67 80 00 00  \tret
+      24: ff ff ff ff  \t<unknown>
+";
+  assert_eq!(disassemble_image(&image, &llvm), expected);
+}
+
+#[test]
+fn test_disassemble_llvm_style_numeric() {
+  let image = assemble_image(STYLES).unwrap();
+  let numeric = disassemble_image(&image, &DisassembleOptions { style: Style::Llvm, pseudo: false, abi_names: false, ..DisassembleOptions::default() });
+  assert!(numeric.contains("\tjalr\tx0, 0(x1)\n"));
+}
+
+#[test]
+fn test_execute_sub_wraps() {
+  let (machine, _) = run_to_end("
+      li a0, 1
+      li a1, 2
+      sub a2, a0, a1
+      neg a3, a1
+    end:
+  ");
+  assert_eq!((machine.registers.get(12), machine.registers.get(13)), (0xffffffff, 0xfffffffe));
+}
+
+#[test]
+fn test_execute_branches() {
+  // each result register is 1 if the branch before it was taken, -1 and 1 compare differently signed and unsigned
+  let (machine, _) = run_to_end("
+      li a0, -1
+      li a1, 1
+      li a2, 1
+      bne a0, a1, bne_taken
+      li a2, 0
+    bne_taken:
+      li a3, 1
+      bne a1, a1, bne_equal
+      li a3, 0
+    bne_equal:
+      li a4, 1
+      bge a0, a1, bge_signed
+      li a4, 0
+    bge_signed:
+      li a5, 1
+      bgeu a0, a1, bgeu_unsigned
+      li a5, 0
+    bgeu_unsigned:
+      li a6, 1
+      bge a1, a0, bge_greater
+      li a6, 0
+    bge_greater:
+      li a7, 1
+      bgeu a1, a0, bgeu_smaller
+      li a7, 0
+    bgeu_smaller:
+      li s2, 1
+      bge a1, a1, bge_equal
+      li s2, 0
+    bge_equal:
+      li s3, 1
+      bgeu a1, a1, bgeu_equal
+      li s3, 0
+    bgeu_equal:
+    end:
+  ");
+  let taken = (12..=19).map(|register| machine.registers.get(register)).collect::<Vec<_>>();
+  assert_eq!(taken, [1, 0, 0, 1, 1, 0, 1, 1]);
+}
+
+#[test]
+fn test_execute_m_extension() {
+  // operands and results from the riscv-tests rv32um vectors
+  let vectors = [
+    ("mul", 0x00007e00, 0xb6db6d00, 0xffa60000),
+    ("mul", 0xffff8000, 0xffff8000, 0x40000000),
+    ("mul", 0xaaaaaaab, 0x0002fe7d, 0x0000ff7f),
+    ("mul", 0x80000000, 0xffffffff, 0x80000000),
+    ("mulh", 0xffff8000, 0xffff8000, 0x00000000),
+    ("mulh", 0x80000000, 0x80000000, 0x40000000),
+    ("mulh", 0xaaaaaaab, 0x0002fe7d, 0xffff0081),
+    ("mulh", 0x80000000, 0xffffffff, 0x00000000),
+    ("mulhsu", 0xffff8000, 0xffff8000, 0xffff8000),
+    ("mulhsu", 0x80000000, 0xffffffff, 0x80000000),
+    ("mulhsu", 0xaaaaaaab, 0x0002fe7d, 0xffff0081),
+    ("mulhsu", 0xffffffff, 0xffffffff, 0xffffffff),
+    ("mulhu", 0xffff8000, 0xffff8000, 0xffff0000),
+    ("mulhu", 0xffffffff, 0xffffffff, 0xfffffffe),
+    ("mulhu", 0xaaaaaaab, 0x0002fe7d, 0x0001fefe),
+    ("mulhu", 0x80000000, 0x80000000, 0x40000000),
+    ("div", 0x00000014, 0x00000006, 0x00000003),
+    ("div", 0xffffffec, 0x00000006, 0xfffffffd),
+    ("div", 0x00000014, 0xfffffffa, 0xfffffffd),
+    ("div", 0xffffffec, 0xfffffffa, 0x00000003),
+    ("div", 0x80000000, 0x00000001, 0x80000000),
+    ("div", 0x80000000, 0xffffffff, 0x80000000),
+    ("div", 0x00000014, 0x00000000, 0xffffffff),
+    ("div", 0xffffffec, 0x00000000, 0xffffffff),
+    ("divu", 0x00000014, 0x00000006, 0x00000003),
+    ("divu", 0xffffffec, 0x00000006, 0x2aaaaaa7),
+    ("divu", 0x00000014, 0xfffffffa, 0x00000000),
+    ("divu", 0x80000000, 0xffffffff, 0x00000000),
+    ("divu", 0x00000014, 0x00000000, 0xffffffff),
+    ("divu", 0x80000000, 0x00000000, 0xffffffff),
+    ("rem", 0x00000014, 0x00000006, 0x00000002),
+    ("rem", 0xffffffec, 0x00000006, 0xfffffffe),
+    ("rem", 0x00000014, 0xfffffffa, 0x00000002),
+    ("rem", 0xffffffec, 0xfffffffa, 0xfffffffe),
+    ("rem", 0x80000000, 0xffffffff, 0x00000000),
+    ("rem", 0x00000014, 0x00000000, 0x00000014),
+    ("rem", 0xffffffec, 0x00000000, 0xffffffec),
+    ("remu", 0x00000014, 0x00000006, 0x00000002),
+    ("remu", 0xffffffec, 0x00000006, 0x00000002),
+    ("remu", 0x00000014, 0xfffffffa, 0x00000014),
+    ("remu", 0x80000000, 0xffffffff, 0x80000000),
+    ("remu", 0x00000014, 0x00000000, 0x00000014),
+    ("remu", 0xffffffec, 0x00000000, 0xffffffec),
+  ];
+  for (mnemonic, a, b, expected) in vectors {
+    let instruction = assemble_normalized_ok(&format!("{mnemonic} x14 x1 x2"));
+    let mut machine = Machine::default();
+    machine.registers.set(1, a);
+    machine.registers.set(2, b);
+    decode(instruction).exec(&mut machine, instruction).unwrap();
+    assert_eq!(machine.registers.get(14), expected, "{mnemonic} {a:#x} {b:#x}");
+    assert_eq!(machine.pc, 4);
+  }
+}
+
+#[test]
+fn test_execute_m_extension_registers() {
+  // the destination may alias a source, and writes to x0 are discarded
+  let instruction = assemble_normalized_ok("mul x1 x1 x1");
+  let mut machine = Machine::default();
+  machine.registers.set(1, 13);
+  decode(instruction).exec(&mut machine, instruction).unwrap();
+  assert_eq!(machine.registers.get(1), 169);
+  let instruction = assemble_normalized_ok("div x0 x1 x0");
+  decode(instruction).exec(&mut machine, instruction).unwrap();
+  assert_eq!(machine.registers.get(0), 0);
+}
+
+#[test]
+fn test_assemble_a_extension() {
+  const SOURCE: &str = "
+    lr.w a0, (a1)
+    lr.w.aq t0, 0(a0)
+    sc.w a2, a3, (a1)
+    sc.w.rl t1, t2, (a0)
+    amoswap.w.aqrl a0, a2, (a1)
+    amoadd.w a0, a2, (a1)
+    amoxor.w a0, a2, (a1)
+    amoand.w a0, a2, (a1)
+    amoor.w a0, a2, (a1)
+    amomin.w a0, a2, (a1)
+    amomax.w a0, a2, (a1)
+    amominu.w a0, a2, (a1)
+    amomaxu.w zero, a2, (a1)
+  ";
+  // encodings and disassembly as by llvm-mc and llvm-objdump
+  let image = assemble_image(SOURCE).unwrap();
+  let llvm = DisassembleOptions { style: Style::Llvm, ..DisassembleOptions::default() };
+  let expected = "
+Disassembly of section .text:
+       0: 2f a5 05 10  \tlr.w\ta0, (a1)
+       4: af 22 05 14  \tlr.w.aq\tt0, (a0)
+       8: 2f a6 d5 18  \tsc.w\ta2, a3, (a1)
+       c: 2f 23 75 1a  \tsc.w.rl\tt1, t2, (a0)
+      10: 2f a5 c5 0e  \tamoswap.w.aqrl\ta0, a2, (a1)
+      14: 2f a5 c5 00  \tamoadd.w\ta0, a2, (a1)
+      18: 2f a5 c5 20  \tamoxor.w\ta0, a2, (a1)
+      1c: 2f a5 c5 60  \tamoand.w\ta0, a2, (a1)
+      20: 2f a5 c5 40  \tamoor.w\ta0, a2, (a1)
+      24: 2f a5 c5 80  \tamomin.w\ta0, a2, (a1)
+      28: 2f a5 c5 a0  \tamomax.w\ta0, a2, (a1)
+      2c: 2f a5 c5 c0  \tamominu.w\ta0, a2, (a1)
+      30: 2f a0 c5 e0  \tamomaxu.w\tzero, a2, (a1)
+";
+  assert_eq!(disassemble_image(&image, &llvm), expected);
+}
+
+#[test]
+fn test_disassemble_a_extension() {
+  let instruction = assemble_normalized_ok("amoswap.w.aqrl x10 x12 (x11)");
+  assert_eq!(disassemble(instruction), "amoswap.w.aqrl x10, x12, (x11)");
+}
+
+#[test]
+fn test_assemble_a_extension_errors() {
+  assert_eq!(assemble("lr.w a0, 4(a1)").unwrap_err()[0].kind, ErrorKind::InvalidOperand { operand: "4(a1)".to_owned(), expected: "`(register)`" });
+  assert!(matches!(assemble("amoadd.w.aq a0, (a1)").unwrap_err()[0].kind, ErrorKind::WrongOperandCount { .. }));
+  assert_eq!(assemble("add.aq a0, a1, a2").unwrap_err()[0].kind, ErrorKind::UnknownMnemonic("add.aq".to_owned()));
+}
+
+#[test]
+fn test_execute_amos() {
+  // memory value, rs2 and the value written back
+  let vectors = [
+    ("amoswap.w", 7, 9, 9),
+    ("amoadd.w", 0xffffffff, 2, 1),
+    ("amoxor.w", 0b1100, 0b1010, 0b0110),
+    ("amoand.w", 0b1100, 0b1010, 0b1000),
+    ("amoor.w", 0b1100, 0b1010, 0b1110),
+    ("amomin.w", 0xfffffffe, 1, 0xfffffffe),
+    ("amomax.w", 0xfffffffe, 1, 1),
+    ("amominu.w", 0xfffffffe, 1, 1),
+    ("amomaxu.w", 0xfffffffe, 1, 0xfffffffe),
+  ];
+  for (mnemonic, old, rs2, new) in vectors {
+    let instruction = assemble_normalized_ok(&format!("{mnemonic} x10 x12 (x11)"));
+    let mut machine = Machine::default();
+    machine.memory.store_word(0x100, old);
+    machine.registers.set(11, 0x100);
+    machine.registers.set(12, rs2);
+    decode(instruction).exec(&mut machine, instruction).unwrap();
+    assert_eq!((machine.registers.get(10), machine.memory.load_word(0x100)), (old, new), "{mnemonic}");
+  }
+}
+
+#[test]
+fn test_execute_spinlock() {
+  // a spinlock taken with lr/sc, a counter incremented atomically and the lock released with a swap
+  const SPINLOCK: &str = "
+      li a1, 0x100
+      li a2, 1
+    acquire:
+      lr.w.aq t0, (a1)
+      bnez t0, acquire
+      sc.w t1, a2, (a1)
+      bnez t1, acquire
+      li a3, 0x104
+      li a4, 5
+      amoadd.w a5, a4, (a3)
+      amoswap.w.rl zero, zero, (a1)
+  ";
+  let mut machine = Machine::default();
+  assemble_image(SPINLOCK).unwrap().load(&mut machine).unwrap();
+  machine.memory.store_word(0x104, 37);
+  machine.cycles(10);
+  assert_eq!(machine.pc, 40);
+  assert_eq!((machine.registers.get(5), machine.registers.get(6), machine.registers.get(15)), (0, 0, 37));
+  assert_eq!((machine.memory.load_word(0x100), machine.memory.load_word(0x104)), (0, 42));
+  assert_eq!(machine.reservation, None);
+}
+
+#[test]
+fn test_execute_sc_reservation() {
+  // sc.w fails without a reservation for its address and releases any reservation
+  let lr = assemble_normalized_ok("lr.w x5 (x11)");
+  let sc = assemble_normalized_ok("sc.w x6 x12 (x11)");
+  let mut machine = Machine::default();
+  machine.registers.set(11, 0x100);
+  machine.registers.set(12, 3);
+  decode(sc).exec(&mut machine, sc).unwrap();
+  assert_eq!((machine.registers.get(6), machine.memory.load_word(0x100)), (1, 0));
+  decode(lr).exec(&mut machine, lr).unwrap();
+  assert_eq!(machine.reservation, Some(0x100));
+  machine.registers.set(11, 0x104);
+  decode(sc).exec(&mut machine, sc).unwrap();
+  assert_eq!((machine.registers.get(6), machine.memory.load_word(0x104)), (1, 0));
+  machine.registers.set(11, 0x100);
+  decode(sc).exec(&mut machine, sc).unwrap();
+  assert_eq!((machine.registers.get(6), machine.memory.load_word(0x100)), (1, 0));
+  decode(lr).exec(&mut machine, lr).unwrap();
+  decode(sc).exec(&mut machine, sc).unwrap();
+  assert_eq!((machine.registers.get(6), machine.memory.load_word(0x100)), (0, 3));
+}
+
+#[test]
+fn test_assemble_f_d_extension() {
+  const SOURCE: &str = "
+    flw ft0, 4(a0)
+    fsw fa1, -8(sp)
+    fld fs0, 16(a1)
+    fsd fs11, 0(a2)
+    fadd.s fa0, fa1, fa2
+    fsub.d fa0, fa1, fa2, rtz
+    fmul.s ft1, ft2, ft3, rne
+    fdiv.d ft1, ft2, ft3, dyn
+    fsqrt.s fa0, fa1, rup
+    fmadd.s fa0, fa1, fa2, fa3
+    fnmsub.d ft0, ft1, ft2, ft3, rmm
+    fmv.s fa0, fa1
+    fneg.d fa0, fa1
+    fsgnjx.s fa0, fa1, fa2
+    fmin.d fa0, fa1, fa2
+    fmax.s fa0, fa1, fa2
+    fcvt.w.s a0, fa0, rtz
+    fcvt.wu.d a0, fa0
+    fcvt.s.w fa0, a0
+    fcvt.d.wu fa0, a1
+    fcvt.s.d fa0, fa1
+    fcvt.d.s fa0, fa1
+    fmv.x.w a0, fa0
+    fmv.w.x fa0, a0
+    feq.s a0, fa0, fa1
+    flt.d a0, fa0, fa1
+    fle.s a0, fa0, fa1
+    fclass.d a0, fa0
+  ";
+  // encodings and disassembly as by llvm-mc and llvm-objdump
+  let image = assemble_image(SOURCE).unwrap();
+  let llvm = DisassembleOptions { style: Style::Llvm, ..DisassembleOptions::default() };
+  let expected = "
+Disassembly of section .text:
+       0: 07 20 45 00  \tflw\tft0, 4(a0)
+       4: 27 2c b1 fe  \tfsw\tfa1, -8(sp)
+       8: 07 b4 05 01  \tfld\tfs0, 16(a1)
+       c: 27 30 b6 01  \tfsd\tfs11, 0(a2)
+      10: 53 f5 c5 00  \tfadd.s\tfa0, fa1, fa2
+      14: 53 95 c5 0a  \tfsub.d\tfa0, fa1, fa2, rtz
+      18: d3 00 31 10  \tfmul.s\tft1, ft2, ft3, rne
+      1c: d3 70 31 1a  \tfdiv.d\tft1, ft2, ft3
+      20: 53 b5 05 58  \tfsqrt.s\tfa0, fa1, rup
+      24: 43 f5 c5 68  \tfmadd.s\tfa0, fa1, fa2, fa3
+      28: 4b c0 20 1a  \tfnmsub.d\tft0, ft1, ft2, ft3, rmm
+      2c: 53 85 b5 20  \tfmv.s\tfa0, fa1
+      30: 53 95 b5 22  \tfneg.d\tfa0, fa1
+      34: 53 a5 c5 20  \tfsgnjx.s\tfa0, fa1, fa2
+      38: 53 85 c5 2a  \tfmin.d\tfa0, fa1, fa2
+      3c: 53 95 c5 28  \tfmax.s\tfa0, fa1, fa2
+      40: 53 15 05 c0  \tfcvt.w.s\ta0, fa0, rtz
+      44: 53 75 15 c2  \tfcvt.wu.d\ta0, fa0
+      48: 53 75 05 d0  \tfcvt.s.w\tfa0, a0
+      4c: 53 85 15 d2  \tfcvt.d.wu\tfa0, a1
+      50: 53 f5 15 40  \tfcvt.s.d\tfa0, fa1
+      54: 53 85 05 42  \tfcvt.d.s\tfa0, fa1
+      58: 53 05 05 e0  \tfmv.x.w\ta0, fa0
+      5c: 53 05 05 f0  \tfmv.w.x\tfa0, a0
+      60: 53 25 b5 a0  \tfeq.s\ta0, fa0, fa1
+      64: 53 15 b5 a2  \tflt.d\ta0, fa0, fa1
+      68: 53 05 b5 a0  \tfle.s\ta0, fa0, fa1
+      6c: 53 15 05 e2  \tfclass.d\ta0, fa0
+";
+  assert_eq!(disassemble_image(&image, &llvm), expected);
+}
+
+#[test]
+fn test_disassemble_f_d_extension_without_aliases() {
+  // without aliases the dynamic rounding mode is shown
+  let image = assemble_image("fadd.s fa0, fa1, fa2\n fneg.d fa0, fa1").unwrap();
+  let no_aliases = DisassembleOptions { style: Style::Llvm, pseudo: false, ..DisassembleOptions::default() };
+  let listing = disassemble_image(&image, &no_aliases);
+  assert!(listing.contains("\tfadd.s\tfa0, fa1, fa2, dyn\n"), "{listing}");
+  assert!(listing.contains("\tfsgnjn.d\tfa0, fa1, fa1\n"), "{listing}");
+}
+
+#[test]
+fn test_disassemble_f_d_extension() {
+  let instruction = assemble_normalized_ok("fsub.d f10 f11 f12 rtz");
+  assert_eq!(disassemble(instruction), "fsub.d f10, f11, f12, rtz");
+  assert_eq!(disassemble(assemble_normalized_ok("fcvt.w.s x10 f10")), "fcvt.w.s x10, f10");
+}
+
+#[test]
+fn test_assemble_f_d_extension_errors() {
+  assert_eq!(assemble("fadd.s fa0, fa1, fa2, up").unwrap_err()[0].kind, ErrorKind::InvalidOperand { operand: "up".to_owned(), expected: "a rounding mode" });
+  assert_eq!(assemble("fadd.s fa0, fa1, a2").unwrap_err()[0].kind, ErrorKind::UnknownRegister("a2".to_owned()));
+  assert!(matches!(assemble("fcvt.d.s fa0, fa1, rtz").unwrap_err()[0].kind, ErrorKind::WrongOperandCount { .. }));
+}
+
+/// executes an instruction on single precision operands in f11, f12 and f13, returning f10 or x10 and the flags
+fn execute_single(instruction: &str, operands: &[f32]) -> (u32, u32, u32) {
+  let instruction = assemble_normalized_ok(instruction);
+  let mut machine = Machine::default();
+  machine.float_registers.set(10, 0f32);
+  for (register, operand) in operands.iter().enumerate() {
+    machine.float_registers.set(11 + register, *operand);
+  }
+  decode(instruction).exec(&mut machine, instruction).unwrap();
+  let result = machine.float_registers.get::<f32>(10);
+  (result.to_bits(), machine.registers.get(10), machine.float_registers.fcsr)
+}
+
+/// like [`execute_single`] for double precision operands
+fn execute_double(instruction: &str, operands: &[f64]) -> (f64, u32, u32) {
+  let instruction = assemble_normalized_ok(instruction);
+  let mut machine = Machine::default();
+  for (register, operand) in operands.iter().enumerate() {
+    machine.float_registers.set(11 + register, *operand);
+  }
+  decode(instruction).exec(&mut machine, instruction).unwrap();
+  (machine.float_registers.get::<f64>(10), machine.registers.get(10), machine.float_registers.fcsr)
+}
+
+#[test]
+fn test_execute_f_arithmetic() {
+  let one_ulp = f32::from_bits(0x3f800001);
+  let half_ulp = 2f32.powi(-24);
+  // ties to even and directed rounding
+  assert_eq!(execute_single("fadd.s f10 f11 f12 rne", &[1.0, half_ulp]), (1f32.to_bits(), 0, NX));
+  assert_eq!(execute_single("fadd.s f10 f11 f12 rup", &[1.0, half_ulp]), (one_ulp.to_bits(), 0, NX));
+  assert_eq!(execute_single("fadd.s f10 f11 f12 rne", &[one_ulp, half_ulp]), (0x3f800002, 0, NX));
+  assert_eq!(execute_single("fadd.s f10 f11 f12 rtz", &[one_ulp, half_ulp]), (one_ulp.to_bits(), 0, NX));
+  assert_eq!(execute_single("fsub.s f10 f11 f12 rdn", &[1.0, 1.0]), ((-0f32).to_bits(), 0, 0));
+  assert_eq!(execute_single("fsub.s f10 f11 f12 rne", &[1.0, 1.0]), (0, 0, 0));
+  assert_eq!(execute_single("fmul.s f10 f11 f12 rne", &[f32::MAX, 2.0]), (f32::INFINITY.to_bits(), 0, OF | NX));
+  assert_eq!(execute_single("fmul.s f10 f11 f12 rtz", &[f32::MAX, 2.0]), (f32::MAX.to_bits(), 0, OF | NX));
+  assert_eq!(execute_single("fmul.s f10 f11 f12 rne", &[f32::MIN_POSITIVE, 0.5]), ((f32::MIN_POSITIVE / 2.0).to_bits(), 0, 0));
+  assert_eq!(execute_single("fmul.s f10 f11 f12 rne", &[f32::MIN_POSITIVE, 0.3]).2, UF | NX);
+  assert_eq!(execute_single("fdiv.s f10 f11 f12 rne", &[1.0, 0.0]), (f32::INFINITY.to_bits(), 0, DZ));
+  assert_eq!(execute_single("fdiv.s f10 f11 f12 rne", &[1.0, 3.0]), ((1.0f32 / 3.0).to_bits(), 0, NX));
+  assert_eq!(execute_single("fdiv.s f10 f11 f12 rdn", &[1.0, 3.0]).0, (1.0f32 / 3.0).next_down().to_bits());
+  assert_eq!(execute_single("fdiv.s f10 f11 f12 rne", &[0.0, 0.0]), (CANONICAL_NAN_S, 0, NV));
+  assert_eq!(execute_single("fsqrt.s f10 f11 rne", &[-1.0]), (CANONICAL_NAN_S, 0, NV));
+  assert_eq!(execute_single("fsqrt.s f10 f11 rne", &[2.0]), (2f32.sqrt().to_bits(), 0, NX));
+  assert_eq!(execute_single("fsqrt.s f10 f11 rne", &[-0.0]), ((-0f32).to_bits(), 0, 0));
+}
+
+#[test]
+fn test_execute_f_fused_multiply_add() {
+  // fused multiply-add rounds once, negating the product and the addend as named
+  let x = 1.0 + 2f32.powi(-12);
+  assert_eq!(execute_single("fmadd.s f10 f11 f12 f13 rne", &[x, x, -(x * x)]), (2f32.powi(-24).to_bits(), 0, 0));
+  assert_eq!(execute_single("fmsub.s f10 f11 f12 f13 rne", &[2.0, 3.0, 1.0]).0, 5f32.to_bits());
+  assert_eq!(execute_single("fnmsub.s f10 f11 f12 f13 rne", &[2.0, 3.0, 1.0]).0, (-5f32).to_bits());
+  assert_eq!(execute_single("fnmadd.s f10 f11 f12 f13 rne", &[2.0, 3.0, 1.0]).0, (-7f32).to_bits());
+  assert_eq!(execute_single("fmadd.s f10 f11 f12 f13 rne", &[f32::INFINITY, 0.0, f32::NAN]), (CANONICAL_NAN_S, 0, NV));
+}
+
+#[test]
+fn test_execute_f_nans() {
+  let signaling = f32::from_bits(0x7f800001);
+  // NaNs are canonical and signaling ones raise NV
+  assert_eq!(execute_single("fadd.s f10 f11 f12 rne", &[f32::from_bits(0xffc00123), 1.0]), (CANONICAL_NAN_S, 0, 0));
+  assert_eq!(execute_single("fadd.s f10 f11 f12 rne", &[signaling, 1.0]), (CANONICAL_NAN_S, 0, NV));
+  // minimum and maximum prefer numbers over NaNs and order -0 below +0
+  assert_eq!(execute_single("fmin.s f10 f11 f12", &[0.0, -0.0]).0, (-0f32).to_bits());
+  assert_eq!(execute_single("fmax.s f10 f11 f12", &[-0.0, 0.0]).0, 0);
+  assert_eq!(execute_single("fmin.s f10 f11 f12", &[f32::NAN, 2.0]), (2f32.to_bits(), 0, 0));
+  assert_eq!(execute_single("fmax.s f10 f11 f12", &[signaling, 2.0]), (2f32.to_bits(), 0, NV));
+  assert_eq!(execute_single("fmax.s f10 f11 f12", &[f32::NAN, f32::NAN]).0, CANONICAL_NAN_S);
+}
+
+#[test]
+fn test_execute_f_sign_injection() {
+  // sign injection works on the bits, even of NaNs
+  assert_eq!(execute_single("fsgnj.s f10 f11 f12", &[1.5, -0.0]).0, (-1.5f32).to_bits());
+  assert_eq!(execute_single("fsgnjn.s f10 f11 f12", &[1.5, -0.0]).0, 1.5f32.to_bits());
+  assert_eq!(execute_single("fsgnjx.s f10 f11 f12", &[-1.5, -2.0]).0, 1.5f32.to_bits());
+}
+
+#[test]
+fn test_execute_f_comparisons() {
+  let signaling = f32::from_bits(0x7f800001);
+  // comparisons write x10, only feq is quiet for quiet NaNs
+  assert_eq!(execute_single("feq.s x10 f11 f12", &[1.0, 1.0]), (0, 1, 0));
+  assert_eq!(execute_single("flt.s x10 f11 f12", &[-0.0, 0.0]), (0, 0, 0));
+  assert_eq!(execute_single("fle.s x10 f11 f12", &[-0.0, 0.0]), (0, 1, 0));
+  assert_eq!(execute_single("feq.s x10 f11 f12", &[f32::NAN, 1.0]), (0, 0, 0));
+  assert_eq!(execute_single("feq.s x10 f11 f12", &[signaling, 1.0]), (0, 0, NV));
+  assert_eq!(execute_single("flt.s x10 f11 f12", &[f32::NAN, 1.0]), (0, 0, NV));
+}
+
+#[test]
+fn test_execute_f_conversions() {
+  // conversions to integers round and saturate
+  assert_eq!(execute_single("fcvt.w.s x10 f11 rne", &[2.5]), (0, 2, NX));
+  assert_eq!(execute_single("fcvt.w.s x10 f11 rmm", &[2.5]), (0, 3, NX));
+  assert_eq!(execute_single("fcvt.w.s x10 f11 rdn", &[-2.5]), (0, -3i32 as u32, NX));
+  assert_eq!(execute_single("fcvt.w.s x10 f11 rtz", &[-2.5]), (0, -2i32 as u32, NX));
+  assert_eq!(execute_single("fcvt.w.s x10 f11 rne", &[3e9]), (0, i32::MAX as u32, NV));
+  assert_eq!(execute_single("fcvt.w.s x10 f11 rne", &[-3e9]), (0, i32::MIN as u32, NV));
+  assert_eq!(execute_single("fcvt.w.s x10 f11 rne", &[f32::NAN]), (0, i32::MAX as u32, NV));
+  assert_eq!(execute_single("fcvt.wu.s x10 f11 rne", &[3e9]), (0, 3_000_000_000, 0));
+  assert_eq!(execute_single("fcvt.wu.s x10 f11 rtz", &[-0.5]), (0, 0, NX));
+  assert_eq!(execute_single("fcvt.wu.s x10 f11 rne", &[-1.0]), (0, 0, NV));
+  assert_eq!(execute_single("fcvt.wu.s x10 f11 rne", &[f32::NEG_INFINITY]), (0, 0, NV));
+}
+
+#[test]
+fn test_execute_f_classification() {
+  let signaling = f32::from_bits(0x7f800001);
+  // classification has one bit for each kind of value
+  let classes = [
+    (f32::NEG_INFINITY, 0),
+    (-1.0, 1),
+    (-f32::from_bits(1), 2),
+    (-0.0, 3),
+    (0.0, 4),
+    (f32::from_bits(1), 5),
+    (1.0, 6),
+    (f32::INFINITY, 7),
+    (signaling, 8),
+    (f32::NAN, 9),
+  ];
+  for (value, bit) in classes {
+    assert_eq!(execute_single("fclass.s x10 f11", &[value]).1, 1 << bit, "{value}");
+  }
+}
+
+#[test]
+fn test_execute_f_dynamic_rounding() {
+  let one_ulp = f32::from_bits(0x3f800001);
+  let half_ulp = 2f32.powi(-24);
+  // rounding with the dynamic rounding mode in frm and accumulating flags
+  let instruction = assemble_normalized_ok("fadd.s f10 f11 f12");
+  let mut machine = Machine::default();
+  machine.float_registers.fcsr = RUP << 5 | DZ;
+  machine.float_registers.set(11, 1f32);
+  machine.float_registers.set(12, half_ulp);
+  decode(instruction).exec(&mut machine, instruction).unwrap();
+  assert_eq!(machine.float_registers.get::<f32>(10), one_ulp);
+  assert_eq!(machine.float_registers.fcsr, RUP << 5 | DZ | NX);
+}
+
+#[test]
+fn test_f_nan_boxing() {
+  // single precision values are NaN-boxed, other bit patterns read as the canonical NaN
+  let mut machine = Machine::default();
+  machine.float_registers.set(1, 1.5f32);
+  assert_eq!(machine.float_registers.get_bits(1), 0xffff_ffff_3fc0_0000);
+  machine.float_registers.set_bits(2, 0x0000_0000_3fc0_0000);
+  assert_eq!(machine.float_registers.get::<f32>(2).to_bits(), CANONICAL_NAN_S);
+  machine.float_registers.set(3, 1.5f64);
+  assert_eq!(machine.float_registers.get::<f32>(3).to_bits(), CANONICAL_NAN_S);
+  // moves copy the bits without checking the boxing
+  for instruction in ["fmv.x.w x10 f2", "fmv.w.x f4 x10", "fcvt.d.s f5 f1", "fcvt.s.d f6 f3 rne"] {
+    let instruction = assemble_normalized_ok(instruction);
+    decode(instruction).exec(&mut machine, instruction).unwrap();
+  }
+  assert_eq!(machine.registers.get(10), 0x3fc0_0000);
+  assert_eq!(machine.float_registers.get::<f32>(4), 1.5);
+  assert_eq!(machine.float_registers.get::<f64>(5), 1.5);
+  assert_eq!(machine.float_registers.get::<f32>(6), 1.5);
+  assert_eq!(machine.float_registers.fcsr, 0);
+}
+
+#[test]
+fn test_execute_d_extension() {
+  let x = 1.0 + 2f64.powi(-30);
+  assert_eq!(execute_double("fmadd.d f10 f11 f12 f13 rne", &[x, x, -(x * x)]), (2f64.powi(-60), 0, 0));
+  assert_eq!(execute_double("fmul.d f10 f11 f12 rne", &[x, x]), (x * x, 0, NX));
+  assert_eq!(execute_double("fdiv.d f10 f11 f12 rdn", &[1.0, 3.0]), (1.0 / 3.0, 0, NX));
+  assert_eq!(execute_double("fdiv.d f10 f11 f12 rup", &[1.0, 3.0]), ((1.0f64 / 3.0).next_up(), 0, NX));
+  assert_eq!(execute_double("fcvt.s.d f10 f11 rne", &[1e300]).2, OF | NX);
+  assert_eq!(execute_double("fcvt.w.d x10 f11 rne", &[-2147483648.4]), (0.0, i32::MIN as u32, NX));
+  assert_eq!(execute_double("fcvt.w.d x10 f11 rne", &[2147483647.5]), (0.0, i32::MAX as u32, NV));
+  assert_eq!(execute_double("fclass.d x10 f11", &[f64::from_bits(1)]).1, 1 << 5);
+}
+
+#[test]
+fn test_execute_d_integer_conversions() {
+  let mut machine = Machine::default();
+  machine.registers.set(11, -7i32 as u32);
+  for instruction in ["fcvt.d.w f10 x11", "fcvt.d.wu f11 x11", "fcvt.s.wu f12 x11 rtz"] {
+    let instruction = assemble_normalized_ok(instruction);
+    decode(instruction).exec(&mut machine, instruction).unwrap();
+  }
+  assert_eq!(machine.float_registers.get::<f64>(10), -7.0);
+  assert_eq!(machine.float_registers.get::<f64>(11), 4294967289.0);
+  assert_eq!(machine.float_registers.get::<f32>(12), 4294967040.0);
+  assert_eq!(machine.float_registers.fcsr, NX);
+}
+
+#[test]
+fn test_execute_hypotenuse() {
+  // the hypotenuse of a right triangle from data in memory, stored back as single precision
+  const HYPOTENUSE: &str = "
+      fld fa0, a, t0
+      fld fa1, b, t0
+      fmul.d fa0, fa0, fa0
+      fmadd.d fa0, fa1, fa1, fa0
+      fsqrt.d fa0, fa0
+      fcvt.s.d fa0, fa0
+      fsw fa0, c, t0
+      fcvt.w.s a0, fa0, rtz
+    .data
+    .align 3
+    a: .double 3.0
+    b: .double 4.0
+    c: .float 0.0
+  ";
+  let mut machine = Machine::default();
+  let image = assemble_image(HYPOTENUSE).unwrap();
+  image.load(&mut machine).unwrap();
+  machine.cycles(11);
+  assert_eq!(machine.registers.get(10), 5);
+  assert_eq!(machine.memory.load_word(image.symbols["c"] as usize), 5f32.to_bits());
+  assert_eq!(machine.float_registers.fcsr, 0);
+}
+
+const COMPRESSED: &str = "
+    l0:
+      c.addi4spn a0, sp, 16
+      c.lw a1, 4(a2)
+      c.sw a1, 4(a2)
+      c.fld fa0, 8(a1)
+      c.fsw fa0, 8(a1)
+      c.nop
+      c.addi a0, -3
+      c.jal l1
+    l1:
+      c.li a0, 5
+      c.addi16sp sp, -32
+      c.lui a1, 0xfffe1
+      c.srli a1, 3
+      c.srai a1, 3
+      c.andi a1, -1
+      c.sub a0, a1
+      c.xor a0, a1
+      c.or a0, a1
+      c.and a0, a1
+      c.j l0
+      c.beqz a0, l1
+      c.bnez a0, l0
+      c.slli a0, 5
+      c.fldsp fa0, 8(sp)
+      c.lwsp a0, 12(sp)
+      c.flwsp fa0, 12(sp)
+      c.jr ra
+      c.mv a0, a1
+      c.ebreak
+      c.jalr a0
+      c.add a0, a1
+      c.fsdsp fa0, 8(sp)
+      c.swsp a0, 12(sp)
+      c.fswsp fa0, 12(sp)
+  ";
+
+#[test]
+fn test_assemble_c_extension() {
+  // encodings and disassembly as by llvm-mc and llvm-objdump -M no-aliases with the C extension
+  let image = assemble_image(COMPRESSED).unwrap();
+  let llvm = DisassembleOptions { style: Style::Llvm, compressed: true, ..DisassembleOptions::default() };
+  let expected = "
+Disassembly of section .text:
+
+00000000 <l0>:
+       0: 08 08        \tc.addi4spn\ta0, sp, 16
+       2: 4c 42        \tc.lw\ta1, 4(a2)
+       4: 4c c2        \tc.sw\ta1, 4(a2)
+       6: 88 25        \tc.fld\tfa0, 8(a1)
+       8: 88 e5        \tc.fsw\tfa0, 8(a1)
+       a: 01 00        \tc.nop\t
+       c: 75 15        \tc.addi\ta0, -3
+       e: 09 20        \tc.jal\t0x10 <l1>
+
+00000010 <l1>:
+      10: 15 45        \tc.li\ta0, 5
+      12: 3d 71        \tc.addi16sp\tsp, -32
+      14: 85 75        \tc.lui\ta1, 1048545
+      16: 8d 81        \tc.srli\ta1, 3
+      18: 8d 85        \tc.srai\ta1, 3
+      1a: fd 99        \tc.andi\ta1, -1
+      1c: 0d 8d        \tc.sub\ta0, a1
+      1e: 2d 8d        \tc.xor\ta0, a1
+      20: 4d 8d        \tc.or\ta0, a1
+      22: 6d 8d        \tc.and\ta0, a1
+      24: f1 bf        \tc.j\t0x0 <l0>
+      26: 6d d5        \tc.beqz\ta0, 0x10 <l1>
+      28: 61 fd        \tc.bnez\ta0, 0x0 <l0>
+      2a: 16 05        \tc.slli\ta0, 5
+      2c: 22 25        \tc.fldsp\tfa0, 8(sp)
+      2e: 32 45        \tc.lwsp\ta0, 12(sp)
+      30: 32 65        \tc.flwsp\tfa0, 12(sp)
+      32: 82 80        \tc.jr\tra
+      34: 2e 85        \tc.mv\ta0, a1
+      36: 02 90        \tc.ebreak\t
+      38: 02 95        \tc.jalr\ta0
+      3a: 2e 95        \tc.add\ta0, a1
+      3c: 2a a4        \tc.fsdsp\tfa0, 8(sp)
+      3e: 2a c6        \tc.swsp\ta0, 12(sp)
+      40: 2a e6        \tc.fswsp\tfa0, 12(sp)
+";
+  assert_eq!(disassemble_image(&image, &DisassembleOptions { pseudo: false, ..llvm }), expected);
+}
+
+#[test]
+fn test_disassemble_c_extension_aliases() {
+  // with aliases compressed instructions show as the instruction they expand to
+  let image = assemble_image(COMPRESSED).unwrap();
+  let llvm = DisassembleOptions { style: Style::Llvm, compressed: true, ..DisassembleOptions::default() };
+  let listing = disassemble_image(&image, &llvm);
+  for line in ["0: 08 08        \taddi\ta0, sp, 16\n", "e: 09 20        \tjal\t0x10 <l1>\n", "14: 85 75        \tlui\ta1, 1048545\n",
+    "1c: 0d 8d        \tsub\ta0, a0, a1\n", "32: 82 80        \tret\n", "34: 2e 85        \tmv\ta0, a1\n", "36: 02 90        \tebreak\t\n"] {
+    assert!(listing.contains(line), "{line}{listing}");
+  }
+}
+
+#[test]
+fn test_disassemble_c_extension() {
+  let instruction = assemble_normalized_ok("c.addi x10 -3");
+  assert_eq!(instruction, 0x1575);
+  assert_eq!(disassemble(instruction), "c.addi x10, -3");
+  assert_eq!(disassemble(assemble_normalized_ok("c.lw x11 4(x12)")), "c.lw x11, 4(x12)");
+}
+
+#[test]
+fn test_assemble_c_extension_errors() {
+  let error = |source: &str| assemble(source).unwrap_err()[0].kind.clone();
+  let invalid = |operand: &str, expected| ErrorKind::InvalidOperand { operand: operand.to_owned(), expected };
+  assert_eq!(error("c.lw a0, 4(a6)"), invalid("a6", "a register from `x8` to `x15`"));
+  assert_eq!(error("c.lwsp a0, 4(a1)"), invalid("a1", "`sp`"));
+  assert_eq!(error("c.addi4spn a0, sp, 0"), invalid("a0, sp, 0", "a nonzero immediate"));
+  assert_eq!(error("c.lui sp, 1"), invalid("sp, 1", "a register other than `x0` and `sp` and a nonzero immediate"));
+  assert_eq!(error("c.slli a0, 32"), invalid("a0, 32", "a shift amount below 32"));
+  assert_eq!(error("c.addi a0, 32"), ErrorKind::ImmediateOutOfRange { value: 32, min: -32, max: 31 });
+  assert_eq!(error("c.lw a1, 3(a2)"), ErrorKind::MisalignedImmediate { value: 3, alignment: 4 });
+  assert_eq!(error("c.jr"), ErrorKind::WrongOperandCount { mnemonic: "c.jr".to_owned(), expected: "rs1", found: 0 });
+}
+
+#[test]
+fn test_execute_c_extension() {
+  // compressed and base instructions mixed, with base instructions at addresses that are only 2-byte aligned
+  const PROGRAM: &str = "
+      c.li a0, 0
+      c.li a1, 5
+    loop:
+      c.add a0, a1
+      c.addi a1, -1
+      c.bnez a1, loop
+      li sp, 0x1000
+      c.swsp a0, 12(sp)
+      c.lwsp a2, 12(sp)
+      c.jal double
+    back:
+      c.mv a3, a0
+      c.j end
+    double:
+      slli a0, a0, 1
+      c.jr ra
+    end:
+      c.nop
+  ";
+  let (machine, image) = run_to_end(PROGRAM);
+  assert_eq!(image.symbols["loop"], 4);
+  assert_eq!((machine.registers.get(10), machine.registers.get(12), machine.registers.get(13)), (30, 15, 30));
+  assert_eq!(machine.registers.get(1), image.symbols["back"]);
+  assert_eq!(machine.memory.load_word(0x100c), 15);
+}
+
+#[test]
+fn test_link_c_extension() {
+  // branches and jumps to symbols of other objects are relocated
+  let object = assemble_object(".globl _start\n_start:\n c.j far\n c.beqz a0, far\n", |_| None).unwrap();
+  let kinds = object.relocations.iter().map(|relocation| (relocation.offset, relocation.kind)).collect::<Vec<_>>();
+  assert_eq!(kinds, [(0, RelocationKind::RvcJump), (2, RelocationKind::RvcBranch)]);
+  let far = assemble_object(".globl far\nfar: c.nop\n", |_| None).unwrap();
+  let image = link(&[object, far], None).unwrap();
+  let listing = disassemble_image(&image, &DisassembleOptions { pseudo: false, compressed: true, ..DisassembleOptions::default() });
+  assert!(listing.contains("c.j 0x4 <far>\n"), "{listing}");
+  assert!(listing.contains("c.beqz a0, 0x4 <far>\n"), "{listing}");
+}
+
+const ZICSR: &str = "
+    csrrw a0, mscratch, a1
+    rdcycle a0
+    rdcycleh a0
+    rdtime a0
+    rdtimeh a0
+    rdinstret a0
+    rdinstreth a0
+    csrr a0, mstatus
+    csrw mstatus, a1
+    csrs mstatus, a1
+    csrc mstatus, a1
+    csrrc a0, mstatus, a1
+    csrrwi a0, mstatus, 5
+    csrwi mstatus, 5
+    csrsi mstatus, 5
+    csrci mstatus, 31
+    csrrsi a0, 0x7c0, 1
+    frcsr a0
+    fscsr a0, a1
+    fscsr a1
+    frrm a0
+    fsrm a1
+    fsrm a0, a1
+    fsrmi 1
+    fsrmi a0, 1
+    frflags a0
+    fsflags a1
+    fsflagsi 1
+    csrr a0, mcycle
+    csrr a0, misa
+    csrr a0, mhartid
+  ";
+
+#[test]
+fn test_assemble_zicsr() {
+  // encodings and disassembly as by llvm-mc and llvm-objdump
+  let image = assemble_image(ZICSR).unwrap();
+  let llvm = DisassembleOptions { style: Style::Llvm, ..DisassembleOptions::default() };
+  let expected = "
+Disassembly of section .text:
+       0: 73 95 05 34  \tcsrrw\ta0, mscratch, a1
+       4: 73 25 00 c0  \trdcycle\ta0
+       8: 73 25 00 c8  \trdcycleh\ta0
+       c: 73 25 10 c0  \trdtime\ta0
+      10: 73 25 10 c8  \trdtimeh\ta0
+      14: 73 25 20 c0  \trdinstret\ta0
+      18: 73 25 20 c8  \trdinstreth\ta0
+      1c: 73 25 00 30  \tcsrr\ta0, mstatus
+      20: 73 90 05 30  \tcsrw\tmstatus, a1
+      24: 73 a0 05 30  \tcsrs\tmstatus, a1
+      28: 73 b0 05 30  \tcsrc\tmstatus, a1
+      2c: 73 b5 05 30  \tcsrrc\ta0, mstatus, a1
+      30: 73 d5 02 30  \tcsrrwi\ta0, mstatus, 5
+      34: 73 d0 02 30  \tcsrwi\tmstatus, 5
+      38: 73 e0 02 30  \tcsrsi\tmstatus, 5
+      3c: 73 f0 0f 30  \tcsrci\tmstatus, 31
+      40: 73 e5 00 7c  \tcsrrsi\ta0, 1984, 1
+      44: 73 25 30 00  \tfrcsr\ta0
+      48: 73 95 35 00  \tfscsr\ta0, a1
+      4c: 73 90 35 00  \tfscsr\ta1
+      50: 73 25 20 00  \tfrrm\ta0
+      54: 73 90 25 00  \tfsrm\ta1
+      58: 73 95 25 00  \tfsrm\ta0, a1
+      5c: 73 d0 20 00  \tfsrmi\t1
+      60: 73 d5 20 00  \tfsrmi\ta0, 1
+      64: 73 25 10 00  \tfrflags\ta0
+      68: 73 90 15 00  \tfsflags\ta1
+      6c: 73 d0 10 00  \tfsflagsi\t1
+      70: 73 25 00 b0  \tcsrr\ta0, mcycle
+      74: 73 25 10 30  \tcsrr\ta0, misa
+      78: 73 25 40 f1  \tcsrr\ta0, mhartid
+";
+  assert_eq!(disassemble_image(&image, &llvm), expected);
+}
+
+#[test]
+fn test_disassemble_zicsr() {
+  let image = assemble_image(ZICSR).unwrap();
+  let listing = disassemble_image(&image, &DisassembleOptions { style: Style::Llvm, pseudo: false, ..DisassembleOptions::default() });
+  assert!(listing.contains("4: 73 25 00 c0  \tcsrrs\ta0, cycle, zero\n"), "{listing}");
+  assert_eq!(disassemble(assemble_normalized_ok("csrrs x10 cycle x0")), "csrrs x10, cycle, x0");
+  assert_eq!(disassemble(assemble_normalized_ok("csrrwi x0 0x7c0 3")), "csrrwi x0, 0x7c0, 3");
+}
+
+#[test]
+fn test_assemble_zicsr_errors() {
+  let error = |source: &str| assemble(source).unwrap_err()[0].kind.clone();
+  assert_eq!(error("csrr a0, 4096"), ErrorKind::ImmediateOutOfRange { value: 4096, min: 0, max: 4095 });
+  assert_eq!(error("csrwi mstatus, 32"), ErrorKind::ImmediateOutOfRange { value: 32, min: 0, max: 31 });
+  assert_eq!(error("rdcycle"), ErrorKind::WrongOperandCount { mnemonic: "rdcycle".to_owned(), expected: "rd", found: 0 });
+}
+
+#[test]
+fn test_execute_counters() {
+  // counters count every instruction, except when an instruction writes them
+  const PROGRAM: &str = "
+      li a0, 3
+      rdcycle s0
+      rdinstret s1
+      nop
+      rdcycle s2
+      rdinstret s3
+      csrw minstret, zero
+      rdinstret s4
+      csrw mcycleh, a0
+      rdcycleh s5
+    end:
+  ";
+  let (machine, _) = run_to_end(PROGRAM);
+  let registers = [8, 9, 18, 19, 20, 21].map(|register| machine.registers.get(register));
+  assert_eq!(registers, [1, 2, 4, 5, 0, 3]);
+  assert_eq!(machine.csrs.instret, 3);
+  assert_eq!(machine.csrs.cycle >> 32, 3);
+}
+
+#[test]
+fn test_execute_csrs() {
+  const PROGRAM: &str = "
+      fsrmi t0, 1
+      fsflagsi 0b101
+      frcsr t1
+      csrr t2, misa
+      csrrs t3, mhartid, zero
+    end:
+  ";
+  let (machine, _) = run_to_end(PROGRAM);
+  let registers = [5, 6, 7, 28].map(|register| machine.registers.get(register));
+  assert_eq!(registers, [0, 0x25, 0x4014_112d, 0]);
+  assert_eq!(machine.float_registers.rounding_mode(), 1);
+}
+
+/// assemble `source`, load it and run until the `end` label
+fn run_to_end(source: &str) -> (Machine, Image) {
+  let mut machine = Machine::default();
+  let image = assemble_image(source).unwrap();
+  image.load(&mut machine).unwrap();
+  while machine.pc != image.symbols["end"] {
+    machine.cycle();
+  }
+  (machine, image)
+}
+
+// a handler recording mcause and mtval and skipping the instruction, which raises each kind of exception
+const TRAPS: &str = "
+      j start
+    handler:
+      csrr t0, mcause
+      csrr t1, mtval
+      sw t0, 0(s0)
+      sw t1, 4(s0)
+      addi s0, s0, 8
+      csrr t0, mepc
+      addi t0, t0, 4
+      csrw mepc, t0
+      mret
+    start:
+      la t0, handler
+      csrw mtvec, t0
+      li s0, 0x1000
+      li a0, 7
+      csrsi mstatus, 8
+      ecall
+    breakpoint:
+      ebreak
+      .word 0xffffffff
+      lw a0, 2(zero)
+      li a1, 0x1000000
+      sw a0, 0(a1)
+    illegal:
+      csrr a0, 0x7c0
+      csrr s1, mstatus
+      csrci misa, 4
+      jal zero, misaligned
+    end:
+      .half 0
+    misaligned:
+      nop
+  ";
+
+#[test]
+fn test_traps() {
+  let (machine, image) = run_to_end(TRAPS);
+  let records = (0..7).map(|index| (machine.memory.load_word(0x1000 + 8 * index), machine.memory.load_word(0x1004 + 8 * index)));
+  let illegal = machine.memory.load_word(image.symbols["illegal"] as usize);
+  assert_eq!(records.collect::<Vec<_>>(), [
+    (11, 0),
+    (3, image.symbols["breakpoint"]),
+    (2, 0xffffffff),
+    (4, 2),
+    (7, 0x1000000),
+    (2, illegal),
+    (0, image.symbols["misaligned"]),
+  ]);
+}
+
+#[test]
+fn test_trap_state() {
+  let (machine, image) = run_to_end(TRAPS);
+  // faulting instructions have no effect, mret restores the interrupt enable and leaves MPP at U-mode
+  assert_eq!(machine.registers.get(10), 7);
+  assert_eq!(machine.registers.get(9), 0x88);
+  assert_eq!(machine.csrs.mepc, image.symbols["end"]);
+  assert_eq!(machine.instruction_alignment(), 4);
+}
+
+#[test]
+fn test_mret() {
+  assert_eq!(assemble_normalized_ok("mret"), 0x30200073);
+  assert_eq!(decode(0x30200073).name, "mret");
+  assert_eq!(disassemble(0x30200073), "mret");
+}
+
+// vectored handlers recording mcause and time, the software interrupt is raised while interrupts are
+// globally disabled and the timer interrupt is rearmed 100 ticks later until it has been taken three times
+const INTERRUPTS: &str = "
+      j start
+      .align 2
+    vectors:
+      j unexpected
+      j unexpected
+      j unexpected
+      j software
+      j unexpected
+      j unexpected
+      j unexpected
+      j timer
+    unexpected:
+      j unexpected
+    software:
+      sw zero, 0(s1)
+      csrr t0, mcause
+      csrr t1, time
+      sw t0, 0(s0)
+      sw t1, 4(s0)
+      addi s0, s0, 8
+      mret
+    timer:
+      csrr t0, mcause
+      csrr t1, time
+      sw t0, 0(s0)
+      sw t1, 4(s0)
+      addi s0, s0, 8
+      addi t1, t1, 100
+      sw t1, 0(s2)
+      addi a1, a1, 1
+      mret
+    start:
+      la t0, vectors
+      ori t0, t0, 1
+      csrw mtvec, t0
+      li s0, 0x1000
+      li s1, 0x2000000
+      li s2, 0x2004000
+      li t0, 50
+      sw t0, 0(s2)
+      sw zero, 4(s2)
+      li t0, 0x88
+      csrw mie, t0
+      li t0, 1
+      sw t0, 0(s1)
+      csrr s3, mip
+      csrsi mstatus, 8
+    loop:
+      addi a0, a0, 1
+      li t0, 3
+      blt a1, t0, loop
+      csrci mstatus, 8
+      li t0, 0x200bff8
+      lw a2, 0(t0)
+    end:
+  ";
+
+#[test]
+fn test_interrupts() {
+  let (machine, _) = run_to_end(INTERRUPTS);
+  let records = (0..4).map(|index| (machine.memory.load_word(0x1000 + 8 * index), machine.memory.load_word(0x1004 + 8 * index)));
+  assert_eq!(records.collect::<Vec<_>>(), [
+    (0x80000003, 20),
+    (0x80000007, 52),
+    (0x80000007, 154),
+    (0x80000007, 256),
+  ]);
+}
+
+#[test]
+fn test_clint() {
+  let (machine, _) = run_to_end(INTERRUPTS);
+  // mtime counts retired instructions and is read through memory as well as the time CSR
+  assert_eq!(machine.registers.get(19), 0x8);
+  assert_eq!(machine.registers.get(12), machine.clint.mtime as u32 - 1);
+  assert_eq!(machine.clint.mtimecmp, 356);
+  assert!(!machine.clint.msip);
+  assert_eq!(machine.csrs.instret, machine.clint.mtime);
+  assert!(machine.csrs.cycle > machine.csrs.instret);
+}
+
+#[test]
+fn test_clint_reserved_offsets() {
+  // offsets above mtime are reserved, they read as zero and ignore writes
+  const PROGRAM: &str = "
+      li t0, -1
+      li a0, 0x200c000
+      sw t0, 0(a0)
+      lw a1, 0(a0)
+      li a2, 0x200fffc
+      sw t0, 0(a2)
+      lw a3, 0(a2)
+      sb t0, 3(a2)
+      lbu a4, 3(a2)
+    end:
+  ";
+  let (machine, _) = run_to_end(PROGRAM);
+  assert_eq!([11, 13, 14].map(|register| machine.registers.get(register)), [0, 0, 0]);
+  assert_eq!((machine.clint.mtime, machine.clint.mtimecmp), (10, u64::MAX));
+}
+
+// M-mode delegates illegal instructions, environment calls from U-mode and the supervisor software interrupt
+// to S-mode, which records each cause and skips the instruction that raised an exception
+const PRIVILEGE_MODES: &str = "
+      j start
+    m_handler:
+      csrr t0, mcause
+      sw t0, 0(s0)
+      addi s0, s0, 4
+      csrr t0, mepc
+      addi t0, t0, 4
+      csrw mepc, t0
+      mret
+    s_handler:
+      csrr t0, scause
+      sw t0, 0(s0)
+      addi s0, s0, 4
+      csrci sip, 2
+      bltz t0, s_return
+      csrr t0, sepc
+      addi t0, t0, 4
+      csrw sepc, t0
+    s_return:
+      sret
+    start:
+      la t0, m_handler
+      csrw mtvec, t0
+      la t0, s_handler
+      csrw stvec, t0
+      li t0, 0x104
+      csrw medeleg, t0
+      csrwi mideleg, 2
+      csrwi mcounteren, 1
+      csrsi mie, 2
+      li s0, 0x1000
+      li t0, 0x1800
+      csrc mstatus, t0
+      li t0, 0x800
+      csrs mstatus, t0
+      la t0, supervisor
+      csrw mepc, t0
+      mret
+    supervisor:
+      csrr a0, sstatus
+      csrr a1, cycle
+      csrr a2, instret
+      csrr a3, mstatus
+      mret
+      ecall
+      la t0, user
+      csrw sepc, t0
+      csrsi sip, 2
+      sret
+    user:
+      csrr a4, cycle
+      sret
+      ecall
+    end:
+  ";
+
+#[test]
+fn test_privilege_modes() {
+  let (machine, _) = run_to_end(PRIVILEGE_MODES);
+  let records = (0..8).map(|index| machine.memory.load_word(0x1000 + 4 * index));
+  assert_eq!(records.collect::<Vec<_>>(), [2, 2, 2, 9, 0x80000001, 2, 2, 8]);
+}
+
+#[test]
+fn test_privilege_state() {
+  let (machine, image) = run_to_end(PRIVILEGE_MODES);
+  // sstatus only shows the S-mode fields, and the S-mode counter access was enabled
+  assert_eq!(machine.registers.get(10), 0);
+  assert_ne!(machine.registers.get(11), 0);
+  assert_eq!([12, 13, 14].map(|register| machine.registers.get(register)), [0, 0, 0]);
+  assert_eq!(machine.privilege_name(), "U");
+  assert_eq!(machine.csrs.sepc, image.symbols["end"]);
+  assert_eq!(machine.mip(), 0);
+}
+
+#[test]
+fn test_sret() {
+  assert_eq!(assemble_normalized_ok("sret"), 0x10200073);
+  assert_eq!(decode(0x10200073).name, "sret");
+  assert_eq!(disassemble(0x10200073), "sret");
+  let bytes = 0x10200073u32.to_le_bytes();
+  let listing = disassemble_bytes(&bytes, 0, &Default::default(), &DisassembleOptions::default());
+  assert!(listing.ends_with("sret\n"));
+}
+
+// M-mode maps the code with an identity megapage, a U-mode page, a read-only page, an execute-only page,
+// an invalid page and a misaligned megapage, then records the page faults S-mode raises accessing them
+const SV32: &str = "
+      j start
+    m_handler:
+      csrr t0, mcause
+      csrr t1, mtval
+      sw t0, 0(s0)
+      sw t1, 4(s0)
+      addi s0, s0, 8
+      li t1, 12
+      beq t0, t1, fetch_fault
+      csrr t0, mepc
+      addi t0, t0, 4
+      csrw mepc, t0
+      mret
+    fetch_fault:
+      csrw mepc, ra
+      mret
+    start:
+      la t0, m_handler
+      csrw mtvec, t0
+      li s0, 0x30000
+      li t0, 0x10000
+      li t1, 0xf
+      sw t1, 0(t0)
+      li t1, 0x4401
+      sw t1, 4(t0)
+      li t1, 0x403
+      sw t1, 8(t0)
+      li t0, 0x11000
+      li t1, 0x8017
+      sw t1, 0(t0)
+      li t1, 0x8403
+      sw t1, 4(t0)
+      li t1, 0x8809
+      sw t1, 8(t0)
+      li t0, 0x21000
+      li t1, 7
+      sw t1, 0(t0)
+      li t0, 0x22000
+      li t1, 9
+      sw t1, 0(t0)
+      li t0, 0x80000010
+      csrw satp, t0
+      li t0, 0x1800
+      csrc mstatus, t0
+      li t0, 0x800
+      csrs mstatus, t0
+      la t0, supervisor
+      csrw mepc, t0
+      mret
+    supervisor:
+      li a0, 0x400000
+      lw t1, 0(a0)
+      li t0, 0x40000
+      csrs sstatus, t0
+      li t2, 42
+      sw t2, 0(a0)
+      lw a1, 0(a0)
+      li a2, 0x401000
+      lw a3, 0(a2)
+      sw a3, 0(a2)
+      li a4, 0x402000
+      lw a5, 0(a4)
+      li t0, 0x80000
+      csrs sstatus, t0
+      lw a5, 0(a4)
+      li t0, 0x403000
+      lw t1, 0(t0)
+      li t0, 0x800000
+      lw t1, 0(t0)
+      jalr ra, 0(a0)
+      li t0, 0x11000
+      li t1, 0x8043
+      sw t1, 4(t0)
+      lw a6, 0(a2)
+      sfence.vma a2
+      lw a7, 0(a2)
+      ecall
+    end:
+  ";
+
+#[test]
+fn test_sv32_page_faults() {
+  let (machine, _) = run_to_end(SV32);
+  let records = (0..7).map(|index| (machine.memory.load_word(0x30000 + 8 * index), machine.memory.load_word(0x30004 + 8 * index)));
+  assert_eq!(records.collect::<Vec<_>>(), [
+    (13, 0x400000),
+    (15, 0x401000),
+    (13, 0x402000),
+    (13, 0x403000),
+    (13, 0x800000),
+    (12, 0x400000),
+    (9, 0),
+  ]);
+}
+
+#[test]
+fn test_sv32_translation() {
+  let (machine, _) = run_to_end(SV32);
+  // the store went to the physical page, and the stale translation of the remapped page was used until sfence.vma
+  assert_eq!(machine.memory.load_word(0x20000), 42);
+  assert_eq!([11, 13, 15, 16, 17].map(|register| machine.registers.get(register)), [42, 7, 9, 7, 42]);
+  assert_eq!(machine.read_csr(0x180), Some(0x80000010));
+}
+
+#[test]
+fn test_sv32_accessed_dirty() {
+  let (machine, _) = run_to_end(SV32);
+  // A is set by every access and D by stores, faulting accesses leave the entries as they were
+  let ptes = [0x10000, 0x10008, 0x11000, 0x11004, 0x11008].map(|address| machine.memory.load_word(address));
+  assert_eq!(ptes, [0xcf, 0x403, 0x80d7, 0x8043, 0x8849]);
+}
+
+#[test]
+fn test_sfence_vma() {
+  assert_eq!(assemble_normalized_ok("sfence.vma x10 x0"), 0x12050073);
+  assert_eq!(decode(0x12050073).name, "sfence.vma");
+  assert_eq!(disassemble(0x12050073), "sfence.vma x10, x0");
+  let bytes = [0x12050073u32, 0x12000073].map(u32::to_le_bytes).concat();
+  let listing = disassemble_bytes(&bytes, 0, &Default::default(), &DisassembleOptions::default());
+  assert!(listing.contains("sfence.vma a0\n") && listing.contains("sfence.vma\n"), "{listing}");
+}
+
+// firmware lets U-mode read and execute its code below 0x2000, read and write 0x4000 to 0x4fff
+// and read the word at 0x5000, and locks 0x6000 to 0x6fff against M-mode as well
+const PMP: &str = "
+      j start
+    m_handler:
+      csrr t0, mcause
+      csrr t1, mtval
+      sw t0, 0(s0)
+      sw t1, 4(s0)
+      addi s0, s0, 8
+      li t1, 1
+      beq t0, t1, fetch_fault
+      csrr t0, mepc
+      addi t0, t0, 4
+      csrw mepc, t0
+      mret
+    fetch_fault:
+      csrw mepc, ra
+      mret
+    start:
+      la t0, m_handler
+      csrw mtvec, t0
+      li s0, 0x8000
+      li t0, 0x5000
+      li t1, 5
+      sw t1, 0(t0)
+      li t0, 0x800
+      csrw pmpaddr0, t0
+      li t0, 0x11ff
+      csrw pmpaddr1, t0
+      li t0, 0x1400
+      csrw pmpaddr2, t0
+      li t0, 0x19ff
+      csrw pmpaddr3, t0
+      li t0, 0x98111b0d
+      csrw pmpcfg0, t0
+      li t0, 0x6000
+      sw zero, 0(t0)
+      csrw pmpaddr3, zero
+      csrr s1, pmpaddr3
+      li t0, 0x00111b0d
+      csrw pmpcfg0, t0
+      csrr s2, pmpcfg0
+      li t0, 0x1800
+      csrc mstatus, t0
+      la t0, user
+      csrw mepc, t0
+      mret
+    user:
+      li a0, 0x4000
+      sw a0, 16(a0)
+      lw a1, 16(a0)
+      li a2, 0x5000
+      lw a3, 0(a2)
+      sw a3, 0(a2)
+      lw a3, 4(a2)
+      lw a4, 0(zero)
+      sw zero, 0(zero)
+      jalr ra, 0(a0)
+      ecall
+    end:
+  ";
+
+#[test]
+fn test_pmp_faults() {
+  let (machine, _) = run_to_end(PMP);
+  let records = (0..6).map(|index| (machine.memory.load_word(0x8000 + 8 * index), machine.memory.load_word(0x8004 + 8 * index)));
+  assert_eq!(records.collect::<Vec<_>>(), [
+    (7, 0x6000),
+    (7, 0x5000),
+    (5, 0x5004),
+    (7, 0),
+    (1, 0x4000),
+    (8, 0),
+  ]);
+}
+
+#[test]
+fn test_pmp_locking() {
+  let (machine, _) = run_to_end(PMP);
+  // the locked entry keeps its address and configuration
+  assert_eq!(machine.registers.get(9), 0x19ff);
+  assert_eq!(machine.registers.get(18), 0x98111b0d);
+}
+
+#[test]
+fn test_pmp_permitted_accesses() {
+  let (machine, _) = run_to_end(PMP);
+  // U-mode accesses succeed where an entry permits them, and faulting stores leave memory unchanged
+  assert_eq!([11, 13, 14].map(|register| machine.registers.get(register)), [0x4000, 5, machine.memory.load_word(0)]);
+  assert_eq!(machine.memory.load_word(0x5000), 5);
+}
+
+#[test]
+fn test_image_load_out_of_memory() {
+  let mut image = assemble_image("nop").unwrap();
+  image.rebase(0x8000_0000);
+  let mut machine = Machine::default();
+  assert_eq!(image.load(&mut machine), Err(ImageError::SectionOutOfMemory {
+    name: ".text".into(),
+    address: 0x8000_0000,
+    size: 4,
+  }));
+}